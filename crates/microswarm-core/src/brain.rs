@@ -0,0 +1,106 @@
+//! Species driven by Rust code instead of a Rhai script.
+//!
+//! A [`Brain`] sees the same [`Senses`] a script does and returns the
+//! microbe's [`Controls`]. Closures work as brains:
+//!
+//! ```
+//! use microswarm::{Color32, Controls, Simulation};
+//!
+//! let mut sim = Simulation::new(400.).unwrap();
+//! let cruisers = sim.add_brain(|_microbe: &_, _senses: &_| Controls {
+//!     forward: true,
+//!     ..Controls::default()
+//! });
+//! sim.spawn(cruisers, 0., 0., 0., Color32::GREEN);
+//! sim.step().unwrap();
+//! assert!(sim.snapshot().microbes[0].x > 0.);
+//! ```
+//!
+//! Brains aren't saved with the world. A loaded world keeps the species,
+//! but its microbes sit still until [`Simulation::set_brain`] gives it a
+//! brain again.
+//!
+//! [`Simulation::set_brain`]: crate::Simulation::set_brain
+
+use std::fmt;
+
+use crate::{Controls, MicrobeState, Senses};
+
+/// The script saved in place of a brain, which does nothing.
+pub(crate) const IDLE: &str = "new_controls()";
+
+/// Decides what a microbe does each tick.
+pub trait Brain {
+    fn think(&mut self, microbe: &MicrobeState, senses: &Senses) -> Controls;
+
+    /// Decides for every living microbe of the species at once, in id
+    /// order. Brains that are slow to call, like [`RemoteBrain`], override
+    /// this to answer a whole tick in one go. Microbes left without
+    /// controls sit still.
+    ///
+    /// [`RemoteBrain`]: crate::remote::RemoteBrain
+    fn think_all(&mut self, microbes: &[(MicrobeState, &Senses)]) -> Vec<Controls> {
+        microbes
+            .iter()
+            .map(|(microbe, senses)| self.think(microbe, senses))
+            .collect()
+    }
+}
+
+impl<F: FnMut(&MicrobeState, &Senses) -> Controls> Brain for F {
+    fn think(&mut self, microbe: &MicrobeState, senses: &Senses) -> Controls {
+        self(microbe, senses)
+    }
+}
+
+impl fmt::Debug for dyn Brain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Brain")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{scripts, Color32, Controls, Simulation};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_brain_sees_what_scripts_see() {
+        let mut sim = Simulation::new(100.).unwrap();
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let log = seen.clone();
+        let watcher = sim.add_brain(move |microbe: &_, senses: &crate::Senses| {
+            log.borrow_mut().push((*microbe, senses.close, senses.far));
+            Controls::default()
+        });
+        let idle = sim.add_species("new_controls()");
+        sim.spawn(watcher, 0., 0., 0., Color32::RED);
+        // Straight ahead, within close range.
+        sim.spawn(idle, 3., 0., 0., Color32::RED);
+
+        sim.step().unwrap();
+        let seen = seen.borrow();
+        assert_eq!(seen.len(), 1);
+        let (microbe, close, far) = seen[0];
+        assert_eq!(microbe.species, watcher);
+        assert_eq!(close, [1, 0, 0, 0]);
+        assert_eq!(far, [1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_set_brain_replaces_script() {
+        let mut sim = Simulation::new(100.).unwrap();
+        let hunters = sim.add_species(scripts::aggressive_hunter_script());
+        sim.set_brain(hunters, |_: &_, _: &_| Controls::default())
+            .unwrap();
+        sim.spawn(hunters, 5., 5., 1., Color32::RED);
+
+        sim.step().unwrap();
+        let microbe = sim.snapshot().microbes[0];
+        assert_eq!((microbe.x, microbe.y, microbe.rotation), (5., 5., 1.));
+        assert!(sim
+            .set_brain(uuid::Uuid::nil(), |_: &_, _: &_| Controls::default())
+            .is_err());
+    }
+}
@@ -0,0 +1,354 @@
+use ecolor::Color32;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f32::consts::PI;
+use uuid::Uuid;
+
+use crate::brain::{self, Brain};
+use crate::climate::Climate;
+use crate::config::Channel;
+use crate::disruption::{Disruption, Scheduled};
+use crate::error::SimError;
+use crate::math::Math;
+use crate::overflow::EnergyCap;
+use crate::population::PopulationCap;
+use crate::quadtree::{QuadTree, Rect};
+use crate::rules::Rules;
+use crate::sandbox::Sandbox;
+use crate::shape::Shape;
+use crate::simulation::DELTA_TIME;
+use crate::standoff::Standoff;
+use crate::trickle::Trickle;
+use crate::tuning::{Tuning, BOX_SIZE};
+use crate::wells::Well;
+use crate::world::World;
+
+/// Sets up a populated [`World`] in one expression.
+///
+/// ```
+/// use microswarm::{scripts, Color32, WorldBuilder};
+///
+/// let world = WorldBuilder::new()
+///     .arena(800.)
+///     .capacity(10)
+///     .seed(42)
+///     .species(scripts::aggressive_hunter_script(), 20, Color32::RED)
+///     .species(scripts::timid_herbivore_script(), 50, Color32::GREEN)
+///     .build()
+///     .unwrap();
+/// assert_eq!(world.species().len(), 2);
+/// assert_eq!(world.snapshot().microbes.len(), 70);
+/// ```
+pub struct WorldBuilder {
+    arena: f32,
+    height: Option<f32>,
+    shape: Shape,
+    climate: Climate,
+    wells: Vec<Well>,
+    trickle: Option<Trickle>,
+    energy_cap: Option<EnergyCap>,
+    population_cap: Option<PopulationCap>,
+    disruptions: Vec<Scheduled>,
+    standoff: Standoff,
+    capacity: usize,
+    seed: Option<u64>,
+    tuning: Tuning,
+    sandbox: Sandbox,
+    math: Math,
+    tick_duration: f32,
+    rules: Option<Rules>,
+    species: Vec<Species>,
+}
+
+struct Species {
+    script: String,
+    brain: Option<Box<dyn Brain>>,
+    count: usize,
+    color: [Channel; 3],
+}
+
+impl Default for WorldBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WorldBuilder {
+    pub fn new() -> Self {
+        Self {
+            arena: BOX_SIZE,
+            height: None,
+            shape: Shape::Rectangle,
+            climate: Climate::Uniform,
+            wells: Vec::new(),
+            trickle: None,
+            energy_cap: None,
+            population_cap: None,
+            disruptions: Vec::new(),
+            standoff: Standoff::Stronger,
+            capacity: 10,
+            seed: None,
+            tuning: Tuning::default(),
+            sandbox: Sandbox::default(),
+            math: Math::default(),
+            tick_duration: DELTA_TIME,
+            rules: None,
+            species: Vec::new(),
+        }
+    }
+
+    /// Half the width of the square arena, centred on the origin.
+    pub fn arena(mut self, arena: f32) -> Self {
+        self.arena = arena;
+        self.height = None;
+        self
+    }
+
+    /// Half the width and half the height of a rectangular arena, centred
+    /// on the origin.
+    pub fn arena_size(mut self, width: f32, height: f32) -> Self {
+        self.arena = width;
+        self.height = Some(height);
+        self
+    }
+
+    /// Cuts the playable area down to a circle or polygon, which microbes
+    /// also spawn inside. See [`crate::shape`].
+    pub fn shape(mut self, shape: Shape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    /// How warm each part of the arena is. See [`crate::climate`].
+    pub fn climate(mut self, climate: Climate) -> Self {
+        self.climate = climate;
+        self
+    }
+
+    /// Adds a point pulling microbes in or pushing them away. See
+    /// [`crate::wells`].
+    pub fn well(mut self, well: Well) -> Self {
+        self.wells.push(well);
+        self
+    }
+
+    /// Limits how much energy microbes hold. See [`crate::overflow`].
+    pub fn energy_cap(mut self, cap: EnergyCap) -> Self {
+        self.energy_cap = Some(cap);
+        self
+    }
+
+    /// Who wins when microbes bite each other. See [`crate::standoff`].
+    pub fn standoff(mut self, standoff: Standoff) -> Self {
+        self.standoff = standoff;
+        self
+    }
+
+    /// Culls microbes past `cap.max`. See [`crate::population`].
+    pub fn population_cap(mut self, cap: PopulationCap) -> Self {
+        self.population_cap = Some(cap);
+        self
+    }
+
+    /// Makes `disruption` happen at the end of `tick`. See
+    /// [`crate::disruption`].
+    pub fn schedule(mut self, tick: u64, disruption: Disruption) -> Self {
+        self.disruptions.push(Scheduled::new(tick, disruption));
+        self
+    }
+
+    /// Keeps spawning wanderers at the arena's edge. See [`crate::trickle`].
+    pub fn trickle(mut self, trickle: Trickle) -> Self {
+        self.trickle = Some(trickle);
+        self
+    }
+
+    /// Microbes a quadtree node holds before it splits.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Fixes every random choice, including where microbes spawn. Without
+    /// one, each build is different.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn tuning(mut self, tuning: Tuning) -> Self {
+        self.tuning = tuning;
+        self
+    }
+
+    /// Limits on every script run. See [`crate::sandbox`].
+    pub fn sandbox(mut self, sandbox: Sandbox) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// How moving and sensing do their trigonometry. See [`crate::math`].
+    pub fn math(mut self, math: Math) -> Self {
+        self.math = math;
+        self
+    }
+
+    /// Seconds of simulated time per tick, [`DELTA_TIME`] by default. See
+    /// [`World::set_tick_duration`] for the values it ignores.
+    pub fn tick_duration(mut self, seconds: f32) -> Self {
+        self.tick_duration = seconds;
+        self
+    }
+
+    /// Plays a match under `rules`. See [`crate::rules`].
+    pub fn rules(mut self, rules: Rules) -> Self {
+        self.rules = Some(rules);
+        self
+    }
+
+    /// Adds a species driven by `script`, with `count` microbes at random
+    /// positions.
+    pub fn species(self, script: impl Into<String>, count: usize, color: Color32) -> Self {
+        self.add(script.into(), None, count, fixed(color))
+    }
+
+    /// Adds a species driven by `brain`, with `count` microbes at random
+    /// positions. See [`crate::brain`].
+    pub fn brain(self, brain: impl Brain + 'static, count: usize, color: Color32) -> Self {
+        self.add(
+            brain::IDLE.to_owned(),
+            Some(Box::new(brain)),
+            count,
+            fixed(color),
+        )
+    }
+
+    /// Adds a species whose microbes each get a color picked from `color`.
+    pub(crate) fn add(
+        mut self,
+        script: String,
+        brain: Option<Box<dyn Brain>>,
+        count: usize,
+        color: [Channel; 3],
+    ) -> Self {
+        self.species.push(Species {
+            script,
+            brain,
+            count,
+            color,
+        });
+        self
+    }
+
+    pub fn build(self) -> Result<World, SimError> {
+        Ok(self.build_with_ids()?.0)
+    }
+
+    /// Like [`WorldBuilder::build`], but also returns the species ids in the
+    /// order they were added.
+    pub fn build_with_ids(self) -> Result<(World, Vec<Uuid>), SimError> {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let size = [self.arena, self.height.unwrap_or(self.arena)];
+        let mut world = World::with_size(size[0], size[1], seed)?;
+        world.microbes = QuadTree::new(
+            Rect::new(-size[0], -size[1], size[0] * 2., size[1] * 2.),
+            self.capacity,
+        );
+        world.tuning = self.tuning;
+        world.set_shape(self.shape.clone());
+        world.set_climate(self.climate);
+        world.wells = self.wells;
+        world.set_energy_cap(self.energy_cap);
+        world.set_population_cap(self.population_cap);
+        world.set_disruptions(self.disruptions);
+        world.set_standoff(self.standoff);
+        world.set_sandbox(self.sandbox);
+        world.set_math(self.math);
+        world.set_tick_duration(self.tick_duration);
+        world.set_rules(self.rules);
+        let rng = &mut StdRng::seed_from_u64(seed);
+        let mut ids = Vec::new();
+        for species in self.species {
+            let id = world.next_id();
+            world.scripts.insert(id, species.script);
+            if let Some(brain) = species.brain {
+                world.brains.insert(id, brain);
+            }
+            for _ in 0..species.count {
+                let [r, g, b] = species.color.map(|channel| channel.sample(rng));
+                let (x, y) = self.shape.sample(size, rng);
+                world.add_microbe(
+                    x,
+                    y,
+                    rng.gen_range(0.0..=(2. * PI)),
+                    id,
+                    Color32::from_rgb(r, g, b),
+                );
+            }
+            ids.push(id);
+        }
+        world.set_trickle(self.trickle);
+        Ok((world, ids))
+    }
+}
+
+fn fixed(color: Color32) -> [Channel; 3] {
+    [color.r(), color.g(), color.b()].map(Channel::Fixed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scripts;
+
+    #[test]
+    fn test_build() {
+        let build = || {
+            WorldBuilder::new()
+                .arena(50.)
+                .capacity(4)
+                .seed(8)
+                .species(scripts::aggressive_hunter_script(), 30, Color32::RED)
+                .species("new_controls()", 5, Color32::BLUE)
+                .build_with_ids()
+                .unwrap()
+        };
+        let (world, ids) = build();
+        assert_eq!(world.seed(), 8);
+        assert_eq!(world.arena(), 50.);
+        let snapshot = world.snapshot();
+        let count = |id| snapshot.microbes.iter().filter(|m| m.species == id).count();
+        assert_eq!((count(ids[0]), count(ids[1])), (30, 5));
+        assert!(snapshot
+            .microbes
+            .iter()
+            .all(|m| m.x.abs() < 50. && m.y.abs() < 50.));
+        assert!(snapshot
+            .microbes
+            .iter()
+            .filter(|m| m.species == ids[1])
+            .all(|m| m.color == Color32::BLUE));
+        assert_eq!(snapshot, build().0.snapshot());
+    }
+
+    #[test]
+    fn test_corridor() {
+        let mut world = WorldBuilder::new()
+            .arena_size(200., 10.)
+            .seed(3)
+            .species(scripts::random_script(), 40, Color32::RED)
+            .build()
+            .unwrap();
+        assert_eq!(world.arena_size(), [200., 10.]);
+        assert_eq!(
+            world.microbes.root.bounds,
+            Rect::new(-200., -10., 400., 20.)
+        );
+        for _ in 0..100 {
+            world.update(crate::DELTA_TIME).unwrap();
+        }
+        let microbes = world.microbes().collect::<Vec<_>>();
+        assert!(microbes.iter().all(|m| m.position().y.abs() <= 10.));
+        assert!(microbes.iter().any(|m| m.position().x.abs() > 20.));
+    }
+}
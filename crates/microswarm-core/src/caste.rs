@@ -0,0 +1,107 @@
+//! Roles a species' microbes are born into.
+//!
+//! A species can list [`Caste`]s, each a preset of traits and optionally a
+//! script of its own. A microbe picks its offspring's caste by setting
+//! `spawn_as` on its controls: if it splits that tick, all four children
+//! are born into the caste of that name, with its traits and running its
+//! script in place of the species'. Children of a microbe that names no
+//! caste, or one its species doesn't have, keep their parent's. Scripts can
+//! read their microbe's caste with `caste()`, which is empty for none.
+//!
+//! ```toml
+//! [[species]]
+//! name = "ants"
+//! script = "scripts/queen.rhai"
+//!
+//! [species.castes.worker]
+//! builtin = "herbivore"
+//!
+//! [species.castes.soldier]
+//! script = "scripts/soldier.rhai"
+//! spiky = true
+//! jaws = { reach = 1.5 }
+//! ```
+//!
+//! ```rhai
+//! let c = new_controls();
+//! if caste() == "" { c.spawn_as = if rand(0..=100) > 80 { "soldier" } else { "worker" }; }
+//! c
+//! ```
+//!
+//! Castes are still one species to stats, rules and everything else.
+
+use serde::{Deserialize, Serialize};
+
+use crate::eyes::Eyes;
+use crate::jaws::Jaws;
+use crate::microbe::Microbe;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Caste {
+    /// What `spawn_as` and `caste()` call it.
+    pub name: String,
+    /// Script source its members run instead of their species'.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+    /// Whether its members are born spiky, rather than as their parent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spiky: Option<bool>,
+    /// The jaws its members are born with, rather than their parent's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jaws: Option<Jaws>,
+    /// The eyes its members are born with, rather than their parent's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eyes: Option<Eyes>,
+}
+
+impl Caste {
+    /// A caste that changes nothing but its members' name for it.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            script: None,
+            spiky: None,
+            jaws: None,
+            eyes: None,
+        }
+    }
+
+    /// Gives a newborn the caste's traits.
+    pub(crate) fn apply(&self, microbe: &mut Microbe) {
+        if let Some(spiky) = self.spiky {
+            microbe.spiky = spiky;
+        }
+        if let Some(jaws) = self.jaws {
+            microbe.jaws = jaws;
+        }
+        if let Some(eyes) = self.eyes {
+            microbe.eyes = eyes;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ecolor::Color32;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_apply() {
+        let mut microbe = Microbe::new(Uuid::nil(), 0., 0., 0., Uuid::nil(), Color32::RED);
+        microbe.spiky = true;
+        Caste::new("worker").apply(&mut microbe);
+        assert!(microbe.spiky);
+        assert_eq!(microbe.jaws, Jaws::default());
+
+        let soldier = Caste {
+            spiky: Some(false),
+            jaws: Some(Jaws::new(1., 2.)),
+            ..Caste::new("soldier")
+        };
+        soldier.apply(&mut microbe);
+        assert!(!microbe.spiky);
+        assert_eq!(microbe.jaws, Jaws::new(1., 2.));
+        assert_eq!(microbe.eyes, Eyes::default());
+    }
+}
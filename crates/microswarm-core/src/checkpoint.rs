@@ -0,0 +1,153 @@
+//! Periodic saves of a long run, so a crash loses minutes instead of a night.
+//!
+//! [`Checkpoints`] saves the world to a directory every few ticks, as
+//! `checkpoint-TICK.json` files that [`Simulation::load`] resumes from. Only
+//! the newest few are kept. Each one is written under a temporary name and
+//! then renamed, so a crash while saving never leaves a truncated
+//! checkpoint behind.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::simulation::Simulation;
+
+const PREFIX: &str = "checkpoint-";
+const EXTENSION: &str = ".json";
+
+#[derive(Debug, Clone)]
+pub struct Checkpoints {
+    dir: PathBuf,
+    every: u64,
+    keep: usize,
+    /// Checkpoints on disk, oldest first.
+    saved: VecDeque<PathBuf>,
+}
+
+impl Checkpoints {
+    /// Saves to `dir` every `every` ticks, keeping the newest `keep` files.
+    /// Creates `dir` if needed. Checkpoints already in it, from an earlier
+    /// run, count towards `keep`.
+    pub fn new(dir: impl Into<PathBuf>, every: u64, keep: usize) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let saved = Self::list(&dir)?
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect();
+        Ok(Self {
+            dir,
+            every: every.max(1),
+            keep: keep.max(1),
+            saved,
+        })
+    }
+
+    /// Saves `sim` if its tick is due a checkpoint, removing the oldest ones
+    /// beyond the limit. Returns the path it saved to.
+    pub fn record(&mut self, sim: &Simulation) -> io::Result<Option<PathBuf>> {
+        let tick = sim.world().tick();
+        if !tick.is_multiple_of(self.every) {
+            return Ok(None);
+        }
+        let path = self.dir.join(format!("{PREFIX}{tick:010}{EXTENSION}"));
+        let partial = path.with_extension("json.partial");
+        sim.save(&partial)?;
+        fs::rename(&partial, &path)?;
+        tracing::debug!(path = %path.display(), "checkpoint saved");
+
+        // A resumed run can save the same tick again.
+        self.saved.retain(|saved| *saved != path);
+        self.saved.push_back(path.clone());
+        while self.saved.len() > self.keep {
+            if let Some(old) = self.saved.pop_front() {
+                match fs::remove_file(&old) {
+                    Ok(()) => {}
+                    Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+                    Err(error) => return Err(error),
+                }
+            }
+        }
+        Ok(Some(path))
+    }
+
+    /// The newest checkpoint in `dir`, to resume from.
+    pub fn latest(dir: impl AsRef<Path>) -> io::Result<Option<PathBuf>> {
+        Ok(Self::list(dir.as_ref())?.pop().map(|(_, path)| path))
+    }
+
+    /// Checkpoints in `dir` with their ticks, oldest first.
+    fn list(dir: &Path) -> io::Result<Vec<(u64, PathBuf)>> {
+        let mut checkpoints = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let tick = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix(PREFIX)?.strip_suffix(EXTENSION))
+                .and_then(|tick| tick.parse::<u64>().ok());
+            if let Some(tick) = tick {
+                checkpoints.push((tick, path));
+            }
+        }
+        checkpoints.sort();
+        Ok(checkpoints)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{scripts, Color32};
+
+    #[test]
+    fn test_checkpoints() {
+        let dir = std::env::temp_dir().join(format!("checkpoints-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut sim = Simulation::with_seed(50., 2).unwrap();
+        let hunters = sim.add_species(scripts::aggressive_hunter_script());
+        for i in 0..10 {
+            sim.spawn(hunters, i as f32 * 5. - 25., 0., 0., Color32::RED);
+        }
+
+        let mut checkpoints = Checkpoints::new(&dir, 10, 3).unwrap();
+        let mut saved = Vec::new();
+        for _ in 0..55 {
+            sim.step().unwrap();
+            saved.extend(checkpoints.record(&sim).unwrap());
+        }
+        assert_eq!(saved.len(), 5);
+        let names = |dir: &Path| {
+            let mut names = fs::read_dir(dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+        assert_eq!(
+            names(&dir),
+            [
+                "checkpoint-0000000030.json",
+                "checkpoint-0000000040.json",
+                "checkpoint-0000000050.json"
+            ]
+        );
+
+        let latest = Checkpoints::latest(&dir).unwrap().unwrap();
+        assert_eq!(latest, saved[4]);
+        let mut resumed = Simulation::load(&latest).unwrap();
+        assert_eq!(resumed.world().tick(), 50);
+
+        // A resumed run picks up the rotation where it left off.
+        let mut checkpoints = Checkpoints::new(&dir, 10, 3).unwrap();
+        for _ in 0..10 {
+            resumed.step().unwrap();
+            checkpoints.record(&resumed).unwrap();
+        }
+        assert_eq!(names(&dir)[0], "checkpoint-0000000040.json");
+        assert_eq!(names(&dir).len(), 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
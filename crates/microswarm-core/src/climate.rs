@@ -0,0 +1,120 @@
+//! Temperature across the arena.
+//!
+//! A [`Climate`] gives every point in the arena a temperature from -1, the
+//! coldest, to 1, the hottest. Warmth speeds microbes up and makes them
+//! hungrier, cold slows them down and saves energy: both their speed and
+//! their energy use are scaled by `1 + temperature_effect * temperature`,
+//! where `temperature_effect` is part of the [`Tuning`]. Scripts can feel
+//! where they are with `temperature()`.
+//!
+//! ```toml
+//! climate = "hot_center"    # or "cold_center", or "uniform", the default
+//! # or hottest at one side, by the angle it's in from the positive x axis:
+//! climate = { gradient = 1.5708 }
+//! ```
+//!
+//! Like [`crate::shape`], the methods here take the arena's `size` as its
+//! half-width and half-height, so the edges are equally far out on an
+//! arena that isn't square.
+//!
+//! [`Tuning`]: crate::Tuning
+
+use serde::{Deserialize, Serialize};
+
+use crate::math::Math;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Climate {
+    /// Mild everywhere, leaving speed and energy use as tuned.
+    #[default]
+    Uniform,
+    /// Hottest in the middle, coldest at the arena's edges and beyond.
+    HotCenter,
+    /// Coldest in the middle, hottest at the arena's edges and beyond.
+    ColdCenter,
+    /// Hottest at the side of the arena this many radians around from the
+    /// positive x axis, coldest at the opposite side.
+    Gradient(f32),
+}
+
+impl Climate {
+    /// The temperature at (`x`, `y`), from -1 to 1.
+    pub fn temperature(&self, size: [f32; 2], x: f32, y: f32, math: Math) -> f32 {
+        let [width, height] = size;
+        let (x, y) = (x / width, y / height);
+        let from_center = || (x * x + y * y).sqrt().min(1.);
+        match self {
+            Climate::Uniform => 0.,
+            Climate::HotCenter => 1. - 2. * from_center(),
+            Climate::ColdCenter => 2. * from_center() - 1.,
+            Climate::Gradient(angle) => {
+                let (sin, cos) = math.sin_cos(*angle);
+                (x * cos + y * sin).clamp(-1., 1.)
+            }
+        }
+    }
+
+    /// What speed and energy use are scaled by at (`x`, `y`), never below
+    /// zero.
+    pub fn factor(&self, size: [f32; 2], x: f32, y: f32, math: Math, effect: f32) -> f32 {
+        (1. + effect * self.temperature(size, x, y, math)).max(0.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temperature() {
+        let size = [100., 50.];
+        let at = |climate: Climate, x, y| climate.temperature(size, x, y, Math::Float);
+
+        assert_eq!(at(Climate::Uniform, 30., 20.), 0.);
+        assert_eq!(at(Climate::HotCenter, 0., 0.), 1.);
+        assert_eq!(at(Climate::HotCenter, 50., 0.), 0.);
+        // The edges are as cold on the short side as on the long one.
+        assert_eq!(at(Climate::HotCenter, 0., -50.), -1.);
+        assert_eq!(at(Climate::HotCenter, 100., 0.), -1.);
+        assert_eq!(at(Climate::HotCenter, 100., 50.), -1.);
+        assert_eq!(at(Climate::ColdCenter, 0., 0.), -1.);
+        assert_eq!(at(Climate::ColdCenter, -100., 0.), 1.);
+
+        let east = Climate::Gradient(0.);
+        assert_eq!(at(east, 100., 0.), 1.);
+        assert_eq!(at(east, -50., 40.), -0.5);
+        assert_eq!(at(east, -200., 0.), -1.);
+        let north = Climate::Gradient(std::f32::consts::FRAC_PI_2);
+        assert!((at(north, 0., 25.) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_factor() {
+        let size = [100., 100.];
+        let factor = |x, effect| Climate::HotCenter.factor(size, x, 0., Math::Float, effect);
+        assert_eq!(factor(0., 0.5), 1.5);
+        assert_eq!(factor(100., 0.5), 0.5);
+        assert_eq!(factor(50., 0.5), 1.);
+        assert_eq!(factor(100., 2.), 0.);
+        assert_eq!(factor(0., 0.), 1.);
+    }
+
+    #[test]
+    fn test_serde() {
+        let climates = [
+            Climate::Uniform,
+            Climate::HotCenter,
+            Climate::ColdCenter,
+            Climate::Gradient(1.5),
+        ];
+        for climate in climates {
+            let json = serde_json::to_string(&climate).unwrap();
+            assert_eq!(serde_json::from_str::<Climate>(&json).unwrap(), climate);
+        }
+        assert_eq!(
+            serde_json::to_string(&Climate::HotCenter).unwrap(),
+            "\"hot_center\""
+        );
+    }
+}
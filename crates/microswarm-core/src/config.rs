@@ -0,0 +1,1458 @@
+//! Experiment configuration loaded from a TOML file such as `world.toml`.
+//!
+//! Every key is optional and falls back to the built-in default, so a config
+//! only needs to list what it changes:
+//!
+//! ```toml
+//! arena = 400.0             # half the width, or [half width, half height]
+//! shape = "rectangle"       # or "circle", or the corners of a convex polygon
+//! climate = "uniform"       # or "hot_center", "cold_center", { gradient = angle }
+//! seed = 42                 # omit for a different run every time
+//! math = "float"            # or "fixed", to replay the run on any platform
+//! tick_duration = 0.1       # seconds of simulated time per tick
+//! standoff = "stronger"     # or "both_hurt", "random" or "trade", see the standoff module
+//!
+//! [tuning]
+//! health = 100.0
+//! speed = 1.5
+//! rotation_speed = 1.0
+//! detect_range_far = 40.0
+//! detect_range_close = 10.0
+//! eat_damage = 30.0
+//! eat_transfer = 0.3        # 0 for flat eat_damage bites
+//! action_energy_consumption = 0.001
+//! reproduction_threshold = 200.0
+//! stamina = 100.0
+//! bite_stamina = 10.0
+//! stamina_regen = 5.0
+//! temperature_effect = 0.5
+//! photosynthesis = 0.25
+//! producer_handicap = 0.5
+//! spike_reflect = 0.5
+//! spike_upkeep = 2.0
+//! cloak_energy = 0.05
+//! starving_speed = 0.5
+//! bulky_speed = 0.75
+//! jaw_cost = 0.5
+//! jaw_upkeep = 0.25
+//! eye_upkeep = 0.5
+//!
+//! [sandbox]                 # limits on every script run, see the sandbox module
+//! max_operations = 100000
+//!
+//! [rules]                   # play a match with a winner, see the rules module
+//! condition = "hill"        # or "last_standing", or "biomass" with ticks = N
+//! x = 0.0                   # the hill's centre, radius, and the ticks to hold it
+//! y = 0.0
+//! radius = 50.0
+//! hold = 1000
+//! time_limit = 20000        # optional, for any condition
+//!
+//! [energy_cap]              # see the overflow module
+//! max = 150.0
+//! overflow = "wasted"       # or "fat", stored and burned back slowly
+//! fat_burn = 1.0
+//!
+//! [population_cap]          # see the population module
+//! max = 2000
+//! cull = "oldest"           # or "weakest", or "random"
+//!
+//! [trickle]                 # fresh prey at the edges, see the trickle module
+//! every = 20
+//! max = 100
+//!
+//! [islands]                 # separate arenas, see the islands module
+//! count = 4
+//! migration = 0.0005        # chance of each microbe moving each tick
+//!
+//! [[species]]
+//! name = "hunter"
+//! builtin = "hunter"        # or: script = "scripts/hunter.rhai"
+//! count = 125
+//! color = [[0, 255], 255, [0, 255]]  # each channel a value or a [min, max] range
+//! producer = false          # true to feed on light, moving and biting weakly
+//! spiky = false             # true to start its microbes with spikes
+//! jaws = { width = 1.0, reach = 1.0 }          # see the jaws module
+//! eyes = { focus = 1.0 }                       # see the eyes module
+//! mutation = { rate = 0.0, magnitude = 0.0 }  # see the mutation module
+//! cooldown = { ticks = 0, speed = 1.0 }        # see the cooldown module
+//! castes = { soldier = { builtin = "hunter", spiky = true } }  # see the caste module
+//! ```
+//!
+//! Listing any `[[species]]` replaces the default species entirely. Script
+//! paths are relative to the config file.
+//!
+//! A species can instead be driven by an agent in another process, as
+//! described in the `remote` module:
+//!
+//! ```toml
+//! [[species]]
+//! name = "agent"
+//! remote = "127.0.0.1:7000"
+//! timeout_ms = 100          # how long each tick waits for the agent
+//! count = 50
+//! ```
+//!
+//! `[[wells]]` add points that pull microbes in or push them away, as
+//! described in [`crate::wells`], and `[[disruptions]]` strike the world
+//! with meteors, famines and blooms, as described in
+//! [`crate::disruption`].
+
+use rand::Rng;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use toml_edit::{DocumentMut, Item, TableLike, Value};
+use uuid::Uuid;
+
+use crate::brain::{self, Brain};
+use crate::builder::WorldBuilder;
+use crate::caste::Caste;
+use crate::climate::Climate;
+use crate::cooldown::Cooldown;
+use crate::disruption::{Disruption, Scheduled};
+use crate::error::SimError;
+use crate::eyes::Eyes;
+use crate::jaws::Jaws;
+use crate::math::Math;
+use crate::mutation::Mutation;
+use crate::overflow::{EnergyCap, Overflow};
+use crate::population::{Cull, PopulationCap};
+#[cfg(feature = "net")]
+use crate::remote::RemoteBrain;
+use crate::rules::{Condition, Rules};
+use crate::sandbox::Sandbox;
+use crate::scripts;
+use crate::shape::Shape;
+use crate::simulation::{Simulation, DELTA_TIME};
+use crate::standoff::Standoff;
+use crate::trickle::Trickle;
+use crate::tuning::{Tuning, BOX_SIZE};
+use crate::wells::Well;
+
+/// How long a tick waits for a remote species' agent by default.
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Half the width of the arena.
+    pub arena: f32,
+    /// Half the height of the arena, if it isn't square.
+    pub arena_height: Option<f32>,
+    /// The playable area within the arena. See [`crate::shape`].
+    pub shape: Shape,
+    /// How warm each part of the arena is. See [`crate::climate`].
+    pub climate: Climate,
+    /// Points pulling microbes in or pushing them away. See
+    /// [`crate::wells`].
+    pub wells: Vec<Well>,
+    /// Spawns wanderers at the arena's edge. See [`crate::trickle`].
+    pub trickle: Option<Trickle>,
+    /// Limits how much energy microbes hold. See [`crate::overflow`].
+    pub energy_cap: Option<EnergyCap>,
+    /// Culls microbes past a population. See [`crate::population`].
+    pub population_cap: Option<PopulationCap>,
+    /// Meteors, famines and blooms at set ticks. See [`crate::disruption`].
+    pub disruptions: Vec<Scheduled>,
+    /// Who wins when microbes bite each other. See [`crate::standoff`].
+    pub standoff: Standoff,
+    /// Seed for the whole run. `None` picks a random one.
+    pub seed: Option<u64>,
+    pub tuning: Tuning,
+    pub sandbox: Sandbox,
+    pub math: Math,
+    /// Seconds of simulated time per tick.
+    pub tick_duration: f32,
+    /// Makes the run a match. See [`crate::rules`].
+    pub rules: Option<Rules>,
+    /// Runs the config on several islands instead of one arena. Only
+    /// [`crate::islands::Archipelago`] reads this; [`Config::build`]
+    /// builds a single island.
+    pub islands: Option<Islands>,
+    pub species: Vec<SpeciesConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeciesConfig {
+    pub name: String,
+    /// Script source.
+    pub script: String,
+    /// Microbes spawned at startup.
+    pub count: usize,
+    /// Red, green and blue channels.
+    pub color: [Channel; 3],
+    /// An agent that drives the species instead of its script.
+    pub remote: Option<Remote>,
+    /// Feeds on light while standing still. See [`World::set_producer`].
+    ///
+    /// [`World::set_producer`]: crate::World::set_producer
+    pub producer: bool,
+    /// Its microbes start spiky. See [`World::set_spiky`].
+    ///
+    /// [`World::set_spiky`]: crate::World::set_spiky
+    pub spiky: bool,
+    /// The jaws its microbes start with. See [`crate::jaws`].
+    pub jaws: Jaws,
+    /// The eyes its microbes start with. See [`crate::eyes`].
+    pub eyes: Eyes,
+    /// How its offspring differ from their parents. See [`crate::mutation`].
+    pub mutation: Mutation,
+    /// How long its microbes rest after splitting. See [`crate::cooldown`].
+    pub cooldown: Cooldown,
+    /// The roles its offspring can be born into. See [`crate::caste`].
+    pub castes: Vec<Caste>,
+}
+
+/// Several arenas run side by side. See [`crate::islands`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Islands {
+    pub count: usize,
+    /// Chance of each microbe migrating to another island each tick.
+    pub migration: f64,
+}
+
+impl Default for Islands {
+    fn default() -> Self {
+        Self {
+            count: 1,
+            migration: 0.,
+        }
+    }
+}
+
+/// An agent driving a species from another process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remote {
+    pub addr: String,
+    /// How long each tick waits for the agent before its microbes sit still.
+    pub timeout: Duration,
+}
+
+/// One color channel, either fixed or picked at random per microbe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Fixed(u8),
+    Range(u8, u8),
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(toml_edit::TomlError),
+    /// A key has the wrong type, an unknown name or an invalid value.
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            ConfigError::Parse(err) => write!(f, "{err}"),
+            ConfigError::Invalid(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Default for Config {
+    /// The built-in species: half vampires, a quarter herbivores and a
+    /// quarter hunters, with the random walker registered but not spawned.
+    fn default() -> Self {
+        let species = |name: &str, count, color| SpeciesConfig {
+            name: name.to_owned(),
+            script: scripts::builtin(name).unwrap_or_default(),
+            count,
+            color,
+            remote: None,
+            producer: false,
+            spiky: false,
+            jaws: Jaws::default(),
+            eyes: Eyes::default(),
+            mutation: Mutation::default(),
+            cooldown: Cooldown::default(),
+            castes: Vec::new(),
+        };
+        let any = Channel::Range(0, 255);
+        Self {
+            arena: BOX_SIZE,
+            arena_height: None,
+            shape: Shape::Rectangle,
+            climate: Climate::Uniform,
+            wells: Vec::new(),
+            trickle: None,
+            energy_cap: None,
+            population_cap: None,
+            disruptions: Vec::new(),
+            standoff: Standoff::Stronger,
+            seed: None,
+            tuning: Tuning::default(),
+            sandbox: Sandbox::default(),
+            math: Math::default(),
+            tick_duration: DELTA_TIME,
+            rules: None,
+            islands: None,
+            species: vec![
+                species("random", 0, [any; 3]),
+                species("hunter", 125, [any, Channel::Fixed(255), any]),
+                species("vampire", 250, [Channel::Fixed(100), any, any]),
+                species(
+                    "herbivore",
+                    125,
+                    [
+                        Channel::Fixed(255),
+                        Channel::Range(0, 50),
+                        Channel::Range(0, 50),
+                    ],
+                ),
+            ],
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_owned(), e))?;
+        Self::parse(&text, path.parent().unwrap_or(Path::new("")))
+    }
+
+    /// Parses a config, resolving script paths against `base`.
+    pub fn parse(text: &str, base: &Path) -> Result<Self, ConfigError> {
+        let doc = text.parse::<DocumentMut>().map_err(ConfigError::Parse)?;
+        let mut config = Config::default();
+        for (key, item) in doc.iter() {
+            match key {
+                "arena" => match item.as_array() {
+                    Some(size) => {
+                        let size = size
+                            .iter()
+                            .map(|value| number(value).filter(|n| *n > 0.))
+                            .collect::<Option<Vec<_>>>();
+                        let Some(&[width, height]) = size.as_deref() else {
+                            return Err(invalid(key, "a positive number or two of them"));
+                        };
+                        config.arena = width;
+                        config.arena_height = Some(height);
+                    }
+                    None => config.arena = positive(key, float(key, item)?)?,
+                },
+                "shape" => config.shape = parse_shape(key, item)?,
+                "climate" => config.climate = parse_climate(key, item)?,
+                "standoff" => {
+                    config.standoff = match item.as_str() {
+                        Some("stronger") => Standoff::Stronger,
+                        Some("both_hurt") => Standoff::BothHurt,
+                        Some("random") => Standoff::Random,
+                        Some("trade") => Standoff::Trade,
+                        _ => {
+                            return Err(invalid(
+                                key,
+                                "\"stronger\", \"both_hurt\", \"random\" or \"trade\"",
+                            ))
+                        }
+                    }
+                }
+                "seed" => {
+                    config.seed = Some(
+                        item.as_integer()
+                            .map(|seed| seed as u64)
+                            .ok_or_else(|| invalid(key, "an integer"))?,
+                    );
+                }
+                "math" => {
+                    config.math = match item.as_str() {
+                        Some("float") => Math::Float,
+                        Some("fixed") => Math::Fixed,
+                        _ => return Err(invalid(key, "\"float\" or \"fixed\"")),
+                    }
+                }
+                "tick_duration" => config.tick_duration = positive(key, float(key, item)?)?,
+                "tuning" => config.tuning = parse_tuning(table(key, item)?)?,
+                "sandbox" => config.sandbox = parse_sandbox(table(key, item)?)?,
+                "rules" => config.rules = Some(parse_rules(table(key, item)?)?),
+                "islands" => config.islands = Some(parse_islands(table(key, item)?)?),
+                "trickle" => config.trickle = Some(parse_trickle(table(key, item)?)?),
+                "energy_cap" => {
+                    config.energy_cap = Some(parse_energy_cap(table(key, item)?)?);
+                }
+                "population_cap" => {
+                    config.population_cap = Some(parse_population_cap(table(key, item)?)?);
+                }
+                "wells" => {
+                    let tables = item
+                        .as_array_of_tables()
+                        .ok_or_else(|| invalid(key, "an array of tables ([[wells]])"))?;
+                    config.wells = tables
+                        .iter()
+                        .map(|table| parse_well(table))
+                        .collect::<Result<_, _>>()?;
+                }
+                "disruptions" => {
+                    let tables = item
+                        .as_array_of_tables()
+                        .ok_or_else(|| invalid(key, "an array of tables ([[disruptions]])"))?;
+                    config.disruptions = tables
+                        .iter()
+                        .map(|table| parse_disruption(table))
+                        .collect::<Result<_, _>>()?;
+                }
+                "species" => {
+                    let tables = item
+                        .as_array_of_tables()
+                        .ok_or_else(|| invalid(key, "an array of tables ([[species]])"))?;
+                    config.species = tables
+                        .iter()
+                        .map(|table| parse_species(table, base))
+                        .collect::<Result<_, _>>()?;
+                }
+                _ => return Err(unknown(key)),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Splits `population` evenly between the species, replacing their counts.
+    pub fn with_population(mut self, population: usize) -> Self {
+        let species = self.species.len().max(1);
+        for (i, config) in self.species.iter_mut().enumerate() {
+            config.count = population / species + usize::from(i < population % species);
+        }
+        self
+    }
+
+    /// Creates the simulation and spawns every species at random positions.
+    /// Also returns the species ids, in the same order as [`Config::species`].
+    pub fn build(&self) -> Result<(Simulation, Vec<Uuid>), SimError> {
+        let mut builder = WorldBuilder::new()
+            .arena_size(self.arena, self.arena_height.unwrap_or(self.arena))
+            .shape(self.shape.clone())
+            .climate(self.climate)
+            .standoff(self.standoff)
+            .tuning(self.tuning.clone())
+            .sandbox(self.sandbox.clone())
+            .math(self.math)
+            .tick_duration(self.tick_duration);
+        for well in &self.wells {
+            builder = builder.well(*well);
+        }
+        if let Some(trickle) = self.trickle {
+            builder = builder.trickle(trickle);
+        }
+        if let Some(cap) = self.energy_cap {
+            builder = builder.energy_cap(cap);
+        }
+        if let Some(cap) = self.population_cap {
+            builder = builder.population_cap(cap);
+        }
+        for scheduled in &self.disruptions {
+            builder = builder.schedule(scheduled.tick, scheduled.disruption);
+        }
+        if let Some(rules) = self.rules {
+            builder = builder.rules(rules);
+        }
+        if let Some(seed) = self.seed {
+            builder = builder.seed(seed);
+        }
+        for species in &self.species {
+            let brain: Option<Box<dyn Brain>> = match &species.remote {
+                #[cfg(feature = "net")]
+                Some(remote) => Some(Box::new(RemoteBrain::new(&remote.addr, remote.timeout))),
+                _ => None,
+            };
+            builder = builder.add(species.script.clone(), brain, species.count, species.color);
+        }
+        let (mut world, ids) = builder.build_with_ids()?;
+        let mut traits = HashMap::new();
+        for (species, id) in self.species.iter().zip(&ids) {
+            world.set_species_name(*id, &species.name);
+            world.set_producer(*id, species.producer);
+            world.set_mutation(*id, species.mutation);
+            world.set_cooldown(*id, species.cooldown);
+            world.set_castes(*id, species.castes.clone());
+            traits.insert(*id, (species.spiky, species.jaws, species.eyes));
+        }
+        world.for_each_microbe_mut(|microbe| {
+            if let Some(&(spiky, jaws, eyes)) = traits.get(&microbe.script_id) {
+                microbe.spiky = spiky;
+                microbe.jaws = jaws;
+                microbe.eyes = eyes;
+            }
+        });
+        Ok((Simulation::from(world), ids))
+    }
+}
+
+impl Channel {
+    pub(crate) fn sample(self, rng: &mut impl Rng) -> u8 {
+        match self {
+            Channel::Fixed(value) => value,
+            Channel::Range(min, max) => rng.gen_range(min..=max),
+        }
+    }
+}
+
+fn parse_tuning(table: &dyn TableLike) -> Result<Tuning, ConfigError> {
+    let mut tuning = Tuning::default();
+    for (key, item) in table.iter() {
+        let value = float(key, item)?;
+        *tuning
+            .field_mut(key)
+            .ok_or_else(|| unknown(&format!("tuning.{key}")))? = value;
+    }
+    Ok(tuning)
+}
+
+fn parse_sandbox(table: &dyn TableLike) -> Result<Sandbox, ConfigError> {
+    let mut sandbox = Sandbox::default();
+    for (key, item) in table.iter() {
+        let value = item
+            .as_integer()
+            .and_then(|value| u64::try_from(value).ok())
+            .ok_or_else(|| invalid(key, "a non-negative integer"))?;
+        if !sandbox.set(key, value) {
+            return Err(unknown(&format!("sandbox.{key}")));
+        }
+    }
+    Ok(sandbox)
+}
+
+fn parse_rules(table: &dyn TableLike) -> Result<Rules, ConfigError> {
+    let ticks = |key: &str, item: &Item| {
+        item.as_integer()
+            .and_then(|ticks| u64::try_from(ticks).ok())
+            .ok_or_else(|| invalid(key, "a non-negative integer"))
+    };
+    let mut condition = None;
+    let mut time_limit = None;
+    let (mut biomass_ticks, mut hold) = (None, None);
+    let (mut x, mut y, mut radius) = (0., 0., None);
+    for (key, item) in table.iter() {
+        match key {
+            "condition" => condition = Some(string(key, item)?),
+            "time_limit" => time_limit = Some(ticks(key, item)?),
+            "ticks" => biomass_ticks = Some(ticks(key, item)?),
+            "hold" => hold = Some(ticks(key, item)?),
+            "x" => x = float(key, item)?,
+            "y" => y = float(key, item)?,
+            "radius" => radius = Some(positive(key, float(key, item)?)?),
+            _ => return Err(unknown(&format!("rules.{key}"))),
+        }
+    }
+    let missing = |key: &str, condition: &str| {
+        ConfigError::Invalid(format!("rules with condition \"{condition}\" need '{key}'"))
+    };
+    let condition = match condition {
+        Some("last_standing") => Condition::LastStanding,
+        Some("biomass") => Condition::Biomass {
+            ticks: biomass_ticks.ok_or_else(|| missing("ticks", "biomass"))?,
+        },
+        Some("hill") => Condition::Hill {
+            x,
+            y,
+            radius: radius.ok_or_else(|| missing("radius", "hill"))?,
+            hold: hold.ok_or_else(|| missing("hold", "hill"))?,
+        },
+        _ => {
+            return Err(invalid(
+                "condition",
+                "\"last_standing\", \"biomass\" or \"hill\"",
+            ))
+        }
+    };
+    Ok(Rules {
+        condition,
+        time_limit,
+    })
+}
+
+fn parse_islands(table: &dyn TableLike) -> Result<Islands, ConfigError> {
+    let mut islands = Islands::default();
+    for (key, item) in table.iter() {
+        match key {
+            "count" => {
+                islands.count = item
+                    .as_integer()
+                    .and_then(|count| usize::try_from(count).ok())
+                    .filter(|count| *count > 0)
+                    .ok_or_else(|| invalid(key, "a positive integer"))?;
+            }
+            "migration" => {
+                islands.migration = f64::from(float(key, item)?);
+                if !(0. ..=1.).contains(&islands.migration) {
+                    return Err(invalid(key, "a chance between 0 and 1"));
+                }
+            }
+            _ => return Err(unknown(&format!("islands.{key}"))),
+        }
+    }
+    Ok(islands)
+}
+
+fn parse_trickle(table: &dyn TableLike) -> Result<Trickle, ConfigError> {
+    let mut trickle = Trickle::new(20, 100);
+    for (key, item) in table.iter() {
+        let value = item
+            .as_integer()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| invalid(key, "a positive integer"))?;
+        match key {
+            "every" => trickle.every = value as u64,
+            "max" => trickle.max = value as usize,
+            _ => return Err(unknown(&format!("trickle.{key}"))),
+        }
+    }
+    Ok(trickle)
+}
+
+fn parse_energy_cap(table: &dyn TableLike) -> Result<EnergyCap, ConfigError> {
+    let mut max = None;
+    let mut cap = EnergyCap::new(0., Overflow::Wasted);
+    for (key, item) in table.iter() {
+        match key {
+            "max" => max = Some(positive(key, float(key, item)?)?),
+            "overflow" => {
+                cap.overflow = match item.as_str() {
+                    Some("wasted") => Overflow::Wasted,
+                    Some("fat") => Overflow::Fat,
+                    _ => return Err(invalid(key, "\"wasted\" or \"fat\"")),
+                }
+            }
+            "fat_burn" => {
+                cap.fat_burn = float(key, item)?;
+                if cap.fat_burn < 0. {
+                    return Err(invalid(key, "a number of at least 0"));
+                }
+            }
+            _ => return Err(unknown(&format!("energy_cap.{key}"))),
+        }
+    }
+    cap.max = max.ok_or_else(|| ConfigError::Invalid("energy_cap is missing a max".into()))?;
+    Ok(cap)
+}
+
+fn parse_population_cap(table: &dyn TableLike) -> Result<PopulationCap, ConfigError> {
+    let mut max = None;
+    let mut cull = Cull::Oldest;
+    for (key, item) in table.iter() {
+        match key {
+            "max" => {
+                max = Some(
+                    item.as_integer()
+                        .and_then(|max| usize::try_from(max).ok())
+                        .filter(|max| *max > 0)
+                        .ok_or_else(|| invalid(key, "a positive integer"))?,
+                );
+            }
+            "cull" => {
+                cull = match item.as_str() {
+                    Some("oldest") => Cull::Oldest,
+                    Some("weakest") => Cull::Weakest,
+                    Some("random") => Cull::Random,
+                    _ => return Err(invalid(key, "\"oldest\", \"weakest\" or \"random\"")),
+                }
+            }
+            _ => return Err(unknown(&format!("population_cap.{key}"))),
+        }
+    }
+    let max = max.ok_or_else(|| ConfigError::Invalid("population_cap is missing a max".into()))?;
+    Ok(PopulationCap::new(max, cull))
+}
+
+fn parse_species(table: &dyn TableLike, base: &Path) -> Result<SpeciesConfig, ConfigError> {
+    let mut name = None;
+    let mut script = None;
+    let mut count = 0;
+    let mut color = [Channel::Range(0, 255); 3];
+    let mut remote = None;
+    let mut timeout = None;
+    let mut producer = false;
+    let mut spiky = false;
+    let mut jaws = Jaws::default();
+    let mut eyes = Eyes::default();
+    let mut mutation = Mutation::default();
+    let mut cooldown = Cooldown::default();
+    let mut castes = Vec::new();
+    for (key, item) in table.iter() {
+        match key {
+            "name" => name = Some(string(key, item)?.to_owned()),
+            "builtin" | "script" => script = Some(parse_script(key, item, base)?),
+            "count" => {
+                count = item
+                    .as_integer()
+                    .and_then(|count| usize::try_from(count).ok())
+                    .ok_or_else(|| invalid(key, "a non-negative integer"))?;
+            }
+            "color" => color = parse_color(item)?,
+            "producer" => {
+                producer = item
+                    .as_bool()
+                    .ok_or_else(|| invalid(key, "true or false"))?;
+            }
+            "spiky" => {
+                spiky = item
+                    .as_bool()
+                    .ok_or_else(|| invalid(key, "true or false"))?;
+            }
+            "jaws" => jaws = parse_jaws(key, item)?,
+            "eyes" => eyes = parse_eyes(key, item)?,
+            "mutation" => mutation = parse_mutation(key, item)?,
+            "cooldown" => cooldown = parse_cooldown(key, item)?,
+            "castes" => castes = parse_castes(key, item, base)?,
+            "remote" if cfg!(feature = "net") => remote = Some(string(key, item)?.to_owned()),
+            "timeout_ms" => {
+                timeout = Some(Duration::from_millis(
+                    item.as_integer()
+                        .and_then(|ms| u64::try_from(ms).ok())
+                        .ok_or_else(|| invalid(key, "a non-negative integer"))?,
+                ));
+            }
+            _ => return Err(unknown(&format!("species.{key}"))),
+        }
+    }
+    let name = name.ok_or_else(|| ConfigError::Invalid("species is missing a name".into()))?;
+    let remote = match (remote, timeout) {
+        (Some(addr), timeout) => Some(Remote {
+            addr,
+            timeout: timeout.unwrap_or(DEFAULT_TIMEOUT),
+        }),
+        (None, Some(_)) => {
+            return Err(ConfigError::Invalid(format!(
+                "species '{name}' has a timeout_ms but no remote"
+            )))
+        }
+        (None, None) => None,
+    };
+    let script = match (script, &remote) {
+        (Some(_), Some(_)) => {
+            return Err(ConfigError::Invalid(format!(
+                "species '{name}' can't have both a script and a remote"
+            )))
+        }
+        (Some(script), None) => script,
+        (None, Some(_)) => brain::IDLE.to_owned(),
+        (None, None) => {
+            return Err(ConfigError::Invalid(format!(
+                "species '{name}' needs a builtin, a script or a remote"
+            )))
+        }
+    };
+    Ok(SpeciesConfig {
+        name,
+        script,
+        count,
+        color,
+        remote,
+        producer,
+        spiky,
+        jaws,
+        eyes,
+        mutation,
+        cooldown,
+        castes,
+    })
+}
+
+/// The source of a `builtin` name or a `script` path.
+fn parse_script(key: &str, item: &Item, base: &Path) -> Result<String, ConfigError> {
+    if key == "builtin" {
+        let builtin = string(key, item)?;
+        return scripts::builtin(builtin).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "unknown builtin script '{builtin}', expected one of {}",
+                scripts::BUILTINS.join(", ")
+            ))
+        });
+    }
+    let path = base.join(string(key, item)?);
+    fs::read_to_string(&path).map_err(|e| ConfigError::Io(path.clone(), e))
+}
+
+fn parse_castes(key: &str, item: &Item, base: &Path) -> Result<Vec<Caste>, ConfigError> {
+    let mut castes = Vec::new();
+    for (name, item) in table(key, item)?.iter() {
+        let mut caste = Caste::new(name);
+        for (field, item) in table(&format!("{key}.{name}"), item)?.iter() {
+            match field {
+                "builtin" | "script" => caste.script = Some(parse_script(field, item, base)?),
+                "spiky" => {
+                    caste.spiky = Some(
+                        item.as_bool()
+                            .ok_or_else(|| invalid(field, "true or false"))?,
+                    );
+                }
+                "jaws" => caste.jaws = Some(parse_jaws(field, item)?),
+                "eyes" => caste.eyes = Some(parse_eyes(field, item)?),
+                _ => return Err(unknown(&format!("species.{key}.{name}.{field}"))),
+            }
+        }
+        castes.push(caste);
+    }
+    Ok(castes)
+}
+
+fn parse_eyes(key: &str, item: &Item) -> Result<Eyes, ConfigError> {
+    const EXPECTED: &str = "{ focus = a positive number }";
+    let table = item.as_table_like().ok_or_else(|| invalid(key, EXPECTED))?;
+    let mut eyes = Eyes::default();
+    for (field, item) in table.iter() {
+        match field {
+            "focus" => {
+                eyes.focus = item
+                    .as_value()
+                    .and_then(number)
+                    .filter(|n| *n > 0.)
+                    .ok_or_else(|| invalid(key, EXPECTED))?;
+            }
+            _ => return Err(unknown(&format!("species.{key}.{field}"))),
+        }
+    }
+    Ok(Eyes::new(eyes.focus))
+}
+
+fn parse_jaws(key: &str, item: &Item) -> Result<Jaws, ConfigError> {
+    const EXPECTED: &str = "{ width = a positive number, reach = a positive number }";
+    let table = item.as_table_like().ok_or_else(|| invalid(key, EXPECTED))?;
+    let mut jaws = Jaws::default();
+    for (field, item) in table.iter() {
+        let value = item
+            .as_value()
+            .and_then(number)
+            .filter(|n| *n > 0.)
+            .ok_or_else(|| invalid(key, EXPECTED))?;
+        match field {
+            "width" => jaws.width = value,
+            "reach" => jaws.reach = value,
+            _ => return Err(unknown(&format!("species.{key}.{field}"))),
+        }
+    }
+    Ok(Jaws::new(jaws.width, jaws.reach))
+}
+
+fn parse_cooldown(key: &str, item: &Item) -> Result<Cooldown, ConfigError> {
+    let table = item
+        .as_table_like()
+        .ok_or_else(|| invalid(key, "{ ticks = a number of ticks, speed = 0 to 1 }"))?;
+    let mut cooldown = Cooldown::default();
+    for (field, item) in table.iter() {
+        match field {
+            "ticks" => {
+                cooldown.ticks = item
+                    .as_integer()
+                    .and_then(|ticks| u64::try_from(ticks).ok())
+                    .ok_or_else(|| invalid(key, "a non-negative number of ticks"))?;
+            }
+            "speed" => {
+                cooldown.speed = item
+                    .as_value()
+                    .and_then(number)
+                    .filter(|n| (0. ..=1.).contains(n))
+                    .ok_or_else(|| invalid(key, "a speed between 0 and 1"))?;
+            }
+            _ => return Err(unknown(&format!("species.{key}.{field}"))),
+        }
+    }
+    Ok(cooldown)
+}
+
+fn parse_mutation(key: &str, item: &Item) -> Result<Mutation, ConfigError> {
+    const EXPECTED: &str = "{ rate = 0 to 1, magnitude = 0 to 1 }";
+    let table = item.as_table_like().ok_or_else(|| invalid(key, EXPECTED))?;
+    let mut mutation = Mutation::default();
+    for (field, item) in table.iter() {
+        let value = item
+            .as_value()
+            .and_then(number)
+            .filter(|n| (0. ..=1.).contains(n))
+            .ok_or_else(|| invalid(key, EXPECTED))?;
+        match field {
+            "rate" => mutation.rate = value,
+            "magnitude" => mutation.magnitude = value,
+            _ => return Err(unknown(&format!("species.{key}.{field}"))),
+        }
+    }
+    Ok(mutation)
+}
+
+fn parse_color(item: &Item) -> Result<[Channel; 3], ConfigError> {
+    let expected = "three channels, each 0-255 or a [min, max] range";
+    let channels = item
+        .as_array()
+        .filter(|array| array.len() == 3)
+        .ok_or_else(|| invalid("color", expected))?
+        .iter()
+        .map(|value| channel(value).ok_or_else(|| invalid("color", expected)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok([channels[0], channels[1], channels[2]])
+}
+
+fn parse_climate(key: &str, item: &Item) -> Result<Climate, ConfigError> {
+    const EXPECTED: &str =
+        "\"uniform\", \"hot_center\", \"cold_center\" or { gradient = angle in radians }";
+    if let Some(name) = item.as_str() {
+        return match name {
+            "uniform" => Ok(Climate::Uniform),
+            "hot_center" => Ok(Climate::HotCenter),
+            "cold_center" => Ok(Climate::ColdCenter),
+            _ => Err(invalid(key, EXPECTED)),
+        };
+    }
+    let table = item.as_table_like().ok_or_else(|| invalid(key, EXPECTED))?;
+    match (
+        table
+            .get("gradient")
+            .and_then(Item::as_value)
+            .and_then(number),
+        table.len(),
+    ) {
+        (Some(angle), 1) => Ok(Climate::Gradient(angle)),
+        _ => Err(invalid(key, EXPECTED)),
+    }
+}
+
+fn parse_disruption(table: &dyn TableLike) -> Result<Scheduled, ConfigError> {
+    let missing = |key: &str| ConfigError::Invalid(format!("disruption is missing a {key}"));
+    let count = |key: &str| {
+        let item = table.get(key).ok_or_else(|| missing(key))?;
+        item.as_integer()
+            .and_then(|n| u64::try_from(n).ok())
+            .ok_or_else(|| invalid(key, "a non-negative integer"))
+    };
+    let number = |key: &str| float(key, table.get(key).ok_or_else(|| missing(key))?);
+    let kind = string("kind", table.get("kind").ok_or_else(|| missing("kind"))?)?;
+    let (disruption, keys): (_, &[&str]) = match kind {
+        "meteor" => (
+            Disruption::Meteor {
+                x: number("x")?,
+                y: number("y")?,
+                radius: positive("radius", number("radius")?)?,
+            },
+            &["x", "y", "radius"],
+        ),
+        "famine" => (
+            Disruption::Famine {
+                ticks: count("ticks")?,
+            },
+            &["ticks"],
+        ),
+        "bloom" => (
+            Disruption::Bloom {
+                count: count("count")? as usize,
+            },
+            &["count"],
+        ),
+        _ => return Err(invalid("kind", "\"meteor\", \"famine\" or \"bloom\"")),
+    };
+    if let Some((key, _)) = table
+        .iter()
+        .find(|(key, _)| !["tick", "kind"].contains(key) && !keys.contains(key))
+    {
+        return Err(unknown(&format!("disruptions.{key}")));
+    }
+    Ok(Scheduled::new(count("tick")?, disruption))
+}
+
+fn parse_well(table: &dyn TableLike) -> Result<Well, ConfigError> {
+    let mut well = Well::new(0., 0., 0.);
+    for (key, item) in table.iter() {
+        match key {
+            "x" => well.x = float(key, item)?,
+            "y" => well.y = float(key, item)?,
+            "strength" => well.strength = float(key, item)?,
+            "active" => {
+                well.active = item
+                    .as_bool()
+                    .ok_or_else(|| invalid(key, "true or false"))?;
+            }
+            _ => return Err(unknown(&format!("wells.{key}"))),
+        }
+    }
+    Ok(well)
+}
+
+fn parse_shape(key: &str, item: &Item) -> Result<Shape, ConfigError> {
+    const EXPECTED: &str = "\"rectangle\", \"circle\" or the [x, y] corners of a convex polygon";
+    if let Some(name) = item.as_str() {
+        return match name {
+            "rectangle" | "square" => Ok(Shape::Rectangle),
+            "circle" => Ok(Shape::Circle),
+            _ => Err(invalid(key, EXPECTED)),
+        };
+    }
+    let corners = item
+        .as_array()
+        .ok_or_else(|| invalid(key, EXPECTED))?
+        .iter()
+        .map(|corner| {
+            let mut xy = corner.as_array()?.iter().map(number);
+            match (xy.next(), xy.next(), xy.next()) {
+                (Some(Some(x)), Some(Some(y)), None) => Some([x, y]),
+                _ => None,
+            }
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| invalid(key, EXPECTED))?;
+    let shape = Shape::Polygon(corners);
+    if shape.is_valid() {
+        Ok(shape)
+    } else {
+        Err(invalid(key, EXPECTED))
+    }
+}
+
+fn channel(value: &Value) -> Option<Channel> {
+    let byte = |value: &Value| value.as_integer().and_then(|v| u8::try_from(v).ok());
+    if let Some(range) = value.as_array() {
+        let mut bounds = range.iter().map(byte);
+        return match (bounds.next(), bounds.next(), bounds.next()) {
+            (Some(Some(min)), Some(Some(max)), None) if min <= max => {
+                Some(Channel::Range(min, max))
+            }
+            _ => None,
+        };
+    }
+    byte(value).map(Channel::Fixed)
+}
+
+fn table<'a>(key: &str, item: &'a Item) -> Result<&'a dyn TableLike, ConfigError> {
+    item.as_table_like().ok_or_else(|| invalid(key, "a table"))
+}
+
+fn string<'a>(key: &str, item: &'a Item) -> Result<&'a str, ConfigError> {
+    item.as_str().ok_or_else(|| invalid(key, "a string"))
+}
+
+/// Like [`float`], for a value in an array.
+fn number(value: &Value) -> Option<f32> {
+    value
+        .as_float()
+        .or_else(|| value.as_integer().map(|i| i as f64))
+        .map(|f| f as f32)
+}
+
+/// Accepts integers too, so `arena = 400` works as well as `arena = 400.0`.
+fn float(key: &str, item: &Item) -> Result<f32, ConfigError> {
+    item.as_float()
+        .or_else(|| item.as_integer().map(|i| i as f64))
+        .map(|f| f as f32)
+        .ok_or_else(|| invalid(key, "a number"))
+}
+
+fn positive(key: &str, value: f32) -> Result<f32, ConfigError> {
+    if value > 0. {
+        Ok(value)
+    } else {
+        Err(invalid(key, "a positive number"))
+    }
+}
+
+fn invalid(key: &str, expected: &str) -> ConfigError {
+    ConfigError::Invalid(format!("'{key}' should be {expected}"))
+}
+
+fn unknown(key: &str) -> ConfigError {
+    ConfigError::Invalid(format!("unknown key '{key}'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_keys_use_defaults() {
+        let config = Config::parse(
+            "math = \"fixed\"\ntick_duration = 0.05\n[tuning]\nspeed = 3\n[sandbox]\nmax_operations = 500\n",
+            Path::new(""),
+        )
+        .unwrap();
+        assert_eq!(config.arena, BOX_SIZE);
+        assert_eq!(config.tuning.speed, 3.);
+        assert_eq!(config.tuning.eat_damage, Tuning::default().eat_damage);
+        assert_eq!(config.sandbox.max_operations, 500);
+        assert_eq!(
+            config.sandbox.max_array_size,
+            Sandbox::default().max_array_size
+        );
+        assert_eq!(config.species, Config::default().species);
+        let (sim, _) = config.build().unwrap();
+        assert_eq!(sim.sandbox().max_operations, 500);
+        assert_eq!(sim.math(), Math::Fixed);
+        assert_eq!(sim.tick_duration(), 0.05);
+        assert_eq!(sim.rules(), None);
+    }
+
+    #[test]
+    fn test_rules() {
+        let config = Config::parse(
+            "[rules]\ncondition = \"hill\"\nx = 10\nradius = 20.5\nhold = 300\ntime_limit = 5000\n",
+            Path::new(""),
+        )
+        .unwrap();
+        let rules = Rules::new(Condition::Hill {
+            x: 10.,
+            y: 0.,
+            radius: 20.5,
+            hold: 300,
+        })
+        .time_limit(5000);
+        assert_eq!(config.rules, Some(rules));
+        assert_eq!(config.build().unwrap().0.rules(), Some(&rules));
+
+        let config = Config::parse(
+            "[rules]\ncondition = \"biomass\"\nticks = 40\n[islands]\ncount = 3\nmigration = 0.25",
+            Path::new(""),
+        )
+        .unwrap();
+        assert_eq!(
+            config.rules,
+            Some(Rules::new(Condition::Biomass { ticks: 40 }))
+        );
+        assert_eq!(
+            config.islands,
+            Some(Islands {
+                count: 3,
+                migration: 0.25
+            })
+        );
+    }
+
+    #[test]
+    fn test_arena_size() {
+        let config = Config::parse("arena = [200, 10.5]", Path::new("")).unwrap();
+        assert_eq!((config.arena, config.arena_height), (200., Some(10.5)));
+        let (sim, _) = config.build().unwrap();
+        assert_eq!(sim.arena_size(), [200., 10.5]);
+        assert!(sim.microbes().all(|m| m.y.abs() <= 10.5));
+
+        let config = Config::parse("arena = 30", Path::new("")).unwrap();
+        assert_eq!(config.arena_height, None);
+        assert_eq!(config.build().unwrap().0.arena_size(), [30., 30.]);
+    }
+
+    #[test]
+    fn test_shape() {
+        let parse = |text| Config::parse(text, Path::new("")).unwrap().shape;
+        assert_eq!(parse("arena = 10"), Shape::Rectangle);
+        assert_eq!(parse("shape = \"circle\""), Shape::Circle);
+        let triangle = parse("shape = [[0, 5], [5, -5.5], [-5, -5]]");
+        assert_eq!(
+            triangle,
+            Shape::Polygon(vec![[0., 5.], [5., -5.5], [-5., -5.]])
+        );
+
+        let config = Config::parse(
+            "arena = 20
+shape = \"circle\"",
+            Path::new(""),
+        )
+        .unwrap();
+        let (sim, _) = config.with_population(100).build().unwrap();
+        assert_eq!(sim.shape(), &Shape::Circle);
+        assert!(sim.microbes().all(|m| m.x * m.x + m.y * m.y <= 400.));
+    }
+
+    #[test]
+    fn test_climate() {
+        let parse = |text| Config::parse(text, Path::new("")).unwrap().climate;
+        assert_eq!(parse("arena = 10"), Climate::Uniform);
+        assert_eq!(parse("climate = \"hot_center\""), Climate::HotCenter);
+        assert_eq!(parse("climate = { gradient = 2 }"), Climate::Gradient(2.));
+        assert_eq!(parse("[climate]\ngradient = -1.5"), Climate::Gradient(-1.5));
+
+        let config = Config::parse("climate = \"cold_center\"", Path::new("")).unwrap();
+        let (sim, _) = config.build().unwrap();
+        assert_eq!(sim.climate(), Climate::ColdCenter);
+    }
+
+    #[test]
+    fn test_disruptions() {
+        let config = Config::parse(
+            "[[disruptions]]\ntick = 10\nkind = \"meteor\"\nx = 1\ny = -2\nradius = 30\n\n\
+             [[disruptions]]\ntick = 20\nkind = \"famine\"\nticks = 5\n\n\
+             [[disruptions]]\ntick = 30\nkind = \"bloom\"\ncount = 8",
+            Path::new(""),
+        )
+        .unwrap();
+        let expected = [
+            Scheduled::new(
+                10,
+                Disruption::Meteor {
+                    x: 1.,
+                    y: -2.,
+                    radius: 30.,
+                },
+            ),
+            Scheduled::new(20, Disruption::Famine { ticks: 5 }),
+            Scheduled::new(30, Disruption::Bloom { count: 8 }),
+        ];
+        assert_eq!(config.disruptions, expected);
+        let (sim, _) = config.build().unwrap();
+        assert_eq!(sim.disruptions(), expected);
+    }
+
+    #[test]
+    fn test_wells() {
+        let config = Config::parse(
+            "[[wells]]\nx = 10\ny = -5.5\nstrength = 20\n\n[[wells]]\nstrength = -3\nactive = false",
+            Path::new(""),
+        )
+        .unwrap();
+        let off = Well {
+            active: false,
+            ..Well::new(0., 0., -3.)
+        };
+        assert_eq!(config.wells, [Well::new(10., -5.5, 20.), off]);
+        let (sim, _) = config.build().unwrap();
+        assert_eq!(sim.wells(), config.wells);
+    }
+
+    #[test]
+    fn test_energy_cap() {
+        let parse = |text| Config::parse(text, Path::new("")).unwrap().energy_cap;
+        assert_eq!(parse("arena = 10"), None);
+        assert_eq!(
+            parse("[energy_cap]\nmax = 150"),
+            Some(EnergyCap::new(150., Overflow::Wasted))
+        );
+        let fat = parse("[energy_cap]\nmax = 120\noverflow = \"fat\"\nfat_burn = 0.5").unwrap();
+        assert_eq!((fat.overflow, fat.fat_burn), (Overflow::Fat, 0.5));
+
+        let config = Config::parse("[energy_cap]\nmax = 150", Path::new("")).unwrap();
+        let (sim, _) = config.build().unwrap();
+        assert_eq!(sim.energy_cap(), config.energy_cap);
+    }
+
+    #[test]
+    fn test_standoff() {
+        let parse = |text| Config::parse(text, Path::new("")).unwrap().standoff;
+        assert_eq!(parse("arena = 10"), Standoff::Stronger);
+        assert_eq!(parse("standoff = \"both_hurt\""), Standoff::BothHurt);
+        assert_eq!(parse("standoff = \"trade\""), Standoff::Trade);
+
+        let config = Config::parse("standoff = \"random\"", Path::new("")).unwrap();
+        let (sim, _) = config.build().unwrap();
+        assert_eq!(sim.standoff(), Standoff::Random);
+    }
+
+    #[test]
+    fn test_population_cap() {
+        let parse = |text| Config::parse(text, Path::new("")).unwrap().population_cap;
+        assert_eq!(parse("arena = 10"), None);
+        assert_eq!(
+            parse("[population_cap]\nmax = 500"),
+            Some(PopulationCap::new(500, Cull::Oldest))
+        );
+        assert_eq!(
+            parse("[population_cap]\nmax = 20\ncull = \"weakest\""),
+            Some(PopulationCap::new(20, Cull::Weakest))
+        );
+
+        let config = Config::parse("[population_cap]\nmax = 50", Path::new("")).unwrap();
+        let (sim, _) = config.build().unwrap();
+        assert_eq!(sim.population_cap(), config.population_cap);
+    }
+
+    #[test]
+    fn test_trickle() {
+        let parse = |text| Config::parse(text, Path::new("")).unwrap().trickle;
+        assert_eq!(parse("arena = 10"), None);
+        assert_eq!(parse("[trickle]\nevery = 5"), Some(Trickle::new(5, 100)));
+        let config = Config::parse("[trickle]\nevery = 5\nmax = 2", Path::new("")).unwrap();
+        let (sim, ids) = config.build().unwrap();
+        assert_eq!(sim.trickle(), Some(Trickle::new(5, 2)));
+        let wanderers = sim.wanderers().unwrap();
+        assert!(!ids.contains(&wanderers));
+        assert_eq!(sim.species_name(wanderers), "wanderers");
+    }
+
+    #[test]
+    fn test_species() {
+        let dir = std::env::temp_dir().join(format!("microswarm-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("mine.rhai"), "new_controls()").unwrap();
+        let config = Config::parse(
+            r#"
+            arena = 50.0
+
+            [[species]]
+            name = "mine"
+            script = "mine.rhai"
+            count = 7
+            color = [10, [20, 30], 40]
+            spiky = true
+            jaws = { width = 1.5 }
+            eyes = { focus = 2.0 }
+            mutation = { rate = 0.5, magnitude = 0.25 }
+            cooldown = { ticks = 40, speed = 0.5 }
+            castes = { worker = {}, soldier = { builtin = "hunter", spiky = false, jaws = { reach = 2.0 } } }
+
+            [[species]]
+            name = "hunters"
+            builtin = "hunter"
+            producer = true
+            "#,
+            &dir,
+        );
+        fs::remove_dir_all(&dir).unwrap();
+        let config = config.unwrap();
+
+        assert_eq!(config.species.len(), 2);
+        assert_eq!(config.species[0].script, "new_controls()");
+        assert_eq!(
+            config.species[0].color,
+            [
+                Channel::Fixed(10),
+                Channel::Range(20, 30),
+                Channel::Fixed(40)
+            ]
+        );
+        assert_eq!(config.species[1].count, 0);
+
+        let (sim, ids) = config.build().unwrap();
+        assert_eq!(sim.arena(), 50.);
+        assert_eq!(ids.len(), 2);
+        assert_eq!(sim.species_name(ids[1]), "hunters");
+        assert!(!sim.is_producer(ids[0]));
+        assert!(sim.is_producer(ids[1]));
+        assert!(sim.world().microbes().all(|m| m.spiky()));
+        assert!(sim
+            .world()
+            .microbes()
+            .all(|m| m.jaws() == Jaws::new(1.5, 1.)));
+        assert!(sim.world().microbes().all(|m| m.eyes() == Eyes::new(2.)));
+        assert_eq!(sim.mutation(ids[0]), Mutation::new(0.5, 0.25));
+        assert!(sim.mutation(ids[1]).is_none());
+        assert_eq!(sim.cooldown(ids[0]), Cooldown::new(40, 0.5));
+        assert!(sim.cooldown(ids[1]).is_none());
+        let castes = sim.castes(ids[0]);
+        assert_eq!(castes[0], Caste::new("worker"));
+        assert_eq!(castes[1].name, "soldier");
+        assert_eq!(castes[1].script, scripts::builtin("hunter"));
+        assert_eq!(castes[1].spiky, Some(false));
+        assert_eq!(castes[1].jaws, Some(Jaws::new(1., 2.)));
+        assert!(sim.castes(ids[1]).is_empty());
+        let snapshot = sim.snapshot();
+        assert_eq!(snapshot.microbes.len(), 7);
+        assert!(snapshot.microbes.iter().all(|m| m.species == ids[0]));
+    }
+
+    #[test]
+    fn test_invalid_configs() {
+        for text in [
+            "arena = -1",
+            "arena = \"big\"",
+            "arena = [10]",
+            "arena = [10, -10]",
+            "arena = [10, 10, 10]",
+            "speed = 2",
+            "math = \"exact\"",
+            "shape = \"hexagon\"",
+            "shape = [[0, 0], [1, 1]]",
+            "shape = [[0, 0], [10, 10], [0, 3], [-10, 10]]",
+            "shape = [[0, 0, 0], [1, 1, 1], [1, 0, 1]]",
+            "climate = \"tropical\"",
+            "climate = 3",
+            "standoff = \"draw\"",
+            "standoff = 1",
+            "climate = { gradient = \"north\" }",
+            "climate = { gradient = 1, center = 2 }",
+            "wells = 3",
+            "[[wells]]\nmass = 1",
+            "disruptions = 3",
+            "[[disruptions]]\nkind = \"famine\"\nticks = 5",
+            "[[disruptions]]\ntick = 1\nkind = \"flood\"",
+            "[[disruptions]]\ntick = 1\nkind = \"famine\"",
+            "[[disruptions]]\ntick = 1\nkind = \"bloom\"\ncount = 5\nradius = 3",
+            "[[disruptions]]\ntick = 1\nkind = \"meteor\"\nx = 0\ny = 0\nradius = -3",
+            "[[wells]]\nactive = \"yes\"",
+            "[trickle]\nevery = 0",
+            "[energy_cap]\noverflow = \"fat\"",
+            "[energy_cap]\nmax = 100\noverflow = \"size\"",
+            "[energy_cap]\nmax = 100\nfat_burn = -1",
+            "[population_cap]\ncull = \"oldest\"",
+            "[population_cap]\nmax = 0",
+            "[population_cap]\nmax = 10\ncull = \"youngest\"",
+            "[trickle]\nrate = 5",
+            "[[species]]\nname = \"a\"\nbuiltin = \"hunter\"\nmutation = 0.5",
+            "[[species]]\nname = \"a\"\nbuiltin = \"hunter\"\nmutation = { rate = 2 }",
+            "[[species]]\nname = \"a\"\nbuiltin = \"hunter\"\nmutation = { speed = 1 }",
+            "[[species]]\nname = \"a\"\nbuiltin = \"hunter\"\ncooldown = 10",
+            "[[species]]\nname = \"a\"\nbuiltin = \"hunter\"\ncastes = [\"soldier\"]",
+            "[[species]]\nname = \"a\"\nbuiltin = \"hunter\"\ncastes = { soldier = { wings = true } }",
+            "[[species]]\nname = \"a\"\nbuiltin = \"hunter\"\ncastes = { soldier = { builtin = \"nope\" } }",
+            "[[species]]\nname = \"a\"\nbuiltin = \"hunter\"\njaws = { width = 0 }",
+            "[[species]]\nname = \"a\"\nbuiltin = \"hunter\"\njaws = { bite = 2 }",
+            "[[species]]\nname = \"a\"\nbuiltin = \"hunter\"\neyes = { focus = -1 }",
+            "[[species]]\nname = \"a\"\nbuiltin = \"hunter\"\neyes = 2",
+            "[[species]]\nname = \"a\"\nbuiltin = \"hunter\"\ncooldown = { ticks = -1 }",
+            "[[species]]\nname = \"a\"\nbuiltin = \"hunter\"\ncooldown = { speed = 2 }",
+            "[rules]\ncondition = \"fastest\"",
+            "[islands]\ncount = 0",
+            "[islands]\nmigration = 2",
+            "[islands]\nbridges = 2",
+            "[rules]\ncondition = \"biomass\"",
+            "[rules]\ncondition = \"hill\"\nradius = 5",
+            "[rules]\ncondition = \"hill\"\nradius = -5\nhold = 9",
+            "[rules]\ncondition = \"last_standing\"\nlimit = 9",
+            "[tuning]\nsped = 2",
+            "[sandbox]\nmax_memory = 2",
+            "[sandbox]\nmax_operations = -1",
+            "[[species]]\nbuiltin = \"hunter\"",
+            "[[species]]\nname = \"x\"",
+            "[[species]]\nname = \"x\"\nbuiltin = \"nope\"",
+            "[[species]]\nname = \"x\"\nbuiltin = \"hunter\"\ncolor = [1, 2]",
+            "[[species]]\nname = \"x\"\nbuiltin = \"hunter\"\ncolor = [1, 2, [9, 3]]",
+            "[[species]]\nname = \"x\"\nbuiltin = \"hunter\"\ncount = -4",
+            "[[species]]\nname = \"x\"\nbuiltin = \"hunter\"\nremote = \"localhost:1\"",
+            "[[species]]\nname = \"x\"\nbuiltin = \"hunter\"\ntimeout_ms = 5",
+            "arena = ",
+        ] {
+            assert!(Config::parse(text, Path::new("")).is_err(), "{text}");
+        }
+    }
+
+    #[cfg(feature = "net")]
+    #[test]
+    fn test_remote_species() {
+        let config = Config::parse(
+            "[[species]]\nname = \"agent\"\nremote = \"127.0.0.1:7000\"\ntimeout_ms = 20\ncount = 2",
+            Path::new(""),
+        )
+        .unwrap();
+        assert_eq!(
+            config.species[0].remote,
+            Some(Remote {
+                addr: "127.0.0.1:7000".to_owned(),
+                timeout: Duration::from_millis(20),
+            })
+        );
+        let (sim, _) = config.build().unwrap();
+        assert!(format!("{:?}", sim.world()).contains("Brain"));
+    }
+
+    #[test]
+    fn test_seeded_builds_match() {
+        let config = Config {
+            seed: Some(3),
+            ..Config::default()
+        };
+        let (a, _) = config.build().unwrap();
+        let (b, _) = config.build().unwrap();
+        assert_eq!(a.seed(), 3);
+        assert_eq!(a.snapshot(), b.snapshot());
+    }
+
+    #[test]
+    fn test_with_population() {
+        let counts = Config::default()
+            .with_population(10)
+            .species
+            .iter()
+            .map(|s| s.count)
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [3, 3, 2, 2]);
+    }
+}
@@ -0,0 +1,353 @@
+//! A line-based control socket for steering headless runs while they go.
+//!
+//! [`Control::serve`] accepts connections from a background thread, for
+//! example `nc 127.0.0.1 7100`. Each line is one command, answered with one
+//! line starting with `ok` or `error`:
+//!
+//! | command               | effect                                           |
+//! |-----------------------|--------------------------------------------------|
+//! | `pause`               | stop stepping                                    |
+//! | `resume`              | carry on stepping                                |
+//! | `step N`              | step `N` ticks, then pause; answers when done    |
+//! | `spawn SPECIES X Y`   | spawn a microbe, answering with its id           |
+//! | `stats`               | tick, microbes and population per species        |
+//! | `save PATH`           | save the world, as with `--save`                 |
+//! | `help`                | list the commands                                |
+//!
+//! Species are named as in the run's config, or by the start of their id.
+//! Commands only take effect between ticks, when the run calls
+//! [`Control::poll`].
+
+use ecolor::Color32;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::simulation::Simulation;
+
+/// How long a paused [`Control::poll`] waits for a command before returning.
+const PAUSED_WAIT: Duration = Duration::from_millis(100);
+
+const HELP: &str = "commands: pause, resume, step N, spawn SPECIES X Y, stats, save PATH, help";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Pause,
+    Resume,
+    Step(u64),
+    Spawn { species: String, x: f32, y: f32 },
+    Stats,
+    Save(PathBuf),
+    Help,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let mut arg = |name: &str| {
+            words
+                .next()
+                .ok_or_else(|| format!("{command} needs {name}"))
+        };
+        let parsed = match command {
+            "pause" => Command::Pause,
+            "resume" => Command::Resume,
+            "step" => Command::Step(number("N", arg("N")?)?),
+            "spawn" => Command::Spawn {
+                species: arg("SPECIES")?.to_owned(),
+                x: number("X", arg("X")?)?,
+                y: number("Y", arg("Y")?)?,
+            },
+            "stats" => Command::Stats,
+            "save" => Command::Save(PathBuf::from(arg("PATH")?)),
+            "help" => Command::Help,
+            _ => return Err(format!("unknown command '{command}', {HELP}")),
+        };
+        match words.next() {
+            Some(extra) => Err(format!("unexpected '{extra}' after {command}")),
+            None => Ok(parsed),
+        }
+    }
+}
+
+fn number<T: FromStr>(name: &str, text: &str) -> Result<T, String> {
+    text.parse()
+        .map_err(|_| format!("{name} should be a number, got '{text}'"))
+}
+
+/// A command from a client, with where to send the answer.
+struct Request {
+    command: Command,
+    reply: Sender<String>,
+}
+
+/// Accepts commands over TCP for a running simulation. Call
+/// [`Control::poll`] before every step.
+pub struct Control {
+    requests: Receiver<Request>,
+    addr: SocketAddr,
+    paused: bool,
+    /// Ticks left to step before pausing, and who asked.
+    stepping: Option<(u64, Sender<String>)>,
+}
+
+impl Control {
+    /// Listens on `addr`, such as `127.0.0.1:7100`. Anyone who can connect
+    /// can save files as this process, so keep it off public interfaces.
+    pub fn serve(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let (sender, requests) = mpsc::channel();
+        thread::Builder::new()
+            .name("control".to_owned())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let sender = sender.clone();
+                    let spawned = thread::Builder::new()
+                        .name("control client".to_owned())
+                        .spawn(move || {
+                            if let Err(error) = converse(stream, &sender) {
+                                tracing::debug!(%error, "control connection failed");
+                            }
+                        });
+                    if let Err(error) = spawned {
+                        tracing::warn!(%error, "couldn't start a control connection");
+                    }
+                }
+            })?;
+        Ok(Self {
+            requests,
+            addr,
+            paused: false,
+            stepping: None,
+        })
+    }
+
+    /// The address actually listened on, useful when binding port 0.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Carries out the commands that have arrived and says whether to step
+    /// now. While paused it waits briefly for a command first, so calling it
+    /// in a loop doesn't spin. `species` names the species for `spawn` and
+    /// `stats`.
+    pub fn poll(&mut self, sim: &mut Simulation, species: &[(String, Uuid)]) -> bool {
+        if let Some((left, reply)) = self.stepping.take() {
+            match left {
+                0 => {
+                    let _ = reply.send(format!("ok paused at tick {}", sim.world().tick()));
+                }
+                left => self.stepping = Some((left, reply)),
+            }
+        }
+        if self.paused && self.stepping.is_none() {
+            match self.requests.recv_timeout(PAUSED_WAIT) {
+                Ok(request) => self.handle(request, sim, species),
+                Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => {}
+            }
+        }
+        while let Ok(request) = self.requests.try_recv() {
+            self.handle(request, sim, species);
+        }
+        match &mut self.stepping {
+            Some((left, _)) => {
+                *left -= 1;
+                true
+            }
+            None => !self.paused,
+        }
+    }
+
+    fn handle(&mut self, request: Request, sim: &mut Simulation, species: &[(String, Uuid)]) {
+        let reply = match request.command {
+            Command::Pause | Command::Step(0) => {
+                self.paused = true;
+                self.cancel_steps(sim);
+                format!("ok paused at tick {}", sim.world().tick())
+            }
+            Command::Resume => {
+                self.paused = false;
+                self.cancel_steps(sim);
+                "ok".to_owned()
+            }
+            Command::Step(ticks) => {
+                self.paused = true;
+                self.cancel_steps(sim);
+                self.stepping = Some((ticks, request.reply));
+                return;
+            }
+            Command::Spawn {
+                species: name,
+                x,
+                y,
+            } => match find(species, &name) {
+                Some(id) => {
+                    // New microbes look like the rest of their species.
+                    let color = sim
+                        .microbes()
+                        .find(|m| m.species == id)
+                        .map_or(Color32::WHITE, |m| m.color);
+                    format!("ok {}", sim.spawn(id, x, y, 0., color))
+                }
+                None => format!("error unknown species '{name}'"),
+            },
+            Command::Stats => {
+                let mut line = format!(
+                    "ok tick={} microbes={}",
+                    sim.world().tick(),
+                    sim.microbes().count()
+                );
+                for (name, id) in species {
+                    let count = sim.microbes().filter(|m| m.species == *id).count();
+                    let _ = write!(line, " {}={count}", name.replace(char::is_whitespace, "_"));
+                }
+                line
+            }
+            Command::Save(path) => match sim.save(&path) {
+                Ok(()) => format!("ok saved {}", path.display()),
+                Err(error) => format!("error {}: {error}", path.display()),
+            },
+            Command::Help => format!("ok {HELP}"),
+        };
+        let _ = request.reply.send(reply);
+    }
+
+    /// Answers an unfinished `step` that another command interrupted.
+    fn cancel_steps(&mut self, sim: &Simulation) {
+        if let Some((_, reply)) = self.stepping.take() {
+            let _ = reply.send(format!("error interrupted at tick {}", sim.world().tick()));
+        }
+    }
+}
+
+fn find(species: &[(String, Uuid)], name: &str) -> Option<Uuid> {
+    species
+        .iter()
+        .find(|(n, _)| n == name)
+        .or_else(|| {
+            species
+                .iter()
+                .find(|(_, id)| id.to_string().starts_with(name))
+        })
+        .map(|(_, id)| *id)
+}
+
+/// Answers one client's commands until it disconnects.
+fn converse(stream: TcpStream, requests: &Sender<Request>) -> io::Result<()> {
+    let mut out = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let answer = match line.parse() {
+            Ok(command) => {
+                let (reply, answer) = mpsc::channel();
+                if requests.send(Request { command, reply }).is_err() {
+                    // The run is over.
+                    return Ok(());
+                }
+                answer
+                    .recv()
+                    .unwrap_or_else(|_| "error run stopped".to_owned())
+            }
+            Err(error) => format!("error {error}"),
+        };
+        writeln!(out, "{answer}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("step 100".parse(), Ok(Command::Step(100)));
+        assert_eq!(
+            " spawn hunter -5 2.5 ".parse(),
+            Ok(Command::Spawn {
+                species: "hunter".to_owned(),
+                x: -5.,
+                y: 2.5
+            })
+        );
+        assert_eq!("save a.json".parse(), Ok(Command::Save("a.json".into())));
+        assert!("step".parse::<Command>().is_err());
+        assert!("step many".parse::<Command>().is_err());
+        assert!("pause now".parse::<Command>().is_err());
+        assert!("jump".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn test_controls_a_run() {
+        let mut control = Control::serve("127.0.0.1:0").unwrap();
+        let mut sim = Simulation::new(100.).unwrap();
+        let idle = sim.add_species("new_controls()");
+        let species = [("idle".to_owned(), idle)];
+        let path = std::env::temp_dir().join(format!("control-{}.json", std::process::id()));
+
+        let addr = control.addr();
+        let save = format!("save {}", path.display());
+        let client = thread::spawn(move || {
+            let stream = TcpStream::connect(addr).unwrap();
+            let mut out = stream.try_clone().unwrap();
+            let mut lines = BufReader::new(stream).lines();
+            let mut send = |command: &str| {
+                writeln!(out, "{command}").unwrap();
+                lines.next().unwrap().unwrap()
+            };
+            [
+                send("pause"),
+                send("spawn idle 1 2"),
+                send("spawn wolf 1 2"),
+                send("step 3"),
+                send("stats"),
+                send(&save),
+                send("fly"),
+                send("resume"),
+            ]
+        });
+        while !client.is_finished() {
+            if control.poll(&mut sim, &species) {
+                sim.step().unwrap();
+            }
+        }
+        let replies = client.join().unwrap();
+        assert!(replies[0].starts_with("ok paused at tick "));
+        let paused_at = replies[0]
+            .rsplit(' ')
+            .next()
+            .unwrap()
+            .parse::<u64>()
+            .unwrap();
+        assert!(replies[1].starts_with("ok "));
+        assert_eq!(replies[2], "error unknown species 'wolf'");
+        assert_eq!(replies[3], format!("ok paused at tick {}", paused_at + 3));
+        assert_eq!(
+            replies[4],
+            format!("ok tick={} microbes=1 idle=1", paused_at + 3)
+        );
+        assert!(replies[5].starts_with("ok saved "));
+        assert!(Simulation::load(&path).is_ok());
+        assert!(replies[6].starts_with("error unknown command 'fly'"));
+        assert_eq!(replies[7], "ok");
+        assert!(!control.paused());
+        let _ = std::fs::remove_file(path);
+    }
+}
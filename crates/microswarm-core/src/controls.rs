@@ -0,0 +1,41 @@
+use rhai::{CustomType, TypeBuilder};
+use serde::{Deserialize, Serialize};
+
+/// What a script asks its microbe to do this tick.
+#[derive(Debug, Clone, Default, PartialEq, Eq, CustomType, Serialize, Deserialize)]
+#[rhai_type(extra = Self::build_extra)]
+#[serde(default)]
+pub struct Controls {
+    pub right: bool,
+    pub left: bool,
+    pub forward: bool,
+    pub back: bool,
+    pub eat: bool,
+    /// Hides the microbe from everyone's far senses next tick, at
+    /// [`Tuning::cloak_energy`] a tick.
+    ///
+    /// [`Tuning::cloak_energy`]: crate::Tuning::cloak_energy
+    pub cloak: bool,
+    /// The caste its offspring are born into if it splits this tick. Empty
+    /// for its own. See [`crate::caste`].
+    pub spawn_as: String,
+}
+
+impl Controls {
+    pub fn new() -> Self {
+        Self {
+            right: false,
+            left: false,
+            forward: false,
+            back: false,
+            eat: false,
+            cloak: false,
+            spawn_as: String::new(),
+        }
+    }
+    fn build_extra(builder: &mut TypeBuilder<Self>) {
+        builder
+            .with_name("Controls")
+            .with_fn("new_controls", Self::new);
+    }
+}
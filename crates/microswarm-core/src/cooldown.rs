@@ -0,0 +1,57 @@
+//! A rest after splitting.
+//!
+//! Without a [`Cooldown`], a microbe that lands one big kill can split on
+//! one tick, eat again and split on the next, setting off a bloom. With
+//! one, a microbe that has just split can't split again for `ticks` ticks,
+//! and moves at `speed` times its usual pace until then. Offspring are
+//! born ready.
+//!
+//! ```toml
+//! [[species]]
+//! name = "hunter"
+//! builtin = "hunter"
+//! cooldown = { ticks = 50, speed = 0.5 }
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Cooldown {
+    /// Ticks after splitting before a microbe can split again.
+    pub ticks: u64,
+    /// Pace while cooling down, as a share of the usual one.
+    pub speed: f32,
+}
+
+impl Default for Cooldown {
+    fn default() -> Self {
+        Self {
+            ticks: 0,
+            speed: 1.,
+        }
+    }
+}
+
+impl Cooldown {
+    pub fn new(ticks: u64, speed: f32) -> Self {
+        Self { ticks, speed }
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.ticks == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serde() {
+        let cooldown = serde_json::from_str::<Cooldown>(r#"{"ticks": 30}"#).unwrap();
+        assert_eq!(cooldown, Cooldown::new(30, 1.));
+        assert!(!cooldown.is_none());
+        assert!(Cooldown::default().is_none());
+    }
+}
@@ -0,0 +1,90 @@
+//! Disasters and windfalls at set ticks.
+//!
+//! A world left alone settles down. Scheduling [`Disruption`]s shakes it up
+//! again, to see how its species cope and recover:
+//!
+//! ```toml
+//! [[disruptions]]
+//! tick = 1000
+//! kind = "meteor"           # kills everything within radius of x, y
+//! x = 0.0
+//! y = 0.0
+//! radius = 80.0
+//!
+//! [[disruptions]]
+//! tick = 2000
+//! kind = "famine"           # halves light and the trickle for a while
+//! ticks = 500
+//!
+//! [[disruptions]]
+//! tick = 3000
+//! kind = "bloom"            # spawns wanderers all over the arena
+//! count = 100
+//! ```
+//!
+//! Each one happens at the end of its tick, after the microbes have moved,
+//! and shows up in the tick's events as [`Event::Disrupted`]. Plugins can
+//! schedule more as the world runs with [`World::schedule`], including for
+//! the tick they're called on.
+//!
+//! [`Event::Disrupted`]: crate::Event::Disrupted
+//! [`World::schedule`]: crate::World::schedule
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Disruption {
+    /// Kills every microbe within `radius` of (`x`, `y`).
+    Meteor { x: f32, y: f32, radius: f32 },
+    /// Halves the light producers feed on and how often wanderers trickle
+    /// in, for `ticks` ticks.
+    Famine { ticks: u64 },
+    /// Spawns `count` wanderers at random points in the arena, as a
+    /// [`crate::trickle`] does at its edge.
+    Bloom { count: usize },
+}
+
+/// A disruption and the tick it happens on.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Scheduled {
+    pub tick: u64,
+    #[serde(flatten)]
+    pub disruption: Disruption,
+}
+
+impl Scheduled {
+    pub fn new(tick: u64, disruption: Disruption) -> Self {
+        Self { tick, disruption }
+    }
+}
+
+impl fmt::Display for Disruption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Disruption::Meteor { x, y, radius } => {
+                write!(f, "meteor struck ({x:.0}, {y:.0}), radius {radius:.0}")
+            }
+            Disruption::Famine { ticks } => write!(f, "famine for {ticks} ticks"),
+            Disruption::Bloom { count } => write!(f, "bloom of {count} wanderers"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serde() {
+        let scheduled = Scheduled::new(5, Disruption::Famine { ticks: 10 });
+        let json = serde_json::to_string(&scheduled).unwrap();
+        assert_eq!(json, r#"{"tick":5,"kind":"famine","ticks":10}"#);
+        assert_eq!(serde_json::from_str::<Scheduled>(&json).unwrap(), scheduled);
+        assert_eq!(
+            Disruption::Bloom { count: 3 }.to_string(),
+            "bloom of 3 wanderers"
+        );
+    }
+}
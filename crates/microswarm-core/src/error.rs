@@ -0,0 +1,23 @@
+use rhai::EvalAltResult;
+use uuid::Uuid;
+
+use crate::config::ConfigError;
+
+/// Why the simulation couldn't be built or stepped.
+#[derive(Debug, thiserror::Error)]
+pub enum SimError {
+    /// A microbe belongs to a species the world has no script for.
+    #[error("no script for species {0}")]
+    MissingScript(Uuid),
+    /// A species script failed while deciding a microbe's controls. Scripts
+    /// that only go over the [`crate::sandbox`] limits don't fail the step;
+    /// their microbes sit still instead.
+    #[error("script for species {species} failed: {error}")]
+    Script {
+        species: Uuid,
+        #[source]
+        error: Box<EvalAltResult>,
+    },
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+}
@@ -0,0 +1,406 @@
+//! What happens in the world, as it happens.
+//!
+//! Every [`World::update`] emits [`Event`]s in the order they happened. They
+//! are collected in the step's [`StepReport`], and passed to subscribers
+//! added with [`Simulation::subscribe`] as soon as the step finishes.
+//!
+//! [`EventLog`] writes the events to a file as JSON Lines, one object per
+//! event with an `event` field saying which:
+//!
+//! ```json
+//! {"event":"born","tick":12,"id":"…","species":"hunter","parent":"…","x":3.5,"y":-1.0}
+//! {"event":"ate","tick":12,"eater":"…","eater_species":"hunter","victim":"…","victim_species":"herbivore"}
+//! {"event":"died","tick":12,"id":"…","species":"herbivore","x":4.0,"y":0.5,"lifespan":80,"cause":"eaten","by":["hunter"]}
+//! {"event":"extinct","tick":30,"species":"herbivore","top_predator":"hunter"}
+//! {"event":"disrupted","tick":40,"kind":"famine","ticks":100}
+//! ```
+//!
+//! `cause` is `eaten`, `starved`, `culled` or `struck`, and `by` lists the species of every bite
+//! that killed it. Species are named as in [`EventLog::name`], or by the
+//! start of their id, and microbes by their full id.
+//!
+//! [`World::update`]: crate::World::update
+//! [`Simulation::subscribe`]: crate::Simulation::subscribe
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::disruption::Disruption;
+use crate::{Death, MicrobeState};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// A microbe split off from `parent`.
+    MicrobeBorn { microbe: MicrobeState, parent: Uuid },
+    /// `eater` bit `victim`. A victim can be bitten by several microbes in
+    /// one tick.
+    MicrobeAte {
+        eater: Uuid,
+        eater_species: Uuid,
+        victim: Uuid,
+        victim_species: Uuid,
+    },
+    /// A microbe died, as told by `cause`. `microbe` is its last state.
+    MicrobeDied {
+        microbe: MicrobeState,
+        lifespan: u64,
+        cause: Cause,
+    },
+    /// The last member of `species` died.
+    SpeciesExtinct {
+        species: Uuid,
+        /// The species that has killed the most of it over the whole run.
+        top_predator: Option<Uuid>,
+    },
+    /// A scheduled [`crate::disruption`] happened.
+    Disrupted { disruption: Disruption },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cause {
+    Starved,
+    /// Eaten by microbes of these species, one entry per bite.
+    Eaten {
+        by: Vec<Uuid>,
+    },
+    /// Removed to keep the world under its [`crate::population`] cap.
+    Culled,
+    /// Killed by a meteor. See [`crate::disruption`].
+    Struck,
+}
+
+/// What happened during the most recent [`World::update`].
+///
+/// [`World::update`]: crate::World::update
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StepReport {
+    pub tick: u64,
+    pub events: Vec<Event>,
+}
+
+impl StepReport {
+    pub fn births(&self) -> usize {
+        self.events
+            .iter()
+            .filter(|e| matches!(e, Event::MicrobeBorn { .. }))
+            .count()
+    }
+
+    pub fn deaths(&self) -> impl Iterator<Item = Death> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            Event::MicrobeDied {
+                microbe,
+                lifespan,
+                cause,
+            } => Some(Death {
+                species: microbe.species,
+                lifespan: *lifespan,
+                eaten: matches!(cause, Cause::Eaten { .. }),
+            }),
+            _ => None,
+        })
+    }
+
+    /// Deaths that were caused by being eaten.
+    pub fn kills(&self) -> usize {
+        self.deaths().filter(|d| d.eaten).count()
+    }
+
+    pub fn extinctions(&self) -> impl Iterator<Item = Uuid> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            Event::SpeciesExtinct { species, .. } => Some(*species),
+            _ => None,
+        })
+    }
+}
+
+type Subscriber = Box<dyn FnMut(u64, &Event)>;
+
+/// Subscribers to a world's events.
+#[derive(Default)]
+pub(crate) struct EventBus {
+    subscribers: Vec<Subscriber>,
+}
+
+impl EventBus {
+    pub(crate) fn subscribe(&mut self, subscriber: impl FnMut(u64, &Event) + 'static) {
+        self.subscribers.push(Box::new(subscriber));
+    }
+
+    pub(crate) fn publish(&mut self, report: &StepReport) {
+        for subscriber in &mut self.subscribers {
+            for event in &report.events {
+                subscriber(report.tick, event);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EventBus({} subscribers)", self.subscribers.len())
+    }
+}
+
+/// One line of an [`EventLog`].
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Line<'a> {
+    Born {
+        tick: u64,
+        id: Uuid,
+        species: &'a str,
+        parent: Uuid,
+        x: f32,
+        y: f32,
+    },
+    Ate {
+        tick: u64,
+        eater: Uuid,
+        eater_species: &'a str,
+        victim: Uuid,
+        victim_species: &'a str,
+    },
+    Died {
+        tick: u64,
+        id: Uuid,
+        species: &'a str,
+        x: f32,
+        y: f32,
+        lifespan: u64,
+        cause: &'static str,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        by: Vec<&'a str>,
+    },
+    Extinct {
+        tick: u64,
+        species: &'a str,
+        top_predator: Option<&'a str>,
+    },
+    Disrupted {
+        tick: u64,
+        #[serde(flatten)]
+        disruption: Disruption,
+    },
+}
+
+/// Writes every event of a run as JSON Lines.
+pub struct EventLog<W: Write> {
+    out: W,
+    names: HashMap<Uuid, String>,
+}
+
+impl EventLog<BufWriter<File>> {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write> EventLog<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            names: HashMap::new(),
+        }
+    }
+
+    /// Names `species` in the output instead of showing its id.
+    pub fn name(&mut self, species: Uuid, name: impl Into<String>) {
+        self.names.insert(species, name.into());
+    }
+
+    /// Writes a line for each of the step's events.
+    pub fn record(&mut self, report: &StepReport) -> io::Result<()> {
+        // Unnamed species go by the start of their id from now on.
+        for species in report.events.iter().flat_map(species_of) {
+            self.names
+                .entry(species)
+                .or_insert_with(|| species.to_string()[..8].to_owned());
+        }
+        let Self { out, names } = self;
+        let name = |species: &Uuid| names[species].as_str();
+        let tick = report.tick;
+        for event in &report.events {
+            let line = match event {
+                Event::MicrobeBorn { microbe, parent } => Line::Born {
+                    tick,
+                    id: microbe.id,
+                    species: name(&microbe.species),
+                    parent: *parent,
+                    x: microbe.x,
+                    y: microbe.y,
+                },
+                Event::MicrobeAte {
+                    eater,
+                    eater_species,
+                    victim,
+                    victim_species,
+                } => Line::Ate {
+                    tick,
+                    eater: *eater,
+                    eater_species: name(eater_species),
+                    victim: *victim,
+                    victim_species: name(victim_species),
+                },
+                Event::MicrobeDied {
+                    microbe,
+                    lifespan,
+                    cause,
+                } => Line::Died {
+                    tick,
+                    id: microbe.id,
+                    species: name(&microbe.species),
+                    x: microbe.x,
+                    y: microbe.y,
+                    lifespan: *lifespan,
+                    cause: match cause {
+                        Cause::Starved => "starved",
+                        Cause::Eaten { .. } => "eaten",
+                        Cause::Culled => "culled",
+                        Cause::Struck => "struck",
+                    },
+                    by: match cause {
+                        Cause::Starved | Cause::Culled | Cause::Struck => Vec::new(),
+                        Cause::Eaten { by } => by.iter().map(name).collect(),
+                    },
+                },
+                Event::SpeciesExtinct {
+                    species,
+                    top_predator,
+                } => Line::Extinct {
+                    tick,
+                    species: name(species),
+                    top_predator: top_predator.as_ref().map(name),
+                },
+                Event::Disrupted { disruption } => Line::Disrupted {
+                    tick,
+                    disruption: *disruption,
+                },
+            };
+            serde_json::to_writer(&mut *out, &line)?;
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// Flushes the output and returns the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Every species an event mentions.
+fn species_of(event: &Event) -> Vec<Uuid> {
+    match event {
+        Event::MicrobeBorn { microbe, .. } => vec![microbe.species],
+        Event::MicrobeAte {
+            eater_species,
+            victim_species,
+            ..
+        } => vec![*eater_species, *victim_species],
+        Event::MicrobeDied { microbe, cause, .. } => {
+            let mut species = vec![microbe.species];
+            if let Cause::Eaten { by } = cause {
+                species.extend(by);
+            }
+            species
+        }
+        Event::SpeciesExtinct {
+            species,
+            top_predator,
+        } => [Some(*species), *top_predator]
+            .into_iter()
+            .flatten()
+            .collect(),
+        Event::Disrupted { .. } => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{scripts, Color32, Simulation};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_subscribers_see_every_event() {
+        let mut sim = Simulation::with_seed(60., 9).unwrap();
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let log = seen.clone();
+        sim.subscribe(move |tick, event| log.borrow_mut().push((tick, event.clone())));
+        let hunters = sim.add_species(scripts::aggressive_hunter_script());
+        for i in 0..30 {
+            sim.spawn(hunters, i as f32 * 4. - 60., 0., i as f32, Color32::RED);
+        }
+
+        let mut reported = Vec::new();
+        for _ in 0..10 {
+            let report = sim.step().unwrap();
+            reported.extend(report.events.iter().map(|e| (report.tick, e.clone())));
+        }
+        assert!(!reported.is_empty());
+        assert_eq!(*seen.borrow(), reported);
+    }
+
+    #[test]
+    fn test_event_log() {
+        let mut sim = Simulation::with_seed(40., 6).unwrap();
+        let hunters = sim.add_species(scripts::aggressive_hunter_script());
+        let idle = sim.add_species("new_controls()");
+        for i in 0..10 {
+            sim.spawn(hunters, i as f32 * 4. - 20., 0., 0., Color32::RED);
+            sim.spawn(idle, i as f32 * 4. - 20., 3., 0., Color32::BLUE);
+        }
+        let mut log = EventLog::new(Vec::new());
+        log.name(hunters, "hunter");
+        let mut events = 0;
+        for _ in 0..300 {
+            let report = sim.step().unwrap();
+            events += report.events.len();
+            log.record(report).unwrap();
+        }
+        let out = String::from_utf8(log.finish().unwrap()).unwrap();
+        let lines = out
+            .lines()
+            .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), events);
+        let kind = |kind: &'static str| lines.iter().filter(move |l| l["event"] == kind);
+        assert!(kind("born").count() > 0);
+        assert!(kind("ate").all(|l| l["eater_species"] == "hunter"));
+        let eaten = kind("died").find(|l| l["cause"] == "eaten").unwrap();
+        assert_eq!(eaten["species"], idle.to_string()[..8]);
+        assert_eq!(eaten["by"][0], "hunter");
+        let id = eaten["id"].as_str().unwrap();
+        assert!(kind("ate").any(|l| l["victim"] == id));
+    }
+
+    #[test]
+    fn test_deaths_and_extinction() {
+        let mut sim = Simulation::new(100.).unwrap();
+        let idle = sim.add_species("new_controls()");
+        sim.spawn(idle, 0., 0., 0., Color32::RED);
+        sim.tuning_mut().action_energy_consumption = 1000.;
+
+        let report = sim.step().unwrap();
+        assert_eq!(report.tick, 1);
+        let deaths = report.deaths().collect::<Vec<_>>();
+        assert_eq!(deaths.len(), 1);
+        assert!(!deaths[0].eaten);
+        assert_eq!(report.extinctions().collect::<Vec<_>>(), vec![idle]);
+        assert!(matches!(
+            report.events.last(),
+            Some(Event::SpeciesExtinct {
+                top_predator: None,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,427 @@
+//! Evolving the parameters of a species script.
+//!
+//! Mark the constants a script should evolve with an `// evolve MIN..MAX`
+//! comment. The script still runs as it is, with the values written in it:
+//!
+//! ```rhai
+//! const TURN_CHANCE = 0.3; // evolve 0.0..1.0
+//! const PATIENCE = 5;      // evolve 0..20
+//!
+//! let controls = new_controls();
+//! controls.forward = true;
+//! controls.right = sense_front() == 0 && rand() < TURN_CHANCE;
+//! controls
+//! ```
+//!
+//! An [`Evolution`] searches for the values that score the best
+//! [`Fitness`] against the config's species. Every generation it plays each
+//! parameter set, or genome, in the same seeded matches, keeps the best few
+//! as they are, and breeds the rest from winners of small tournaments with
+//! uniform crossover and mutation. Constants written without a decimal point
+//! stay integers.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::{Channel, Config, SpeciesConfig};
+use crate::scripts;
+use crate::tournament::{self, Score};
+
+/// A script with constants marked for evolution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    lines: Vec<String>,
+    params: Vec<Param>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    /// The value written in the script.
+    pub default: f64,
+    pub min: f64,
+    pub max: f64,
+    pub integer: bool,
+    /// Index of the line declaring it.
+    line: usize,
+}
+
+impl Template {
+    /// Finds the `// evolve MIN..MAX` constants in `source`.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let lines = source.lines().map(str::to_owned).collect::<Vec<_>>();
+        let mut params = Vec::new();
+        for (index, line) in lines.iter().enumerate() {
+            let Some((code, range)) = line.split_once("// evolve") else {
+                continue;
+            };
+            let error = |message: &str| format!("line {}: {message}", index + 1);
+            let (name, value) = code
+                .trim()
+                .strip_prefix("const ")
+                .and_then(|decl| decl.strip_suffix(';'))
+                .and_then(|decl| decl.split_once('='))
+                .ok_or_else(|| error("expected `const NAME = VALUE; // evolve MIN..MAX`"))?;
+            let value = value.trim();
+            let number = |text: &str| text.trim().parse::<f64>().ok();
+            let default = number(value).ok_or_else(|| error("the value should be a number"))?;
+            let (min, max) = range
+                .split_once("..")
+                .and_then(|(min, max)| Some((number(min)?, number(max)?)))
+                .filter(|(min, max)| min <= max)
+                .ok_or_else(|| error("expected a range such as 0.0..1.0"))?;
+            params.push(Param {
+                name: name.trim().to_owned(),
+                default,
+                min,
+                max,
+                integer: !value.contains(['.', 'e', 'E']),
+                line: index,
+            });
+        }
+        if params.is_empty() {
+            return Err("no constants are marked with `// evolve MIN..MAX`".to_owned());
+        }
+        Ok(Self { lines, params })
+    }
+
+    /// Checks that the script parses with its default values.
+    pub fn check(&self) -> Result<(), String> {
+        scripts::check(&self.render(&self.defaults())).map_err(|e| e.to_string())
+    }
+
+    pub fn params(&self) -> &[Param] {
+        &self.params
+    }
+
+    /// The values written in the script.
+    pub fn defaults(&self) -> Vec<f64> {
+        self.params.iter().map(|p| p.default).collect()
+    }
+
+    /// The script with `genome`'s values in place of the marked constants.
+    pub fn render(&self, genome: &[f64]) -> String {
+        let mut lines = self.lines.clone();
+        for (param, value) in self.params.iter().zip(genome) {
+            let line = &mut lines[param.line];
+            let comment = &line[line.find("// evolve").unwrap()..];
+            let indent = &line[..line.len() - line.trim_start().len()];
+            *line = format!(
+                "{indent}const {} = {}; {comment}",
+                param.name,
+                param.format(*value)
+            );
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+impl Param {
+    /// How `value` is written in a script.
+    pub fn format(&self, value: f64) -> String {
+        match self.integer {
+            true => format!("{}", value.round() as i64),
+            false => format!("{value:?}"),
+        }
+    }
+}
+
+/// What an evolved species is scored on, averaged over its matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fitness {
+    /// Total energy of the species' living microbes at the end.
+    #[default]
+    Biomass,
+    /// Living microbes at the end.
+    Survivors,
+    /// Deaths of other microbes the species took a bite in.
+    Kills,
+}
+
+impl Fitness {
+    fn score(self, score: &Score) -> f32 {
+        match self {
+            Fitness::Biomass => score.biomass,
+            Fitness::Survivors => score.survivors as f32,
+            Fitness::Kills => score.kills as f32,
+        }
+    }
+}
+
+impl FromStr for Fitness {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "biomass" => Ok(Fitness::Biomass),
+            "survivors" => Ok(Fitness::Survivors),
+            "kills" => Ok(Fitness::Kills),
+            _ => Err(format!(
+                "unknown fitness '{name}', expected biomass, survivors or kills"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Fitness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Fitness::Biomass => "biomass",
+            Fitness::Survivors => "survivors",
+            Fitness::Kills => "kills",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Evolution {
+    pub template: Template,
+    /// Arena, tuning and seed for every match. Its species are the
+    /// opponents.
+    pub config: Config,
+    pub fitness: Fitness,
+    /// Genomes per generation.
+    pub size: usize,
+    pub generations: usize,
+    /// Seeded matches each genome plays per generation.
+    pub matches: usize,
+    pub ticks: u64,
+    /// Microbes each species starts a match with.
+    pub population: usize,
+    /// Chance of each gene mutating in a child.
+    pub mutation: f64,
+}
+
+/// A genome and the fitness it scored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scored {
+    pub genome: Vec<f64>,
+    pub fitness: f32,
+}
+
+/// How a generation did.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Generation {
+    /// Counting from 1.
+    pub number: usize,
+    pub best: Scored,
+    pub mean: f32,
+}
+
+impl Evolution {
+    /// Evolves the template for every generation, calling `progress` after
+    /// each, and returns the best genome found.
+    pub fn run(&self, mut progress: impl FnMut(&Generation)) -> Result<Scored, String> {
+        let seed = self.config.seed.unwrap_or_else(rand::random);
+        let rng = &mut StdRng::seed_from_u64(seed);
+        let params = self.template.params();
+        let size = self.size.max(2);
+        let elite = (size / 10).max(1);
+
+        // Start from the script as written, plus random genomes.
+        let mut genomes = vec![self.template.defaults()];
+        while genomes.len() < size {
+            genomes.push(
+                params
+                    .iter()
+                    .map(|p| rng.gen_range(p.min..=p.max))
+                    .collect(),
+            );
+        }
+        let mut scored = Vec::<Scored>::new();
+        for number in 1..=self.generations.max(1) {
+            let mut fresh = genomes
+                .par_iter()
+                .map(|genome| {
+                    Ok(Scored {
+                        genome: genome.clone(),
+                        fitness: self.evaluate(genome, seed)?,
+                    })
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            // Elites are carried over with the fitness they already scored.
+            scored.truncate(elite);
+            scored.append(&mut fresh);
+            scored.sort_by(|a, b| b.fitness.total_cmp(&a.fitness));
+
+            let mean = scored.iter().map(|s| s.fitness).sum::<f32>() / scored.len() as f32;
+            progress(&Generation {
+                number,
+                best: scored[0].clone(),
+                mean,
+            });
+
+            genomes = (elite..size)
+                .map(|_| {
+                    let a = &select(&scored, rng).genome;
+                    let b = &select(&scored, rng).genome;
+                    self.child(a, b, rng)
+                })
+                .collect();
+        }
+        Ok(scored.swap_remove(0))
+    }
+
+    /// The mean fitness of `genome` over the generation's matches.
+    fn evaluate(&self, genome: &[f64], seed: u64) -> Result<f32, String> {
+        let mut species = self
+            .config
+            .species
+            .iter()
+            .map(|s| SpeciesConfig {
+                count: self.population,
+                ..s.clone()
+            })
+            .collect::<Vec<_>>();
+        species.push(SpeciesConfig {
+            name: "evolved".to_owned(),
+            script: self.template.render(genome),
+            count: self.population,
+            color: [Channel::Fixed(255); 3],
+            remote: None,
+            producer: false,
+            spiky: false,
+            jaws: Default::default(),
+            eyes: Default::default(),
+            mutation: Default::default(),
+            cooldown: Default::default(),
+            castes: Vec::new(),
+        });
+        let mut total = 0.;
+        for round in 0..self.matches.max(1) {
+            let config = Config {
+                seed: Some(seed.wrapping_add(round as u64)),
+                species: species.clone(),
+                ..self.config.clone()
+            };
+            let scores = tournament::play(&config, self.ticks)?;
+            total += self.fitness.score(scores.last().unwrap());
+        }
+        Ok(total / self.matches.max(1) as f32)
+    }
+
+    /// Uniform crossover of `a` and `b`, then mutation.
+    fn child(&self, a: &[f64], b: &[f64], rng: &mut impl Rng) -> Vec<f64> {
+        self.template
+            .params()
+            .iter()
+            .zip(a.iter().zip(b))
+            .map(|(param, (a, b))| {
+                let mut gene = if rng.gen_bool(0.5) { *a } else { *b };
+                if rng.gen_bool(self.mutation.clamp(0., 1.)) {
+                    let spread = (param.max - param.min) * 0.2;
+                    gene += rng.gen_range(-spread..=spread);
+                }
+                gene.clamp(param.min, param.max)
+            })
+            .collect()
+    }
+}
+
+/// The fittest of three genomes picked at random.
+fn select<'a>(scored: &'a [Scored], rng: &mut impl Rng) -> &'a Scored {
+    (0..3)
+        .map(|_| &scored[rng.gen_range(0..scored.len())])
+        .max_by(|a, b| a.fitness.total_cmp(&b.fitness))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "\
+// Stands still until eating is likely to pay off.
+    const MOVE = 0.0; // evolve 0.0..1.0
+const WAIT = 3;       // evolve 0..10
+
+let controls = new_controls();
+controls.forward = MOVE > 0.5 && WAIT >= 0;
+controls.eat = true;
+controls
+";
+
+    #[test]
+    fn test_template() {
+        let template = Template::parse(SCRIPT).unwrap();
+        let params = template.params();
+        assert_eq!(params.len(), 2);
+        assert_eq!(
+            (
+                params[0].name.as_str(),
+                params[0].default,
+                params[0].integer
+            ),
+            ("MOVE", 0., false)
+        );
+        assert_eq!(
+            (params[1].min, params[1].max, params[1].integer),
+            (0., 10., true)
+        );
+
+        let rendered = template.render(&[0.75, 6.6]);
+        assert!(rendered.contains("\n    const MOVE = 0.75; // evolve 0.0..1.0\n"));
+        assert!(rendered.contains("\nconst WAIT = 7; // evolve 0..10\n"));
+        assert_eq!(Template::parse(&rendered).unwrap().defaults(), [0.75, 7.]);
+        template.check().unwrap();
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        for source in [
+            "new_controls()",
+            "let x = 1; // evolve 0..1",
+            "const X = big; // evolve 0..1",
+            "const X = 1; // evolve 2..1",
+            "const X = 1; // evolve 0..",
+        ] {
+            assert!(Template::parse(source).is_err(), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_evolution_finds_better_genomes() {
+        let evolution = Evolution {
+            template: Template::parse(SCRIPT).unwrap(),
+            config: Config {
+                arena: 30.,
+                seed: Some(5),
+                species: vec![SpeciesConfig {
+                    name: "prey".to_owned(),
+                    script: "new_controls()".to_owned(),
+                    count: 0,
+                    color: [Channel::Fixed(0); 3],
+                    remote: None,
+                    producer: false,
+                    spiky: false,
+                    jaws: Default::default(),
+                    eyes: Default::default(),
+                    mutation: Default::default(),
+                    cooldown: Default::default(),
+                    castes: Vec::new(),
+                }],
+                ..Config::default()
+            },
+            fitness: Fitness::Kills,
+            size: 6,
+            generations: 3,
+            matches: 1,
+            ticks: 15,
+            population: 10,
+            mutation: 0.5,
+        };
+        let mut generations = Vec::new();
+        let best = evolution.run(|g| generations.push(g.clone())).unwrap();
+        assert_eq!(generations.len(), 3);
+        // Elitism never loses the best genome.
+        assert!(generations
+            .windows(2)
+            .all(|w| w[1].best.fitness >= w[0].best.fitness));
+        assert_eq!(best, generations[2].best);
+        // Moving toward the prey beats standing still.
+        assert!(best.genome[0] > 0.5, "{best:?}");
+    }
+}
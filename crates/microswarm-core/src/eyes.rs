@@ -0,0 +1,128 @@
+//! How far and how wide a microbe sees.
+//!
+//! Every microbe has [`Eyes`], passed on to its offspring and changed by
+//! [`crate::mutation`]. They trade range for field of view: a `focus` above
+//! 1 sees that many times further than [`Tuning::detect_range_far`] through
+//! cones that many times narrower, and one below 1 sees closer through wider
+//! cones. Only the far senses depend on it; close senses and
+//! [`crate::jaws`] don't. Seeing further costs upkeep: each tick the microbe
+//! spends [`Tuning::eye_upkeep`] more of the usual energy for every time
+//! over 1 its focus is, and saves as much below it.
+//!
+//! ```toml
+//! [[species]]
+//! name = "spotters"
+//! builtin = "herbivore"
+//! eyes = { focus = 2.0 }    # telescopic: twice as far, half as wide
+//! ```
+//!
+//! [`Tuning::detect_range_far`]: crate::Tuning::detect_range_far
+//! [`Tuning::eye_upkeep`]: crate::Tuning::eye_upkeep
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+use crate::jaws::HALF_ANGLE;
+
+/// The narrowest and widest focus, as a factor either way of the usual.
+pub const MAX_FOCUS: f32 = 4.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Unclamped")]
+pub struct Eyes {
+    /// How many times further, and narrower, than usual they see.
+    pub focus: f32,
+}
+
+impl Default for Eyes {
+    fn default() -> Self {
+        Self { focus: 1. }
+    }
+}
+
+/// Eyes as a scene or save writes them, clamped as they're read.
+#[derive(Deserialize)]
+#[serde(default)]
+struct Unclamped {
+    focus: f32,
+}
+
+impl Default for Unclamped {
+    fn default() -> Self {
+        Self {
+            focus: Eyes::default().focus,
+        }
+    }
+}
+
+impl From<Unclamped> for Eyes {
+    fn from(eyes: Unclamped) -> Self {
+        Self::new(eyes.focus)
+    }
+}
+
+impl Eyes {
+    pub fn new(focus: f32) -> Self {
+        Self {
+            focus: focus.clamp(1. / MAX_FOCUS, MAX_FOCUS),
+        }
+    }
+
+    /// Far range, given the usual one.
+    pub fn range(&self, usual: f32) -> f32 {
+        usual * self.focus
+    }
+
+    /// Half the angle of each far cone, in radians.
+    pub fn half_angle(&self) -> f32 {
+        (HALF_ANGLE / self.focus).min(PI)
+    }
+
+    /// Scales the focus by up to `magnitude` either way, with chance `rate`.
+    pub(crate) fn mutate(&mut self, rate: f64, magnitude: f32, rng: &mut impl Rng) {
+        if magnitude > 0. && rng.gen_bool(rate) {
+            *self = Self::new(self.focus * (1. + rng.gen_range(-magnitude..=magnitude)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_tradeoff() {
+        let usual = Eyes::default();
+        assert_eq!((usual.range(40.), usual.half_angle()), (40., HALF_ANGLE));
+        let telescopic = Eyes::new(2.);
+        assert_eq!(telescopic.range(40.), 80.);
+        assert_eq!(telescopic.half_angle(), HALF_ANGLE / 2.);
+        assert_eq!(Eyes::new(0.1), Eyes::new(1. / MAX_FOCUS));
+        assert_eq!(Eyes::new(1. / MAX_FOCUS).half_angle(), PI);
+    }
+
+    #[test]
+    fn test_mutate() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut eyes = Eyes::default();
+        eyes.mutate(0., 1., &mut rng);
+        assert_eq!(eyes, Eyes::default());
+        for _ in 0..200 {
+            eyes.mutate(1., 0.5, &mut rng);
+            assert!((1. / MAX_FOCUS..=MAX_FOCUS).contains(&eyes.focus));
+        }
+        assert_ne!(eyes, Eyes::default());
+    }
+
+    #[test]
+    fn test_serde() {
+        let read = |json| serde_json::from_str::<Eyes>(json).unwrap();
+        assert_eq!(read("{}"), Eyes::default());
+        assert_eq!(read(r#"{"focus": 0}"#), Eyes::new(1. / MAX_FOCUS));
+        assert_eq!(read(r#"{"focus": -2}"#), Eyes::new(1. / MAX_FOCUS));
+        assert_eq!(read(r#"{"focus": 2}"#), Eyes::new(2.));
+    }
+}
@@ -0,0 +1,187 @@
+//! Species scripts shared at a URL.
+//!
+//! [`fetch`] downloads a script over HTTPS, such as the raw link to a gist,
+//! and vets it before it goes anywhere near a world: it must be text, parse,
+//! stay within the [`Sandbox`] limits and run a few trial ticks without
+//! failing. The species is named by the script's [`scripts::name`] comment,
+//! or else by the file in the URL.
+//!
+//! ```no_run
+//! use microswarm::fetch;
+//! use microswarm::sandbox::Sandbox;
+//! use microswarm::Simulation;
+//!
+//! let mut sim = Simulation::new(400.).unwrap();
+//! let url = "https://gist.githubusercontent.com/someone/1234/raw/pack.rhai";
+//! let fetched = fetch::fetch(url, &Sandbox::default()).unwrap();
+//! let species = sim.add_species(fetched.source);
+//! sim.set_species_name(species, &fetched.name);
+//! ```
+//!
+//! Downloads go through the system's `curl`, limited to HTTPS all the way
+//! through any redirects and to [`MAX_SCRIPT_SIZE`] bytes.
+
+use ecolor::Color32;
+use rhai::Engine;
+use std::io;
+use std::process::Command;
+
+use crate::error::SimError;
+use crate::sandbox::{self, Sandbox};
+use crate::scripts;
+use crate::simulation::Simulation;
+
+/// The largest script [`fetch`] downloads, in bytes.
+pub const MAX_SCRIPT_SIZE: usize = 256 * 1024;
+
+/// How long [`fetch`] waits for a download, in seconds.
+const TIMEOUT: u32 = 30;
+
+/// Ticks a script must run for without failing.
+const TRIAL_TICKS: usize = 3;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    #[error("not an https:// URL")]
+    NotHttps,
+    #[error("couldn't run curl: {0}")]
+    Curl(#[source] io::Error),
+    #[error("download failed: {0}")]
+    Download(String),
+    #[error("script is over {MAX_SCRIPT_SIZE} bytes")]
+    TooLarge,
+    #[error("script isn't UTF-8 text")]
+    NotText,
+    #[error("script doesn't parse: {0}")]
+    Parse(#[source] rhai::ParseError),
+    #[error("script goes over the sandbox limits: {0}")]
+    OverLimit(#[source] rhai::ParseError),
+    #[error("script failed a trial run: {0}")]
+    Trial(#[source] SimError),
+}
+
+/// A script downloaded by [`fetch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fetched {
+    pub name: String,
+    pub source: String,
+}
+
+/// Downloads the script at `url` and vets it against `sandbox`.
+pub fn fetch(url: &str, sandbox: &Sandbox) -> Result<Fetched, FetchError> {
+    if !url.starts_with("https://") {
+        return Err(FetchError::NotHttps);
+    }
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location"])
+        .args(["--proto", "=https", "--proto-redir", "=https"])
+        .args(["--max-filesize", &MAX_SCRIPT_SIZE.to_string()])
+        .args(["--max-time", &TIMEOUT.to_string()])
+        .arg("--")
+        .arg(url)
+        .output()
+        .map_err(FetchError::Curl)?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        return Err(FetchError::Download(message.trim().to_owned()));
+    }
+    // curl can't tell the size ahead of a download without a length.
+    if output.stdout.len() > MAX_SCRIPT_SIZE {
+        return Err(FetchError::TooLarge);
+    }
+    let source = String::from_utf8(output.stdout).map_err(|_| FetchError::NotText)?;
+    vet(&source, sandbox)?;
+    Ok(Fetched {
+        name: url_name(url, &source),
+        source,
+    })
+}
+
+/// Checks that `source` parses within `sandbox` and survives a few ticks
+/// of a throwaway world. Going over the operation limits while running
+/// only makes a microbe sit still, so it isn't caught here.
+pub fn vet(source: &str, sandbox: &Sandbox) -> Result<(), FetchError> {
+    let mut engine = Engine::new();
+    sandbox.apply(&mut engine);
+    if let Err(error) = engine.compile(source) {
+        return Err(match sandbox::over_limit(&error.clone().into()) {
+            true => FetchError::OverLimit(error),
+            false => FetchError::Parse(error),
+        });
+    }
+
+    let mut sim = Simulation::with_seed(100., 0).map_err(FetchError::Trial)?;
+    sim.set_sandbox(sandbox.clone());
+    let species = sim.add_species(source);
+    let rival = sim.add_species("new_controls()");
+    sim.spawn(species, 0., 0., 0., Color32::RED);
+    sim.spawn(rival, 20., 0., 0., Color32::BLUE);
+    for _ in 0..TRIAL_TICKS {
+        sim.step().map_err(FetchError::Trial)?;
+    }
+    Ok(())
+}
+
+/// The script's [`scripts::name`] comment, or else the stem of the last
+/// part of `url`'s path.
+pub fn url_name(url: &str, source: &str) -> String {
+    if let Some(name) = scripts::name(source) {
+        return name.to_owned();
+    }
+    let url = url.split(['?', '#']).next().unwrap_or_default();
+    let url = url.strip_prefix("https://").unwrap_or(url);
+    // A bare host is named as it is.
+    let Some((host, path)) = url.trim_end_matches('/').split_once('/') else {
+        return url.trim_end_matches('/').to_owned();
+    };
+    let file = path.rsplit('/').next().unwrap_or(host);
+    match file.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem.to_owned(),
+        _ => file.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_name() {
+        let gist = "https://gist.githubusercontent.com/someone/1234/raw/abcd/pack.rhai";
+        assert_eq!(url_name(gist, "new_controls()"), "pack");
+        assert_eq!(url_name(gist, "// name: Wolves\nnew_controls()"), "Wolves");
+        assert_eq!(
+            url_name("https://example.com/bots/sly.rhai?raw=true#L1", ""),
+            "sly"
+        );
+        assert_eq!(url_name("https://example.com/", ""), "example.com");
+    }
+
+    #[test]
+    fn test_only_https() {
+        for url in ["http://example.com/a.rhai", "file:///etc/passwd", "-o out"] {
+            assert!(matches!(
+                fetch(url, &Sandbox::default()),
+                Err(FetchError::NotHttps)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_vet() {
+        let sandbox = Sandbox::default();
+        vet(&scripts::builtin("hunter").unwrap(), &sandbox).unwrap();
+        assert!(matches!(
+            vet("let controls = ;", &sandbox),
+            Err(FetchError::Parse(_))
+        ));
+        assert!(matches!(vet("42", &sandbox), Err(FetchError::Trial(_))));
+        let deep = format!("{}1{}", "(".repeat(100), ")".repeat(100));
+        assert!(matches!(
+            vet(&deep, &sandbox),
+            Err(FetchError::OverLimit(_))
+        ));
+        // Running out of operations only stops the microbe.
+        vet("let c = new_controls(); loop {} c", &sandbox).unwrap();
+    }
+}
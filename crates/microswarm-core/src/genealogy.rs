@@ -0,0 +1,226 @@
+//! The family forest of a run, written as a Graphviz DOT graph.
+//!
+//! A [`Genealogy`] follows a run tick by tick and remembers every microbe
+//! it saw: the founders that were spawned, and every child with an edge from
+//! its parent labelled with the tick it was born on. Nodes are filled with
+//! their species' color, and dead microbes are drawn dashed. Render the file
+//! with, for example:
+//!
+//! ```text
+//! dot -Tsvg genealogy.dot -o genealogy.svg
+//! ```
+//!
+//! Long runs produce very large forests; `sfdp` copes with them better than
+//! `dot`.
+
+use ecolor::Color32;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::events::{Event, StepReport};
+use crate::palette::Palette;
+use crate::simulation::{MicrobeState, Snapshot};
+
+#[derive(Debug, Clone, Default)]
+pub struct Genealogy {
+    names: HashMap<Uuid, String>,
+    /// Species in the order they were first seen, which picks their color.
+    species: Vec<Uuid>,
+    nodes: Vec<Node>,
+    index: HashMap<Uuid, usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    id: Uuid,
+    species: Uuid,
+    parent: Option<Uuid>,
+    born: u64,
+    died: Option<u64>,
+}
+
+impl Genealogy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Labels `species` with `name` in the graph, instead of its short id.
+    pub fn name(&mut self, species: Uuid, name: impl Into<String>) {
+        self.names.insert(species, name.into());
+    }
+
+    /// Adds the births and deaths of a step, and any microbes in `snapshot`
+    /// not seen before, such as the ones spawned at the start.
+    pub fn record(&mut self, snapshot: &Snapshot, report: &StepReport) {
+        for species in &snapshot.species {
+            if !self.species.contains(species) {
+                self.species.push(*species);
+            }
+        }
+        let children = report
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                Event::MicrobeBorn { microbe, .. } => Some(microbe.id),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+        let died = report.events.iter().filter_map(|event| match event {
+            Event::MicrobeDied { microbe, .. } => Some(microbe),
+            _ => None,
+        });
+        // Founders come first, so parents are listed before their children.
+        for microbe in snapshot.microbes.iter().chain(died.clone()) {
+            if !children.contains(&microbe.id) {
+                self.add(microbe, None);
+            }
+        }
+        for event in &report.events {
+            if let Event::MicrobeBorn { microbe, parent } = event {
+                self.add(microbe, Some(*parent));
+            }
+        }
+        for microbe in died {
+            self.add(microbe, None);
+            self.nodes[self.index[&microbe.id]].died = Some(report.tick);
+        }
+    }
+
+    fn add(&mut self, microbe: &MicrobeState, parent: Option<Uuid>) {
+        if self.index.contains_key(&microbe.id) {
+            return;
+        }
+        self.index.insert(microbe.id, self.nodes.len());
+        self.nodes.push(Node {
+            id: microbe.id,
+            species: microbe.species,
+            parent,
+            born: microbe.born,
+            died: None,
+        });
+    }
+
+    /// Microbes seen so far.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Writes the forest as a DOT digraph.
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        let colors = Palette::OkabeIto.colors(self.species.len(), &mut rand::thread_rng());
+        let color = |species: &Uuid| {
+            let index = self.species.iter().position(|s| s == species);
+            index.map_or(Color32::GRAY, |i| colors[i])
+        };
+        writeln!(out, "digraph genealogy {{")?;
+        writeln!(out, "  rankdir=LR;")?;
+        writeln!(
+            out,
+            "  node [shape=circle, style=filled, width=0.3, fontsize=8];"
+        )?;
+        writeln!(out, "  edge [fontsize=8];")?;
+        for node in &self.nodes {
+            let [r, g, b, _] = color(&node.species).to_array();
+            let style = match node.died {
+                Some(_) => "filled,dashed",
+                None => "filled",
+            };
+            let mut tooltip = format!(
+                "{}, born tick {}",
+                self.species_name(&node.species),
+                node.born
+            );
+            if let Some(died) = node.died {
+                tooltip += &format!(", died tick {died}");
+            }
+            writeln!(
+                out,
+                "  \"{}\" [label=\"{}\", fillcolor=\"#{r:02x}{g:02x}{b:02x}\", style=\"{style}\", tooltip=\"{}\"];",
+                node.id,
+                &node.id.to_string()[..8],
+                escape(&tooltip)
+            )?;
+        }
+        for node in &self.nodes {
+            if let Some(parent) = node.parent {
+                writeln!(
+                    out,
+                    "  \"{parent}\" -> \"{}\" [label=\"{}\"];",
+                    node.id, node.born
+                )?;
+            }
+        }
+        writeln!(out, "}}")
+    }
+
+    /// Writes the forest to a DOT file at `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write(&mut out)?;
+        out.flush()
+    }
+
+    fn species_name(&self, species: &Uuid) -> String {
+        match self.names.get(species) {
+            Some(name) => name.clone(),
+            None => species.to_string()[..8].to_owned(),
+        }
+    }
+}
+
+/// Escapes a string for a quoted DOT attribute.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{scripts, Color32, Simulation};
+
+    #[test]
+    fn test_genealogy() {
+        let mut sim = Simulation::with_seed(40., 6).unwrap();
+        let hunters = sim.add_species(scripts::aggressive_hunter_script());
+        let idle = sim.add_species("new_controls()");
+        for i in 0..10 {
+            sim.spawn(hunters, i as f32 * 4. - 20., 0., 0., Color32::RED);
+            sim.spawn(idle, i as f32 * 4. - 20., 3., 0., Color32::BLUE);
+        }
+        let mut genealogy = Genealogy::new();
+        genealogy.name(hunters, "hun\"ter");
+        genealogy.record(&sim.snapshot(), &StepReport::default());
+        assert_eq!(genealogy.len(), 20);
+
+        let (mut births, mut deaths) = (0, 0);
+        for _ in 0..300 {
+            sim.step().unwrap();
+            births += sim.world().report().births();
+            deaths += sim.world().report().deaths().count();
+            genealogy.record(&sim.snapshot(), sim.world().report());
+        }
+        assert!(births > 0 && deaths > 0);
+        assert_eq!(genealogy.len(), 20 + births);
+
+        let mut out = Vec::new();
+        genealogy.write(&mut out).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+        assert!(dot.starts_with("digraph genealogy {"));
+        assert!(dot.trim_end().ends_with('}'));
+        assert_eq!(dot.matches(" -> ").count(), births);
+        assert_eq!(dot.matches("dashed").count(), deaths);
+        assert!(dot.contains("tooltip=\"hun\\\"ter, born tick 0\""));
+        // Every edge starts at a microbe that was seen.
+        for line in dot.lines().filter(|l| l.contains(" -> ")) {
+            let parent = line.trim().split('"').nth(1).unwrap();
+            assert!(dot.contains(&format!("  \"{parent}\" [")));
+        }
+    }
+}
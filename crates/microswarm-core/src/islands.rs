@@ -0,0 +1,222 @@
+//! Several arenas side by side, with microbes now and then moving between
+//! them.
+//!
+//! An [`Archipelago`] runs one world per island from the same config, each
+//! with its own seed, and steps them together. After every step each
+//! microbe has a small chance of migrating to another island picked at
+//! random, where it arrives at the same position with its energy, age and
+//! lineage. Islands that rarely mix evolve apart, so a species can survive
+//! on one island after being wiped out on another, which keeps far more
+//! variety going than one well-mixed arena.
+//!
+//! Every island gets the config's full population, and its species are
+//! matched across islands by their position in the config. Set up in
+//! `world.toml` with:
+//!
+//! ```toml
+//! [islands]
+//! count = 4
+//! migration = 0.0005   # chance of each microbe moving each tick
+//! ```
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::error::SimError;
+use crate::simulation::Simulation;
+
+/// One microbe moving between islands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Migration {
+    pub microbe: Uuid,
+    pub from: usize,
+    pub to: usize,
+}
+
+#[derive(Debug)]
+pub struct Archipelago {
+    islands: Vec<Simulation>,
+    /// Each island's species ids, in config order.
+    species: Vec<Vec<Uuid>>,
+    migration: f64,
+    rng: StdRng,
+    migrations: Vec<Migration>,
+}
+
+impl Archipelago {
+    /// Builds `config` once per island of its `[islands]`, or a single
+    /// island without any.
+    pub fn new(config: &Config) -> Result<Self, SimError> {
+        let islands = config.islands.unwrap_or_default();
+        let seed = config.seed.unwrap_or_else(rand::random);
+        let mut archipelago = Self {
+            islands: Vec::new(),
+            species: Vec::new(),
+            migration: islands.migration.clamp(0., 1.),
+            rng: StdRng::seed_from_u64(seed),
+            migrations: Vec::new(),
+        };
+        for island in 0..islands.count.max(1) {
+            let config = Config {
+                seed: Some(seed.wrapping_add(island as u64)),
+                ..config.clone()
+            };
+            let (sim, ids) = config.build()?;
+            archipelago.islands.push(sim);
+            archipelago.species.push(ids);
+        }
+        Ok(archipelago)
+    }
+
+    pub fn islands(&self) -> &[Simulation] {
+        &self.islands
+    }
+
+    pub fn island_mut(&mut self, island: usize) -> Option<&mut Simulation> {
+        self.islands.get_mut(island)
+    }
+
+    /// The species ids on `island`, in config order.
+    pub fn species(&self, island: usize) -> &[Uuid] {
+        self.species.get(island).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.islands[0].world().tick()
+    }
+
+    /// The microbes that moved in the last step.
+    pub fn migrations(&self) -> &[Migration] {
+        &self.migrations
+    }
+
+    /// Steps every island, then moves migrants. If an island's step fails,
+    /// the islands before it have already stepped.
+    pub fn step(&mut self) -> Result<(), SimError> {
+        for island in &mut self.islands {
+            island.step()?;
+        }
+        self.migrations.clear();
+        let count = self.islands.len();
+        if count < 2 || self.migration == 0. {
+            return Ok(());
+        }
+
+        let mut arrivals = vec![Vec::new(); count];
+        for from in 0..count {
+            let world = self.islands[from].world_mut();
+            let mut microbes = world.microbes.take_items();
+            // Id order keeps the draws the same however the tree is laid out.
+            microbes.sort_by_key(|m| m.id);
+            for mut microbe in microbes {
+                if !self.rng.gen_bool(self.migration) {
+                    world.microbes.insert(microbe);
+                    continue;
+                }
+                let to = (from + self.rng.gen_range(1..count)) % count;
+                let index = self.species[from]
+                    .iter()
+                    .position(|s| *s == microbe.script_id);
+                let Some(species) = index.and_then(|i| self.species[to].get(i)) else {
+                    // Species added to one island by hand stay there.
+                    world.microbes.insert(microbe);
+                    continue;
+                };
+                self.migrations.push(Migration {
+                    microbe: microbe.id,
+                    from,
+                    to,
+                });
+                microbe.script_id = *species;
+                arrivals[to].push(microbe);
+            }
+        }
+        for (island, arrivals) in self.islands.iter_mut().zip(arrivals) {
+            let world = island.world_mut();
+            for microbe in arrivals {
+                world.microbes.insert(microbe);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Channel, Islands, SpeciesConfig};
+
+    fn config(count: usize, migration: f64) -> Config {
+        let species = |name: &str, script: &str| SpeciesConfig {
+            name: name.to_owned(),
+            script: script.to_owned(),
+            count: 20,
+            color: [Channel::Fixed(255); 3],
+            remote: None,
+            producer: false,
+            spiky: false,
+            jaws: Default::default(),
+            eyes: Default::default(),
+            mutation: Default::default(),
+            cooldown: Default::default(),
+            castes: Vec::new(),
+        };
+        Config {
+            arena: 50.,
+            seed: Some(8),
+            islands: Some(Islands { count, migration }),
+            species: vec![
+                species("idle", "new_controls()"),
+                species("walker", &crate::scripts::random_script()),
+            ],
+            ..Config::default()
+        }
+    }
+
+    fn population(archipelago: &Archipelago) -> Vec<usize> {
+        let islands = archipelago.islands();
+        islands.iter().map(|i| i.microbes().count()).collect()
+    }
+
+    #[test]
+    fn test_islands_are_independent_without_migration() {
+        let mut archipelago = Archipelago::new(&config(3, 0.)).unwrap();
+        assert_eq!(population(&archipelago), [40, 40, 40]);
+        let positions = |sim: &Simulation| sim.microbes().map(|m| (m.x, m.y)).collect::<Vec<_>>();
+        let islands = archipelago.islands();
+        assert_ne!(positions(&islands[0]), positions(&islands[1]));
+        for _ in 0..20 {
+            archipelago.step().unwrap();
+        }
+        assert!(archipelago.migrations().is_empty());
+        assert_eq!(population(&archipelago), [40, 40, 40]);
+        assert_eq!(archipelago.tick(), 20);
+    }
+
+    #[test]
+    fn test_migration() {
+        let run = || {
+            let mut archipelago = Archipelago::new(&config(3, 0.02)).unwrap();
+            let mut migrations = Vec::new();
+            for _ in 0..50 {
+                archipelago.step().unwrap();
+                migrations.extend_from_slice(archipelago.migrations());
+            }
+            (archipelago, migrations)
+        };
+        let (archipelago, migrations) = run();
+        assert!(!migrations.is_empty());
+        assert!(migrations.iter().all(|m| m.from != m.to && m.to < 3));
+        assert_eq!(population(&archipelago).iter().sum::<usize>(), 120);
+        assert_ne!(population(&archipelago), [40, 40, 40]);
+
+        // Migrants take on their new island's ids for their species.
+        for (i, island) in archipelago.islands().iter().enumerate() {
+            let species = archipelago.species(i);
+            assert!(island.microbes().all(|m| species.contains(&m.species)));
+        }
+        assert_eq!(run().1, migrations);
+    }
+}
@@ -0,0 +1,154 @@
+//! How far and how wide a microbe can bite.
+//!
+//! Every microbe has [`Jaws`], passed on to its offspring and changed by
+//! [`crate::mutation`]. The usual ones bite whatever is within
+//! [`Tuning::detect_range_close`] and 0.4π radians either side of the
+//! microbe's heading: `reach` scales the range and `width` the angle. Jaws
+//! bigger than that cost more: each tick of eating costs
+//! [`Tuning::jaw_cost`] for every time over the area of the usual cone the
+//! jaws' cone covers. Smaller jaws cost no less to bite with, but bigger
+//! and smaller ones also raise or lower the microbe's
+//! [`Microbe::metabolism`] by [`Tuning::jaw_upkeep`].
+//!
+//! ```toml
+//! [[species]]
+//! name = "gulpers"
+//! builtin = "hunter"
+//! jaws = { width = 1.5, reach = 1.2 }
+//! ```
+//!
+//! [`Tuning::detect_range_close`]: crate::Tuning::detect_range_close
+//! [`Tuning::jaw_cost`]: crate::Tuning::jaw_cost
+//! [`Tuning::jaw_upkeep`]: crate::Tuning::jaw_upkeep
+//! [`Microbe::metabolism`]: crate::Microbe::metabolism
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// Half the angle of the usual jaws' cone.
+pub const HALF_ANGLE: f32 = PI * 0.4;
+/// Jaws can't get wider than a full circle.
+pub const MAX_WIDTH: f32 = PI / HALF_ANGLE;
+pub const MAX_REACH: f32 = 4.;
+/// Jaws can't shrink to nothing.
+pub const MIN_SIZE: f32 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Unclamped")]
+pub struct Jaws {
+    /// Angle of the cone, as a share of the usual one.
+    pub width: f32,
+    /// Range of the cone, as a share of the usual one.
+    pub reach: f32,
+}
+
+impl Default for Jaws {
+    fn default() -> Self {
+        Self {
+            width: 1.,
+            reach: 1.,
+        }
+    }
+}
+
+/// Jaws as a scene or save writes them, clamped as they're read.
+#[derive(Deserialize)]
+#[serde(default)]
+struct Unclamped {
+    width: f32,
+    reach: f32,
+}
+
+impl Default for Unclamped {
+    fn default() -> Self {
+        let Jaws { width, reach } = Jaws::default();
+        Self { width, reach }
+    }
+}
+
+impl From<Unclamped> for Jaws {
+    fn from(jaws: Unclamped) -> Self {
+        Self::new(jaws.width, jaws.reach)
+    }
+}
+
+impl Jaws {
+    pub fn new(width: f32, reach: f32) -> Self {
+        Self { width, reach }.clamped()
+    }
+
+    /// Half the angle of the cone, in radians.
+    pub fn half_angle(&self) -> f32 {
+        HALF_ANGLE * self.width
+    }
+
+    /// How many times the usual cone's area this one covers.
+    pub fn size(&self) -> f32 {
+        self.width * self.reach * self.reach
+    }
+
+    /// Energy each tick of eating costs on top of the usual.
+    pub(crate) fn cost(&self, jaw_cost: f32) -> f32 {
+        jaw_cost * (self.size() - 1.).max(0.)
+    }
+
+    /// Scales width and reach each by up to `magnitude` either way, with
+    /// chance `rate` each.
+    pub(crate) fn mutate(&mut self, rate: f64, magnitude: f32, rng: &mut impl Rng) {
+        for size in [&mut self.width, &mut self.reach] {
+            if magnitude > 0. && rng.gen_bool(rate) {
+                *size *= 1. + rng.gen_range(-magnitude..=magnitude);
+            }
+        }
+        *self = self.clamped();
+    }
+
+    fn clamped(self) -> Self {
+        Self {
+            width: self.width.clamp(MIN_SIZE, MAX_WIDTH),
+            reach: self.reach.clamp(MIN_SIZE, MAX_REACH),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_cost() {
+        assert_eq!(Jaws::default().cost(2.), 0.);
+        assert_eq!(Jaws::new(0.5, 0.5).cost(2.), 0.);
+        assert_eq!(Jaws::new(2., 1.5).cost(2.), 7.);
+        assert_eq!(Jaws::new(10., 0.).half_angle(), PI);
+    }
+
+    #[test]
+    fn test_mutate() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut jaws = Jaws::default();
+        jaws.mutate(0., 1., &mut rng);
+        assert_eq!(jaws, Jaws::default());
+        for _ in 0..200 {
+            jaws.mutate(1., 0.5, &mut rng);
+            assert!((MIN_SIZE..=MAX_WIDTH).contains(&jaws.width));
+            assert!((MIN_SIZE..=MAX_REACH).contains(&jaws.reach));
+        }
+        assert_ne!(jaws, Jaws::default());
+    }
+
+    #[test]
+    fn test_serde() {
+        let read = |json| serde_json::from_str::<Jaws>(json).unwrap();
+        assert_eq!(read(r#"{"reach": 2}"#), Jaws::new(1., 2.));
+        assert_eq!(
+            read(r#"{"width": 100, "reach": -1}"#),
+            Jaws::new(MAX_WIDTH, MIN_SIZE)
+        );
+        let jaws = Jaws::new(1.5, 0.5);
+        assert_eq!(read(&serde_json::to_string(&jaws).unwrap()), jaws);
+    }
+}
@@ -0,0 +1,194 @@
+//! Results kept across tournaments and runs.
+//!
+//! A [`Leaderboard`] adds up every match a script has played, by name, in a
+//! JSON file that each tournament or scored run updates:
+//!
+//! ```json
+//! {"scripts": {"hunter": {"matches": 40, "wins": 31, "survival": 31250.0, "biomass": 41200.0}}}
+//! ```
+//!
+//! It keeps totals rather than means, so adding a short tournament to a
+//! long history weighs each match the same. [`Leaderboard::ranked`] orders
+//! scripts by the share of matches they won, then by mean biomass.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::tournament::{Score, Standing};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Leaderboard {
+    #[serde(default)]
+    pub scripts: BTreeMap<String, Record>,
+}
+
+/// One script's totals over every match it played.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Record {
+    pub matches: usize,
+    /// Matches where it ended with more biomass than every opponent.
+    pub wins: usize,
+    /// Sum over matches of the mean ticks its microbes lived.
+    pub survival: f64,
+    /// Sum over matches of its biomass at the end.
+    pub biomass: f64,
+}
+
+impl Record {
+    pub fn win_rate(&self) -> f64 {
+        self.wins as f64 / self.matches.max(1) as f64
+    }
+
+    pub fn mean_survival(&self) -> f64 {
+        self.survival / self.matches.max(1) as f64
+    }
+
+    pub fn mean_biomass(&self) -> f64 {
+        self.biomass / self.matches.max(1) as f64
+    }
+}
+
+impl Leaderboard {
+    /// Reads the leaderboard at `path`, or an empty one if there's no file
+    /// yet.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        match fs::read(path) {
+            Ok(json) => Ok(serde_json::from_slice(&json)?),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Writes the leaderboard to `path`, creating its directory if needed.
+    /// The file is replaced in one go, so a crash never leaves half of it.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let partial = path.with_extension("json.partial");
+        fs::write(&partial, serde_json::to_string_pretty(self)?)?;
+        fs::rename(partial, path)
+    }
+
+    /// Adds a tournament's standings.
+    pub fn add_standings(&mut self, standings: &[Standing]) {
+        for standing in standings {
+            let record = self.scripts.entry(standing.name.clone()).or_default();
+            let matches = standing.matches as f64;
+            record.matches += standing.matches;
+            record.wins += standing.wins;
+            record.survival += f64::from(standing.survival) * matches;
+            record.biomass += f64::from(standing.biomass) * matches;
+        }
+    }
+
+    /// Adds one match between the named scripts, such as a headless run.
+    /// The one with more biomass than every other wins; a script alone in
+    /// its match doesn't.
+    pub fn add_match(&mut self, scores: &[(String, Score)]) {
+        for (i, (name, score)) in scores.iter().enumerate() {
+            let mut opponents = scores
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, (_, other))| other)
+                .peekable();
+            let won =
+                opponents.peek().is_some() && opponents.all(|other| score.biomass > other.biomass);
+            let record = self.scripts.entry(name.clone()).or_default();
+            record.matches += 1;
+            record.wins += usize::from(won);
+            record.survival += f64::from(score.survival);
+            record.biomass += f64::from(score.biomass);
+        }
+    }
+
+    /// Every script from first to last.
+    pub fn ranked(&self) -> Vec<(&str, &Record)> {
+        let mut ranked = self
+            .scripts
+            .iter()
+            .map(|(name, record)| (name.as_str(), record))
+            .collect::<Vec<_>>();
+        ranked.sort_by(|(_, a), (_, b)| {
+            b.win_rate()
+                .total_cmp(&a.win_rate())
+                .then(b.mean_biomass().total_cmp(&a.mean_biomass()))
+        });
+        ranked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn standing(name: &str, wins: usize, biomass: f32) -> Standing {
+        Standing {
+            name: name.to_owned(),
+            matches: 4,
+            wins,
+            points: wins as f32,
+            survivors: 10.,
+            biomass,
+            kills: 0.,
+            survival: 20.,
+        }
+    }
+
+    fn score(biomass: f32) -> Score {
+        Score {
+            survivors: 5,
+            biomass,
+            kills: 0,
+            survival: 50.,
+        }
+    }
+
+    #[test]
+    fn test_leaderboard() {
+        let mut board = Leaderboard::default();
+        board.add_standings(&[standing("hunter", 3, 100.), standing("idle", 1, 40.)]);
+        board.add_match(&[
+            ("idle".to_owned(), score(80.)),
+            ("new".to_owned(), score(10.)),
+        ]);
+        board.add_match(&[("solo".to_owned(), score(500.))]);
+
+        let idle = board.scripts["idle"];
+        assert_eq!((idle.matches, idle.wins), (5, 2));
+        assert_eq!(idle.mean_biomass(), (4. * 40. + 80.) / 5.);
+        assert_eq!(idle.mean_survival(), (4. * 20. + 50.) / 5.);
+        assert_eq!(board.scripts["solo"].wins, 0);
+
+        let ranked = board.ranked();
+        let names = ranked.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        assert_eq!(names, ["hunter", "idle", "solo", "new"]);
+    }
+
+    #[test]
+    fn test_load_and_save() {
+        let dir = std::env::temp_dir().join(format!("leaderboard-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("leaderboard.json");
+        assert_eq!(Leaderboard::load(&path).unwrap(), Leaderboard::default());
+
+        let mut board = Leaderboard::default();
+        board.add_standings(&[standing("hunter", 3, 100.)]);
+        board.save(&path).unwrap();
+        let mut loaded = Leaderboard::load(&path).unwrap();
+        assert_eq!(loaded, board);
+
+        loaded.add_standings(&[standing("hunter", 1, 60.)]);
+        loaded.save(&path).unwrap();
+        let hunter = Leaderboard::load(&path).unwrap().scripts["hunter"];
+        assert_eq!((hunter.matches, hunter.wins), (8, 4));
+        assert_eq!(hunter.mean_biomass(), 80.);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
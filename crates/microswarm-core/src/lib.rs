@@ -0,0 +1,114 @@
+//! A sandbox where microbes driven by Rhai scripts hunt, flee and reproduce.
+//!
+//! [`Simulation`] is the entry point: register species scripts, spawn
+//! microbes, and step the world forward. The built-in species live in
+//! [`scripts`], which also documents the functions available to scripts.
+//! [`WorldBuilder`] sets up a populated world in one go, and [`islands`]
+//! runs several worlds with microbes migrating between them. [`config`]
+//! builds a populated simulation from a `world.toml` experiment file,
+//! [`plugin`] adds custom rules to the world, [`brain`] drives species from
+//! Rust instead of Rhai, [`events`] reports what happens in it, [`stats`]
+//! keeps per-species numbers on it, and [`observer`] runs custom analysis
+//! after every tick. [`rules`] turns a run into a match with a winner, and
+//! [`standoff`] settles fights between two biting microbes. [`shape`] makes
+//! the arena a circle or polygon instead of a square, [`climate`] makes
+//! parts of it hotter than others, [`wells`] pull microbes towards points
+//! in it, and [`trickle`] keeps fresh prey coming. [`sandbox`] limits what
+//! untrusted scripts can do, and [`math`] makes runs reproducible across
+//! platforms. [`jaws`] and [`eyes`] set how far and wide each microbe bites
+//! and sees, and [`caste`] splits a species into roles its offspring are
+//! born into. [`mutation`] makes offspring differ from their parents,
+//! [`cooldown`] rests microbes after they split, [`overflow`] caps the
+//! energy microbes can hold, and [`population`] how many there are.
+//! [`disruption`] strikes the world with meteors, famines and blooms at set
+//! ticks. [`scene`] reads and writes worlds as readable JSON. [`replay`],
+//! [`metrics`] and [`genealogy`] write runs to disk, [`checkpoint`] saves
+//! long runs as they go, [`verify`] checks a recorded run plays out the
+//! same again, [`tournament`] ranks scripts against each other,
+//! [`leaderboard`] keeps their results across runs, [`evolve`] tunes a
+//! script's constants, and [`sweep`] runs experiments across a range of
+//! simulation constants. [`watcher`] adds species from scripts dropped into
+//! a directory while a world runs, and [`reload`] applies edits to a config
+//! file to the world it built.
+//!
+//! This crate has no GUI dependencies. The desktop viewer and command line
+//! are in `microswarm-viewer`.
+//!
+//! The default `net` feature adds [`monitor`] and [`stream`], which serve
+//! runs over the network from background threads, [`control`], which takes
+//! commands for them, [`lobby`], which takes scripts for a match over HTTP,
+//! [`fetch`], which downloads scripts shared at a URL, and [`remote`]
+//! brains.
+//! Turn it off to build for targets without sockets or threads, such as
+//! `wasm32-unknown-unknown`.
+
+pub mod brain;
+mod builder;
+pub mod caste;
+pub mod checkpoint;
+pub mod climate;
+pub mod config;
+#[cfg(feature = "net")]
+pub mod control;
+mod controls;
+pub mod cooldown;
+pub mod disruption;
+mod error;
+pub mod events;
+pub mod evolve;
+pub mod eyes;
+#[cfg(feature = "net")]
+pub mod fetch;
+pub mod genealogy;
+pub mod islands;
+pub mod jaws;
+pub mod leaderboard;
+#[cfg(feature = "net")]
+pub mod lobby;
+pub mod math;
+pub mod metrics;
+mod microbe;
+#[cfg(feature = "net")]
+pub mod monitor;
+pub mod mutation;
+pub mod observer;
+pub mod overflow;
+pub mod palette;
+pub mod plugin;
+pub mod population;
+pub mod quadtree;
+mod random;
+pub mod reload;
+#[cfg(feature = "net")]
+pub mod remote;
+pub mod replay;
+pub mod rules;
+pub mod sandbox;
+pub mod scene;
+pub mod scripts;
+pub mod shape;
+mod simulation;
+pub mod standoff;
+pub mod stats;
+#[cfg(feature = "net")]
+pub mod stream;
+pub mod sweep;
+mod systems;
+pub mod tournament;
+pub mod trickle;
+mod tuning;
+pub mod verify;
+pub mod watcher;
+pub mod wells;
+mod world;
+
+pub use builder::WorldBuilder;
+pub use controls::Controls;
+pub use ecolor::Color32;
+pub use error::SimError;
+pub use events::{Event, StepReport};
+pub use microbe::{Death, Microbe, Transform, Vector2};
+pub use simulation::{MicrobeState, Simulation, Snapshot, DELTA_TIME};
+pub use systems::Senses;
+pub use tuning::*;
+pub use world::{UnknownSpecies, World};
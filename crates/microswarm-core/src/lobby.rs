@@ -0,0 +1,251 @@
+//! Scripts submitted over HTTP before a match, for party games.
+//!
+//! [`Lobby::open`] takes entries from a background thread until
+//! [`Lobby::close`]. Players submit a script with any HTTP client:
+//!
+//! ```text
+//! curl --data-binary @pack.rhai http://192.168.1.20:9002/scripts
+//! ```
+//!
+//! | request          | answer                                                  |
+//! |------------------|---------------------------------------------------------|
+//! | `POST /scripts`  | `201` with the entry's name, or why it was turned away  |
+//! | `GET /scripts`   | `{"open": true, "entries": ["Pack", …]}`                |
+//!
+//! Every script is vetted as with [`fetch::vet`] before it's let in, so a
+//! match never starts with a script that won't run. Entries are named by
+//! their [`scripts::name`] comment, or `player N`, with a number added to
+//! tell apart entries of the same name.
+//!
+//! [`fetch::vet`]: crate::fetch::vet
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::fetch::{self, MAX_SCRIPT_SIZE};
+use crate::sandbox::Sandbox;
+use crate::scripts;
+
+/// Entries a lobby takes by default.
+pub const MAX_ENTRIES: usize = 16;
+
+/// A script let into the lobby.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub source: String,
+    /// Who submitted it.
+    pub from: SocketAddr,
+}
+
+#[derive(Debug)]
+struct State {
+    entries: Vec<Entry>,
+    open: bool,
+    max_entries: usize,
+    sandbox: Sandbox,
+}
+
+/// Takes script submissions until it's closed.
+pub struct Lobby {
+    state: Arc<Mutex<State>>,
+    addr: SocketAddr,
+}
+
+impl Lobby {
+    /// Listens on `addr`, such as `0.0.0.0:9002`, vetting entries against
+    /// `sandbox`.
+    pub fn open(addr: impl ToSocketAddrs, sandbox: Sandbox) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State {
+            entries: Vec::new(),
+            open: true,
+            max_entries: MAX_ENTRIES,
+            sandbox,
+        }));
+        let shared = state.clone();
+        thread::Builder::new()
+            .name("lobby".to_owned())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    if let Err(error) = respond(stream, &shared) {
+                        tracing::debug!(%error, "lobby request failed");
+                    }
+                }
+            })?;
+        Ok(Self { state, addr })
+    }
+
+    /// Turns entries away once there are `max` of them.
+    pub fn max_entries(self, max: usize) -> Self {
+        lock(&self.state).max_entries = max;
+        self
+    }
+
+    /// The address actually listened on, useful when binding port 0.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The entries so far.
+    pub fn entries(&self) -> Vec<Entry> {
+        lock(&self.state).entries.clone()
+    }
+
+    /// Stops taking entries and returns them, in the order they came in.
+    pub fn close(&self) -> Vec<Entry> {
+        let mut state = lock(&self.state);
+        state.open = false;
+        state.entries.clone()
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn respond(mut stream: TcpStream, state: &Mutex<State>) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let from = stream.peer_addr()?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    let mut length = 0;
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(usize::MAX);
+            }
+        }
+        header.clear();
+    }
+
+    let mut words = request.split_whitespace();
+    let (method, path) = (words.next().unwrap_or(""), words.next().unwrap_or(""));
+    let (status, body) = match (method, path) {
+        ("GET", "/scripts") => ("200 OK", listing(&lock(state))),
+        ("POST", "/scripts") if length > MAX_SCRIPT_SIZE => (
+            "413 Payload Too Large",
+            format!("scripts can be at most {MAX_SCRIPT_SIZE} bytes\n"),
+        ),
+        ("POST", "/scripts") => {
+            let mut source = vec![0; length];
+            reader.read_exact(&mut source)?;
+            submit(state, source, from)
+        }
+        _ => (
+            "404 Not Found",
+            "POST a script to /scripts, or GET /scripts for the entries\n".to_owned(),
+        ),
+    };
+    let content_type = match body.starts_with('{') {
+        true => "application/json",
+        false => "text/plain; charset=utf-8",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+/// Vets `source` and adds it to the lobby, answering with a status and body.
+fn submit(state: &Mutex<State>, source: Vec<u8>, from: SocketAddr) -> (&'static str, String) {
+    let Ok(source) = String::from_utf8(source) else {
+        return ("400 Bad Request", "scripts must be UTF-8 text\n".to_owned());
+    };
+    let sandbox = {
+        let state = lock(state);
+        if !state.open {
+            return ("409 Conflict", "the lobby is closed\n".to_owned());
+        }
+        state.sandbox.clone()
+    };
+    // Vetting runs the script, so it's done without holding the lock.
+    if let Err(error) = fetch::vet(&source, &sandbox) {
+        return ("400 Bad Request", format!("{error}\n"));
+    }
+
+    let mut state = lock(state);
+    if !state.open {
+        return ("409 Conflict", "the lobby is closed\n".to_owned());
+    }
+    if state.entries.len() >= state.max_entries {
+        return ("409 Conflict", "the lobby is full\n".to_owned());
+    }
+    let base = match scripts::name(&source) {
+        Some(name) => name.to_owned(),
+        None => format!("player {}", state.entries.len() + 1),
+    };
+    let taken = |name: &str| state.entries.iter().any(|entry| entry.name == name);
+    let name = (1..)
+        .map(|n| match n {
+            1 => base.clone(),
+            n => format!("{base} {n}"),
+        })
+        .find(|name| !taken(name))
+        .unwrap_or(base);
+    tracing::info!(name, %from, "script entered");
+    state.entries.push(Entry {
+        name: name.clone(),
+        source,
+        from,
+    });
+    ("201 Created", format!("{name}\n"))
+}
+
+/// The lobby's state as JSON.
+fn listing(state: &State) -> String {
+    let names = state
+        .entries
+        .iter()
+        .map(|entry| entry.name.as_str())
+        .collect::<Vec<_>>();
+    serde_json::json!({ "open": state.open, "entries": names }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(addr: SocketAddr, method: &str, body: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{method} /scripts HTTP/1.1\r\nHost: test\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_lobby() {
+        let lobby = Lobby::open("127.0.0.1:0", Sandbox::default())
+            .unwrap()
+            .max_entries(3);
+        let addr = lobby.addr();
+        let post = |body: &str| request(addr, "POST", body);
+
+        assert!(post("// name: Rock\nnew_controls()").starts_with("HTTP/1.1 201"));
+        assert!(post("// name: Rock\nnew_controls()").ends_with("Rock 2\n"));
+        assert!(post("new_controls()").ends_with("player 3\n"));
+        assert!(post("new_controls()").contains("full"));
+        assert!(post("let controls = ;").starts_with("HTTP/1.1 400"));
+        assert!(request(addr, "GET", "")
+            .ends_with(r#"{"entries":["Rock","Rock 2","player 3"],"open":true}"#));
+
+        let entries = lobby.close();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].source, "// name: Rock\nnew_controls()");
+        assert!(post("new_controls()").contains("closed"));
+    }
+}
@@ -0,0 +1,191 @@
+//! How a world does the trigonometry behind moving and sensing.
+//!
+//! Rust's `sin`, `cos` and `atan2` call the platform's maths library, whose
+//! last bits differ between operating systems, architectures and compiler
+//! versions. Within a run those differences grow until a seeded world
+//! plays out differently on another machine. [`Math::Fixed`] avoids them:
+//!
+//! - `sin` and `cos` are looked up in a table of [`TABLE_SIZE`] angles,
+//!   built once from a series that only adds and multiplies,
+//! - `atan2` is a polynomial, again only adding and multiplying,
+//! - positions are rounded to a grid of `1 / `[`POSITION_SCALE`] every
+//!   tick, so tiny differences in headings can't pile up.
+//!
+//! Addition, multiplication, division and square roots are exactly rounded
+//! everywhere, and Rust never fuses them, so these give the same bits on
+//! every platform. Headings are coarser than with [`Math::Float`], about a
+//! tenth of a degree, which makes no visible difference to the swarm.
+//!
+//! Scripts are unaffected: Rhai's own trigonometry still uses the platform's
+//! library, so species that call `sin` or `cos` aren't portable either way.
+
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{FRAC_PI_2, PI};
+use std::sync::OnceLock;
+
+/// Angles in a full turn of the [`Math::Fixed`] sine table.
+pub const TABLE_SIZE: usize = 4096;
+
+/// Grid steps per unit that [`Math::Fixed`] rounds positions to. A power
+/// of two, so every step is exact in an `f32` across any sensible arena.
+pub const POSITION_SCALE: f32 = 256.;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Math {
+    /// The platform's `f32` functions. Fastest and most precise, but only
+    /// reproducible on the same platform and build.
+    #[default]
+    Float,
+    /// Table-based trigonometry and grid-rounded positions, reproducible
+    /// everywhere.
+    Fixed,
+}
+
+impl Math {
+    pub fn sin_cos(self, angle: f32) -> (f32, f32) {
+        match self {
+            Math::Float => (angle.sin(), angle.cos()),
+            Math::Fixed => {
+                let turn = (angle * (TABLE_SIZE as f32 / (2. * PI))).round() as i64;
+                let index = turn.rem_euclid(TABLE_SIZE as i64) as usize;
+                let table = table();
+                (table[index], table[(index + TABLE_SIZE / 4) % TABLE_SIZE])
+            }
+        }
+    }
+
+    pub fn atan2(self, y: f32, x: f32) -> f32 {
+        match self {
+            Math::Float => y.atan2(x),
+            Math::Fixed => atan2(y, x),
+        }
+    }
+
+    /// Rounds a coordinate to the grid positions live on.
+    pub fn position(self, value: f32) -> f32 {
+        match self {
+            Math::Float => value,
+            Math::Fixed => (value * POSITION_SCALE).round() / POSITION_SCALE,
+        }
+    }
+}
+
+/// The sines of a full turn, in [`TABLE_SIZE`] steps.
+fn table() -> &'static [f32; TABLE_SIZE] {
+    static TABLE: OnceLock<Box<[f32; TABLE_SIZE]>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let quarter = TABLE_SIZE / 4;
+        let mut table = Box::new([0.; TABLE_SIZE]);
+        for i in 0..=quarter {
+            let sine = series_sin(i as f64 * std::f64::consts::FRAC_PI_2 / quarter as f64) as f32;
+            table[i] = sine;
+            table[(TABLE_SIZE / 2 - i) % TABLE_SIZE] = sine;
+            table[(TABLE_SIZE / 2 + i) % TABLE_SIZE] = -sine;
+            table[(TABLE_SIZE - i) % TABLE_SIZE] = -sine;
+        }
+        table
+    })
+}
+
+/// The Taylor series of `sin`, which converges well past `f32` precision
+/// on `0..=π/2`.
+fn series_sin(x: f64) -> f64 {
+    let mut term = x;
+    let mut sum = x;
+    for n in 1..12 {
+        term *= -x * x / ((2 * n) as f64 * (2 * n + 1) as f64);
+        sum += term;
+    }
+    sum
+}
+
+/// `atan2` from a polynomial fit of `atan` on `-1..=1`, accurate to about
+/// `1e-5` radians.
+fn atan2(y: f32, x: f32) -> f32 {
+    if x == 0. && y == 0. {
+        return 0.;
+    }
+    let atan = |z: f32| {
+        let z2 = z * z;
+        z * (0.999_866
+            + z2 * (-0.330_299_5 + z2 * (0.180_141 + z2 * (-0.085_133 + z2 * 0.020_835_1))))
+    };
+    if x.abs() >= y.abs() {
+        let angle = atan(y / x);
+        match (x < 0., y < 0.) {
+            (false, _) => angle,
+            (true, false) => angle + PI,
+            (true, true) => angle - PI,
+        }
+    } else {
+        let angle = -atan(x / y);
+        if y < 0. {
+            angle - FRAC_PI_2
+        } else {
+            angle + FRAC_PI_2
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{scripts, Color32, Simulation, WorldBuilder};
+
+    #[test]
+    fn test_fixed_trigonometry() {
+        let step = 2. * PI / TABLE_SIZE as f32;
+        for i in -5000..5000 {
+            let angle = i as f32 * 0.003;
+            let (sin, cos) = Math::Fixed.sin_cos(angle);
+            assert!((sin - angle.sin()).abs() <= step, "sin {angle}");
+            assert!((cos - angle.cos()).abs() <= step, "cos {angle}");
+        }
+        assert_eq!(Math::Fixed.sin_cos(0.), (0., 1.));
+        assert_eq!(Math::Fixed.sin_cos(FRAC_PI_2), (1., 0.));
+
+        for i in 0..360 {
+            let angle = (i as f32).to_radians() - PI;
+            let (y, x) = (angle.sin() * 7., angle.cos() * 7.);
+            let diff = Math::Fixed.atan2(y, x) - y.atan2(x);
+            // Either side of ±π is the same heading.
+            let diff = (diff + PI).rem_euclid(2. * PI) - PI;
+            assert!(diff.abs() < 1e-4, "atan2 {angle}");
+        }
+        assert_eq!(Math::Fixed.atan2(0., 0.), 0.);
+    }
+
+    #[test]
+    fn test_fixed_run() {
+        let run = || {
+            let mut sim = WorldBuilder::new()
+                .arena(60.)
+                .seed(5)
+                .math(Math::Fixed)
+                .species(scripts::aggressive_hunter_script(), 20, Color32::RED)
+                .species(scripts::timid_herbivore_script(), 20, Color32::GREEN)
+                .build()
+                .map(Simulation::from)
+                .unwrap();
+            for _ in 0..100 {
+                sim.step().unwrap();
+            }
+            sim.snapshot()
+        };
+        let snapshot = run();
+        let on_grid = |v: f32| Math::Fixed.position(v) == v;
+        assert!(snapshot
+            .microbes
+            .iter()
+            .all(|m| on_grid(m.x) && on_grid(m.y)));
+        assert_eq!(snapshot, run());
+    }
+
+    #[test]
+    fn test_position() {
+        assert_eq!(Math::Float.position(0.1234), 0.1234);
+        assert_eq!(Math::Fixed.position(0.1234), 32. / 256.);
+        assert_eq!(Math::Fixed.position(-399.999), -400.);
+    }
+}
@@ -0,0 +1,240 @@
+//! Per-species metrics written to CSV or JSON for analysis outside the app.
+//!
+//! Every `interval` ticks the exporter writes one row per species, from the
+//! world's [`crate::stats`]:
+//!
+//! | field         | meaning                                                  |
+//! |---------------|----------------------------------------------------------|
+//! | `tick`        | tick the row was taken on                                |
+//! | `species`     | species name, or the start of its id if it has none      |
+//! | `population`  | living microbes                                          |
+//! | `mean_energy` | mean energy of the living microbes, 0 if there are none  |
+//! | `mean_age`    | mean age in ticks of the living microbes, 0 if none      |
+//! | `births`      | microbes born since the previous row                     |
+//! | `deaths`      | microbes that died since the previous row                |
+//! | `kills`       | deaths of other microbes this species took a bite in     |
+//!
+//! CSV files have a header line. JSON files hold one object per line (JSON
+//! Lines), which `pandas.read_json(path, lines=True)` and R's
+//! `jsonlite::stream_in` read directly.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::stats::TickStats;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    /// JSON for `.json` and `.jsonl` paths, CSV otherwise.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json" | "jsonl") => Self::Json,
+            _ => Self::Csv,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Counts {
+    births: usize,
+    deaths: usize,
+    kills: usize,
+}
+
+#[derive(Serialize)]
+struct Row<'a> {
+    tick: u64,
+    species: &'a str,
+    population: usize,
+    mean_energy: f32,
+    mean_age: f32,
+    births: usize,
+    deaths: usize,
+    kills: usize,
+}
+
+/// Writes a row per species every `interval` ticks.
+pub struct Exporter<W: Write> {
+    out: W,
+    format: Format,
+    interval: u64,
+    names: HashMap<Uuid, String>,
+    /// Counts since the last rows were written.
+    counts: BTreeMap<Uuid, Counts>,
+}
+
+impl Exporter<BufWriter<File>> {
+    /// Creates `path`, picking the format from its extension.
+    pub fn create(path: impl AsRef<Path>, interval: u64) -> io::Result<Self> {
+        let format = Format::from_path(path.as_ref());
+        Self::new(BufWriter::new(File::create(path)?), format, interval)
+    }
+}
+
+impl<W: Write> Exporter<W> {
+    pub fn new(mut out: W, format: Format, interval: u64) -> io::Result<Self> {
+        if format == Format::Csv {
+            writeln!(
+                out,
+                "tick,species,population,mean_energy,mean_age,births,deaths,kills"
+            )?;
+        }
+        Ok(Self {
+            out,
+            format,
+            interval: interval.max(1),
+            names: HashMap::new(),
+            counts: BTreeMap::new(),
+        })
+    }
+
+    /// Names `species` in the output instead of showing its id.
+    pub fn name(&mut self, species: Uuid, name: impl Into<String>) {
+        self.names.insert(species, name.into());
+    }
+
+    /// Adds a tick's births, deaths and kills to the running counts, and
+    /// writes rows if the tick falls on the interval. Pass it every tick's
+    /// stats, such as `world.stats().latest()` after each step.
+    pub fn record(&mut self, stats: &TickStats) -> io::Result<()> {
+        for (species, tick) in &stats.species {
+            let counts = self.counts.entry(*species).or_default();
+            counts.births += tick.births;
+            counts.deaths += tick.deaths;
+            counts.kills += tick.kills;
+        }
+        if stats.tick.is_multiple_of(self.interval) {
+            self.write_rows(stats)?;
+        }
+        Ok(())
+    }
+
+    fn write_rows(&mut self, stats: &TickStats) -> io::Result<()> {
+        for (species, tick) in &stats.species {
+            let counts = self.counts.remove(species).unwrap_or_default();
+            let id = species.to_string();
+            let name = self.names.get(species).map_or(&id[..8], String::as_str);
+            let row = Row {
+                tick: stats.tick,
+                species: name,
+                population: tick.population,
+                mean_energy: tick.mean_energy,
+                mean_age: tick.mean_age,
+                births: counts.births,
+                deaths: counts.deaths,
+                kills: counts.kills,
+            };
+            match self.format {
+                Format::Csv => writeln!(
+                    self.out,
+                    "{},{},{},{},{},{},{},{}",
+                    row.tick,
+                    csv_field(row.species),
+                    row.population,
+                    row.mean_energy,
+                    row.mean_age,
+                    row.births,
+                    row.deaths,
+                    row.kills
+                )?,
+                Format::Json => {
+                    serde_json::to_writer(&mut self.out, &row)?;
+                    writeln!(self.out)?;
+                }
+            }
+        }
+        self.counts.clear();
+        Ok(())
+    }
+
+    /// Flushes the output and returns the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Quotes a CSV field if it needs it.
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{scripts, Color32, Simulation};
+
+    fn export(format: Format) -> String {
+        let mut sim = Simulation::with_seed(60., 2).unwrap();
+        let hunters = sim.add_species(scripts::aggressive_hunter_script());
+        let idle = sim.add_species("new_controls()");
+        for i in 0..20 {
+            sim.spawn(hunters, i as f32 * 4. - 40., 0., i as f32, Color32::RED);
+        }
+        let mut exporter = Exporter::new(Vec::new(), format, 5).unwrap();
+        exporter.name(hunters, "hunter, aggressive");
+        for _ in 0..10 {
+            sim.step().unwrap();
+            exporter
+                .record(sim.world().stats().latest().unwrap())
+                .unwrap();
+        }
+        let out = String::from_utf8(exporter.finish().unwrap()).unwrap();
+        assert!(out.contains(&idle.to_string()[..8]));
+        out
+    }
+
+    #[test]
+    fn test_csv() {
+        let out = export(Format::Csv);
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            "tick,species,population,mean_energy,mean_age,births,deaths,kills"
+        );
+        // Two species, at ticks 5 and 10.
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1..]
+            .iter()
+            .any(|l| l.starts_with("5,\"hunter, aggressive\",")));
+        assert!(lines[1..]
+            .iter()
+            .all(|l| l.starts_with("5,") || l.starts_with("10,")));
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let out = export(Format::Json);
+        let rows = out
+            .lines()
+            .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(rows.len(), 4);
+        let hunter = &rows[..2]
+            .iter()
+            .find(|r| r["species"] == "hunter, aggressive")
+            .unwrap();
+        assert_eq!(hunter["tick"], 5);
+        assert!(hunter["population"].as_u64().unwrap() > 0);
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(Format::from_path(Path::new("run.jsonl")), Format::Json);
+        assert_eq!(Format::from_path(Path::new("run.csv")), Format::Csv);
+        assert_eq!(Format::from_path(Path::new("run")), Format::Csv);
+    }
+}
@@ -0,0 +1,351 @@
+use ecolor::Color32;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use uuid::Uuid;
+
+use crate::controls::Controls;
+use crate::eyes::Eyes;
+use crate::jaws::Jaws;
+use crate::math::Math;
+use crate::quadtree::{Locatable, Point, Relocatable};
+use crate::simulation::{MicrobeState, DELTA_TIME};
+use crate::tuning::{Tuning, HEALTH, STAMINA};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Vector2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Where a microbe is and which way it faces.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+    pub(crate) position: Vector2,
+    pub(crate) rotation: f32,
+}
+
+impl Transform {
+    pub fn new(x: f32, y: f32, rotation: f32) -> Self {
+        Self {
+            position: Vector2 { x, y },
+            rotation,
+        }
+    }
+
+    pub fn position(&self) -> Vector2 {
+        self.position
+    }
+
+    /// Heading in radians, where 0 faces along the positive x axis.
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+}
+
+/// A living microbe, as returned by [`crate::World::microbes`].
+///
+/// ```
+/// use microswarm::{scripts, Color32, WorldBuilder};
+///
+/// let world = WorldBuilder::new()
+///     .species(scripts::random_script(), 10, Color32::YELLOW)
+///     .build()
+///     .unwrap();
+/// for microbe in world.microbes() {
+///     assert_eq!(microbe.lineage(), microbe.id());
+///     assert_eq!(microbe.energy(), world.tuning().health);
+///     assert_eq!(microbe.color(), Color32::YELLOW);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Microbe {
+    pub(crate) id: Uuid,
+    pub(crate) lineage: Uuid,
+    pub(crate) transform: Transform,
+    pub(crate) script_id: Uuid,
+    pub(crate) energy: f32,
+    /// Spent by biting and regained by resting, apart from energy. Saves
+    /// from before stamina start full.
+    #[serde(default = "full_stamina")]
+    pub(crate) stamina: f32,
+    /// Hurts whatever bites it, at a cost to its upkeep. Passed on to its
+    /// offspring.
+    #[serde(default)]
+    pub(crate) spiky: bool,
+    /// What it can bite. Passed on to its offspring.
+    #[serde(default)]
+    pub(crate) jaws: Jaws,
+    /// How far and wide it sees. Passed on to its offspring.
+    #[serde(default)]
+    pub(crate) eyes: Eyes,
+    /// Whether it cloaked last tick, hiding from far senses.
+    #[serde(default)]
+    pub(crate) cloaked: bool,
+    /// Energy put aside past an [`crate::overflow::EnergyCap`].
+    #[serde(default)]
+    pub(crate) fat: f32,
+    /// Ticks left before it can split again. See [`crate::cooldown`].
+    #[serde(default)]
+    pub(crate) cooldown: u64,
+    /// Its index in its species' castes, if it has one. See
+    /// [`crate::caste`].
+    #[serde(default)]
+    pub(crate) caste: Option<usize>,
+    #[serde(with = "rgba")]
+    pub(crate) color: Color32,
+    /// Tick the microbe was spawned or born on.
+    pub(crate) born: u64,
+}
+
+impl Locatable for Microbe {
+    fn location(&self) -> Point {
+        Point::new(self.transform.position.x, self.transform.position.y)
+    }
+}
+
+impl Relocatable for Microbe {
+    fn set_location(&mut self, point: Point) {
+        self.transform.position.x = point.x();
+        self.transform.position.y = point.y();
+    }
+}
+
+impl Microbe {
+    /// A microbe without a parent, founding a lineage named after itself.
+    pub(crate) fn new(
+        id: Uuid,
+        x: f32,
+        y: f32,
+        rotation: f32,
+        script_id: Uuid,
+        color: Color32,
+    ) -> Self {
+        Self {
+            id,
+            lineage: id,
+            transform: Transform::new(x, y, rotation),
+            script_id,
+            energy: HEALTH,
+            stamina: STAMINA,
+            spiky: false,
+            jaws: Jaws::default(),
+            eyes: Eyes::default(),
+            cloaked: false,
+            fat: 0.,
+            cooldown: 0,
+            caste: None,
+            color,
+            born: 0,
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// The microbe that founded this one's line of descent.
+    pub fn lineage(&self) -> Uuid {
+        self.lineage
+    }
+
+    /// The species the microbe belongs to.
+    pub fn script_id(&self) -> Uuid {
+        self.script_id
+    }
+
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    pub fn position(&self) -> Vector2 {
+        self.transform.position
+    }
+
+    /// Heading in radians, where 0 faces along the positive x axis.
+    pub fn rotation(&self) -> f32 {
+        self.transform.rotation
+    }
+
+    pub fn energy(&self) -> f32 {
+        self.energy
+    }
+
+    pub fn stamina(&self) -> f32 {
+        self.stamina
+    }
+
+    pub fn spiky(&self) -> bool {
+        self.spiky
+    }
+
+    /// How far and wide it can bite. See [`crate::jaws`].
+    pub fn jaws(&self) -> Jaws {
+        self.jaws
+    }
+
+    /// How far and wide it sees. See [`crate::eyes`].
+    pub fn eyes(&self) -> Eyes {
+        self.eyes
+    }
+
+    /// Whether it cloaked last tick, so that only microbes within
+    /// [`Tuning::detect_range_close`] can sense it.
+    pub fn cloaked(&self) -> bool {
+        self.cloaked
+    }
+
+    /// Energy stored past the world's energy cap. See [`crate::overflow`].
+    pub fn fat(&self) -> f32 {
+        self.fat
+    }
+
+    /// Ticks left before it can split again. See [`crate::cooldown`].
+    pub fn cooldown(&self) -> u64 {
+        self.cooldown
+    }
+
+    /// Its index in [`World::castes`] of its species, if it has a caste.
+    ///
+    /// [`World::castes`]: crate::World::castes
+    pub fn caste(&self) -> Option<usize> {
+        self.caste
+    }
+
+    /// How many times [`Tuning::action_energy_consumption`] it spends each
+    /// tick, for its traits: spikes, jaws bigger than usual and eyes that
+    /// see further all cost upkeep, and smaller jaws and closer eyes save
+    /// some. The usual microbe's is 1.
+    pub fn metabolism(&self, tuning: &Tuning) -> f32 {
+        let spikes = if self.spiky { tuning.spike_upkeep } else { 1. };
+        let jaws = 1. + tuning.jaw_upkeep * (self.jaws.size() - 1.);
+        let eyes = 1. + tuning.eye_upkeep * (self.eyes.focus - 1.);
+        (spikes * jaws * eyes).max(0.)
+    }
+
+    /// Whether eating this tick would bite, rather than find the microbe
+    /// too tired to.
+    pub(crate) fn can_bite(&self, controls: &Controls, tuning: &Tuning) -> bool {
+        controls.eat && self.stamina >= tuning.bite_stamina
+    }
+
+    pub fn color(&self) -> Color32 {
+        self.color
+    }
+
+    /// Tick the microbe was spawned or born on.
+    pub fn born(&self) -> u64 {
+        self.born
+    }
+
+    pub(crate) fn state(&self) -> MicrobeState {
+        MicrobeState {
+            id: self.id,
+            lineage: self.lineage,
+            species: self.script_id,
+            x: self.transform.position.x,
+            y: self.transform.position.y,
+            rotation: self.transform.rotation,
+            energy: self.energy,
+            stamina: self.stamina,
+            spiky: self.spiky,
+            jaws: self.jaws,
+            eyes: self.eyes,
+            cloaked: self.cloaked,
+            fat: self.fat,
+            cooldown: self.cooldown,
+            caste: self.caste,
+            color: self.color,
+            born: self.born,
+        }
+    }
+
+    /// `warmth` scales how far it moves and the energy it spends, for the
+    /// [`crate::climate`] where it is, and `pace` how far alone. Its
+    /// energy slows it too, by [`Tuning::speed_factor`].
+    pub(crate) fn update(
+        &mut self,
+        controls: &Controls,
+        tuning: &Tuning,
+        math: Math,
+        delta_time: f32,
+        warmth: f32,
+        pace: f32,
+    ) {
+        // Tuning is per tick of the default length, so a world with shorter
+        // ticks covers the same ground and spends the same energy and
+        // stamina in the same simulated time.
+        let scale = delta_time / DELTA_TIME;
+        // Apply controls to movement
+        let speed = tuning.speed * scale * warmth * pace * tuning.speed_factor(self.energy);
+        let consumption =
+            tuning.action_energy_consumption * scale * warmth * self.metabolism(tuning);
+        self.energy -= consumption;
+
+        // Update position based on controls
+        if controls.forward || controls.back {
+            let (sin, cos) = math.sin_cos(self.transform.rotation);
+            if controls.forward {
+                // self.energy -= ACTION_ENERGY_CONSUMPTION;
+                self.transform.position.x += cos * speed;
+                self.transform.position.y += sin * speed;
+            } else {
+                // self.energy -= ACTION_ENERGY_CONSUMPTION;
+                self.transform.position.x -= cos * speed;
+                self.transform.position.y -= sin * speed;
+            }
+        }
+
+        // Update rotation based on controls
+        let rotation_speed = tuning.rotation_speed * scale;
+        if controls.right {
+            self.transform.rotation += rotation_speed;
+        }
+        if controls.left {
+            self.transform.rotation -= rotation_speed;
+        }
+
+        if controls.eat {
+            self.energy -= consumption + self.jaws.cost(tuning.jaw_cost) * scale;
+        }
+        self.cloaked = controls.cloak;
+        if controls.cloak {
+            self.energy -= tuning.cloak_energy * scale;
+        }
+        if self.can_bite(controls, tuning) {
+            self.stamina -= tuning.bite_stamina * scale;
+        } else {
+            self.stamina = (self.stamina + tuning.stamina_regen * scale).min(tuning.stamina);
+        }
+
+        self.transform.rotation %= 2.0 * PI;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Death {
+    pub species: Uuid,
+    /// Ticks between birth and death.
+    pub lifespan: u64,
+    /// Whether the microbe was eaten, as opposed to starving.
+    pub eaten: bool,
+}
+
+fn full_stamina() -> f32 {
+    STAMINA
+}
+
+/// Colors are stored as plain `[r, g, b, a]` arrays, so saved worlds don't
+/// depend on how the color crate serializes them.
+mod rgba {
+    use ecolor::Color32;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color32, serializer: S) -> Result<S::Ok, S::Error> {
+        color.to_array().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color32, D::Error> {
+        let [r, g, b, a] = <[u8; 4]>::deserialize(deserializer)?;
+        Ok(Color32::from_rgba_premultiplied(r, g, b, a))
+    }
+}
@@ -0,0 +1,301 @@
+//! A Prometheus `/metrics` endpoint for watching long headless runs.
+//!
+//! [`Monitor::serve`] answers scrapes from a background thread with:
+//!
+//! | metric                                  | type    | labels             |
+//! |-----------------------------------------|---------|--------------------|
+//! | `microswarm_ticks_total`                | counter |                    |
+//! | `microswarm_tick_rate`                  | gauge   |                    |
+//! | `microswarm_microbes`                   | gauge   |                    |
+//! | `microswarm_population`                 | gauge   | `species`          |
+//! | `microswarm_births_total`               | counter | `species`          |
+//! | `microswarm_deaths_total`               | counter | `species`, `cause` |
+//! | `microswarm_bites_total`                | counter | `species`          |
+//!
+//! `microswarm_tick_rate` is ticks per second over the last few seconds,
+//! and `cause` is `eaten`, `starved`, `culled` or `struck`.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::events::{Cause, Event, StepReport};
+use crate::simulation::Snapshot;
+
+/// How far back the tick rate looks.
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Default)]
+struct Metrics {
+    ticks: u64,
+    /// When recent ticks were recorded, oldest first.
+    recent: VecDeque<(Instant, u64)>,
+    microbes: usize,
+    names: HashMap<Uuid, String>,
+    population: BTreeMap<Uuid, u64>,
+    births: BTreeMap<Uuid, u64>,
+    eaten: BTreeMap<Uuid, u64>,
+    starved: BTreeMap<Uuid, u64>,
+    culled: BTreeMap<Uuid, u64>,
+    struck: BTreeMap<Uuid, u64>,
+    bites: BTreeMap<Uuid, u64>,
+}
+
+impl Metrics {
+    fn tick_rate(&self) -> f64 {
+        match (self.recent.front(), self.recent.back()) {
+            (Some((start, first)), Some((end, last))) if end > start => {
+                (last - first) as f64 / (*end - *start).as_secs_f64()
+            }
+            _ => 0.,
+        }
+    }
+
+    fn label(&self, species: &Uuid) -> String {
+        let id = species.to_string();
+        let name = self.names.get(species).map_or(&id[..8], String::as_str);
+        name.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    }
+
+    /// Samples of a per-species metric, with any extra labels.
+    fn per_species(&self, counts: &BTreeMap<Uuid, u64>, labels: &str) -> Vec<(String, u64)> {
+        counts
+            .iter()
+            .map(|(species, count)| {
+                let species = self.label(species);
+                (format!("{{species=\"{species}\"{labels}}}"), *count)
+            })
+            .collect()
+    }
+
+    /// The metrics in the Prometheus text format.
+    fn render(&self) -> String {
+        let mut out = String::new();
+        let mut family = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+            let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}");
+            for (labels, value) in samples {
+                let _ = writeln!(out, "{name}{labels} {value}");
+            }
+        };
+        let plain = |value: String| vec![(String::new(), value)];
+        let counts = |samples: Vec<(String, u64)>| {
+            samples
+                .into_iter()
+                .map(|(labels, count)| (labels, count.to_string()))
+                .collect()
+        };
+
+        family(
+            "microswarm_ticks_total",
+            "counter",
+            "Ticks simulated.",
+            plain(self.ticks.to_string()),
+        );
+        family(
+            "microswarm_tick_rate",
+            "gauge",
+            "Ticks per second.",
+            plain(format!("{:.3}", self.tick_rate())),
+        );
+        family(
+            "microswarm_microbes",
+            "gauge",
+            "Living microbes.",
+            plain(self.microbes.to_string()),
+        );
+        family(
+            "microswarm_population",
+            "gauge",
+            "Living microbes per species.",
+            counts(self.per_species(&self.population, "")),
+        );
+        family(
+            "microswarm_births_total",
+            "counter",
+            "Microbes born.",
+            counts(self.per_species(&self.births, "")),
+        );
+        let mut deaths = self.per_species(&self.eaten, ",cause=\"eaten\"");
+        deaths.extend(self.per_species(&self.starved, ",cause=\"starved\""));
+        deaths.extend(self.per_species(&self.culled, ",cause=\"culled\""));
+        deaths.extend(self.per_species(&self.struck, ",cause=\"struck\""));
+        family(
+            "microswarm_deaths_total",
+            "counter",
+            "Microbes died.",
+            counts(deaths),
+        );
+        family(
+            "microswarm_bites_total",
+            "counter",
+            "Bites taken by microbes of the species.",
+            counts(self.per_species(&self.bites, "")),
+        );
+        out
+    }
+}
+
+/// Serves metrics over HTTP while a simulation runs. Feed it every step with
+/// [`Monitor::record`].
+pub struct Monitor {
+    metrics: Arc<Mutex<Metrics>>,
+    addr: SocketAddr,
+}
+
+impl Monitor {
+    /// Listens on `addr`, such as `0.0.0.0:9100`, and answers requests for
+    /// `/metrics` until the process exits.
+    pub fn serve(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let metrics = Arc::new(Mutex::new(Metrics::default()));
+        let shared = metrics.clone();
+        thread::Builder::new()
+            .name("metrics".to_owned())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    // A misbehaving client only costs its own request.
+                    if let Err(error) = respond(stream, &shared) {
+                        tracing::debug!(%error, "metrics request failed");
+                    }
+                }
+            })?;
+        Ok(Self { metrics, addr })
+    }
+
+    /// The address actually listened on, useful when binding port 0.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Uses `name` as the `species` label instead of the species id.
+    pub fn name(&self, species: Uuid, name: impl Into<String>) {
+        self.lock().names.insert(species, name.into());
+    }
+
+    pub fn record(&self, snapshot: &Snapshot, report: &StepReport) {
+        let mut metrics = self.lock();
+        let now = Instant::now();
+        metrics.ticks += 1;
+        metrics.recent.push_back((now, snapshot.tick));
+        while metrics
+            .recent
+            .front()
+            .is_some_and(|(at, _)| now - *at > RATE_WINDOW)
+        {
+            metrics.recent.pop_front();
+        }
+
+        metrics.microbes = snapshot.microbes.len();
+        let mut population = snapshot
+            .species
+            .iter()
+            .map(|s| (*s, 0))
+            .collect::<BTreeMap<_, _>>();
+        for microbe in &snapshot.microbes {
+            *population.entry(microbe.species).or_default() += 1;
+        }
+        metrics.population = population;
+
+        for event in &report.events {
+            match event {
+                Event::MicrobeBorn { microbe, .. } => {
+                    *metrics.births.entry(microbe.species).or_default() += 1;
+                }
+                Event::MicrobeAte { eater_species, .. } => {
+                    *metrics.bites.entry(*eater_species).or_default() += 1;
+                }
+                Event::MicrobeDied { microbe, cause, .. } => {
+                    let deaths = match cause {
+                        Cause::Eaten { .. } => &mut metrics.eaten,
+                        Cause::Starved => &mut metrics.starved,
+                        Cause::Culled => &mut metrics.culled,
+                        Cause::Struck => &mut metrics.struck,
+                    };
+                    *deaths.entry(microbe.species).or_default() += 1;
+                }
+                Event::SpeciesExtinct { .. } | Event::Disrupted { .. } => {}
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Metrics> {
+        // The serving thread never panics while holding the lock, and stale
+        // metrics are better than none.
+        self.metrics.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn respond(mut stream: TcpStream, metrics: &Mutex<Metrics>) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // Drain the headers so the client sees a clean close.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let path = request.split_whitespace().nth(1).unwrap_or("");
+    let (status, content_type, body) = if path == "/metrics" {
+        let body = metrics.lock().unwrap_or_else(|e| e.into_inner()).render();
+        ("200 OK", "text/plain; version=0.0.4; charset=utf-8", body)
+    } else {
+        ("404 Not Found", "text/plain", "try /metrics\n".to_owned())
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color32, Simulation};
+    use std::io::Read;
+
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: test\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_serves_metrics() {
+        let monitor = Monitor::serve("127.0.0.1:0").unwrap();
+        let mut sim = Simulation::new(100.).unwrap();
+        let idle = sim.add_species("new_controls()");
+        monitor.name(idle, "idle \"one\"");
+        sim.spawn(idle, 0., 0., 0., Color32::RED);
+        sim.spawn(idle, 10., 0., 0., Color32::RED);
+        sim.tuning_mut().action_energy_consumption = 1000.;
+        for _ in 0..2 {
+            sim.step().unwrap();
+            monitor.record(&sim.snapshot(), sim.world().report());
+        }
+
+        let response = get(monitor.addr(), "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("\nmicroswarm_ticks_total 2\n"));
+        assert!(response.contains("\nmicroswarm_microbes 0\n"));
+        assert!(response.contains(r#"microswarm_population{species="idle \"one\""} 0"#));
+        assert!(response
+            .contains(r#"microswarm_deaths_total{species="idle \"one\"",cause="starved"} 2"#));
+        assert!(response.contains("# TYPE microswarm_tick_rate gauge"));
+
+        assert!(get(monitor.addr(), "/").starts_with("HTTP/1.1 404"));
+    }
+}
@@ -0,0 +1,142 @@
+//! Offspring that differ from their parents.
+//!
+//! Every species has a [`Mutation`], by default none at all, so offspring
+//! are exact copies. With one, each child's color channels drift with
+//! chance `rate`, by up to `magnitude` of the full 0-255 range, its
+//! spikes (see [`crate::World::set_spiky`]) come or go with chance `rate`,
+//! its [`crate::jaws`] widen, narrow, lengthen or shorten by up to
+//! `magnitude` of their size with chance `rate` each, and so does the focus
+//! of its [`crate::eyes`]. Rates and magnitudes outside 0 to 1 are clamped
+//! to that range, and NaN counts as 0.
+//!
+//! Scripts are shared by the whole species and never mutate;
+//! [`crate::evolve`] tunes their constants between runs instead.
+//!
+//! ```toml
+//! [[species]]
+//! name = "drifters"
+//! builtin = "herbivore"
+//! mutation = { rate = 0.1, magnitude = 0.2 }
+//! ```
+//!
+//! Rates can be changed while the world runs, with
+//! [`crate::Simulation::set_mutation`].
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::microbe::Microbe;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Mutation {
+    /// Chance, from 0 to 1, of each of a child's traits mutating.
+    pub rate: f32,
+    /// How far a mutated trait can move: a color channel by this share of
+    /// 255, and the size of jaws or focus of eyes by this share of itself.
+    pub magnitude: f32,
+}
+
+impl Mutation {
+    pub fn new(rate: f32, magnitude: f32) -> Self {
+        Self { rate, magnitude }.clamped()
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.rate.is_nan() || self.rate <= 0.
+    }
+
+    /// The mutation with its rate and magnitude in 0 to 1.
+    fn clamped(self) -> Self {
+        let unit = |value: f32| {
+            if value.is_nan() {
+                0.
+            } else {
+                value.clamp(0., 1.)
+            }
+        };
+        Self {
+            rate: unit(self.rate),
+            magnitude: unit(self.magnitude),
+        }
+    }
+
+    /// Mutates a newborn `child` in place.
+    pub(crate) fn apply(&self, child: &mut Microbe, rng: &mut impl Rng) {
+        if self.is_none() {
+            return;
+        }
+        // Fields are public, so they may have been set out of range since.
+        let Self { rate, magnitude } = self.clamped();
+        let rate = f64::from(rate);
+        let reach = (magnitude * 255.).round() as i16;
+        let [r, g, b, a] = child.color.to_array();
+        let [r, g, b] = [r, g, b].map(|channel| {
+            if reach == 0 || !rng.gen_bool(rate) {
+                return channel;
+            }
+            (i16::from(channel) + rng.gen_range(-reach..=reach)).clamp(0, 255) as u8
+        });
+        child.color = ecolor::Color32::from_rgba_premultiplied(r, g, b, a);
+        if rng.gen_bool(rate) {
+            child.spiky = !child.spiky;
+        }
+        child.jaws.mutate(rate, magnitude, rng);
+        child.eyes.mutate(rate, magnitude, rng);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ecolor::Color32;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use uuid::Uuid;
+
+    fn child() -> Microbe {
+        let color = Color32::from_rgb(100, 0, 255);
+        Microbe::new(Uuid::nil(), 0., 0., 0., Uuid::nil(), color)
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut unchanged = child();
+        Mutation::default().apply(&mut unchanged, &mut rng);
+        assert_eq!(unchanged.color, child().color);
+
+        // Always mutating flips the spikes, and keeps channels in range.
+        let mut mutated = child();
+        Mutation::new(1., 0.1).apply(&mut mutated, &mut rng);
+        assert!(mutated.spiky);
+        let [r, g, b, _] = mutated.color.to_array();
+        assert!(r.abs_diff(100) <= 26 && g <= 26 && b >= 229);
+
+        let mut colors = std::collections::HashSet::new();
+        for _ in 0..20 {
+            let mut child = child();
+            Mutation::new(1., 1.).apply(&mut child, &mut rng);
+            colors.insert(child.color);
+        }
+        assert!(colors.len() > 1);
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        assert_eq!(Mutation::new(f32::NAN, f32::NAN), Mutation::default());
+        assert_eq!(Mutation::new(2., f32::INFINITY), Mutation::new(1., 1.));
+        assert!(Mutation::new(f32::NAN, 0.5).is_none());
+
+        let mut rng = StdRng::seed_from_u64(0);
+        for magnitude in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -1.] {
+            let mut child = child();
+            Mutation {
+                rate: 1.,
+                magnitude,
+            }
+            .apply(&mut child, &mut rng);
+            assert!(child.jaws.width.is_finite() && child.eyes.focus.is_finite());
+        }
+    }
+}
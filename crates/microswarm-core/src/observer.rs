@@ -0,0 +1,119 @@
+//! Read-only analysis after every tick.
+//!
+//! An [`Observer`] sees the whole [`World`] once each update has finished,
+//! with its microbes, [`World::report`] and [`World::stats`], so custom
+//! metrics such as clustering or spatial segregation can be computed in
+//! place instead of from exported snapshots. Unlike a
+//! [`WorldPlugin`](crate::plugin::WorldPlugin) it can't change anything.
+//! Closures taking a `&World` are observers too:
+//!
+//! ```
+//! use microswarm::{scripts, Color32, Simulation};
+//! use std::cell::Cell;
+//! use std::rc::Rc;
+//!
+//! let mut sim = Simulation::with_seed(100., 1).unwrap();
+//! let walkers = sim.add_species(scripts::random_script());
+//! sim.spawn(walkers, 0., 0., 0., Color32::RED);
+//!
+//! // The furthest any microbe has been from the centre.
+//! let furthest = Rc::new(Cell::new(0f32));
+//! let seen = furthest.clone();
+//! sim.add_observer(Box::new(move |world: &microswarm::World| {
+//!     for microbe in world.microbes() {
+//!         let position = microbe.position();
+//!         seen.set(seen.get().max(position.x.hypot(position.y)));
+//!     }
+//! }));
+//! for _ in 0..10 {
+//!     sim.step().unwrap();
+//! }
+//! assert!(furthest.get() > 0.);
+//! ```
+//!
+//! Observers aren't saved with the world; add them again after
+//! [`World::load`].
+
+use std::fmt;
+
+use crate::world::World;
+
+pub trait Observer {
+    /// Shown when the world is debug printed.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Called at the end of every update, after events are published.
+    fn observe(&mut self, world: &World);
+}
+
+impl<F: FnMut(&World)> Observer for F {
+    fn observe(&mut self, world: &World) {
+        self(world)
+    }
+}
+
+impl fmt::Debug for dyn Observer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{scripts, Color32, Simulation};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Mean distance between microbes of different species, per tick.
+    struct Segregation(Rc<RefCell<Vec<(u64, f32)>>>);
+
+    impl Observer for Segregation {
+        fn observe(&mut self, world: &World) {
+            let microbes = world.microbes().collect::<Vec<_>>();
+            let (mut total, mut pairs) = (0., 0);
+            for (i, a) in microbes.iter().enumerate() {
+                for b in &microbes[i + 1..] {
+                    if a.script_id() != b.script_id() {
+                        let (a, b) = (a.position(), b.position());
+                        total += (a.x - b.x).hypot(a.y - b.y);
+                        pairs += 1;
+                    }
+                }
+            }
+            let mean = if pairs == 0 { 0. } else { total / pairs as f32 };
+            self.0.borrow_mut().push((world.tick(), mean));
+        }
+    }
+
+    #[test]
+    fn test_observers_see_every_tick() {
+        let mut sim = Simulation::with_seed(50., 3).unwrap();
+        let hunters = sim.add_species(scripts::aggressive_hunter_script());
+        let idle = sim.add_species("new_controls()");
+        for i in 0..10 {
+            sim.spawn(hunters, i as f32 * 4. - 20., 0., 0., Color32::RED);
+            sim.spawn(idle, i as f32 * 4. - 20., 10., 0., Color32::BLUE);
+        }
+        let segregation = Rc::new(RefCell::new(Vec::new()));
+        sim.add_observer(Box::new(Segregation(segregation.clone())));
+        let ticks = Rc::new(RefCell::new(Vec::new()));
+        let seen = ticks.clone();
+        sim.add_observer(Box::new(move |world: &World| {
+            // Stats are up to date by the time observers run.
+            let stats = world.stats().latest().unwrap();
+            seen.borrow_mut().push((world.tick(), stats.tick));
+        }));
+        assert!(format!("{:?}", sim.world()).contains("Segregation"));
+
+        for _ in 0..5 {
+            sim.step().unwrap();
+        }
+        assert_eq!(*ticks.borrow(), (1..=5).map(|t| (t, t)).collect::<Vec<_>>());
+        let segregation = segregation.borrow();
+        assert_eq!(segregation.len(), 5);
+        assert!(segregation.iter().all(|(_, mean)| *mean > 0.));
+    }
+}
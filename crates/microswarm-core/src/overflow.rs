@@ -0,0 +1,127 @@
+//! A limit on how much energy a microbe can hold.
+//!
+//! Without an [`EnergyCap`], energy only stops building up when a microbe
+//! reaches [`Tuning::reproduction_threshold`] and splits. With one, energy
+//! past `max` after eating either goes to waste or is put aside as fat,
+//! which flows back at no more than `fat_burn` per default-length tick
+//! whenever the microbe is below the cap again. A cap below the reproduction threshold stops
+//! microbes splitting at all.
+//!
+//! ```toml
+//! [energy_cap]
+//! max = 150.0
+//! overflow = "fat"          # or "wasted", the default
+//! fat_burn = 1.0            # energy per default-length tick from fat
+//! ```
+//!
+//! [`Tuning::reproduction_threshold`]: crate::Tuning::reproduction_threshold
+
+use serde::{Deserialize, Serialize};
+
+use crate::microbe::Microbe;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Overflow {
+    /// Energy past the cap is lost.
+    #[default]
+    Wasted,
+    /// Energy past the cap is stored as fat.
+    Fat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EnergyCap {
+    pub max: f32,
+    #[serde(default)]
+    pub overflow: Overflow,
+    /// Most fat turned back into energy per default-length tick.
+    #[serde(default = "fat_burn")]
+    pub fat_burn: f32,
+}
+
+impl EnergyCap {
+    pub fn new(max: f32, overflow: Overflow) -> Self {
+        Self {
+            max,
+            overflow,
+            fat_burn: fat_burn(),
+        }
+    }
+
+    /// Brings a microbe's energy back under the cap, or up towards it from
+    /// its fat. `scale` is the tick's length over [`crate::DELTA_TIME`].
+    pub(crate) fn apply(&self, microbe: &mut Microbe, scale: f32) {
+        let excess = microbe.energy - self.max;
+        if excess > 0. {
+            microbe.energy = self.max;
+            if self.overflow == Overflow::Fat {
+                microbe.fat += excess;
+            }
+        } else if microbe.fat > 0. {
+            let burned = microbe.fat.min(-excess).min(self.fat_burn * scale);
+            microbe.fat -= burned;
+            microbe.energy += burned;
+        }
+    }
+}
+
+fn fat_burn() -> f32 {
+    1.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ecolor::Color32;
+    use uuid::Uuid;
+
+    fn microbe(energy: f32) -> Microbe {
+        let mut microbe = Microbe::new(Uuid::nil(), 0., 0., 0., Uuid::nil(), Color32::RED);
+        microbe.energy = energy;
+        microbe
+    }
+
+    #[test]
+    fn test_wasted() {
+        let cap = EnergyCap::new(150., Overflow::Wasted);
+        let mut full = microbe(180.);
+        cap.apply(&mut full, 1.);
+        assert_eq!((full.energy, full.fat), (150., 0.));
+        let mut hungry = microbe(40.);
+        cap.apply(&mut hungry, 1.);
+        assert_eq!(hungry.energy, 40.);
+    }
+
+    #[test]
+    fn test_fat() {
+        let cap = EnergyCap {
+            fat_burn: 2.,
+            ..EnergyCap::new(150., Overflow::Fat)
+        };
+        let mut microbe = microbe(155.);
+        cap.apply(&mut microbe, 1.);
+        assert_eq!((microbe.energy, microbe.fat), (150., 5.));
+
+        // Burned slowly, and never past the cap.
+        microbe.energy = 140.;
+        cap.apply(&mut microbe, 1.);
+        assert_eq!((microbe.energy, microbe.fat), (142., 3.));
+        microbe.energy = 149.;
+        cap.apply(&mut microbe, 1.);
+        assert_eq!((microbe.energy, microbe.fat), (150., 2.));
+
+        // A half-length tick burns half as much.
+        microbe.energy = 140.;
+        cap.apply(&mut microbe, 0.5);
+        assert_eq!((microbe.energy, microbe.fat), (141., 1.));
+    }
+
+    #[test]
+    fn test_serde() {
+        let cap = serde_json::from_str::<EnergyCap>(r#"{"max": 120, "overflow": "fat"}"#).unwrap();
+        assert_eq!(cap, EnergyCap::new(120., Overflow::Fat));
+        let json = serde_json::to_string(&cap).unwrap();
+        assert_eq!(serde_json::from_str::<EnergyCap>(&json).unwrap(), cap);
+    }
+}
@@ -0,0 +1,96 @@
+use ecolor::Color32;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Species color presets. The named presets are colorblind-safe and ordered
+/// so the first few entries are the most distinguishable from each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Palette {
+    /// A random color per species.
+    #[default]
+    Random,
+    /// Okabe & Ito, "Color Universal Design" (2008).
+    OkabeIto,
+    /// Paul Tol's "bright" qualitative scheme.
+    TolBright,
+    /// Evenly spaced hues, for when there are more species than preset colors.
+    EvenHues,
+}
+
+const OKABE_ITO: [Color32; 8] = [
+    Color32::from_rgb(230, 159, 0),
+    Color32::from_rgb(86, 180, 233),
+    Color32::from_rgb(0, 158, 115),
+    Color32::from_rgb(240, 228, 66),
+    Color32::from_rgb(0, 114, 178),
+    Color32::from_rgb(213, 94, 0),
+    Color32::from_rgb(204, 121, 167),
+    Color32::from_rgb(255, 255, 255),
+];
+
+const TOL_BRIGHT: [Color32; 7] = [
+    Color32::from_rgb(68, 119, 170),
+    Color32::from_rgb(238, 102, 119),
+    Color32::from_rgb(34, 136, 51),
+    Color32::from_rgb(204, 187, 68),
+    Color32::from_rgb(102, 204, 238),
+    Color32::from_rgb(170, 51, 119),
+    Color32::from_rgb(187, 187, 187),
+];
+
+impl Palette {
+    pub const ALL: [Palette; 4] = [
+        Palette::Random,
+        Palette::OkabeIto,
+        Palette::TolBright,
+        Palette::EvenHues,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Palette::Random => "Random",
+            Palette::OkabeIto => "Okabe-Ito",
+            Palette::TolBright => "Tol bright",
+            Palette::EvenHues => "Even hues",
+        }
+    }
+
+    /// Returns `count` colors, one per species. Presets that run out of
+    /// entries fall back to evenly spaced hues.
+    pub fn colors(self, count: usize, rng: &mut impl Rng) -> Vec<Color32> {
+        let preset: &[Color32] = match self {
+            Palette::Random => {
+                return (0..count)
+                    .map(|_| Color32::from_rgb(rng.gen(), rng.gen(), rng.gen()))
+                    .collect();
+            }
+            Palette::OkabeIto => &OKABE_ITO,
+            Palette::TolBright => &TOL_BRIGHT,
+            Palette::EvenHues => &[],
+        };
+        if count <= preset.len() {
+            return preset[..count].to_vec();
+        }
+        (0..count)
+            .map(|i| ecolor::Hsva::new(i as f32 / count as f32, 0.8, 0.95, 1.).into())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_colors_are_distinct() {
+        let mut rng = rand::thread_rng();
+        for palette in Palette::ALL.into_iter().filter(|p| *p != Palette::Random) {
+            for count in [1, 3, 7, 12] {
+                let colors = palette.colors(count, &mut rng);
+                assert_eq!(colors.len(), count);
+                assert_eq!(colors.iter().collect::<HashSet<_>>().len(), count);
+            }
+        }
+    }
+}
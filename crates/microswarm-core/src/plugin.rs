@@ -0,0 +1,149 @@
+//! Custom world rules.
+//!
+//! A [`WorldPlugin`] hooks into every tick of [`World::update`], so rules
+//! like taxes, weather or scoring can be added without touching the core
+//! update. Register plugins with [`Simulation::plugin`]:
+//!
+//! ```
+//! use microswarm::plugin::WorldPlugin;
+//! use microswarm::{scripts, Color32, MicrobeState, Simulation};
+//!
+//! /// Takes a slice of every microbe's energy each tick.
+//! struct Tax(f32);
+//!
+//! impl WorldPlugin for Tax {
+//!     fn after_combat(&mut self, _microbe: &MicrobeState, energy: &mut f32) {
+//!         *energy *= 1. - self.0;
+//!     }
+//! }
+//!
+//! let mut sim = Simulation::new(400.).unwrap().plugin(Tax(0.01));
+//! let hunters = sim.add_species(scripts::aggressive_hunter_script());
+//! sim.spawn(hunters, 0., 0., 0., Color32::GREEN);
+//! sim.step().unwrap();
+//! assert!(sim.snapshot().microbes[0].energy < 99.);
+//! ```
+//!
+//! Plugins aren't saved with the world; register them again after
+//! [`Simulation::load`].
+//!
+//! [`Simulation::plugin`]: crate::Simulation::plugin
+//! [`Simulation::load`]: crate::Simulation::load
+
+use std::fmt;
+
+use crate::{Controls, Death, MicrobeState, World};
+
+/// Hooks called while the world updates. Every hook does nothing by default,
+/// so plugins only implement the ones they need.
+pub trait WorldPlugin {
+    /// Shown when the world is debug printed.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Called at the start of each tick, before any microbe senses. Changes to
+    /// tuning and new microbes take effect this tick. Spawns made here aren't
+    /// passed to [`WorldPlugin::on_spawn`].
+    fn before_tick(&mut self, _world: &mut World) {}
+
+    /// Called after a microbe's script has decided what to do, and can
+    /// override the decision.
+    fn after_sense(&mut self, _microbe: &MicrobeState, _controls: &mut Controls) {}
+
+    /// Called once bites are settled, with the microbe's energy after moving,
+    /// eating and being eaten. The microbe reproduces or dies based on the
+    /// energy this leaves it with.
+    fn after_combat(&mut self, _microbe: &MicrobeState, _energy: &mut f32) {}
+
+    /// Called for every microbe spawned into the world or born in it.
+    fn on_spawn(&mut self, _microbe: &MicrobeState) {}
+
+    /// Called for every microbe that dies, with its last state.
+    fn on_death(&mut self, _microbe: &MicrobeState, _death: &Death) {}
+}
+
+impl fmt::Debug for dyn WorldPlugin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{scripts, Color32, Simulation};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Debug, Default)]
+    struct Census {
+        ticks: usize,
+        spawned: usize,
+        died: usize,
+    }
+
+    /// Counts hook calls, and starves everything if `famine` is set.
+    struct Counter {
+        census: Rc<RefCell<Census>>,
+        famine: bool,
+    }
+
+    impl WorldPlugin for Counter {
+        fn before_tick(&mut self, _world: &mut World) {
+            self.census.borrow_mut().ticks += 1;
+        }
+
+        fn after_combat(&mut self, _microbe: &MicrobeState, energy: &mut f32) {
+            if self.famine {
+                *energy = 0.;
+            }
+        }
+
+        fn on_spawn(&mut self, _microbe: &MicrobeState) {
+            self.census.borrow_mut().spawned += 1;
+        }
+
+        fn on_death(&mut self, _microbe: &MicrobeState, _death: &Death) {
+            self.census.borrow_mut().died += 1;
+        }
+    }
+
+    struct Freeze;
+
+    impl WorldPlugin for Freeze {
+        fn after_sense(&mut self, _microbe: &MicrobeState, controls: &mut Controls) {
+            *controls = Controls::new();
+        }
+    }
+
+    #[test]
+    fn test_hooks_are_called() {
+        let census = Rc::new(RefCell::new(Census::default()));
+        let mut sim = Simulation::new(100.).unwrap().plugin(Counter {
+            census: census.clone(),
+            famine: true,
+        });
+        let idle = sim.add_species("new_controls()");
+        sim.spawn(idle, 0., 0., 0., Color32::RED);
+        sim.spawn(idle, 10., 0., 0., Color32::RED);
+
+        let report = sim.step().unwrap();
+        assert_eq!(report.deaths().count(), 2);
+        assert!(sim.snapshot().microbes.is_empty());
+        let census = census.borrow();
+        assert_eq!((census.ticks, census.spawned, census.died), (1, 2, 2));
+    }
+
+    #[test]
+    fn test_after_sense_overrides_scripts() {
+        let mut sim = Simulation::new(100.).unwrap().plugin(Freeze);
+        let hunters = sim.add_species(scripts::aggressive_hunter_script());
+        sim.spawn(hunters, 5., 5., 1., Color32::RED);
+
+        sim.step().unwrap();
+        let microbe = sim.snapshot().microbes[0];
+        assert_eq!((microbe.x, microbe.y, microbe.rotation), (5., 5., 1.));
+        assert!(format!("{:?}", sim.world()).contains("Freeze"));
+    }
+}
@@ -0,0 +1,109 @@
+//! A ceiling on how many microbes a world holds.
+//!
+//! A bloom can multiply the population until every tick takes seconds. A
+//! [`PopulationCap`] stops that: whenever a tick ends with more than `max`
+//! microbes, the excess die, chosen by its [`Cull`]. They're reported as
+//! [`Cause::Culled`].
+//!
+//! ```toml
+//! [population_cap]
+//! max = 2000
+//! cull = "oldest"           # or "weakest", or "random"
+//! ```
+//!
+//! [`Cause::Culled`]: crate::events::Cause::Culled
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::microbe::Microbe;
+
+/// Which microbes die first when there are too many.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cull {
+    /// Those born longest ago.
+    #[default]
+    Oldest,
+    /// Those with the least energy.
+    Weakest,
+    /// Any, at random.
+    Random,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PopulationCap {
+    pub max: usize,
+    #[serde(default)]
+    pub cull: Cull,
+}
+
+impl PopulationCap {
+    pub fn new(max: usize, cull: Cull) -> Self {
+        Self { max, cull }
+    }
+
+    /// The ids of the microbes to cull to bring `microbes` down to `max`.
+    pub(crate) fn victims(&self, microbes: &[Microbe], rng: &mut impl Rng) -> HashSet<Uuid> {
+        let Some(excess) = microbes.len().checked_sub(self.max).filter(|n| *n > 0) else {
+            return HashSet::new();
+        };
+        let mut order = microbes.iter().collect::<Vec<_>>();
+        match self.cull {
+            Cull::Oldest => order.sort_by_key(|m| (m.born, m.id)),
+            Cull::Weakest => {
+                order.sort_by(|a, b| a.energy.total_cmp(&b.energy).then(a.id.cmp(&b.id)))
+            }
+            Cull::Random => order.shuffle(rng),
+        }
+        order.into_iter().take(excess).map(|m| m.id).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ecolor::Color32;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn microbes() -> Vec<Microbe> {
+        (1..=4)
+            .map(|n| {
+                let mut microbe =
+                    Microbe::new(Uuid::from_u128(n), 0., 0., 0., Uuid::nil(), Color32::RED);
+                microbe.born = n as u64;
+                microbe.energy = (5 - n) as f32;
+                microbe
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_victims() {
+        let rng = &mut StdRng::seed_from_u64(0);
+        let ids = |ns: &[u128]| {
+            ns.iter()
+                .map(|n| Uuid::from_u128(*n))
+                .collect::<HashSet<_>>()
+        };
+        let mut cap = |max, cull| PopulationCap::new(max, cull).victims(&microbes(), rng);
+
+        assert_eq!(cap(2, Cull::Oldest), ids(&[1, 2]));
+        assert_eq!(cap(3, Cull::Weakest), ids(&[4]));
+        assert_eq!(cap(1, Cull::Random).len(), 3);
+        assert!(cap(4, Cull::Oldest).is_empty());
+        assert!(cap(10, Cull::Random).is_empty());
+    }
+
+    #[test]
+    fn test_serde() {
+        let cap = serde_json::from_str::<PopulationCap>(r#"{"max": 10}"#).unwrap();
+        assert_eq!(cap, PopulationCap::new(10, Cull::Oldest));
+        let json = serde_json::to_string(&PopulationCap::new(5, Cull::Weakest)).unwrap();
+        assert_eq!(json, r#"{"max":5,"cull":"weakest"}"#);
+    }
+}
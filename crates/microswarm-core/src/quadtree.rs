@@ -0,0 +1,2081 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::f32::consts::PI;
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Sub};
+
+/// A coordinate type for [`Point`], [`Rect`] and [`QuadTree`]: `f32`, as
+/// the world uses, `f64` for more range, or an integer type for grid or
+/// fixed-point coordinates. Integer distances are squared, so keep them
+/// well inside the type's range.
+pub trait Scalar:
+    Copy
+    + PartialOrd
+    + Debug
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+{
+    const ZERO: Self;
+    const TWO: Self;
+
+    /// The square root, rounded down for integers.
+    fn sqrt(self) -> Self;
+}
+
+macro_rules! impl_scalar {
+    ($($t:ty => $zero:literal, $two:literal, $sqrt:ident;)*) => {
+        $(impl Scalar for $t {
+            const ZERO: Self = $zero;
+            const TWO: Self = $two;
+
+            fn sqrt(self) -> Self {
+                <$t>::$sqrt(self)
+            }
+        })*
+    };
+}
+
+impl_scalar! {
+    f32 => 0., 2., sqrt;
+    f64 => 0., 2., sqrt;
+    i32 => 0, 2, isqrt;
+    i64 => 0, 2, isqrt;
+}
+
+/// The larger of `a` and `b`, for scalars that are only partially ordered.
+fn max<N: Scalar>(a: N, b: N) -> N {
+    if b > a {
+        b
+    } else {
+        a
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rect<N = f32> {
+    x: N,
+    y: N,
+    width: N,
+    height: N,
+}
+
+impl<N: Scalar> Rect<N> {
+    pub fn new(x: N, y: N, width: N, height: N) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn x(&self) -> N {
+        self.x
+    }
+
+    pub fn y(&self) -> N {
+        self.y
+    }
+
+    pub fn width(&self) -> N {
+        self.width
+    }
+
+    pub fn height(&self) -> N {
+        self.height
+    }
+
+    /// The square reaching `half_extent` from `center` on every side.
+    pub fn from_center(center: Point<N>, half_extent: N) -> Self {
+        Rect::new(
+            center.x - half_extent,
+            center.y - half_extent,
+            half_extent * N::TWO,
+            half_extent * N::TWO,
+        )
+    }
+
+    pub fn center(&self) -> Point<N> {
+        Point::new(self.x + self.width / N::TWO, self.y + self.height / N::TWO)
+    }
+
+    /// The rect grown by `margin` on every side, or shrunk by a negative
+    /// one.
+    pub fn expanded(&self, margin: N) -> Self {
+        Rect::new(
+            self.x - margin,
+            self.y - margin,
+            self.width + margin * N::TWO,
+            self.height + margin * N::TWO,
+        )
+    }
+
+    /// Whether the rects overlap, edges included.
+    pub fn intersects(&self, other: &Rect<N>) -> bool {
+        !(other.x > self.x + self.width
+            || other.x + other.width < self.x
+            || other.y > self.y + self.height
+            || other.y + other.height < self.y)
+    }
+
+    /// Whether `point` is in the rect, edges included.
+    pub fn contains_point(&self, point: Point<N>) -> bool {
+        point.x >= self.x
+            && point.x <= self.x + self.width
+            && point.y >= self.y
+            && point.y <= self.y + self.height
+    }
+
+    /// Whether all of `other` is in the rect, edges included.
+    pub fn contains_rect(&self, other: &Rect<N>) -> bool {
+        other.x >= self.x
+            && other.x + other.width <= self.x + self.width
+            && other.y >= self.y
+            && other.y + other.height <= self.y + self.height
+    }
+
+    /// Northwest, northeast, southwest and southeast, for subdividing.
+    fn quarters(&self) -> [Rect<N>; 4] {
+        let half_width = self.width / N::TWO;
+        let half_height = self.height / N::TWO;
+        let (middle_x, middle_y) = (self.x + half_width, self.y + half_height);
+        [
+            Rect::new(self.x, self.y, half_width, half_height),
+            Rect::new(middle_x, self.y, half_width, half_height),
+            Rect::new(self.x, middle_y, half_width, half_height),
+            Rect::new(middle_x, middle_y, half_width, half_height),
+        ]
+    }
+
+    fn corners(&self) -> [Point<N>; 4] {
+        let (right, bottom) = (self.x + self.width, self.y + self.height);
+        [
+            Point::new(self.x, self.y),
+            Point::new(right, self.y),
+            Point::new(self.x, bottom),
+            Point::new(right, bottom),
+        ]
+    }
+
+    /// The squared distance from `point` to the nearest part of the rect,
+    /// zero inside it.
+    pub fn distance_squared(&self, point: Point<N>) -> N {
+        let dx = max(
+            max(self.x - point.x, point.x - (self.x + self.width)),
+            N::ZERO,
+        );
+        let dy = max(
+            max(self.y - point.y, point.y - (self.y + self.height)),
+            N::ZERO,
+        );
+        dx * dx + dy * dy
+    }
+}
+
+/// Levels below the root a tree subdivides to. Nodes this deep keep every
+/// item past their capacity, so many items at one point can't subdivide
+/// forever.
+pub const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct QuadTreeNode<T: Locatable<N>, N: Scalar = f32> {
+    pub(crate) bounds: Rect<N>,
+    pub(crate) capacity: usize,
+    /// Levels below the root.
+    depth: usize,
+    /// At most `capacity`, except at [`MAX_DEPTH`] or for a point every
+    /// child turns away, which stay here rather than being lost.
+    items: Vec<T>,
+    children: Option<Box<[QuadTreeNode<T, N>; 4]>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Point<N = f32> {
+    x: N,
+    y: N,
+}
+
+impl<N: Scalar> Point<N> {
+    pub fn new(x: N, y: N) -> Self {
+        Self { x, y }
+    }
+
+    pub fn x(&self) -> N {
+        self.x
+    }
+
+    pub fn y(&self) -> N {
+        self.y
+    }
+
+    /// Cheaper than [`Point::distance_to`] for comparing distances, and
+    /// exact for integer coordinates.
+    pub fn distance_squared(&self, other: Point<N>) -> N {
+        let (dx, dy) = (self.x - other.x, self.y - other.y);
+        dx * dx + dy * dy
+    }
+
+    pub fn distance_to(&self, other: Point<N>) -> N {
+        self.distance_squared(other).sqrt()
+    }
+}
+
+pub trait Locatable<N: Scalar = f32> {
+    fn location(&self) -> Point<N>;
+}
+
+/// Items that can be moved in place with [`QuadTree::relocate`].
+pub trait Relocatable<N: Scalar = f32>: Locatable<N> {
+    fn set_location(&mut self, point: Point<N>);
+}
+
+/// What became of an item a node was asked to move.
+enum Relocation<T> {
+    NotFound,
+    Moved,
+    /// Moved out of the node, for an ancestor to take in.
+    Left(T),
+}
+
+impl<T: Locatable<N>, N: Scalar> QuadTreeNode<T, N> {
+    fn new(bounds: Rect<N>, capacity: usize) -> Self {
+        QuadTreeNode {
+            bounds,
+            capacity,
+            depth: 0,
+            items: Vec::new(),
+            children: None,
+        }
+    }
+
+    fn subdivide(&mut self) {
+        let children = self.bounds.quarters().map(|bounds| QuadTreeNode {
+            depth: self.depth + 1,
+            ..QuadTreeNode::new(bounds, self.capacity)
+        });
+        self.children = Some(Box::new(children));
+    }
+
+    fn insert(&mut self, data: T) -> Option<T> {
+        if !self.bounds.contains_point(data.location()) {
+            return Some(data);
+        }
+
+        if self.items.len() < self.capacity || self.depth >= MAX_DEPTH {
+            self.items.push(data);
+            return None;
+        }
+
+        if self.children.is_none() {
+            self.subdivide();
+        }
+
+        let mut data = data;
+        if let Some(ref mut children) = self.children {
+            for child in children.iter_mut() {
+                if let Some(rejected) = child.insert(data) {
+                    data = rejected;
+                } else {
+                    return None;
+                }
+            }
+        }
+
+        // Rounding can leave a sliver of the bounds that no child covers.
+        self.items.push(data);
+        None
+    }
+
+    /// Fills in this empty node from `items`, keeping the first `capacity`
+    /// and sharing the rest out between its children in one pass.
+    fn build(&mut self, mut items: Vec<T>) {
+        if items.len() <= self.capacity || self.depth >= MAX_DEPTH {
+            self.items = items;
+            return;
+        }
+        let rest = items.split_off(self.capacity);
+        self.items = items;
+        self.subdivide();
+        let Some(children) = &mut self.children else {
+            return;
+        };
+        let mut quarters: [Vec<T>; 4] = Default::default();
+        for item in rest {
+            let location = item.location();
+            match children
+                .iter()
+                .position(|child| child.bounds.contains_point(location))
+            {
+                Some(i) => quarters[i].push(item),
+                None => self.items.push(item),
+            }
+        }
+        for (child, items) in children.iter_mut().zip(quarters) {
+            child.build(items);
+        }
+    }
+
+    fn take_items(&mut self) -> Vec<T> {
+        let mut found_items = Vec::new();
+        if let Some(ref mut children) = self.children {
+            found_items = children.iter_mut().fold(found_items, |mut acc, c| {
+                acc.extend(c.take_items());
+                acc
+            });
+        }
+        found_items.append(&mut self.items);
+        found_items
+    }
+
+    fn clear(&mut self) {
+        self.items.clear();
+        for child in self.children.iter_mut().flat_map(|c| c.iter_mut()) {
+            child.clear();
+        }
+    }
+
+    fn len(&self) -> usize {
+        let children = self.children.iter().flat_map(|c| c.iter());
+        self.items.len() + children.map(QuadTreeNode::len).sum::<usize>()
+    }
+
+    /// Levels below this node to its deepest descendant.
+    fn height(&self) -> usize {
+        let children = self.children.iter().flat_map(|c| c.iter());
+        children.map(|child| child.height() + 1).max().unwrap_or(0)
+    }
+
+    /// Hands every item `predicate` picks to `removed`.
+    fn remove_where(
+        &mut self,
+        predicate: &mut impl FnMut(&T) -> bool,
+        removed: &mut impl FnMut(T),
+    ) {
+        let mut i = 0;
+        while i < self.items.len() {
+            if predicate(&self.items[i]) {
+                removed(self.items.swap_remove(i));
+            } else {
+                i += 1;
+            }
+        }
+        if let Some(ref mut children) = self.children {
+            for child in children.iter_mut() {
+                child.remove_where(predicate, removed);
+            }
+        }
+        self.repack();
+    }
+
+    fn remove_at(&mut self, point: Point<N>, predicate: &mut impl FnMut(&T) -> bool) -> Option<T> {
+        if !self.bounds.contains_point(point) {
+            return None;
+        }
+        let found = self
+            .items
+            .iter()
+            .position(|item| item.location() == point && predicate(item));
+        let removed = match found {
+            Some(i) => Some(self.items.swap_remove(i)),
+            None => self
+                .children
+                .iter_mut()
+                .flat_map(|c| c.iter_mut())
+                .find_map(|child| child.remove_at(point, predicate)),
+        };
+        if removed.is_some() {
+            self.repack();
+        }
+        removed
+    }
+
+    fn relocate(
+        &mut self,
+        old: Point<N>,
+        new: Point<N>,
+        predicate: &mut impl FnMut(&T) -> bool,
+    ) -> Relocation<T>
+    where
+        T: Relocatable<N>,
+    {
+        if !self.bounds.contains_point(old) {
+            return Relocation::NotFound;
+        }
+        let found = self
+            .items
+            .iter()
+            .position(|item| item.location() == old && predicate(item));
+        if let Some(i) = found {
+            self.items[i].set_location(new);
+            if self.bounds.contains_point(new) {
+                return Relocation::Moved;
+            }
+            let item = self.items.swap_remove(i);
+            self.repack();
+            return Relocation::Left(item);
+        }
+
+        let children = self.children.iter_mut().flat_map(|c| c.iter_mut());
+        let relocation = children
+            .map(|child| child.relocate(old, new, predicate))
+            .find(|relocation| !matches!(relocation, Relocation::NotFound));
+        match relocation {
+            Some(Relocation::Left(item)) if self.bounds.contains_point(new) => {
+                self.insert(item);
+                self.repack();
+                Relocation::Moved
+            }
+            Some(Relocation::Left(item)) => {
+                self.repack();
+                Relocation::Left(item)
+            }
+            relocation => relocation.unwrap_or(Relocation::NotFound),
+        }
+    }
+
+    /// Moves the children's items back into this node once they fit, so
+    /// removals don't leave a trail of empty subdivisions. Otherwise fills
+    /// this node back up from its children, as inserting would have, so
+    /// items sit as shallow as they can.
+    fn repack(&mut self) {
+        let Some(children) = &mut self.children else {
+            return;
+        };
+        if self.items.len() + children.iter().map(QuadTreeNode::len).sum::<usize>() <= self.capacity
+        {
+            self.items = self.take_items();
+            self.children = None;
+            return;
+        }
+        while self.items.len() < self.capacity {
+            match children.iter_mut().find_map(QuadTreeNode::pop) {
+                Some(item) => self.items.push(item),
+                None => break,
+            }
+        }
+    }
+
+    /// Takes out any one item, from as high up as there is one.
+    fn pop(&mut self) -> Option<T> {
+        let item = match self.items.pop() {
+            Some(item) => item,
+            None => self
+                .children
+                .iter_mut()
+                .flat_map(|c| c.iter_mut())
+                .find_map(QuadTreeNode::pop)?,
+        };
+        self.repack();
+        Some(item)
+    }
+
+    fn for_each_in_rect(&self, rect: &Rect<N>, f: &mut impl FnMut(&T)) {
+        if !self.bounds.intersects(rect) {
+            return;
+        }
+        for item in &self.items {
+            if rect.contains_point(item.location()) {
+                f(item);
+            }
+        }
+        for child in self.children.iter().flat_map(|c| c.iter()) {
+            child.for_each_in_rect(rect, f);
+        }
+    }
+
+    fn for_each_in_rect_mut(&mut self, rect: &Rect<N>, f: &mut impl FnMut(&mut T)) {
+        if !self.bounds.intersects(rect) {
+            return;
+        }
+        for item in &mut self.items {
+            if rect.contains_point(item.location()) {
+                f(item);
+            }
+        }
+        for child in self.children.iter_mut().flat_map(|c| c.iter_mut()) {
+            child.for_each_in_rect_mut(rect, f);
+        }
+    }
+
+    fn query_mut<'a>(&'a mut self, rect: &Rect<N>, found: &mut Vec<&'a mut T>) {
+        if !self.bounds.intersects(rect) {
+            return;
+        }
+        let items = self.items.iter_mut();
+        found.extend(items.filter(|item| rect.contains_point(item.location())));
+        for child in self.children.iter_mut().flat_map(|c| c.iter_mut()) {
+            child.query_mut(rect, found);
+        }
+    }
+
+    fn visit_nodes(&self, visit: &mut impl FnMut(&Rect<N>, &[T]) -> bool) {
+        if !visit(&self.bounds, &self.items) {
+            return;
+        }
+        for child in self.children.iter().flat_map(|c| c.iter()) {
+            child.visit_nodes(visit);
+        }
+    }
+
+    fn query_circle<'a>(&'a self, center: Point<N>, radius_squared: N, found: &mut Vec<&'a T>) {
+        if self.bounds.distance_squared(center) > radius_squared {
+            return;
+        }
+        for item in &self.items {
+            if item.location().distance_squared(center) <= radius_squared {
+                found.push(item);
+            }
+        }
+        for child in self.children.iter().flat_map(|c| c.iter()) {
+            child.query_circle(center, radius_squared, found);
+        }
+    }
+}
+
+impl<T: Locatable> QuadTreeNode<T> {
+    fn query_sector<'a>(
+        &'a self,
+        sector: &Sector<impl Fn(f32, f32) -> f32>,
+        found: &mut Vec<&'a T>,
+    ) {
+        if !sector.reaches(&self.bounds) {
+            return;
+        }
+        for item in &self.items {
+            if sector.contains(item.location()) {
+                found.push(item);
+            }
+        }
+        for child in self.children.iter().flat_map(|c| c.iter()) {
+            child.query_sector(sector, found);
+        }
+    }
+}
+
+/// A cone for [`QuadTree::query_sector_with`].
+struct Sector<F> {
+    center: Point,
+    direction: f32,
+    half_angle: f32,
+    radius_squared: f32,
+    atan2: F,
+}
+
+impl<F: Fn(f32, f32) -> f32> Sector<F> {
+    /// Slack for the node test, so an approximate `atan2` never prunes a
+    /// node holding an item it would accept.
+    const SLACK: f32 = 0.01;
+
+    /// The angle from the cone's direction to `point`, in `(-π, π]`.
+    fn offset(&self, point: Point) -> f32 {
+        let angle = (self.atan2)(point.y - self.center.y, point.x - self.center.x);
+        PI - (PI - (angle - self.direction)).rem_euclid(2. * PI)
+    }
+
+    fn contains(&self, point: Point) -> bool {
+        let distance = point.distance_squared(self.center);
+        distance <= self.radius_squared
+            && (distance == 0. || self.offset(point).abs() <= self.half_angle)
+    }
+
+    /// Whether any of `rect` might be in the cone.
+    fn reaches(&self, rect: &Rect) -> bool {
+        if rect.distance_squared(self.center) > self.radius_squared {
+            return false;
+        }
+        if self.half_angle >= PI || rect.contains_point(self.center) {
+            return true;
+        }
+        // From outside, a rect spans less than half a turn, so measure its
+        // corners from its middle to keep them clear of the wrap at ±π.
+        let towards = self.offset(rect.center());
+        let (low, high) = rect
+            .corners()
+            .iter()
+            .fold((0f32, 0f32), |(low, high), corner| {
+                let offset = PI - (PI - (self.offset(*corner) - towards)).rem_euclid(2. * PI);
+                (low.min(offset), high.max(offset))
+            });
+        let limit = self.half_angle + Self::SLACK;
+        [-2. * PI, 0., 2. * PI]
+            .into_iter()
+            .any(|turn| towards + turn + high >= -limit && towards + turn + low <= limit)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QuadTree<T: Locatable<N>, N: Scalar = f32> {
+    pub(crate) root: QuadTreeNode<T, N>,
+    /// Items in the whole tree.
+    len: usize,
+}
+
+impl<T: Locatable<N>, N: Scalar> QuadTree<T, N> {
+    pub fn new(bounds: Rect<N>, capacity: usize) -> Self {
+        QuadTree {
+            root: QuadTreeNode::new(bounds, capacity),
+            len: 0,
+        }
+    }
+
+    /// A tree holding `items`, built top down by splitting them between
+    /// each node's children rather than inserting them one at a time.
+    /// Items outside `bounds` are dropped, as [`QuadTree::insert`] would.
+    pub fn from_iter(bounds: Rect<N>, capacity: usize, items: impl IntoIterator<Item = T>) -> Self {
+        let items = items
+            .into_iter()
+            .filter(|item| bounds.contains_point(item.location()))
+            .collect::<Vec<_>>();
+        let mut tree = QuadTree::new(bounds, capacity);
+        tree.len = items.len();
+        tree.root.build(items);
+        tree
+    }
+
+    /// Adds `data`, returning false and dropping it if it's outside the
+    /// tree's bounds.
+    pub fn insert(&mut self, data: T) -> bool {
+        let inserted = self.root.insert(data).is_none();
+        self.len += usize::from(inserted);
+        inserted
+    }
+
+    pub fn take_items(&mut self) -> Vec<T> {
+        self.len = 0;
+        self.root.take_items()
+    }
+
+    /// Removes every item, keeping the nodes and their storage for the
+    /// next ones.
+    pub fn clear(&mut self) {
+        self.root.clear();
+        self.len = 0;
+    }
+
+    /// Removes and returns every item `predicate` picks, in one pass over
+    /// the tree, collapsing subdivisions that are no longer needed.
+    pub fn remove_where(&mut self, mut predicate: impl FnMut(&T) -> bool) -> Vec<T> {
+        let mut removed = Vec::new();
+        self.root
+            .remove_where(&mut predicate, &mut |item| removed.push(item));
+        self.len -= removed.len();
+        removed
+    }
+
+    /// Keeps only the items `keep` picks, dropping the rest as it goes
+    /// rather than collecting them like [`QuadTree::remove_where`].
+    pub fn retain(&mut self, mut keep: impl FnMut(&T) -> bool) {
+        let mut removed = 0;
+        self.root
+            .remove_where(&mut |item| !keep(item), &mut |_| removed += 1);
+        self.len -= removed;
+    }
+
+    /// Removes the first item located exactly at `point` that `predicate`
+    /// picks, only visiting the nodes that cover `point`.
+    pub fn remove_at(
+        &mut self,
+        point: Point<N>,
+        mut predicate: impl FnMut(&T) -> bool,
+    ) -> Option<T> {
+        let removed = self.root.remove_at(point, &mut predicate);
+        self.len -= usize::from(removed.is_some());
+        removed
+    }
+
+    /// Moves the first item at `old` that `predicate` picks to `new`. It
+    /// only changes nodes if `new` is outside the one holding it, and then
+    /// only goes up as far as a node that covers `new`. Returns whether an
+    /// item moved; `new` outside the tree moves nothing.
+    pub fn relocate(
+        &mut self,
+        old: Point<N>,
+        new: Point<N>,
+        mut predicate: impl FnMut(&T) -> bool,
+    ) -> bool
+    where
+        T: Relocatable<N>,
+    {
+        if !self.root.bounds.contains_point(new) {
+            return false;
+        }
+        match self.root.relocate(old, new, &mut predicate) {
+            Relocation::NotFound => false,
+            Relocation::Moved => true,
+            // The root covers `new`, so it never passes an item up.
+            Relocation::Left(item) => self.root.insert(item).is_none(),
+        }
+    }
+
+    /// Items in the tree, kept count of rather than counted.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn items(&self) -> Vec<&T> {
+        self.iter().collect()
+    }
+
+    /// The item closest to `point`, if there are any.
+    pub fn nearest(&self, point: Point<N>) -> Option<&T> {
+        self.nearest_where(point, |_| true)
+    }
+
+    /// The item closest to `point` that `predicate` picks, such as any but
+    /// the one at `point` itself.
+    pub fn nearest_where(&self, point: Point<N>, predicate: impl FnMut(&T) -> bool) -> Option<&T> {
+        self.knn_where(point, 1, predicate).pop()
+    }
+
+    /// The `k` items closest to `point`, closest first. There are fewer if
+    /// the tree holds fewer.
+    pub fn knn(&self, point: Point<N>, k: usize) -> Vec<&T> {
+        self.knn_where(point, k, |_| true)
+    }
+
+    /// Like [`QuadTree::knn`], counting only the items `predicate` picks.
+    /// Nodes are visited closest first, and once `k` items are found, any
+    /// further away than all of them are skipped.
+    pub fn knn_where(
+        &self,
+        point: Point<N>,
+        k: usize,
+        mut predicate: impl FnMut(&T) -> bool,
+    ) -> Vec<&T> {
+        if k == 0 {
+            return Vec::new();
+        }
+        // Kept sorted by distance, closest first.
+        let mut best: Vec<(N, &T)> = Vec::with_capacity(k);
+        let mut nodes = BinaryHeap::from([Nearest {
+            distance: self.root.bounds.distance_squared(point),
+            node: &self.root,
+        }]);
+        while let Some(Nearest { distance, node }) = nodes.pop() {
+            if best.len() == k && distance >= best[k - 1].0 {
+                break;
+            }
+            for item in &node.items {
+                let distance = item.location().distance_squared(point);
+                let within = best.len() < k || distance < best[k - 1].0;
+                if within && predicate(item) {
+                    let at = best.partition_point(|(closest, _)| *closest <= distance);
+                    best.insert(at, (distance, item));
+                    best.truncate(k);
+                }
+            }
+            for child in node.children.iter().flat_map(|c| c.iter()) {
+                nodes.push(Nearest {
+                    distance: child.bounds.distance_squared(point),
+                    node: child,
+                });
+            }
+        }
+        best.into_iter().map(|(_, item)| item).collect()
+    }
+
+    /// Every item, without collecting them first. The order follows the
+    /// tree's layout rather than insertion.
+    pub fn iter(&self) -> Iter<'_, T, N> {
+        Iter {
+            items: self.root.items.iter(),
+            nodes: self.root.children.iter().flat_map(|c| c.iter()).collect(),
+        }
+    }
+
+    pub fn query(&self, rect: &Rect<N>) -> Vec<&T> {
+        self.query_iter(rect).collect()
+    }
+
+    /// Like [`QuadTree::query`], finding items as they're asked for, so
+    /// counting them or stopping at the first allocates nothing.
+    pub fn query_iter(&self, rect: &Rect<N>) -> QueryIter<'_, T, N> {
+        QueryIter {
+            rect: *rect,
+            items: [].iter(),
+            nodes: vec![&self.root],
+        }
+    }
+
+    /// Answers each of `rects` as [`QuadTree::query`] would, spread over
+    /// rayon's threads, in the same order as `rects`.
+    pub fn par_query_many(&self, rects: &[Rect<N>]) -> Vec<Vec<&T>>
+    where
+        T: Sync,
+        N: Sync,
+    {
+        rects.par_iter().map(|rect| self.query(rect)).collect()
+    }
+
+    /// Answers each `(center, radius)` as [`QuadTree::query_circle`] would,
+    /// spread over rayon's threads, in the same order as `circles`.
+    pub fn par_query_circles(&self, circles: &[(Point<N>, N)]) -> Vec<Vec<&T>>
+    where
+        T: Sync,
+        N: Sync,
+    {
+        circles
+            .par_iter()
+            .map(|&(center, radius)| self.query_circle(center, radius))
+            .collect()
+    }
+
+    /// Calls `f` with every item in `rect`, without collecting them.
+    pub fn for_each_in_rect(&self, rect: &Rect<N>, mut f: impl FnMut(&T)) {
+        self.root.for_each_in_rect(rect, &mut f);
+    }
+
+    /// Like [`QuadTree::query`], lending the items mutably so neighbours
+    /// can be changed in place. Moving them this way leaves them in the
+    /// wrong node; use [`QuadTree::relocate`] for that.
+    pub fn query_mut(&mut self, rect: &Rect<N>) -> Vec<&mut T> {
+        let mut found = Vec::new();
+        self.root.query_mut(rect, &mut found);
+        found
+    }
+
+    /// Like [`QuadTree::for_each_in_rect`], with each item lent mutably as
+    /// [`QuadTree::query_mut`] does.
+    pub fn for_each_in_rect_mut(&mut self, rect: &Rect<N>, mut f: impl FnMut(&mut T)) {
+        self.root.for_each_in_rect_mut(rect, &mut f);
+    }
+
+    /// Calls `visit` with every node's bounds and the items it holds
+    /// itself, parents before children. Returning false skips the node's
+    /// children, for searches with their own pruning or for drawing only
+    /// the top few levels.
+    pub fn visit_nodes(&self, mut visit: impl FnMut(&Rect<N>, &[T]) -> bool) {
+        self.root.visit_nodes(&mut visit);
+    }
+
+    /// Levels below the root of the deepest node, zero until the root
+    /// subdivides and at most [`MAX_DEPTH`].
+    pub fn depth(&self) -> usize {
+        self.root.height()
+    }
+
+    /// Nodes in the tree, the root included, empty or not.
+    pub fn node_count(&self) -> usize {
+        let mut count = 0;
+        self.visit_nodes(|_, _| {
+            count += 1;
+            true
+        });
+        count
+    }
+
+    /// How many nodes hold each number of items themselves: the first
+    /// entry counts the empty ones, the next those holding one, and so
+    /// on. Entries past `capacity` mean items piled up at [`MAX_DEPTH`] or
+    /// in a sliver no child covers.
+    pub fn items_per_node(&self) -> Vec<usize> {
+        let mut counts = Vec::new();
+        self.visit_nodes(|_, items| {
+            if counts.len() <= items.len() {
+                counts.resize(items.len() + 1, 0);
+            }
+            counts[items.len()] += 1;
+            true
+        });
+        counts
+    }
+
+    /// Every item within `radius` of `center`, edge included. Nodes the
+    /// circle doesn't reach are skipped.
+    pub fn query_circle(&self, center: Point<N>, radius: N) -> Vec<&T> {
+        let mut found = Vec::new();
+        self.root.query_circle(center, radius * radius, &mut found);
+        found
+    }
+}
+
+impl<T: Locatable> QuadTree<T> {
+    /// Every item within `radius` of `center` and at most `half_angle`
+    /// radians either side of `direction`, such as what a microbe can see
+    /// ahead of it. Nodes wholly outside the cone are skipped.
+    pub fn query_sector(
+        &self,
+        center: Point,
+        direction: f32,
+        half_angle: f32,
+        radius: f32,
+    ) -> Vec<&T> {
+        self.query_sector_with(center, direction, half_angle, radius, f32::atan2)
+    }
+
+    /// Like [`QuadTree::query_sector`], measuring angles with `atan2(y, x)`,
+    /// such as [`Math::atan2`](crate::math::Math::atan2) for a world that
+    /// has to play out the same on every platform.
+    pub fn query_sector_with(
+        &self,
+        center: Point,
+        direction: f32,
+        half_angle: f32,
+        radius: f32,
+        atan2: impl Fn(f32, f32) -> f32,
+    ) -> Vec<&T> {
+        let sector = Sector {
+            center,
+            direction,
+            half_angle,
+            radius_squared: radius * radius,
+            atan2,
+        };
+        let mut found = Vec::new();
+        self.root.query_sector(&sector, &mut found);
+        found
+    }
+}
+
+/// A node waiting to be searched by [`QuadTree::knn_where`], ordered so
+/// the closest comes out of the heap first.
+struct Nearest<'a, T: Locatable<N>, N: Scalar> {
+    distance: N,
+    node: &'a QuadTreeNode<T, N>,
+}
+
+impl<T: Locatable<N>, N: Scalar> PartialEq for Nearest<'_, T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: Locatable<N>, N: Scalar> Eq for Nearest<'_, T, N> {}
+
+impl<T: Locatable<N>, N: Scalar> PartialOrd for Nearest<'_, T, N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Locatable<N>, N: Scalar> Ord for Nearest<'_, T, N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .distance
+            .partial_cmp(&self.distance)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Iterator over a [`QuadTree`]'s items, from [`QuadTree::iter`].
+pub struct Iter<'a, T: Locatable<N>, N: Scalar = f32> {
+    items: std::slice::Iter<'a, T>,
+    /// Nodes still to visit.
+    nodes: Vec<&'a QuadTreeNode<T, N>>,
+}
+
+impl<'a, T: Locatable<N>, N: Scalar> Iterator for Iter<'a, T, N> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        loop {
+            if let Some(item) = self.items.next() {
+                return Some(item);
+            }
+            let node = self.nodes.pop()?;
+            self.items = node.items.iter();
+            if let Some(children) = &node.children {
+                self.nodes.extend(children.iter());
+            }
+        }
+    }
+}
+
+/// Iterator over the items in a rect, from [`QuadTree::query_iter`].
+pub struct QueryIter<'a, T: Locatable<N>, N: Scalar = f32> {
+    rect: Rect<N>,
+    items: std::slice::Iter<'a, T>,
+    /// Nodes still to visit that overlap the rect, the next one last.
+    nodes: Vec<&'a QuadTreeNode<T, N>>,
+}
+
+impl<'a, T: Locatable<N>, N: Scalar> Iterator for QueryIter<'a, T, N> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        loop {
+            if let Some(item) = self
+                .items
+                .find(|item| self.rect.contains_point(item.location()))
+            {
+                return Some(item);
+            }
+            let node = self.nodes.pop()?;
+            if !node.bounds.intersects(&self.rect) {
+                continue;
+            }
+            self.items = node.items.iter();
+            if let Some(children) = &node.children {
+                // Reversed, so children are visited in order.
+                self.nodes.extend(children.iter().rev());
+            }
+        }
+    }
+}
+
+impl<'a, T: Locatable<N>, N: Scalar> IntoIterator for &'a QuadTree<T, N> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, N>;
+
+    fn into_iter(self) -> Iter<'a, T, N> {
+        self.iter()
+    }
+}
+
+/// Things that take up space, such as obstacles and resource patches, for
+/// a [`BoundedQuadTree`].
+pub trait BoundedLocatable<N: Scalar = f32> {
+    fn bounds(&self) -> Rect<N>;
+}
+
+/// A quadtree of items with an extent. Each item is kept in the deepest
+/// node that holds all of it, so one straddling a split stays with the
+/// parent rather than being copied into every child it touches.
+#[derive(Debug, Clone)]
+pub struct BoundedQuadTree<T: BoundedLocatable<N>, N: Scalar = f32> {
+    root: BoundedNode<T, N>,
+    /// Items in the whole tree.
+    len: usize,
+}
+
+#[derive(Debug, Clone)]
+struct BoundedNode<T, N> {
+    bounds: Rect<N>,
+    capacity: usize,
+    /// Levels below the root.
+    depth: usize,
+    /// The items that fit here but in none of the children. Past
+    /// `capacity` the node subdivides, keeping only these.
+    items: Vec<T>,
+    children: Option<Box<[BoundedNode<T, N>; 4]>>,
+}
+
+impl<T: BoundedLocatable<N>, N: Scalar> BoundedNode<T, N> {
+    fn new(bounds: Rect<N>, capacity: usize) -> Self {
+        BoundedNode {
+            bounds,
+            capacity,
+            depth: 0,
+            items: Vec::new(),
+            children: None,
+        }
+    }
+
+    fn insert(&mut self, item: T) {
+        if let Some(children) = &mut self.children {
+            let extent = item.bounds();
+            match children
+                .iter_mut()
+                .find(|child| child.bounds.contains_rect(&extent))
+            {
+                Some(child) => child.insert(item),
+                None => self.items.push(item),
+            }
+            return;
+        }
+        self.items.push(item);
+        if self.items.len() > self.capacity && self.depth < MAX_DEPTH {
+            let children = self.bounds.quarters().map(|bounds| BoundedNode {
+                depth: self.depth + 1,
+                ..BoundedNode::new(bounds, self.capacity)
+            });
+            self.children = Some(Box::new(children));
+            for item in std::mem::take(&mut self.items) {
+                self.insert(item);
+            }
+        }
+    }
+
+    fn take_items(&mut self) -> Vec<T> {
+        let mut items = std::mem::take(&mut self.items);
+        for child in self.children.iter_mut().flat_map(|c| c.iter_mut()) {
+            items.append(&mut child.take_items());
+        }
+        items
+    }
+
+    fn len(&self) -> usize {
+        let children = self.children.iter().flat_map(|c| c.iter());
+        self.items.len() + children.map(BoundedNode::len).sum::<usize>()
+    }
+
+    fn remove_where(&mut self, predicate: &mut impl FnMut(&T) -> bool, removed: &mut Vec<T>) {
+        let mut i = 0;
+        while i < self.items.len() {
+            if predicate(&self.items[i]) {
+                removed.push(self.items.swap_remove(i));
+            } else {
+                i += 1;
+            }
+        }
+        if let Some(ref mut children) = self.children {
+            for child in children.iter_mut() {
+                child.remove_where(predicate, removed);
+            }
+        }
+        if self.children.is_some() && self.len() <= self.capacity {
+            self.items = self.take_items();
+            self.children = None;
+        }
+    }
+
+    fn query<'a>(&'a self, rect: &Rect<N>, found: &mut Vec<&'a T>) {
+        if !self.bounds.intersects(rect) {
+            return;
+        }
+        found.extend(
+            self.items
+                .iter()
+                .filter(|item| item.bounds().intersects(rect)),
+        );
+        for child in self.children.iter().flat_map(|c| c.iter()) {
+            child.query(rect, found);
+        }
+    }
+}
+
+impl<T: BoundedLocatable<N>, N: Scalar> BoundedQuadTree<T, N> {
+    pub fn new(bounds: Rect<N>, capacity: usize) -> Self {
+        BoundedQuadTree {
+            root: BoundedNode::new(bounds, capacity),
+            len: 0,
+        }
+    }
+
+    /// Adds `data`, returning false and dropping it if any of it is outside
+    /// the tree's bounds.
+    pub fn insert(&mut self, data: T) -> bool {
+        if !self.root.bounds.contains_rect(&data.bounds()) {
+            return false;
+        }
+        self.root.insert(data);
+        self.len += 1;
+        true
+    }
+
+    pub fn take_items(&mut self) -> Vec<T> {
+        self.len = 0;
+        let items = self.root.take_items();
+        self.root.children = None;
+        items
+    }
+
+    /// Removes and returns every item `predicate` picks, collapsing
+    /// subdivisions that are no longer needed.
+    pub fn remove_where(&mut self, mut predicate: impl FnMut(&T) -> bool) -> Vec<T> {
+        let mut removed = Vec::new();
+        self.root.remove_where(&mut predicate, &mut removed);
+        self.len -= removed.len();
+        removed
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every item whose bounds overlap `rect`, edges included.
+    pub fn query(&self, rect: &Rect<N>) -> Vec<&T> {
+        let mut found = Vec::new();
+        self.root.query(rect, &mut found);
+        found
+    }
+
+    /// Every item covering `point`, such as the patches a microbe is on.
+    pub fn query_point(&self, point: Point<N>) -> Vec<&T> {
+        self.query(&Rect::new(point.x, point.y, N::ZERO, N::ZERO))
+    }
+}
+
+/// Trees are saved as their bounds, capacity and a flat item list, with
+/// the layout of their nodes so they load exactly as they were, items in
+/// the same nodes and in the same order. Saves without a layout, or whose
+/// layout doesn't fit the items, are rebuilt by reinserting the items.
+///
+/// Unlike the rest of the tree, which has no ties to the simulation, these
+/// impls aren't behind a feature: serde is a required dependency of this
+/// crate, and [`crate::World`] saves its microbes through them.
+#[derive(Serialize, Deserialize)]
+struct SavedTree<I, N> {
+    bounds: Rect<N>,
+    capacity: usize,
+    /// Node by node, parents before children.
+    items: Vec<I>,
+    /// For each node in the same order, how many of the items it holds
+    /// and whether it's subdivided.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    layout: Option<Vec<(usize, bool)>>,
+}
+
+impl<T: Locatable<N>, N: Scalar> QuadTreeNode<T, N> {
+    fn save<'a>(&'a self, items: &mut Vec<&'a T>, layout: &mut Vec<(usize, bool)>) {
+        items.extend(&self.items);
+        layout.push((self.items.len(), self.children.is_some()));
+        for child in self.children.iter().flat_map(|c| c.iter()) {
+            child.save(items, layout);
+        }
+    }
+
+    /// Fills in this node and its children from a saved layout. Returns
+    /// `None` if the layout runs out or puts an item outside its node.
+    fn load(
+        &mut self,
+        layout: &mut impl Iterator<Item = (usize, bool)>,
+        items: &mut impl Iterator<Item = T>,
+    ) -> Option<()> {
+        let (count, split) = layout.next()?;
+        self.items.extend(items.take(count));
+        let placed = self
+            .items
+            .iter()
+            .all(|i| self.bounds.contains_point(i.location()));
+        if self.items.len() != count || !placed {
+            return None;
+        }
+        if split {
+            self.subdivide();
+            for child in self.children.iter_mut().flat_map(|c| c.iter_mut()) {
+                child.load(layout, items)?;
+            }
+        }
+        Some(())
+    }
+}
+
+impl<T: Locatable<N> + Serialize, N: Scalar + Serialize> Serialize for QuadTree<T, N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (mut items, mut layout) = (Vec::with_capacity(self.len), Vec::new());
+        self.root.save(&mut items, &mut layout);
+        SavedTree {
+            bounds: self.root.bounds,
+            capacity: self.root.capacity,
+            items,
+            layout: Some(layout),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T, N> Deserialize<'de> for QuadTree<T, N>
+where
+    T: Locatable<N> + Deserialize<'de>,
+    N: Scalar + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let saved = SavedTree::<T, N>::deserialize(deserializer)?;
+        let mut tree = QuadTree::new(saved.bounds, saved.capacity);
+        let count = saved.items.len();
+        let mut items = saved.items.into_iter();
+        if let Some(layout) = saved.layout {
+            let mut layout = layout.into_iter();
+            let loaded = tree.root.load(&mut layout, &mut items);
+            if loaded.is_some() && layout.next().is_none() && items.len() == 0 {
+                tree.len = count;
+                return Ok(tree);
+            }
+        }
+        let items = tree
+            .take_items()
+            .into_iter()
+            .chain(items)
+            .collect::<Vec<_>>();
+        tree = QuadTree::new(saved.bounds, saved.capacity);
+        for item in items {
+            tree.insert(item);
+        }
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Item {
+        tag: String,
+        location: Point,
+    }
+
+    impl Locatable for Item {
+        fn location(&self) -> Point {
+            self.location
+        }
+    }
+
+    impl Relocatable for Item {
+        fn set_location(&mut self, point: Point) {
+            self.location = point;
+        }
+    }
+
+    fn create_item(tag: &str, x: f32, y: f32) -> Item {
+        Item {
+            tag: tag.to_owned(),
+            location: Point::new(x, y),
+        }
+    }
+
+    #[test]
+    fn test_insert_and_query() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 4);
+
+        // Insert some points
+        qt.insert(Item {
+            tag: "A".to_owned(),
+            location: Point::new(10., 10.),
+        });
+        qt.insert(Item {
+            tag: "B".to_owned(),
+            location: Point::new(20., 20.),
+        });
+        qt.insert(Item {
+            tag: "C".to_owned(),
+            location: Point::new(31., 31.),
+        });
+
+        // Query a range that should include two points
+        let query_bounds = Rect::new(5.0, 5.0, 25.0, 25.0);
+        let results = qt.query(&query_bounds);
+
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().any(|&data| data.tag == "A"));
+        assert!(results.iter().any(|&data| data.tag == "B"));
+    }
+
+    #[test]
+    fn test_subdivision_triggers() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 4);
+
+        // Add items to trigger subdivision
+        qt.insert(create_item("A", 10.0, 10.0));
+        qt.insert(create_item("B", 11.0, 11.0));
+        qt.insert(create_item("C", 12.0, 12.0));
+        qt.insert(create_item("D", 13.0, 13.0));
+        qt.insert(create_item("E", 14.0, 14.0)); // This should trigger subdivision
+
+        // Query small region containing all points
+        let results = qt.query(&Rect::new(9.0, 9.0, 6.0, 6.0));
+        assert_eq!(results.len(), 5);
+    }
+
+    #[test]
+    fn test_subdivision_distribution() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 4);
+
+        // Add points in different quadrants
+        // Northwest quadrant
+        qt.insert(create_item("NW1", 20.0, 20.0));
+        qt.insert(create_item("NW2", 21.0, 21.0));
+
+        // Northeast quadrant
+        qt.insert(create_item("NE1", 70.0, 20.0));
+        qt.insert(create_item("NE2", 71.0, 21.0));
+
+        // Southwest quadrant
+        qt.insert(create_item("SW1", 20.0, 70.0));
+        qt.insert(create_item("SW2", 21.0, 71.0));
+
+        // Southeast quadrant
+        qt.insert(create_item("SE1", 70.0, 70.0));
+        qt.insert(create_item("SE2", 71.0, 71.0));
+
+        // Query each quadrant separately
+        let nw_results = qt.query(&Rect::new(0.0, 0.0, 50.0, 50.0));
+        let ne_results = qt.query(&Rect::new(50.0, 0.0, 50.0, 50.0));
+        let sw_results = qt.query(&Rect::new(0.0, 50.0, 50.0, 50.0));
+        let se_results = qt.query(&Rect::new(50.0, 50.0, 50.0, 50.0));
+
+        assert_eq!(nw_results.len(), 2);
+        assert_eq!(ne_results.len(), 2);
+        assert_eq!(sw_results.len(), 2);
+        assert_eq!(se_results.len(), 2);
+    }
+
+    #[test]
+    fn test_deep_subdivision() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 2); // Smaller capacity to force deeper subdivision
+
+        // Add many points in a small area to force multiple subdivisions
+        for i in 0..16 {
+            let x = 10.0 + (i as f32) * 0.1;
+            let y = 10.0 + (i as f32) * 0.1;
+            qt.insert(create_item(&format!("P{}", i), x, y));
+        }
+
+        // Query the small area containing all points
+        let results = qt.query(&Rect::new(9.0, 9.0, 3.0, 3.0));
+        assert_eq!(results.len(), 16);
+
+        // Query a very small area containing just a few points
+        let small_results = qt.query(&Rect::new(10.0, 10.0, 0.2, 0.2));
+        assert!(!small_results.is_empty() && small_results.len() < 16);
+    }
+
+    #[test]
+    fn test_boundary_cases() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 4);
+
+        // Test points exactly on boundaries
+        qt.insert(create_item("Center", 50.0, 50.0)); // Center point
+        qt.insert(create_item("Edge", 100.0, 50.0)); // Right edge
+        qt.insert(create_item("Corner", 100.0, 100.0)); // Corner point
+        qt.insert(create_item("TopEdge", 50.0, 0.0)); // Top edge
+
+        // Query including boundary points
+        let results = qt.query(&Rect::new(49.0, 49.0, 2.0, 2.0));
+        assert_eq!(results.len(), 1); // Should only find center point
+
+        // Query exact boundary
+        let edge_results = qt.query(&Rect::new(100.0, 0.0, 0.0, 100.0));
+        assert_eq!(edge_results.len(), 2); // Should find edge and corner points
+    }
+
+    #[test]
+    fn test_empty_areas() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 4);
+
+        // Add points only in one quadrant
+        qt.insert(create_item("A", 10.0, 10.0));
+        qt.insert(create_item("B", 11.0, 11.0));
+        qt.insert(create_item("C", 12.0, 12.0));
+
+        // Query empty quadrants
+        let empty_ne = qt.query(&Rect::new(51.0, 0.0, 49.0, 49.0));
+        let empty_sw = qt.query(&Rect::new(0.0, 51.0, 49.0, 49.0));
+        let empty_se = qt.query(&Rect::new(51.0, 51.0, 49.0, 49.0));
+
+        assert_eq!(empty_ne.len(), 0);
+        assert_eq!(empty_sw.len(), 0);
+        assert_eq!(empty_se.len(), 0);
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 2);
+        assert_eq!(qt.iter().count(), 0);
+        for i in 0..20 {
+            let x = 5.0 + (i as f32) * 4.5;
+            qt.insert(create_item(&i.to_string(), x, 100.0 - x));
+        }
+        let mut tags = qt
+            .iter()
+            .map(|item| item.tag.parse().unwrap())
+            .collect::<Vec<u32>>();
+        tags.sort();
+        assert_eq!(tags, (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_remove_where() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 2);
+        for i in 0..16 {
+            let x = 10.0 + (i as f32) * 5.0;
+            qt.insert(create_item(&i.to_string(), x, x));
+        }
+        assert!(qt.root.children.is_some());
+
+        let removed = qt.remove_where(|item| item.tag.parse::<u32>().unwrap() % 4 != 0);
+        assert_eq!(removed.len(), 12);
+        assert_eq!(qt.len(), 4);
+        assert_eq!(qt.query(&Rect::new(0.0, 0.0, 100.0, 100.0)).len(), 4);
+
+        qt.remove_where(|item| item.tag != "0");
+        // One item fits in the root, so the subdivisions go.
+        assert!(qt.root.children.is_none());
+        assert_eq!(qt.items()[0].tag, "0");
+        assert!(qt.remove_where(|_| false).is_empty());
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 2);
+        for i in 0..40 {
+            qt.insert(create_item(
+                &i.to_string(),
+                (i * 37 % 100) as f32,
+                (i * 61 % 100) as f32,
+            ));
+        }
+        let mut seen = 0;
+        qt.retain(|item| {
+            seen += 1;
+            item.tag.ends_with('7')
+        });
+        assert_eq!(seen, 40);
+        assert_eq!(qt.len(), 4);
+        assert_eq!(qt.iter().count(), 4);
+        assert!(qt.iter().all(|item| item.tag.ends_with('7')));
+        qt.retain(|_| false);
+        assert!(qt.is_empty());
+        assert!(qt.root.children.is_none());
+    }
+
+    #[test]
+    fn test_remove_at() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 2);
+        for (tag, x) in [("A", 10.0), ("B", 10.0), ("C", 10.0), ("D", 80.0)] {
+            qt.insert(create_item(tag, x, x));
+        }
+        let point = Point::new(10.0, 10.0);
+        assert!(qt.remove_at(Point::new(50.0, 50.0), |_| true).is_none());
+        assert_eq!(
+            qt.remove_at(point, |item| item.tag == "C").unwrap().tag,
+            "C"
+        );
+        assert!(qt.remove_at(point, |item| item.tag == "D").is_none());
+        assert_eq!(qt.remove_at(point, |_| true).unwrap().tag, "A");
+        assert_eq!(qt.len(), 2);
+        assert!(qt.root.children.is_none());
+        let mut tags = qt.iter().map(|item| item.tag.as_str()).collect::<Vec<_>>();
+        tags.sort();
+        assert_eq!(tags, ["B", "D"]);
+    }
+
+    #[test]
+    fn test_nearest() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 2);
+        assert!(qt.nearest(Point::new(50.0, 50.0)).is_none());
+        let points = (0..40).map(|i| ((i * 37 % 100) as f32, (i * 61 % 100) as f32));
+        for (i, (x, y)) in points.clone().enumerate() {
+            qt.insert(create_item(&i.to_string(), x, y));
+        }
+        for target in [(0.0, 0.0), (50.0, 50.0), (99.0, 3.0), (-20.0, 130.0)] {
+            let point = Point::new(target.0, target.1);
+            let distance = |item: &Item| item.location.distance_squared(point);
+            let expected = qt.iter().map(distance).min_by(f32::total_cmp);
+            assert_eq!(qt.nearest(point).map(distance), expected);
+        }
+
+        let (x, y) = points.clone().nth(5).unwrap();
+        assert_eq!(qt.nearest(Point::new(x, y)).unwrap().tag, "5");
+        let other = qt.nearest_where(Point::new(x, y), |item| item.tag != "5");
+        assert_ne!(other.unwrap().tag, "5");
+        assert!(qt.nearest_where(Point::new(x, y), |_| false).is_none());
+    }
+
+    #[test]
+    fn test_knn() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 2);
+        for i in 0..50 {
+            qt.insert(create_item(
+                &i.to_string(),
+                (i * 37 % 100) as f32,
+                (i * 61 % 100) as f32,
+            ));
+        }
+        let point = Point::new(40.0, 60.0);
+        let distance = |item: &Item| item.location.distance_squared(point);
+        let mut expected = qt.iter().map(distance).collect::<Vec<_>>();
+        expected.sort_by(f32::total_cmp);
+        for k in [0, 1, 5, 50, 60] {
+            let found = qt
+                .knn(point, k)
+                .into_iter()
+                .map(distance)
+                .collect::<Vec<_>>();
+            assert_eq!(found, expected[..k.min(50)]);
+        }
+        let even = qt.knn_where(point, 3, |item| item.tag.parse::<u32>().unwrap() % 2 == 0);
+        assert_eq!(even.len(), 3);
+        assert!(even
+            .iter()
+            .all(|item| item.tag.parse::<u32>().unwrap() % 2 == 0));
+    }
+
+    #[test]
+    fn test_query_circle() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 2);
+        for i in 0..50 {
+            qt.insert(create_item(
+                &i.to_string(),
+                (i * 37 % 100) as f32,
+                (i * 61 % 100) as f32,
+            ));
+        }
+        for (x, y, radius) in [(50.0, 50.0, 20.0), (0.0, 0.0, 35.0), (-10.0, 50.0, 5.0)] {
+            let center = Point::new(x, y);
+            let within = |item: &&Item| item.location.distance_squared(center) <= radius * radius;
+            let mut expected = qt.iter().filter(within).map(|i| &i.tag).collect::<Vec<_>>();
+            let mut found = qt
+                .query_circle(center, radius)
+                .into_iter()
+                .map(|i| &i.tag)
+                .collect::<Vec<_>>();
+            expected.sort();
+            found.sort();
+            assert_eq!(found, expected);
+        }
+        // The corners of the bounding square are outside the circle.
+        qt.insert(create_item("corner", 18.0, 18.0));
+        let found = qt.query_circle(Point::new(10.0, 10.0), 10.0);
+        assert!(found.iter().all(|item| item.tag != "corner"));
+    }
+
+    #[test]
+    fn test_query_sector() {
+        let mut qt = QuadTree::new(Rect::new(-50.0, -50.0, 100.0, 100.0), 2);
+        for i in 0..80 {
+            let (x, y) = ((i * 37 % 100) as f32 - 50.0, (i * 61 % 100) as f32 - 50.0);
+            qt.insert(create_item(&i.to_string(), x, y));
+        }
+        let center = Point::new(5.0, -5.0);
+        // Cones facing every way, including across the wrap at ±π.
+        for (direction, half_angle) in [(0.0, 0.5), (PI, 0.3), (-3.0, 0.4), (2.0, 1.2), (7.0, PI)] {
+            let within = |item: &&Item| {
+                let (dx, dy) = (item.location.x - center.x, item.location.y - center.y);
+                let offset = (dy.atan2(dx) - direction).rem_euclid(2. * PI);
+                let offset = offset.min(2. * PI - offset);
+                dx * dx + dy * dy <= 40.0 * 40.0 && offset <= half_angle
+            };
+            let mut expected = qt.iter().filter(within).map(|i| &i.tag).collect::<Vec<_>>();
+            let found = qt.query_sector(center, direction, half_angle, 40.0);
+            let mut found = found.into_iter().map(|i| &i.tag).collect::<Vec<_>>();
+            expected.sort();
+            found.sort();
+            assert!(!expected.is_empty());
+            assert_eq!(found, expected, "direction {direction}");
+        }
+    }
+
+    #[test]
+    fn test_query_iter() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 2);
+        for i in 0..50 {
+            qt.insert(create_item(
+                &i.to_string(),
+                (i * 37 % 100) as f32,
+                (i * 61 % 100) as f32,
+            ));
+        }
+        let rect = Rect::new(20.0, 30.0, 40.0, 25.0);
+        let tags = |items: Vec<&Item>| items.into_iter().map(|i| i.tag.clone()).collect::<Vec<_>>();
+        let expected = qt
+            .iter()
+            .filter(|i| rect.contains_point(i.location))
+            .count();
+        assert!(expected > 0);
+        assert_eq!(qt.query_iter(&rect).count(), expected);
+        assert_eq!(tags(qt.query_iter(&rect).collect()), tags(qt.query(&rect)));
+        let outside = Rect::new(200.0, 200.0, 10.0, 10.0);
+        assert!(qt.query_iter(&outside).next().is_none());
+    }
+
+    #[test]
+    fn test_relocate() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 2);
+        for (i, x) in [10.0, 20.0, 30.0, 40.0, 80.0, 90.0].into_iter().enumerate() {
+            qt.insert(create_item(&i.to_string(), x, x));
+        }
+        let tag = |tag: &'static str| move |item: &Item| item.tag == tag;
+
+        // Within the same node, and across to another quadrant.
+        assert!(qt.relocate(Point::new(30.0, 30.0), Point::new(31.0, 29.0), tag("2")));
+        assert!(qt.relocate(Point::new(40.0, 40.0), Point::new(75.0, 15.0), tag("3")));
+        assert!(!qt.relocate(Point::new(40.0, 40.0), Point::new(1.0, 1.0), tag("3")));
+        assert!(!qt.relocate(Point::new(10.0, 10.0), Point::new(10.0, 10.0), tag("1")));
+        assert!(!qt.relocate(Point::new(80.0, 80.0), Point::new(150.0, 0.0), tag("4")));
+
+        assert_eq!(qt.len(), 6);
+        let find = |rect| {
+            qt.query(&rect)
+                .into_iter()
+                .map(|i| i.tag.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(find(Rect::new(74.0, 14.0, 2.0, 2.0)), ["3"]);
+        assert_eq!(find(Rect::new(30.0, 28.0, 2.0, 2.0)), ["2"]);
+        assert!(find(Rect::new(39.0, 39.0, 2.0, 2.0)).is_empty());
+        assert_eq!(find(Rect::new(79.0, 79.0, 2.0, 2.0)), ["4"]);
+    }
+
+    #[test]
+    fn test_same_point_overflows() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 2);
+        for i in 0..100 {
+            assert!(qt.insert(create_item(&i.to_string(), 30.0, 30.0)));
+        }
+        assert!(!qt.insert(create_item("outside", 101.0, 30.0)));
+        assert_eq!(qt.len(), 100);
+        assert_eq!(qt.query(&Rect::new(29.0, 29.0, 2.0, 2.0)).len(), 100);
+        let mut depth = 0;
+        let mut node = &qt.root;
+        while let Some(children) = &node.children {
+            node = children.iter().find(|c| c.len() > 0).unwrap();
+            depth += 1;
+        }
+        assert_eq!(depth, MAX_DEPTH);
+        assert_eq!(node.items.len(), 100 - 2 * MAX_DEPTH);
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let items = || {
+            (0..200).map(|i| {
+                // Some outside the bounds, and a pile at one point.
+                let (x, y) = match i % 10 {
+                    0 => (120.0, 50.0),
+                    1 => (30.0, 30.0),
+                    _ => ((i * 37 % 100) as f32, (i * 61 % 100) as f32),
+                };
+                create_item(&i.to_string(), x, y)
+            })
+        };
+        let bounds = Rect::new(0.0, 0.0, 100.0, 100.0);
+        let built = QuadTree::from_iter(bounds, 3, items());
+        let mut inserted = QuadTree::new(bounds, 3);
+        for item in items() {
+            inserted.insert(item);
+        }
+
+        assert_eq!(built.len(), 180);
+        assert_eq!(built.iter().count(), 180);
+        // Building in one go lays the items out as inserting them would.
+        let nodes = |qt: &QuadTree<Item>| {
+            let mut nodes = Vec::new();
+            qt.visit_nodes(|bounds, items| {
+                nodes.push((
+                    *bounds,
+                    items.iter().map(|i| i.tag.clone()).collect::<Vec<_>>(),
+                ));
+                true
+            });
+            nodes
+        };
+        assert_eq!(nodes(&built), nodes(&inserted));
+
+        let empty = QuadTree::<Item>::from_iter(bounds, 3, []);
+        assert!(empty.is_empty());
+        assert!(empty.root.children.is_none());
+    }
+
+    #[test]
+    fn test_bounded_tree() {
+        #[derive(Debug, PartialEq)]
+        struct Patch(&'static str, Rect);
+
+        impl BoundedLocatable for Patch {
+            fn bounds(&self) -> Rect {
+                self.1
+            }
+        }
+
+        let mut tree = BoundedQuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 2);
+        assert!(tree.insert(Patch("nw", Rect::new(10.0, 10.0, 5.0, 5.0))));
+        assert!(tree.insert(Patch("se", Rect::new(80.0, 80.0, 10.0, 10.0))));
+        // Across the middle, so it stays at the root once that splits.
+        assert!(tree.insert(Patch("middle", Rect::new(40.0, 40.0, 20.0, 20.0))));
+        assert!(tree.insert(Patch("ne", Rect::new(60.0, 5.0, 10.0, 10.0))));
+        assert!(!tree.insert(Patch("edge", Rect::new(95.0, 50.0, 10.0, 10.0))));
+        assert_eq!(tree.len(), 4);
+        assert_eq!(
+            tree.root.items,
+            [Patch("middle", Rect::new(40.0, 40.0, 20.0, 20.0))]
+        );
+
+        let tags = |found: Vec<&Patch>| {
+            let mut tags = found.iter().map(|p| p.0).collect::<Vec<_>>();
+            tags.sort();
+            tags
+        };
+        // Overlapping counts, even where no corner or center is inside.
+        assert_eq!(
+            tags(tree.query(&Rect::new(45.0, 0.0, 20.0, 45.0))),
+            ["middle", "ne"]
+        );
+        assert_eq!(tags(tree.query_point(Point::new(50.0, 50.0))), ["middle"]);
+        assert_eq!(tags(tree.query_point(Point::new(90.0, 90.0))), ["se"]);
+        assert!(tree.query_point(Point::new(30.0, 70.0)).is_empty());
+
+        let removed = tree.remove_where(|p| p.0.len() == 2);
+        assert_eq!(tags(removed.iter().collect()), ["ne", "nw", "se"]);
+        assert_eq!(tree.len(), 1);
+        assert!(tree.root.children.is_none());
+        assert_eq!(tree.take_items().len(), 1);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn test_structure_stats() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 2);
+        assert_eq!((qt.depth(), qt.node_count()), (0, 1));
+        assert_eq!(qt.items_per_node(), [1]);
+
+        for i in 0..3 {
+            qt.insert(create_item(&i.to_string(), 10.0, 10.0 + i as f32));
+        }
+        // The third goes into the northwest child.
+        assert_eq!((qt.depth(), qt.node_count()), (1, 5));
+        assert_eq!(qt.items_per_node(), [3, 1, 1]);
+
+        // Two a level until MAX_DEPTH, which keeps the rest.
+        for _ in 0..100 {
+            qt.insert(create_item("pile", 80.0, 80.0));
+        }
+        assert_eq!(qt.depth(), MAX_DEPTH);
+        let counts = qt.items_per_node();
+        assert_eq!(counts.len(), 100 - 2 * (MAX_DEPTH - 1) + 1);
+        assert_eq!(counts[counts.len() - 1], 1);
+        assert_eq!(counts.iter().sum::<usize>(), qt.node_count());
+    }
+
+    #[test]
+    fn test_len_and_clear() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 2);
+        assert!(qt.is_empty());
+        for i in 0..20 {
+            qt.insert(create_item(&i.to_string(), (i * 5) as f32, 50.0));
+        }
+        qt.insert(create_item("outside", 150.0, 50.0));
+        assert_eq!(qt.len(), 20);
+        qt.remove_at(Point::new(10.0, 50.0), |_| true);
+        qt.remove_where(|item| item.tag.len() == 2);
+        assert_eq!(qt.len(), 9);
+        assert_eq!(qt.len(), qt.iter().count());
+
+        qt.clear();
+        assert!(qt.is_empty());
+        assert_eq!(qt.iter().count(), 0);
+        // The subdivisions stay, ready for the next items.
+        assert!(qt.root.children.is_some());
+        qt.insert(create_item("again", 90.0, 90.0));
+        assert_eq!(qt.len(), 1);
+        assert_eq!(qt.take_items().len(), 1);
+        assert!(qt.is_empty());
+    }
+
+    #[test]
+    fn test_removals_keep_the_tree_shallow() {
+        let depth = |qt: &QuadTree<Item>| {
+            fn depth(node: &QuadTreeNode<Item>) -> usize {
+                let children = node.children.iter().flat_map(|c| c.iter());
+                1 + children.map(depth).max().unwrap_or(0)
+            }
+            depth(&qt.root)
+        };
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 4);
+        for i in 0..64 {
+            let offset = i as f32 * 0.2;
+            qt.insert(create_item(&i.to_string(), 10.0 + offset, 10.0 + offset));
+        }
+        assert!(depth(&qt) > 3);
+
+        // The first few, which sat in the higher nodes, go first.
+        qt.remove_where(|item| item.tag.parse::<u32>().unwrap() < 56);
+        assert_eq!(qt.len(), 8);
+        assert_eq!(qt.root.items.len(), 4);
+        assert_eq!(depth(&qt), 2);
+        assert_eq!(qt.query(&Rect::new(0.0, 0.0, 30.0, 30.0)).len(), 8);
+
+        qt.remove_where(|item| item.tag.parse::<u32>().unwrap() < 60);
+        assert_eq!(depth(&qt), 1);
+        assert_eq!(qt.iter().count(), 4);
+    }
+
+    #[test]
+    fn test_callbacks() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 2);
+        for i in 0..30 {
+            qt.insert(create_item(
+                &i.to_string(),
+                (i * 37 % 100) as f32,
+                (i * 61 % 100) as f32,
+            ));
+        }
+        let rect = Rect::new(10.0, 10.0, 50.0, 40.0);
+        let mut found = Vec::new();
+        qt.for_each_in_rect(&rect, |item| found.push(item.tag.clone()));
+        let expected = qt
+            .query(&rect)
+            .into_iter()
+            .map(|i| i.tag.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(found, expected);
+
+        for item in qt.query_mut(&rect) {
+            item.tag.push('!');
+        }
+        qt.for_each_in_rect_mut(&rect, |item| item.tag.push('?'));
+        let changed = qt.iter().filter(|i| i.tag.ends_with("!?")).count();
+        assert_eq!(changed, expected.len());
+        assert_eq!(
+            qt.iter().filter(|i| i.tag.contains(['!', '?'])).count(),
+            changed
+        );
+
+        let (mut nodes, mut items) = (0, 0);
+        qt.visit_nodes(|_, here| {
+            nodes += 1;
+            items += here.len();
+            true
+        });
+        assert!(nodes > 1);
+        assert_eq!(items, 30);
+        let mut visited = Vec::new();
+        qt.visit_nodes(|bounds, _| {
+            visited.push(*bounds);
+            false
+        });
+        assert_eq!(visited, [qt.root.bounds]);
+    }
+
+    #[test]
+    fn test_par_query_many() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 2);
+        for i in 0..200 {
+            qt.insert(create_item(
+                &i.to_string(),
+                (i * 37 % 100) as f32,
+                (i * 61 % 100) as f32,
+            ));
+        }
+        let rects = (0..50)
+            .map(|i| Rect::new((i * 13 % 90) as f32, (i * 7 % 90) as f32, 15.0, 20.0))
+            .collect::<Vec<_>>();
+        let tags = |items: &[&Item]| items.iter().map(|i| i.tag.clone()).collect::<Vec<_>>();
+        let answers = qt.par_query_many(&rects);
+        assert_eq!(answers.len(), rects.len());
+        for (rect, answer) in rects.iter().zip(&answers) {
+            assert_eq!(tags(answer), tags(&qt.query(rect)));
+        }
+
+        let circles = [(Point::new(50.0, 50.0), 10.0), (Point::new(0.0, 0.0), 30.0)];
+        let answers = qt.par_query_circles(&circles);
+        for (&(center, radius), answer) in circles.iter().zip(&answers) {
+            assert_eq!(tags(answer), tags(&qt.query_circle(center, radius)));
+        }
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 2);
+        for (i, x) in [10.0, 20.0, 60.0, 70.0, 80.0].into_iter().enumerate() {
+            qt.insert(create_item(&i.to_string(), x, x));
+        }
+
+        let json = serde_json::to_string(&qt).unwrap();
+        let loaded = serde_json::from_str::<QuadTree<Item>>(&json).unwrap();
+
+        assert_eq!(loaded.root.bounds, qt.root.bounds);
+        assert_eq!(loaded.root.capacity, 2);
+        assert_eq!(loaded.iter().count(), 5);
+        assert_eq!(loaded.query(&Rect::new(55.0, 55.0, 30.0, 30.0)).len(), 3);
+    }
+
+    #[test]
+    fn test_serde_keeps_layout() {
+        let nodes = |qt: &QuadTree<Item>| {
+            let mut nodes = Vec::new();
+            qt.visit_nodes(|bounds, items| {
+                nodes.push((
+                    *bounds,
+                    items.iter().map(|i| i.tag.clone()).collect::<Vec<_>>(),
+                ));
+                true
+            });
+            nodes
+        };
+        let mut qt = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0), 2);
+        for i in 0..30 {
+            qt.insert(create_item(
+                &i.to_string(),
+                (i * 37 % 100) as f32,
+                (i * 61 % 100) as f32,
+            ));
+        }
+        // Removals leave a layout that reinserting wouldn't reproduce.
+        qt.remove_where(|item| item.tag.ends_with('3'));
+
+        let json = serde_json::to_string(&qt).unwrap();
+        let loaded = serde_json::from_str::<QuadTree<Item>>(&json).unwrap();
+        assert_eq!(nodes(&loaded), nodes(&qt));
+        assert_eq!(loaded.len(), qt.len());
+
+        // Saves from before layouts, and layouts that don't fit, reinsert.
+        for layout in [
+            "",
+            r#","layout":[[9,false]]"#,
+            r#","layout":[[0,false],[1,false]]"#,
+        ] {
+            let json = format!(
+                r#"{{"bounds":{{"x":0,"y":0,"width":100,"height":100}},"capacity":1,"items":[{},{}]{layout}}}"#,
+                r#"{"tag":"a","location":{"x":10,"y":10}}"#,
+                r#"{"tag":"b","location":{"x":90,"y":90}}"#,
+            );
+            let loaded = serde_json::from_str::<QuadTree<Item>>(&json).unwrap();
+            assert_eq!(loaded.len(), 2);
+            assert_eq!(loaded.query(&Rect::new(80.0, 80.0, 20.0, 20.0)).len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_rect_and_point_helpers() {
+        let rect = Rect::from_center(Point::new(10.0, 20.0), 5.0);
+        assert_eq!(rect, Rect::new(5.0, 15.0, 10.0, 10.0));
+        assert_eq!(rect.center(), Point::new(10.0, 20.0));
+        assert_eq!(rect.expanded(1.0), Rect::new(4.0, 14.0, 12.0, 12.0));
+        assert_eq!(rect.expanded(-5.0).center(), rect.center());
+
+        assert!(rect.contains_rect(&rect));
+        assert!(rect.expanded(1.0).contains_rect(&rect));
+        assert!(!rect.contains_rect(&rect.expanded(1.0)));
+        assert!(!rect.contains_rect(&Rect::new(14.0, 15.0, 2.0, 2.0)));
+
+        assert_eq!(rect.distance_squared(Point::new(18.0, 29.0)), 25.0);
+        assert_eq!(rect.distance_squared(Point::new(6.0, 16.0)), 0.0);
+        let point = Point::new(3.0, 4.0);
+        assert_eq!(point.distance_squared(Point::new(0.0, 0.0)), 25.0);
+        assert_eq!(point.distance_to(Point::new(0.0, 0.0)), 5.0);
+    }
+
+    #[test]
+    fn test_other_scalars() {
+        struct Cell(Point<i64>);
+
+        impl Locatable<i64> for Cell {
+            fn location(&self) -> Point<i64> {
+                self.0
+            }
+        }
+
+        // Odd widths don't halve evenly; the sliver stays with the parent.
+        let mut grid = QuadTree::new(Rect::new(0, 0, 101, 101), 1);
+        for (x, y) in [(0, 0), (50, 50), (51, 51), (100, 100), (101, 101)] {
+            assert!(grid.insert(Cell(Point::new(x, y))));
+        }
+        assert!(!grid.insert(Cell(Point::new(102, 0))));
+        assert_eq!(grid.len(), 5);
+        assert_eq!(grid.query(&Rect::new(50, 50, 1, 1)).len(), 2);
+        assert_eq!(grid.query_circle(Point::new(0, 0), 72).len(), 2);
+        let nearest = grid.nearest(Point::new(99, 99)).unwrap();
+        assert_eq!(nearest.0, Point::new(100, 100));
+        assert_eq!(nearest.0.distance_to(Point::new(103, 104)), 5);
+
+        struct Star(Point<f64>);
+
+        impl Locatable<f64> for Star {
+            fn location(&self) -> Point<f64> {
+                self.0
+            }
+        }
+
+        // Far enough out that f32 can't tell these apart.
+        let far = 1.0e9;
+        let mut sky = QuadTree::new(Rect::new(far, far, 1.0, 1.0), 1);
+        sky.insert(Star(Point::new(far + 0.25, far)));
+        sky.insert(Star(Point::new(far + 0.5, far)));
+        let found = sky.knn(Point::new(far + 0.3, far), 2);
+        assert_eq!(found[0].0.x(), far + 0.25);
+        assert_eq!(found[1].0.x(), far + 0.5);
+    }
+}
@@ -0,0 +1,138 @@
+//! Seeded randomness. Everything random in a world is derived from its seed,
+//! so the same seed and scripts always produce the same run.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rhai::{Engine, EvalAltResult, FLOAT, INT};
+use std::cell::RefCell;
+use std::ops::{Range, RangeInclusive};
+use uuid::Uuid;
+
+const GOLDEN: u64 = 0x9E37_79B9_7F4A_7C15;
+
+thread_local! {
+    /// The generator behind the script `rand` functions, reseeded before
+    /// each script runs.
+    static SCRIPT_RNG: RefCell<StdRng> = RefCell::new(StdRng::seed_from_u64(0));
+}
+
+/// The `n`th id handed out by a world with this seed.
+pub(crate) fn uuid(seed: u64, n: u64) -> Uuid {
+    let mut rng = StdRng::seed_from_u64(seed ^ n.wrapping_mul(GOLDEN));
+    uuid::Builder::from_random_bytes(rng.gen()).into_uuid()
+}
+
+/// Reseeds the script generator for one microbe's script on one tick. Each
+/// run gets its own stream, so results don't depend on evaluation order.
+pub(crate) fn reseed_scripts(seed: u64, tick: u64, microbe: Uuid) {
+    let (high, low) = microbe.as_u64_pair();
+    let seed = seed ^ tick.wrapping_mul(GOLDEN) ^ high ^ low.rotate_left(32);
+    SCRIPT_RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+/// A generator of its own for something happening to one microbe, such as
+/// its mutations at birth.
+pub(crate) fn for_microbe(seed: u64, microbe: Uuid) -> StdRng {
+    let (high, low) = microbe.as_u64_pair();
+    StdRng::seed_from_u64(seed ^ GOLDEN ^ high.rotate_left(32) ^ low)
+}
+
+/// A generator of its own for something happening to the world as a whole
+/// on one tick, such as a wanderer trickling in.
+pub(crate) fn for_tick(seed: u64, tick: u64) -> StdRng {
+    StdRng::seed_from_u64(seed.rotate_left(17) ^ tick.wrapping_mul(GOLDEN))
+}
+
+fn with_rng<T>(f: impl FnOnce(&mut StdRng) -> T) -> T {
+    SCRIPT_RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+fn empty(range: impl std::fmt::Debug) -> Box<EvalAltResult> {
+    format!("rand: range {range:?} is empty").into()
+}
+
+/// Registers `rand`, `rand_float` and `rand_bool`, matching the signatures
+/// scripts used to get from `rhai-rand`.
+pub(crate) fn register(engine: &mut Engine) {
+    engine
+        .register_fn("rand", || with_rng(|rng| rng.gen::<INT>()))
+        .register_fn(
+            "rand",
+            |range: Range<INT>| -> Result<INT, Box<EvalAltResult>> {
+                if range.is_empty() {
+                    return Err(empty(range));
+                }
+                Ok(with_rng(|rng| rng.gen_range(range)))
+            },
+        )
+        .register_fn(
+            "rand",
+            |range: RangeInclusive<INT>| -> Result<INT, Box<EvalAltResult>> {
+                if range.is_empty() {
+                    return Err(empty(range));
+                }
+                Ok(with_rng(|rng| rng.gen_range(range)))
+            },
+        )
+        .register_fn(
+            "rand",
+            |start: INT, end: INT| -> Result<INT, Box<EvalAltResult>> {
+                if start > end {
+                    return Err(empty(start..=end));
+                }
+                Ok(with_rng(|rng| rng.gen_range(start..=end)))
+            },
+        )
+        .register_fn("rand_float", || with_rng(|rng| rng.gen::<FLOAT>()))
+        .register_fn(
+            "rand_float",
+            |start: FLOAT, end: FLOAT| -> Result<FLOAT, Box<EvalAltResult>> {
+                if start.is_nan() || end.is_nan() || start > end {
+                    return Err(empty(start..=end));
+                }
+                Ok(with_rng(|rng| rng.gen_range(start..=end)))
+            },
+        )
+        .register_fn("rand_bool", || with_rng(|rng| rng.gen::<bool>()))
+        .register_fn(
+            "rand_bool",
+            |probability: FLOAT| -> Result<bool, Box<EvalAltResult>> {
+                if !(0.0..=1.0).contains(&probability) {
+                    return Err(
+                        format!("rand_bool: probability {probability} is not in 0..=1").into(),
+                    );
+                }
+                Ok(with_rng(|rng| rng.gen_bool(probability)))
+            },
+        );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scripts_repeat_after_reseeding() {
+        let mut engine = Engine::new();
+        register(&mut engine);
+        let script = "[rand(0..=100), rand(1, 6), rand_float(), rand_bool(0.5)]";
+        let id = Uuid::nil();
+
+        reseed_scripts(7, 3, id);
+        let first = format!("{:?}", engine.eval::<rhai::Array>(script).unwrap());
+        reseed_scripts(7, 3, id);
+        let again = format!("{:?}", engine.eval::<rhai::Array>(script).unwrap());
+        assert_eq!(first, again);
+
+        assert!(engine.eval::<INT>("rand(5..5)").is_err());
+        assert!(engine.eval::<bool>("rand_bool(2.0)").is_err());
+    }
+
+    #[test]
+    fn test_uuids_depend_on_seed_and_index() {
+        assert_eq!(uuid(1, 1), uuid(1, 1));
+        assert_ne!(uuid(1, 1), uuid(1, 2));
+        assert_ne!(uuid(1, 1), uuid(2, 1));
+        assert_eq!(uuid(1, 1).get_version_num(), 4);
+    }
+}
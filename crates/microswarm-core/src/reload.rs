@@ -0,0 +1,271 @@
+//! Edits to a config file applied to the world it built while it runs.
+//!
+//! A [`ConfigWatcher`] looks at a config such as `world.toml` now and then,
+//! and when it has been saved, applies whatever changed that a running
+//! world can take: the `[tuning]` constants, the `tick_duration`, the
+//! `climate`, the `standoff`, the `[[wells]]`, the `[[disruptions]]` yet to
+//! happen, the `[trickle]`, the `[energy_cap]`, the `[population_cap]` and
+//! the `[sandbox]` limits.
+//! Everything else, such as the arena, seed or species, only takes effect
+//! when the world is built again, so it's reported in
+//! [`Changes::need_restart`] instead.
+//!
+//! ```no_run
+//! use microswarm::config::Config;
+//! use microswarm::reload::ConfigWatcher;
+//!
+//! let (mut sim, _) = Config::load("world.toml").unwrap().build().unwrap();
+//! let mut watcher = ConfigWatcher::new("world.toml").unwrap();
+//! loop {
+//!     sim.step().unwrap();
+//!     if let Ok(Some(changes)) = watcher.poll(&mut sim) {
+//!         println!("applied {:?}", changes.applied);
+//!     }
+//! }
+//! ```
+//!
+//! Only keys whose value in the file changed are applied, so tuning changed
+//! some other way, such as with the viewer's sliders, is left alone unless
+//! the file changes the same key.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config::{Config, ConfigError};
+use crate::simulation::Simulation;
+use crate::tuning::Tuning;
+
+/// What changed in a config, by key, such as `tuning.speed`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Changes {
+    /// Keys applied to the running world.
+    pub applied: Vec<String>,
+    /// Keys that take effect the next time the world is built.
+    pub need_restart: Vec<String>,
+}
+
+impl Changes {
+    /// Compares `old` and `new` and applies what it can of `new` to `sim`.
+    pub fn apply(old: &Config, new: &Config, sim: &mut Simulation) -> Self {
+        let mut changes = Changes::default();
+        for field in Tuning::FIELDS {
+            let value = |tuning: &Tuning| tuning.clone().field_mut(field).copied();
+            let Some(value) = value(&new.tuning).filter(|v| Some(*v) != value(&old.tuning)) else {
+                continue;
+            };
+            if let Some(live) = sim.tuning_mut().field_mut(field) {
+                *live = value;
+            }
+            changes.applied.push(format!("tuning.{field}"));
+        }
+        if new.tick_duration != old.tick_duration {
+            sim.set_tick_duration(new.tick_duration);
+            changes.applied.push("tick_duration".to_owned());
+        }
+        if new.climate != old.climate {
+            sim.set_climate(new.climate);
+            changes.applied.push("climate".to_owned());
+        }
+        if new.standoff != old.standoff {
+            sim.set_standoff(new.standoff);
+            changes.applied.push("standoff".to_owned());
+        }
+        if new.wells != old.wells {
+            *sim.wells_mut() = new.wells.clone();
+            changes.applied.push("wells".to_owned());
+        }
+        if new.disruptions != old.disruptions {
+            let tick = sim.tick();
+            let ahead = new.disruptions.iter().filter(|s| s.tick > tick);
+            sim.set_disruptions(ahead.copied().collect());
+            changes.applied.push("disruptions".to_owned());
+        }
+        if new.trickle != old.trickle {
+            sim.set_trickle(new.trickle);
+            changes.applied.push("trickle".to_owned());
+        }
+        if new.energy_cap != old.energy_cap {
+            sim.set_energy_cap(new.energy_cap);
+            changes.applied.push("energy_cap".to_owned());
+        }
+        if new.population_cap != old.population_cap {
+            sim.set_population_cap(new.population_cap);
+            changes.applied.push("population_cap".to_owned());
+        }
+        if new.sandbox != old.sandbox {
+            sim.set_sandbox(new.sandbox.clone());
+            changes.applied.push("sandbox".to_owned());
+        }
+
+        let restart = [
+            (
+                "arena",
+                old.arena != new.arena || old.arena_height != new.arena_height,
+            ),
+            ("shape", old.shape != new.shape),
+            ("seed", old.seed != new.seed),
+            ("math", old.math != new.math),
+            ("rules", old.rules != new.rules),
+            ("islands", old.islands != new.islands),
+            ("species", old.species != new.species),
+        ];
+        changes.need_restart = restart
+            .into_iter()
+            .filter(|(_, changed)| *changed)
+            .map(|(key, _)| key.to_owned())
+            .collect();
+        changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.need_restart.is_empty()
+    }
+}
+
+impl fmt::Display for Changes {
+    /// Such as `applied tuning.speed; restart to apply arena`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.applied.is_empty() {
+            write!(f, "applied {}", self.applied.join(", "))?;
+        }
+        if !self.need_restart.is_empty() {
+            if !self.applied.is_empty() {
+                write!(f, "; ")?;
+            }
+            write!(f, "restart to apply {}", self.need_restart.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    path: PathBuf,
+    /// The config as of the last successful load.
+    config: Config,
+    modified: Option<SystemTime>,
+    interval: Duration,
+    checked: Option<Instant>,
+}
+
+impl ConfigWatcher {
+    /// Watches the config at `path`, taking it as it is now as the one the
+    /// world was built from.
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref().to_owned();
+        Ok(Self {
+            modified: modified(&path),
+            config: Config::load(&path)?,
+            path,
+            interval: Duration::from_secs(1),
+            checked: None,
+        })
+    }
+
+    /// How often [`ConfigWatcher::poll`] looks at the file.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Applies what changed if the file was saved since the last look and
+    /// the interval has passed. A config that doesn't load is reported
+    /// once, and the changes are compared against the last one that did.
+    pub fn poll(&mut self, sim: &mut Simulation) -> Result<Option<Changes>, ConfigError> {
+        if self.checked.is_some_and(|at| at.elapsed() < self.interval) {
+            return Ok(None);
+        }
+        self.checked = Some(Instant::now());
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return Ok(None);
+        }
+        self.modified = modified;
+
+        let config = Config::load(&self.path)?;
+        let changes = Changes::apply(&self.config, &config, sim);
+        self.config = config;
+        if !changes.is_empty() {
+            tracing::info!(
+                path = %self.path.display(),
+                applied = ?changes.applied,
+                need_restart = ?changes.need_restart,
+                "config reloaded"
+            );
+        }
+        Ok(Some(changes).filter(|changes| !changes.is_empty()))
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::climate::Climate;
+    use crate::wells::Well;
+
+    #[test]
+    fn test_changes() {
+        let old = Config::parse("[tuning]\nspeed = 2.0", Path::new("")).unwrap();
+        let (mut sim, _) = old.build().unwrap();
+        sim.tuning_mut().eat_damage = 5.;
+        let new = Config::parse(
+            "arena = 100.0\nclimate = \"hot_center\"\n[tuning]\nspeed = 3.0\n[sandbox]\nmax_operations = 10\n[[wells]]\nstrength = 5",
+            Path::new(""),
+        )
+        .unwrap();
+
+        let changes = Changes::apply(&old, &new, &mut sim);
+        assert_eq!(
+            changes.applied,
+            ["tuning.speed", "climate", "wells", "sandbox"]
+        );
+        assert_eq!(changes.need_restart, ["arena"]);
+        assert_eq!(
+            changes.to_string(),
+            "applied tuning.speed, climate, wells, sandbox; restart to apply arena"
+        );
+        assert_eq!(sim.wells(), [Well::new(0., 0., 5.)]);
+        assert_eq!(sim.climate(), Climate::HotCenter);
+        assert_eq!(sim.tuning().speed, 3.);
+        // Changed by hand, and not in the file, so it stays.
+        assert_eq!(sim.tuning().eat_damage, 5.);
+        assert_eq!(sim.sandbox().max_operations, 10);
+        assert!(Changes::apply(&new, &new, &mut sim).is_empty());
+    }
+
+    #[test]
+    fn test_watcher() {
+        let dir = std::env::temp_dir().join(format!("microswarm-reload-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("world.toml");
+        fs::write(&path, "[tuning]\nspeed = 2.0").unwrap();
+        let (mut sim, _) = Config::load(&path).unwrap().build().unwrap();
+        let mut watcher = ConfigWatcher::new(&path).unwrap().interval(Duration::ZERO);
+        assert_eq!(watcher.poll(&mut sim).unwrap(), None);
+
+        // Saves a second apart, however coarse the file system's times are.
+        let save = |text: &str, seconds| {
+            fs::write(&path, text).unwrap();
+            let file = fs::File::options().write(true).open(&path).unwrap();
+            let at = SystemTime::now() + Duration::from_secs(seconds);
+            file.set_modified(at).unwrap();
+        };
+        save("[tuning]\nspeed = oops", 1);
+        assert!(watcher.poll(&mut sim).is_err());
+        save("[tuning]\nspeed = 4.0", 2);
+        let changes = watcher.poll(&mut sim).unwrap().unwrap();
+        assert_eq!(changes.applied, ["tuning.speed"]);
+        assert_eq!(sim.tuning().speed, 4.);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,277 @@
+//! Brains that run in another process, such as reinforcement learning agents
+//! written in any language.
+//!
+//! A [`RemoteBrain`] connects to an agent over TCP and speaks JSON Lines.
+//! Every tick it sends one request covering all the living microbes of its
+//! species, with what each one senses:
+//!
+//! ```json
+//! {"seq": 12, "microbes": [
+//!   {"id": "6f0c…", "x": 10.5, "y": -3.0, "rotation": 1.57, "energy": 98.2,
+//!    "stamina": 60.0, "close": [0, 1, 0, 0], "far": [2, 1, 0, 0], "wall": 42.0}
+//! ]}
+//! ```
+//!
+//! `close` and `far` count other lineages in front, left, right and back, as
+//! the `sense_*` script functions do, and `wall` is `sense_wall()`. The
+//! agent answers with the request's `seq` and controls for each microbe, in
+//! the same order. Missing flags are false:
+//!
+//! ```json
+//! {"seq": 12, "controls": [{"forward": true, "eat": true}]}
+//! ```
+//!
+//! If no answer arrives within the timeout, the species' microbes sit still
+//! for the tick and the late answer is skipped when it turns up. An agent
+//! that isn't listening, hangs up or sends something unreadable is treated
+//! the same way, and the brain reconnects on a later tick.
+
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::brain::Brain;
+use crate::{Controls, MicrobeState, Senses};
+
+/// How long to wait before trying an agent that couldn't be reached again.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Serialize)]
+struct Request<'a> {
+    seq: u64,
+    microbes: Vec<Sensed<'a>>,
+}
+
+#[derive(Serialize)]
+struct Sensed<'a> {
+    id: Uuid,
+    x: f32,
+    y: f32,
+    rotation: f32,
+    energy: f32,
+    stamina: f32,
+    close: &'a [i64; 4],
+    far: &'a [i64; 4],
+    wall: f32,
+}
+
+#[derive(Deserialize)]
+struct Reply {
+    seq: u64,
+    #[serde(default)]
+    controls: Vec<Controls>,
+}
+
+struct Connection {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+    /// A reply read only partly before a timeout.
+    partial: Vec<u8>,
+}
+
+/// A [`Brain`] answered by an agent listening at an address. See the
+/// [module docs](self) for the protocol.
+pub struct RemoteBrain {
+    addr: String,
+    timeout: Duration,
+    connection: Option<Connection>,
+    last_attempt: Option<Instant>,
+    seq: u64,
+}
+
+impl RemoteBrain {
+    /// A brain that asks the agent at `addr`, such as `127.0.0.1:7000`, and
+    /// waits at most `timeout` for each tick's answer. It connects when it's
+    /// first asked.
+    pub fn new(addr: impl Into<String>, timeout: Duration) -> Self {
+        Self {
+            addr: addr.into(),
+            timeout,
+            connection: None,
+            last_attempt: None,
+            seq: 0,
+        }
+    }
+
+    fn connect(&mut self) -> Option<&mut Connection> {
+        if self.connection.is_none() {
+            if self
+                .last_attempt
+                .is_some_and(|at| at.elapsed() < RECONNECT_DELAY)
+            {
+                return None;
+            }
+            self.last_attempt = Some(Instant::now());
+            self.connection = match self.open() {
+                Ok(connection) => {
+                    tracing::info!(addr = %self.addr, "connected to the agent");
+                    Some(connection)
+                }
+                Err(error) => {
+                    tracing::warn!(addr = %self.addr, %error, "couldn't reach the agent");
+                    None
+                }
+            };
+        }
+        self.connection.as_mut()
+    }
+
+    fn open(&self) -> io::Result<Connection> {
+        let addr = self
+            .addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "no address"))?;
+        let stream = TcpStream::connect_timeout(&addr, self.timeout.max(Duration::from_millis(1)))?;
+        stream.set_nodelay(true)?;
+        stream.set_write_timeout(Some(self.timeout.max(Duration::from_millis(1))))?;
+        Ok(Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            stream,
+            partial: Vec::new(),
+        })
+    }
+
+    /// Sends a request and waits for its answer. `Ok(None)` means it timed
+    /// out.
+    fn ask(&mut self, request: &Request) -> io::Result<Option<Vec<Controls>>> {
+        let deadline = Instant::now() + self.timeout;
+        let Some(connection) = self.connect() else {
+            return Ok(None);
+        };
+        let mut line = serde_json::to_vec(request)?;
+        line.push(b'\n');
+        connection.stream.write_all(&line)?;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            connection.stream.set_read_timeout(Some(remaining))?;
+            match connection.reader.read_until(b'\n', &mut connection.partial) {
+                Ok(_) if connection.partial.last() == Some(&b'\n') => {
+                    let reply = serde_json::from_slice::<Reply>(&connection.partial);
+                    connection.partial.clear();
+                    let reply = reply?;
+                    // Anything else is the late answer to an earlier tick.
+                    if reply.seq == request.seq {
+                        return Ok(Some(reply.controls));
+                    }
+                }
+                Ok(_) => return Err(ErrorKind::UnexpectedEof.into()),
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    return Ok(None);
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Brain for RemoteBrain {
+    fn think(&mut self, microbe: &MicrobeState, senses: &Senses) -> Controls {
+        self.think_all(&[(*microbe, senses)])
+            .pop()
+            .unwrap_or_default()
+    }
+
+    fn think_all(&mut self, microbes: &[(MicrobeState, &Senses)]) -> Vec<Controls> {
+        self.seq += 1;
+        let request = Request {
+            seq: self.seq,
+            microbes: microbes
+                .iter()
+                .map(|(microbe, senses)| Sensed {
+                    id: microbe.id,
+                    x: microbe.x,
+                    y: microbe.y,
+                    rotation: microbe.rotation,
+                    energy: microbe.energy,
+                    stamina: microbe.stamina,
+                    close: &senses.close,
+                    far: &senses.far,
+                    wall: senses.wall,
+                })
+                .collect(),
+        };
+        match self.ask(&request) {
+            Ok(Some(controls)) => controls,
+            Ok(None) => {
+                tracing::debug!(addr = %self.addr, seq = self.seq, "agent missed the tick");
+                Vec::new()
+            }
+            Err(error) => {
+                tracing::warn!(addr = %self.addr, %error, "lost the agent");
+                self.connection = None;
+                Vec::new()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color32, Simulation};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// An agent that answers each request with `answer`, after `delay`.
+    fn agent(delay: Duration, answer: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            for line in BufReader::new(stream).lines() {
+                let request: serde_json::Value = serde_json::from_str(&line.unwrap()).unwrap();
+                let count = request["microbes"].as_array().unwrap().len();
+                let controls = vec![answer; count].join(",");
+                thread::sleep(delay);
+                let reply = format!("{{\"seq\":{},\"controls\":[{controls}]}}\n", request["seq"]);
+                if writer.write_all(reply.as_bytes()).is_err() {
+                    return;
+                }
+            }
+        });
+        addr
+    }
+
+    fn x_after_steps(addr: String, steps: usize) -> Vec<f32> {
+        let mut sim = Simulation::new(100.).unwrap();
+        let remote = sim.add_brain(RemoteBrain::new(addr, Duration::from_millis(200)));
+        sim.spawn(remote, 0., -20., 0., Color32::RED);
+        sim.spawn(remote, 0., 20., 0., Color32::RED);
+        (0..steps)
+            .map(|_| {
+                sim.step().unwrap();
+                sim.snapshot().microbes[0].x
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_agent_drives_microbes() {
+        let addr = agent(Duration::ZERO, r#"{"forward":true}"#);
+        let xs = x_after_steps(addr, 3);
+        assert!(xs[0] > 0. && xs[1] > xs[0] && xs[2] > xs[1]);
+    }
+
+    #[test]
+    fn test_slow_agent_counts_as_idle() {
+        // Each answer misses its tick and is skipped by the next one.
+        let addr = agent(Duration::from_millis(300), r#"{"forward":true}"#);
+        assert_eq!(x_after_steps(addr, 2), [0., 0.]);
+    }
+
+    #[test]
+    fn test_missing_agent_counts_as_idle() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        assert_eq!(x_after_steps(addr, 2), [0., 0.]);
+    }
+}
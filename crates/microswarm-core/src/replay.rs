@@ -0,0 +1,550 @@
+//! Compact recordings of a run that can be played back without the scripts.
+//!
+//! A recording is a gzipped stream of frames, one per recorded tick. Each
+//! frame lists the microbes born and died since the previous frame, then the
+//! change in position, rotation and energy of every living microbe, in a
+//! fixed order both sides agree on. Positions are kept to 1/16 of a unit and
+//! energy to 1/10, which is below what the viewer can show.
+//!
+//! A recording made with [`Recorder::for_world`] also starts with the whole
+//! world it began from, scripts included, so [`crate::verify`] can play the
+//! run again and check it turns out the same.
+
+use ecolor::Color32;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::{BTreeMap, HashMap};
+use std::f32::consts::TAU;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::ops::Range;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::simulation::{MicrobeState, Snapshot};
+use crate::world::World;
+
+const MAGIC: &[u8; 4] = b"MSWR";
+const VERSION: u8 = 2;
+const POSITION_SCALE: f32 = 16.;
+const ENERGY_SCALE: f32 = 10.;
+/// Frames between the decoder states kept for seeking.
+const KEYFRAME_INTERVAL: usize = 64;
+
+#[derive(Debug, Clone)]
+struct Track {
+    id: Uuid,
+    lineage: Uuid,
+    species: usize,
+    color: [u8; 4],
+    born: u64,
+    x: i32,
+    y: i32,
+    rotation: u8,
+    energy: i32,
+}
+
+/// What both the writer and the reader know after each frame.
+#[derive(Debug, Clone, Default)]
+struct Tracks {
+    species: Vec<Uuid>,
+    alive: BTreeMap<u32, Track>,
+    next_handle: u32,
+}
+
+impl Tracks {
+    fn snapshot(&self, tick: u64) -> Snapshot {
+        Snapshot {
+            tick,
+            species: self.species.clone(),
+            microbes: self
+                .alive
+                .values()
+                .map(|track| {
+                    let [r, g, b, a] = track.color;
+                    MicrobeState {
+                        id: track.id,
+                        lineage: track.lineage,
+                        species: self.species[track.species],
+                        x: track.x as f32 / POSITION_SCALE,
+                        y: track.y as f32 / POSITION_SCALE,
+                        rotation: track.rotation as f32 / 256. * TAU,
+                        energy: track.energy as f32 / ENERGY_SCALE,
+                        // Not recorded.
+                        stamina: 0.,
+                        spiky: false,
+                        jaws: Default::default(),
+                        eyes: Default::default(),
+                        cloaked: false,
+                        fat: 0.,
+                        cooldown: 0,
+                        caste: None,
+                        color: Color32::from_rgba_premultiplied(r, g, b, a),
+                        born: track.born,
+                    }
+                })
+                .collect(),
+            ..Snapshot::default()
+        }
+    }
+}
+
+/// Writes a recording, one [`Snapshot`] at a time.
+pub struct Recorder<W: Write> {
+    out: GzEncoder<W>,
+    tracks: Tracks,
+    handles: HashMap<Uuid, u32>,
+    frames: usize,
+}
+
+impl Recorder<BufWriter<File>> {
+    pub fn create(path: impl AsRef<Path>, arena: f32) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), arena)
+    }
+
+    /// Like [`Recorder::for_world`], writing to a file at `path`.
+    pub fn create_for_world(path: impl AsRef<Path>, world: &World) -> io::Result<Self> {
+        Self::for_world(BufWriter::new(File::create(path)?), world)
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(writer: W, arena: f32) -> io::Result<Self> {
+        Self::with_header(writer, arena, &[])
+    }
+
+    /// Starts a recording that keeps `world` as it is now, so the run can be
+    /// verified later. Record its current snapshot first.
+    pub fn for_world(writer: W, world: &World) -> io::Result<Self> {
+        Self::with_header(writer, world.arena(), &serde_json::to_vec(world)?)
+    }
+
+    fn with_header(writer: W, arena: f32, world: &[u8]) -> io::Result<Self> {
+        let mut out = GzEncoder::new(writer, Compression::default());
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION])?;
+        out.write_all(&arena.to_le_bytes())?;
+        out.write_all(&(world.len() as u32).to_le_bytes())?;
+        out.write_all(world)?;
+        Ok(Self {
+            out,
+            tracks: Tracks::default(),
+            handles: HashMap::new(),
+            frames: 0,
+        })
+    }
+
+    pub fn record(&mut self, snapshot: &Snapshot) -> io::Result<()> {
+        let mut frame = Vec::new();
+        write_varint(&mut frame, snapshot.tick);
+
+        let mut new_species = Vec::new();
+        for id in snapshot
+            .species
+            .iter()
+            .chain(snapshot.microbes.iter().map(|m| &m.species))
+        {
+            if !self.tracks.species.contains(id) && !new_species.contains(id) {
+                new_species.push(*id);
+            }
+        }
+        write_varint(&mut frame, new_species.len() as u64);
+        for id in &new_species {
+            frame.extend_from_slice(id.as_bytes());
+        }
+        self.tracks.species.append(&mut new_species);
+        let species_index = self
+            .tracks
+            .species
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (*id, i))
+            .collect::<HashMap<_, _>>();
+
+        let births = snapshot
+            .microbes
+            .iter()
+            .filter(|m| !self.handles.contains_key(&m.id))
+            .collect::<Vec<_>>();
+        write_varint(&mut frame, births.len() as u64);
+        for microbe in births {
+            let track = Track {
+                id: microbe.id,
+                lineage: microbe.lineage,
+                species: species_index[&microbe.species],
+                color: microbe.color.to_array(),
+                born: microbe.born,
+                x: 0,
+                y: 0,
+                rotation: 0,
+                energy: 0,
+            };
+            frame.extend_from_slice(track.id.as_bytes());
+            frame.extend_from_slice(track.lineage.as_bytes());
+            write_varint(&mut frame, track.species as u64);
+            frame.extend_from_slice(&track.color);
+            write_varint(&mut frame, track.born);
+            let handle = self.tracks.next_handle;
+            self.tracks.next_handle += 1;
+            self.handles.insert(track.id, handle);
+            self.tracks.alive.insert(handle, track);
+        }
+
+        let current = snapshot
+            .microbes
+            .iter()
+            .map(|m| (m.id, m))
+            .collect::<HashMap<_, _>>();
+        let deaths = self
+            .tracks
+            .alive
+            .iter()
+            .filter(|(_, track)| !current.contains_key(&track.id))
+            .map(|(handle, _)| *handle)
+            .collect::<Vec<_>>();
+        write_varint(&mut frame, deaths.len() as u64);
+        let mut previous = 0;
+        for handle in deaths {
+            write_varint(&mut frame, (handle - previous) as u64);
+            previous = handle;
+            let track = self.tracks.alive.remove(&handle).expect("dead track");
+            self.handles.remove(&track.id);
+        }
+
+        for track in self.tracks.alive.values_mut() {
+            let microbe = current[&track.id];
+            let [x, y, rotation, energy] = quantize(microbe);
+            let rotation = rotation as u8;
+            write_signed(&mut frame, x - track.x);
+            write_signed(&mut frame, y - track.y);
+            frame.push(rotation);
+            write_signed(&mut frame, energy - track.energy);
+            (track.x, track.y, track.rotation, track.energy) = (x, y, rotation, energy);
+        }
+
+        self.out.write_all(&frame)?;
+        self.frames += 1;
+        if self.frames.is_multiple_of(KEYFRAME_INTERVAL) {
+            // Ends the deflate block, so a recording that is cut off later
+            // still decodes up to here.
+            self.out.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Flushes the compressed stream and returns the underlying writer.
+    pub fn finish(self) -> io::Result<W> {
+        self.out.finish()
+    }
+}
+
+/// A microbe's position, rotation and energy as a recording keeps them.
+pub(crate) fn quantize(microbe: &MicrobeState) -> [i32; 4] {
+    [
+        (microbe.x * POSITION_SCALE).round() as i32,
+        (microbe.y * POSITION_SCALE).round() as i32,
+        (microbe.rotation.rem_euclid(TAU) / TAU * 256.).round() as i32 % 256,
+        (microbe.energy * ENERGY_SCALE).round() as i32,
+    ]
+}
+
+/// A recording loaded for playback, with random access to its frames.
+pub struct Replay {
+    arena: f32,
+    /// Where the world the run started from is in `data`, if it was kept.
+    world: Option<Range<usize>>,
+    data: Vec<u8>,
+    /// Start of each frame in `data`.
+    offsets: Vec<usize>,
+    ticks: Vec<u64>,
+    /// State before every `KEYFRAME_INTERVAL`th frame.
+    keyframes: Vec<Tracks>,
+    /// The last decoded frame and the state after it.
+    cursor: Option<(usize, Tracks)>,
+}
+
+impl Replay {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read(File::open(path)?)
+    }
+
+    /// Loads a recording. A recording that was cut off, for example because
+    /// the recorder crashed, plays up to its last complete frame.
+    pub fn read(reader: impl Read) -> io::Result<Self> {
+        let mut data = Vec::new();
+        if let Err(err) = GzDecoder::new(reader).read_to_end(&mut data) {
+            // Whatever was decoded before the damage is still usable.
+            if data.is_empty() {
+                return Err(err);
+            }
+        }
+        if data.len() < 9 || &data[..4] != MAGIC {
+            return Err(invalid("not a microswarm recording"));
+        }
+        let arena = f32::from_le_bytes(data[5..9].try_into().unwrap());
+        let (world, mut position) = match data[4] {
+            // Recordings from before the world was kept.
+            1 => (None, 9),
+            VERSION => {
+                let mut header = &data[9..];
+                let length = u32::from_le_bytes(read_bytes::<4>(&mut header)?) as usize;
+                if header.len() < length {
+                    return Err(invalid("recording cut off in its header"));
+                }
+                let world = (length > 0).then_some(13..13 + length);
+                (world, 13 + length)
+            }
+            _ => return Err(invalid("unsupported recording version")),
+        };
+
+        let mut replay = Self {
+            arena,
+            world,
+            data,
+            offsets: Vec::new(),
+            ticks: Vec::new(),
+            keyframes: Vec::new(),
+            cursor: None,
+        };
+        let mut tracks = Tracks::default();
+        while position < replay.data.len() {
+            if replay.offsets.len().is_multiple_of(KEYFRAME_INTERVAL) {
+                replay.keyframes.push(tracks.clone());
+            }
+            let mut reader = &replay.data[position..];
+            let Ok(tick) = decode_frame(&mut reader, &mut tracks) else {
+                break;
+            };
+            replay.offsets.push(position);
+            replay.ticks.push(tick);
+            position = replay.data.len() - reader.len();
+        }
+        Ok(replay)
+    }
+
+    pub fn arena(&self) -> f32 {
+        self.arena
+    }
+
+    /// The world the run started from, if the recording kept it.
+    pub fn world(&self) -> io::Result<Option<World>> {
+        match &self.world {
+            Some(range) => World::read(&self.data[range.clone()]).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Number of recorded frames.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// The tick each frame was recorded on.
+    pub fn ticks(&self) -> &[u64] {
+        &self.ticks
+    }
+
+    /// Decodes frame `index`. Stepping forward one frame at a time is cheap;
+    /// jumping around replays from the nearest keyframe.
+    pub fn frame(&mut self, index: usize) -> Option<Snapshot> {
+        if index >= self.len() {
+            return None;
+        }
+        let (mut next, mut tracks) = match self.cursor.take() {
+            Some((current, tracks)) if current < index && index - current <= KEYFRAME_INTERVAL => {
+                (current + 1, tracks)
+            }
+            _ => {
+                let keyframe = index / KEYFRAME_INTERVAL;
+                (
+                    keyframe * KEYFRAME_INTERVAL,
+                    self.keyframes[keyframe].clone(),
+                )
+            }
+        };
+        let mut tick = 0;
+        while next <= index {
+            let mut reader = &self.data[self.offsets[next]..];
+            tick = decode_frame(&mut reader, &mut tracks).ok()?;
+            next += 1;
+        }
+        let snapshot = tracks.snapshot(tick);
+        self.cursor = Some((index, tracks));
+        Some(snapshot)
+    }
+}
+
+fn decode_frame(data: &mut &[u8], tracks: &mut Tracks) -> io::Result<u64> {
+    let tick = read_varint(data)?;
+    for _ in 0..read_varint(data)? {
+        tracks.species.push(read_uuid(data)?);
+    }
+    for _ in 0..read_varint(data)? {
+        let id = read_uuid(data)?;
+        let lineage = read_uuid(data)?;
+        let species = read_varint(data)? as usize;
+        if species >= tracks.species.len() {
+            return Err(invalid("unknown species"));
+        }
+        let color = read_bytes::<4>(data)?;
+        let born = read_varint(data)?;
+        tracks.alive.insert(
+            tracks.next_handle,
+            Track {
+                id,
+                lineage,
+                species,
+                color,
+                born,
+                x: 0,
+                y: 0,
+                rotation: 0,
+                energy: 0,
+            },
+        );
+        tracks.next_handle += 1;
+    }
+    let mut handle = 0;
+    for _ in 0..read_varint(data)? {
+        handle += read_varint(data)? as u32;
+        tracks.alive.remove(&handle);
+    }
+    for track in tracks.alive.values_mut() {
+        track.x += read_signed(data)?;
+        track.y += read_signed(data)?;
+        track.rotation = read_bytes::<1>(data)?[0];
+        track.energy += read_signed(data)?;
+    }
+    Ok(tick)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_signed(out: &mut Vec<u8>, value: i32) {
+    write_varint(out, ((value << 1) ^ (value >> 31)) as u32 as u64);
+}
+
+fn read_varint(data: &mut &[u8]) -> io::Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let [byte] = read_bytes::<1>(data)?;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid("varint too long"))
+}
+
+fn read_signed(data: &mut &[u8]) -> io::Result<i32> {
+    let value = read_varint(data)? as u32;
+    Ok((value >> 1) as i32 ^ -((value & 1) as i32))
+}
+
+fn read_bytes<const N: usize>(data: &mut &[u8]) -> io::Result<[u8; N]> {
+    if data.len() < N {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let (bytes, rest) = data.split_at(N);
+    *data = rest;
+    Ok(bytes.try_into().unwrap())
+}
+
+fn read_uuid(data: &mut &[u8]) -> io::Result<Uuid> {
+    read_bytes::<16>(data).map(Uuid::from_bytes)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{scripts, Simulation};
+
+    fn record(sim: &mut Simulation, ticks: usize) -> (Vec<u8>, Vec<Snapshot>) {
+        let mut recorder = Recorder::new(Vec::new(), sim.arena()).unwrap();
+        let mut snapshots = Vec::new();
+        for _ in 0..ticks {
+            sim.step().unwrap();
+            let snapshot = sim.snapshot();
+            recorder.record(&snapshot).unwrap();
+            snapshots.push(snapshot);
+        }
+        (recorder.finish().unwrap(), snapshots)
+    }
+
+    #[test]
+    fn test_varints() {
+        let mut out = Vec::new();
+        for value in [0, -1, 1, 63, -64, 300, i32::MAX, i32::MIN] {
+            write_signed(&mut out, value);
+        }
+        let mut data = out.as_slice();
+        for value in [0, -1, 1, 63, -64, 300, i32::MAX, i32::MIN] {
+            assert_eq!(read_signed(&mut data).unwrap(), value);
+        }
+        assert!(data.is_empty());
+    }
+
+    #[test]
+    fn test_playback_matches_the_run() {
+        let mut sim = Simulation::with_seed(80., 5).unwrap();
+        let hunters = sim.add_species(scripts::aggressive_hunter_script());
+        let vampires = sim.add_species(scripts::vampire_microbe_script());
+        for i in 0..60 {
+            let species = if i % 3 == 0 { hunters } else { vampires };
+            let offset = i as f32 * 2.5 - 75.;
+            sim.spawn(species, offset, offset * 0.5, i as f32, Color32::GOLD);
+        }
+        let (data, snapshots) = record(&mut sim, 150);
+
+        let mut replay = Replay::read(data.as_slice()).unwrap();
+        assert_eq!(replay.len(), snapshots.len());
+        assert_eq!(replay.arena(), 80.);
+        // Forwards, then jumping backwards across keyframes.
+        for index in (0..snapshots.len()).chain([3, 140, 70, 0]) {
+            let frame = replay.frame(index).unwrap();
+            let expected = &snapshots[index];
+            assert_eq!(frame.tick, expected.tick);
+            assert_eq!(frame.species, expected.species);
+            assert_eq!(frame.microbes.len(), expected.microbes.len());
+            for microbe in &frame.microbes {
+                let original = expected
+                    .microbes
+                    .iter()
+                    .find(|m| m.id == microbe.id)
+                    .unwrap();
+                assert!((microbe.x - original.x).abs() <= 0.51 / POSITION_SCALE);
+                assert!((microbe.energy - original.energy).abs() <= 0.51 / ENERGY_SCALE);
+                assert_eq!(microbe.lineage, original.lineage);
+                assert_eq!(microbe.color, original.color);
+            }
+        }
+        assert!(replay.frame(snapshots.len()).is_none());
+    }
+
+    #[test]
+    fn test_truncated_recording_plays_complete_frames() {
+        let mut sim = Simulation::with_seed(50., 1).unwrap();
+        let species = sim.add_species(scripts::random_script());
+        sim.spawn(species, 0., 0., 0., Color32::RED);
+        let (data, _) = record(&mut sim, 200);
+
+        let replay = Replay::read(&data[..data.len() - 12]).unwrap();
+        assert!((KEYFRAME_INTERVAL * 3..200).contains(&replay.len()));
+        assert!(Replay::read(&b"nonsense"[..]).is_err());
+    }
+}
@@ -0,0 +1,351 @@
+//! Matches with a winner.
+//!
+//! A world left to itself runs forever. Given [`Rules`], it referees a match
+//! between its species instead, checking after every update whether one of
+//! them has won:
+//!
+//! - [`Condition::LastStanding`]: the last species with living microbes,
+//! - [`Condition::Biomass`]: the species with the most energy after a number
+//!   of ticks,
+//! - [`Condition::Hill`]: the first species to hold a zone for long enough.
+//!   A species holds it on ticks where it has more microbes inside than any
+//!   other.
+//!
+//! A time limit ends matches that drag on, in favour of whoever leads. When
+//! every species dies out the match is a draw, as is a tie for the lead.
+//! The outcome is kept as a [`MatchResult`] on the world, which carries on
+//! updating afterwards; runs stop by checking [`World::result`].
+//!
+//! ```
+//! use microswarm::rules::{Condition, Reason, Rules};
+//! use microswarm::{scripts, Color32, WorldBuilder};
+//!
+//! let mut world = WorldBuilder::new()
+//!     .arena(40.)
+//!     .seed(1)
+//!     .rules(Rules::new(Condition::Biomass { ticks: 20 }))
+//!     .species(scripts::aggressive_hunter_script(), 10, Color32::RED)
+//!     .species("new_controls()", 10, Color32::GREEN)
+//!     .build()
+//!     .unwrap();
+//! while world.result().is_none() {
+//!     world.update(microswarm::DELTA_TIME).unwrap();
+//! }
+//! let result = world.result().unwrap();
+//! assert_eq!((result.tick, result.reason), (20, Reason::Biomass));
+//! assert_eq!(result.winner, Some(result.placings[0].species));
+//! ```
+//!
+//! Rules and the referee's tallies are saved with the world. Going back to
+//! an earlier tick with [`World::restore`] forgets a result reached after
+//! it, but not the time spent holding the hill since.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::world::World;
+
+/// How a match is won.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "condition", rename_all = "snake_case")]
+pub enum Condition {
+    /// Ends when at most one species has living microbes.
+    LastStanding,
+    /// Ends after `ticks`, won by the species with the most biomass.
+    Biomass { ticks: u64 },
+    /// Won by the first species to hold the circle at (`x`, `y`) for `hold`
+    /// ticks in total.
+    Hill {
+        x: f32,
+        y: f32,
+        radius: f32,
+        hold: u64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rules {
+    pub condition: Condition,
+    /// Ends the match after this many ticks if nobody has won yet.
+    #[serde(default)]
+    pub time_limit: Option<u64>,
+}
+
+impl Rules {
+    pub fn new(condition: Condition) -> Self {
+        Self {
+            condition,
+            time_limit: None,
+        }
+    }
+
+    pub fn time_limit(mut self, ticks: u64) -> Self {
+        self.time_limit = Some(ticks);
+        self
+    }
+}
+
+/// Why a match ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    /// One species outlived the rest.
+    LastStanding,
+    /// Every species died out.
+    Extinction,
+    /// The biomass condition's ticks were up.
+    Biomass,
+    /// A species held the hill long enough.
+    Hill,
+    /// The time limit was reached.
+    TimeLimit,
+}
+
+/// One species' standing when a match ended.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Placing {
+    pub species: Uuid,
+    pub population: usize,
+    pub biomass: f32,
+    /// Ticks it held the hill, 0 without one.
+    pub hill: u64,
+}
+
+/// How a match ended.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchResult {
+    pub tick: u64,
+    /// `None` for a draw.
+    pub winner: Option<Uuid>,
+    pub reason: Reason,
+    /// Every species, from first to last.
+    pub placings: Vec<Placing>,
+}
+
+/// Keeps the tallies for a world's rules and decides when it's over.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Referee {
+    pub rules: Rules,
+    /// Ticks each species has held the hill.
+    hill: BTreeMap<Uuid, u64>,
+    pub result: Option<MatchResult>,
+}
+
+impl Referee {
+    pub fn new(rules: Rules) -> Self {
+        Self {
+            rules,
+            hill: BTreeMap::new(),
+            result: None,
+        }
+    }
+
+    /// Checks `world` after an update, unless the match is already over.
+    pub fn judge(&mut self, world: &World) {
+        if self.result.is_some() {
+            return;
+        }
+        let tick = world.tick();
+        if let Condition::Hill { x, y, radius, .. } = self.rules.condition {
+            let mut inside = BTreeMap::<Uuid, usize>::new();
+            for microbe in world.microbes() {
+                let position = microbe.position();
+                let (dx, dy) = (position.x - x, position.y - y);
+                if dx * dx + dy * dy <= radius * radius {
+                    *inside.entry(microbe.script_id()).or_default() += 1;
+                }
+            }
+            if let Some(holder) = leader(inside.into_iter().map(|(id, n)| (id, n as f32))) {
+                *self.hill.entry(holder).or_default() += 1;
+            }
+        }
+
+        let placings = self.placings(world);
+        let alive = placings.iter().filter(|p| p.population > 0).count();
+        let reason = match self.rules.condition {
+            _ if alive == 0 => Some(Reason::Extinction),
+            Condition::LastStanding if alive == 1 => Some(Reason::LastStanding),
+            Condition::Biomass { ticks } if tick >= ticks => Some(Reason::Biomass),
+            Condition::Hill { hold, .. } if self.hill.values().any(|held| *held >= hold) => {
+                Some(Reason::Hill)
+            }
+            _ if self.rules.time_limit.is_some_and(|limit| tick >= limit) => {
+                Some(Reason::TimeLimit)
+            }
+            _ => None,
+        };
+        let Some(reason) = reason else {
+            return;
+        };
+        let winner = match reason {
+            Reason::Extinction => None,
+            Reason::LastStanding => placings.iter().find(|p| p.population > 0),
+            Reason::Hill => placings.first(),
+            // Placings are in order, so a tie with the runner-up is a draw.
+            _ => match placings.as_slice() {
+                [first, second, ..] if self.key(first) == self.key(second) => None,
+                [first, ..] => Some(first),
+                [] => None,
+            },
+        }
+        .map(|p| p.species);
+        tracing::info!(tick, ?winner, ?reason, "match ended");
+        self.result = Some(MatchResult {
+            tick,
+            winner,
+            reason,
+            placings,
+        });
+    }
+
+    /// Every species in `world`, best first.
+    fn placings(&self, world: &World) -> Vec<Placing> {
+        let stats = world.stats().latest().cloned().unwrap_or_default();
+        let mut placings = stats
+            .species
+            .iter()
+            .map(|(species, stats)| Placing {
+                species: *species,
+                population: stats.population,
+                biomass: stats.biomass,
+                hill: self.hill.get(species).copied().unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+        placings.sort_by(|a, b| {
+            let (a, b) = (self.key(a), self.key(b));
+            b.0.cmp(&a.0).then(b.1.total_cmp(&a.1))
+        });
+        placings
+    }
+
+    /// What placings are ranked on: time on the hill if there is one, then
+    /// biomass.
+    fn key(&self, placing: &Placing) -> (u64, f32) {
+        (placing.hill, placing.biomass)
+    }
+}
+
+/// The one species strictly ahead of the others, if any.
+fn leader(scores: impl IntoIterator<Item = (Uuid, f32)>) -> Option<Uuid> {
+    let mut best: Option<(Uuid, f32)> = None;
+    let mut tied = false;
+    for (species, score) in scores {
+        match best {
+            Some((_, top)) if score < top => {}
+            Some((_, top)) if score == top => tied = true,
+            _ => {
+                best = Some((species, score));
+                tied = false;
+            }
+        }
+    }
+    best.filter(|_| !tied).map(|(species, _)| species)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{scripts, Color32, Simulation};
+
+    fn play(rules: Rules, setup: impl FnOnce(&mut Simulation)) -> MatchResult {
+        let mut sim = Simulation::with_seed(40., 4).unwrap();
+        sim.set_rules(Some(rules));
+        setup(&mut sim);
+        for _ in 0..2000 {
+            sim.step().unwrap();
+            if let Some(result) = sim.result() {
+                return result.clone();
+            }
+        }
+        panic!("the match never ended");
+    }
+
+    #[test]
+    fn test_last_standing() {
+        let mut ids = (Uuid::nil(), Uuid::nil());
+        let result = play(Rules::new(Condition::LastStanding), |sim| {
+            let hunters = sim.add_species(scripts::aggressive_hunter_script());
+            let idle = sim.add_species("new_controls()");
+            sim.tuning_mut().action_energy_consumption = 2.;
+            for i in 0..10 {
+                sim.spawn(hunters, i as f32 * 4. - 20., 0., 0., Color32::RED);
+                sim.spawn(idle, i as f32 * 4. - 20., 3., 0., Color32::BLUE);
+            }
+            ids = (hunters, idle);
+        });
+        assert_eq!(result.reason, Reason::LastStanding);
+        assert_eq!(result.winner, Some(ids.0));
+        assert_eq!(result.placings[0].species, ids.0);
+        assert_eq!(result.placings[1].population, 0);
+    }
+
+    #[test]
+    fn test_hill() {
+        let mut ids = (Uuid::nil(), Uuid::nil());
+        let hill = Condition::Hill {
+            x: 10.,
+            y: 10.,
+            radius: 5.,
+            hold: 30,
+        };
+        let result = play(Rules::new(hill), |sim| {
+            let inside = sim.add_species("new_controls()");
+            let outside = sim.add_species("new_controls()");
+            sim.spawn(inside, 10., 10., 0., Color32::RED);
+            sim.spawn(outside, -10., -10., 0., Color32::BLUE);
+            sim.spawn(outside, -12., -10., 0., Color32::BLUE);
+            ids = (inside, outside);
+        });
+        assert_eq!((result.tick, result.reason), (30, Reason::Hill));
+        assert_eq!(result.winner, Some(ids.0));
+        assert_eq!(result.placings[0].hill, 30);
+        assert_eq!(result.placings[1].hill, 0);
+    }
+
+    #[test]
+    fn test_draws() {
+        let both = |sim: &mut Simulation| {
+            for x in [-10., 10.] {
+                let idle = sim.add_species("new_controls()");
+                sim.spawn(idle, x, 0., 0., Color32::RED);
+            }
+        };
+        // Nobody can reach the hill, so the time limit decides a tie.
+        let hill = Condition::Hill {
+            x: 30.,
+            y: 30.,
+            radius: 1.,
+            hold: 100,
+        };
+        let result = play(Rules::new(hill).time_limit(50), both);
+        assert_eq!((result.tick, result.reason), (50, Reason::TimeLimit));
+        assert_eq!(result.winner, None);
+
+        let result = play(Rules::new(Condition::Biomass { ticks: 10 }), both);
+        assert_eq!(
+            (result.tick, result.reason, result.winner),
+            (10, Reason::Biomass, None)
+        );
+
+        let mut sim = Simulation::with_seed(40., 4).unwrap();
+        sim.set_rules(Some(Rules::new(Condition::LastStanding)));
+        sim.tuning_mut().action_energy_consumption = 1000.;
+        both(&mut sim);
+        sim.step().unwrap();
+        let result = sim.result().unwrap();
+        assert_eq!((result.reason, result.winner), (Reason::Extinction, None));
+    }
+
+    #[test]
+    fn test_leader() {
+        let (a, b) = (Uuid::from_u128(1), Uuid::from_u128(2));
+        assert_eq!(leader([(a, 1.), (b, 3.)]), Some(b));
+        assert_eq!(leader([(a, 3.), (b, 3.)]), None);
+        assert_eq!(
+            leader([(a, 3.), (b, 3.), (Uuid::nil(), 4.)]),
+            Some(Uuid::nil())
+        );
+        assert_eq!(leader([]), None);
+    }
+}
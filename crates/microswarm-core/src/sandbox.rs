@@ -0,0 +1,175 @@
+//! Limits on what species scripts can do.
+//!
+//! Scripts may come from anyone, such as entrants to a tournament, so the
+//! engine that runs them is locked down: scripts can't import modules or
+//! read files, `eval` and `print` are turned off, and every run of a script
+//! is capped by a [`Sandbox`]. A script that goes over a limit stops, and
+//! its microbe sits still for the tick.
+//!
+//! The limits are set in a config's `[sandbox]` table:
+//!
+//! ```toml
+//! [sandbox]
+//! max_operations = 100000   # per microbe per tick
+//! max_string_size = 4096    # bytes
+//! max_array_size = 1024
+//! max_map_size = 256
+//! max_call_depth = 32
+//! max_expr_depth = 64
+//! ```
+
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Engine, EvalAltResult, ParseErrorType};
+use serde::{Deserialize, Serialize};
+
+/// Resource caps for every script run. Zero means unlimited.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sandbox {
+    /// Operations a script may take to decide one microbe's controls.
+    pub max_operations: u64,
+    /// Bytes in any one string.
+    pub max_string_size: usize,
+    /// Elements in any one array.
+    pub max_array_size: usize,
+    /// Entries in any one object map.
+    pub max_map_size: usize,
+    /// Nested function calls.
+    pub max_call_depth: usize,
+    /// Nesting of expressions, at the top level and inside functions.
+    pub max_expr_depth: usize,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self {
+            max_operations: 100_000,
+            max_string_size: 4096,
+            max_array_size: 1024,
+            max_map_size: 256,
+            max_call_depth: 32,
+            max_expr_depth: 64,
+        }
+    }
+}
+
+impl Sandbox {
+    /// Sets `name` to `value`, or returns `false` if there is no such limit.
+    pub fn set(&mut self, name: &str, value: u64) -> bool {
+        let size = value as usize;
+        match name {
+            "max_operations" => self.max_operations = value,
+            "max_string_size" => self.max_string_size = size,
+            "max_array_size" => self.max_array_size = size,
+            "max_map_size" => self.max_map_size = size,
+            "max_call_depth" => self.max_call_depth = size,
+            "max_expr_depth" => self.max_expr_depth = size,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Applies the limits to `engine` and cuts it off from the host.
+    pub(crate) fn apply(&self, engine: &mut Engine) {
+        engine
+            .set_max_operations(self.max_operations)
+            .set_max_string_size(self.max_string_size)
+            .set_max_array_size(self.max_array_size)
+            .set_max_map_size(self.max_map_size)
+            .set_max_call_levels(match self.max_call_depth {
+                0 => usize::MAX,
+                depth => depth,
+            })
+            .set_max_expr_depths(self.max_expr_depth, self.max_expr_depth)
+            .set_max_modules(0)
+            .set_module_resolver(DummyModuleResolver::new())
+            .on_print(|_| {})
+            .on_debug(|_, _, _| {})
+            .disable_symbol("eval");
+    }
+}
+
+/// Whether `error` means a script went over a limit, rather than being
+/// broken.
+pub(crate) fn over_limit(error: &EvalAltResult) -> bool {
+    match error {
+        EvalAltResult::ErrorTooManyOperations(_)
+        | EvalAltResult::ErrorTooManyVariables(_)
+        | EvalAltResult::ErrorTooManyModules(_)
+        | EvalAltResult::ErrorStackOverflow(_)
+        | EvalAltResult::ErrorDataTooLarge(..) => true,
+        EvalAltResult::ErrorParsing(error, _) => matches!(
+            error,
+            ParseErrorType::ExprTooDeep | ParseErrorType::LiteralTooLarge(..)
+        ),
+        EvalAltResult::ErrorInFunctionCall(_, _, error, _)
+        | EvalAltResult::ErrorInModule(_, error, _) => over_limit(error),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color32, SimError, Simulation};
+
+    /// Whether a microbe running `script` moves in one step.
+    fn moves(script: &str) -> bool {
+        let mut sim = Simulation::new(100.).unwrap();
+        let species = sim.add_species(script);
+        sim.spawn(species, 0., 0., 0., Color32::RED);
+        sim.step().unwrap();
+        sim.snapshot().microbes[0].x > 0.
+    }
+
+    #[test]
+    fn test_scripts_within_limits_run() {
+        assert!(moves(
+            "let c = new_controls(); let a = [1, 2, 3]; c.forward = a.len() == 3; c"
+        ));
+    }
+
+    #[test]
+    fn test_scripts_over_limits_stop() {
+        let escape = [
+            // Never finishes.
+            "let c = new_controls(); c.forward = true; loop {} c",
+            // Runs the host out of memory.
+            r#"let c = new_controls(); c.forward = true; let s = "x"; loop { s += s; } c"#,
+            "let c = new_controls(); c.forward = true; let a = []; loop { a.push(0); } c",
+            "fn f(c) { f(c) } let c = new_controls(); c.forward = true; f(c)",
+            // Reaches outside the sandbox.
+            r#"import "secrets" as s; let c = new_controls(); c.forward = true; c"#,
+        ];
+        for script in escape {
+            assert!(!moves(script), "{script}");
+        }
+    }
+
+    #[test]
+    fn test_eval_is_disabled() {
+        let mut sim = Simulation::new(100.).unwrap();
+        let species = sim.add_species(r#"eval("new_controls()")"#);
+        sim.spawn(species, 0., 0., 0., Color32::RED);
+        assert!(matches!(sim.step(), Err(SimError::Script { .. })));
+    }
+
+    #[test]
+    fn test_limits_are_configurable() {
+        let mut sim = Simulation::new(100.).unwrap();
+        let species = sim.add_species(
+            "let c = new_controls(); let n = 0; for i in 0..100 { n += i; } c.forward = n > 0; c",
+        );
+        sim.spawn(species, 0., 0., 0., Color32::RED);
+        let mut sandbox = Sandbox::default();
+        assert!(sandbox.set("max_operations", 50));
+        assert!(!sandbox.set("max_memory", 50));
+        sim.set_sandbox(sandbox);
+        sim.step().unwrap();
+        assert_eq!(sim.snapshot().microbes[0].x, 0.);
+
+        sim.set_sandbox(Sandbox::default());
+        sim.step().unwrap();
+        assert!(sim.snapshot().microbes[0].x > 0.);
+    }
+}
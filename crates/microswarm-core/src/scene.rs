@@ -0,0 +1,592 @@
+//! A readable JSON format for a whole world, for writing starting positions
+//! by hand and for diffing states between ticks.
+//!
+//! Unlike [`World::save`], which stores the world's internals, a scene lists
+//! its species by name and its microbes as plain objects that refer to them:
+//!
+//! ```json
+//! {
+//!   "arena": 100.0,
+//!   "height": 50.0,
+//!   "seed": 42,
+//!   "species": [
+//!     { "name": "hunter", "builtin": "hunter", "color": "#e69f00" },
+//!     { "name": "rock", "script": "new_controls()" }
+//!   ],
+//!   "microbes": [
+//!     { "species": "hunter", "x": -20.0, "y": 0.0 },
+//!     { "species": "rock", "x": 20.0, "y": 0.0, "rotation": 3.14, "energy": 50.0 }
+//!   ]
+//! }
+//! ```
+//!
+//! Only the species' names, their scripts and the microbes' positions are
+//! required. `height` (for an arena that isn't square), `tick`, `ids`,
+//! `shape`, `climate`, `wells`, `trickle`, `energy_cap`, `population_cap`,
+//! `standoff`, `disruptions`, `famine_until`, `tuning`, `sandbox`, `math`
+//! and `tick_duration` can be given at the top level, and `rotation`,
+//! `energy`, `stamina`, `spiky`, `jaws`, `eyes`, `cloaked`, `fat`,
+//! `cooldown`, `caste`, `color`, `born`, `id` and `lineage` per microbe; a
+//! microbe without a color takes its species' color. Exported scenes fill
+//! in every field. Kill counts, plugins and brains aren't part of a scene.
+//! A species named `wanderers` is the one a trickle spawns.
+//!
+//! [`World::save`]: crate::World::save
+
+use ecolor::Color32;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::caste::Caste;
+use crate::climate::Climate;
+use crate::config::ConfigError;
+use crate::cooldown::Cooldown;
+use crate::disruption::Scheduled;
+use crate::error::SimError;
+use crate::eyes::Eyes;
+use crate::jaws::Jaws;
+use crate::math::Math;
+use crate::microbe::{Microbe, Transform};
+use crate::mutation::Mutation;
+use crate::overflow::EnergyCap;
+use crate::population::PopulationCap;
+use crate::sandbox::Sandbox;
+use crate::scripts;
+use crate::shape::Shape;
+use crate::simulation::DELTA_TIME;
+use crate::standoff::Standoff;
+use crate::trickle::Trickle;
+use crate::tuning::{Tuning, BOX_SIZE};
+use crate::wells::Well;
+use crate::world::World;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scene {
+    #[serde(default = "default_arena")]
+    pub arena: f32,
+    /// Half the height of the arena, if it isn't square.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<f32>,
+    /// `"rectangle"`, `"circle"` or `{"polygon": [[x, y], ...]}`.
+    #[serde(default)]
+    pub shape: Shape,
+    /// `"uniform"`, `"hot_center"`, `"cold_center"` or `{"gradient": angle}`.
+    #[serde(default)]
+    pub climate: Climate,
+    /// Points pulling microbes in or pushing them away, each
+    /// `{"x": x, "y": y, "strength": strength}`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wells: Vec<Well>,
+    /// Wanderers spawning at the edge, as `{"every": ticks, "max": count}`.
+    /// See [`crate::trickle`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trickle: Option<Trickle>,
+    /// How much energy a microbe can hold, as `{"max": m, "overflow":
+    /// "fat", "fat_burn": b}`. See [`crate::overflow`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub energy_cap: Option<EnergyCap>,
+    /// How many microbes the world holds, as `{"max": n, "cull": "oldest"}`.
+    /// See [`crate::population`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub population_cap: Option<PopulationCap>,
+    /// Disruptions yet to happen, each `{"tick": t, "kind": "meteor", ...}`.
+    /// See [`crate::disruption`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disruptions: Vec<Scheduled>,
+    /// The first tick after the current famine, if the world is in one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub famine_until: Option<u64>,
+    /// A random seed when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub tick: u64,
+    /// Ids handed out so far, so the world hands out the same ids as the one
+    /// the scene was taken from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ids: Option<u64>,
+    #[serde(default)]
+    pub tuning: Tuning,
+    #[serde(default)]
+    pub sandbox: Sandbox,
+    #[serde(default)]
+    pub math: Math,
+    /// `"stronger"`, `"both_hurt"`, `"random"` or `"trade"`. See
+    /// [`crate::standoff`].
+    #[serde(default)]
+    pub standoff: Standoff,
+    /// Seconds of simulated time per tick.
+    #[serde(default = "default_tick_duration")]
+    pub tick_duration: f32,
+    pub species: Vec<SceneSpecies>,
+    #[serde(default)]
+    pub microbes: Vec<SceneMicrobe>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneSpecies {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    /// The name of one of the [`scripts::builtin`] scripts, instead of
+    /// `script`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub builtin: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+    /// The default color of its microbes, as `#rrggbb`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Whether it feeds on light. See [`World::set_producer`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub producer: bool,
+    /// How its offspring differ, as `{"rate": r, "magnitude": m}`. See
+    /// [`crate::mutation`].
+    #[serde(default, skip_serializing_if = "Mutation::is_none")]
+    pub mutation: Mutation,
+    /// How long its microbes rest after splitting, as `{"ticks": t,
+    /// "speed": s}`. See [`crate::cooldown`].
+    #[serde(default, skip_serializing_if = "Cooldown::is_none")]
+    pub cooldown: Cooldown,
+    /// The roles its offspring can be born into. See [`crate::caste`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub castes: Vec<Caste>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneMicrobe {
+    /// The name of its species.
+    pub species: String,
+    pub x: f32,
+    pub y: f32,
+    #[serde(default)]
+    pub rotation: f32,
+    /// Full health when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub energy: Option<f32>,
+    /// Full stamina when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stamina: Option<f32>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub spiky: bool,
+    /// The usual jaws when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jaws: Option<Jaws>,
+    /// The usual eyes when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eyes: Option<Eyes>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cloaked: bool,
+    /// No fat when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fat: Option<f32>,
+    /// Ready to split when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooldown: Option<u64>,
+    /// The name of its caste, if it has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caste: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// The scene's tick when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub born: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    /// Its own id when missing, founding a new lineage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lineage: Option<Uuid>,
+}
+
+fn default_arena() -> f32 {
+    BOX_SIZE
+}
+
+fn default_tick_duration() -> f32 {
+    DELTA_TIME
+}
+
+impl Scene {
+    /// Describes `world`, naming its species as [`World::species_name`]
+    /// does.
+    pub fn capture(world: &World) -> Self {
+        let name = |id: &Uuid| world.species_name(*id);
+        let snapshot = world.snapshot();
+        Self {
+            arena: world.arena(),
+            height: world.height,
+            shape: world.shape().clone(),
+            climate: world.climate(),
+            wells: world.wells().to_vec(),
+            trickle: world.trickle(),
+            energy_cap: world.energy_cap(),
+            population_cap: world.population_cap(),
+            disruptions: world.disruptions().to_vec(),
+            famine_until: Some(world.famine_until).filter(|_| world.in_famine()),
+            seed: Some(world.seed()),
+            tick: snapshot.tick,
+            ids: Some(snapshot.ids),
+            tuning: snapshot.tuning,
+            sandbox: world.sandbox().clone(),
+            math: world.math(),
+            standoff: world.standoff(),
+            tick_duration: world.tick_duration(),
+            species: snapshot
+                .species
+                .iter()
+                .map(|id| SceneSpecies {
+                    name: name(id),
+                    id: Some(*id),
+                    builtin: None,
+                    script: world.scripts.get(id).cloned(),
+                    color: None,
+                    producer: world.is_producer(*id),
+                    mutation: world.mutation(*id),
+                    cooldown: world.cooldown(*id),
+                    castes: world.castes(*id).to_vec(),
+                })
+                .collect(),
+            microbes: snapshot
+                .microbes
+                .iter()
+                .map(|m| SceneMicrobe {
+                    species: name(&m.species),
+                    x: m.x,
+                    y: m.y,
+                    rotation: m.rotation,
+                    energy: Some(m.energy),
+                    stamina: Some(m.stamina),
+                    spiky: m.spiky,
+                    jaws: Some(m.jaws).filter(|jaws| *jaws != Jaws::default()),
+                    eyes: Some(m.eyes).filter(|eyes| *eyes != Eyes::default()),
+                    cloaked: m.cloaked,
+                    fat: Some(m.fat).filter(|fat| *fat > 0.),
+                    cooldown: Some(m.cooldown).filter(|ticks| *ticks > 0),
+                    caste: m
+                        .caste
+                        .and_then(|caste| world.castes(m.species).get(caste))
+                        .map(|caste| caste.name.clone()),
+                    color: Some(hex(m.color)),
+                    born: Some(m.born),
+                    id: Some(m.id),
+                    lineage: Some(m.lineage),
+                })
+                .collect(),
+        }
+    }
+
+    /// Creates the world the scene describes. Also returns the species ids,
+    /// in the same order as [`Scene::species`].
+    pub fn build(&self) -> Result<(World, Vec<Uuid>), SimError> {
+        let invalid = |message: String| SimError::Config(ConfigError::Invalid(message));
+        let mut world = World::with_size(
+            self.arena,
+            self.height.unwrap_or(self.arena),
+            self.seed.unwrap_or_else(rand::random),
+        )?;
+        if !self.shape.is_valid() {
+            return Err(invalid("'shape' should be a convex polygon".to_owned()));
+        }
+        world.set_shape(self.shape.clone());
+        world.set_climate(self.climate);
+        *world.wells_mut() = self.wells.clone();
+        world.set_energy_cap(self.energy_cap);
+        world.set_population_cap(self.population_cap);
+        world.set_disruptions(self.disruptions.clone());
+        world.famine_until = self.famine_until.unwrap_or(0);
+        world.tuning = self.tuning.clone();
+        world.set_sandbox(self.sandbox.clone());
+        world.set_math(self.math);
+        world.set_standoff(self.standoff);
+        if !(self.tick_duration.is_finite() && self.tick_duration > 0.) {
+            return Err(invalid(
+                "'tick_duration' should be a positive number".to_owned(),
+            ));
+        }
+        world.set_tick_duration(self.tick_duration);
+        world.tick = self.tick;
+
+        let mut species = HashMap::new();
+        let mut ids = Vec::new();
+        for entry in &self.species {
+            let script = match (&entry.builtin, &entry.script) {
+                (Some(builtin), None) => scripts::builtin(builtin).ok_or_else(|| {
+                    invalid(format!(
+                        "species '{}' has unknown builtin script '{builtin}'",
+                        entry.name
+                    ))
+                })?,
+                (None, Some(script)) => script.clone(),
+                _ => {
+                    return Err(invalid(format!(
+                        "species '{}' needs either a builtin or a script",
+                        entry.name
+                    )))
+                }
+            };
+            let color = match &entry.color {
+                Some(color) => parse_hex(color).ok_or_else(|| {
+                    invalid(format!(
+                        "species '{}' has invalid color '{color}'",
+                        entry.name
+                    ))
+                })?,
+                None => Color32::WHITE,
+            };
+            let id = entry.id.unwrap_or_else(|| world.next_id());
+            if species.insert(entry.name.as_str(), (id, color)).is_some() {
+                return Err(invalid(format!("species '{}' is listed twice", entry.name)));
+            }
+            world.scripts.insert(id, script);
+            world.set_species_name(id, &entry.name);
+            world.set_producer(id, entry.producer);
+            world.set_mutation(id, entry.mutation);
+            world.set_cooldown(id, entry.cooldown);
+            world.set_castes(id, entry.castes.clone());
+            ids.push(id);
+        }
+
+        world.wanderers = species.get("wanderers").map(|&(id, _)| id);
+        world.set_trickle(self.trickle);
+
+        for microbe in &self.microbes {
+            let &(script_id, color) = species.get(microbe.species.as_str()).ok_or_else(|| {
+                invalid(format!("microbe of unknown species '{}'", microbe.species))
+            })?;
+            let color = match &microbe.color {
+                Some(text) => parse_hex(text)
+                    .ok_or_else(|| invalid(format!("invalid microbe color '{text}'")))?,
+                None => color,
+            };
+            let caste = match &microbe.caste {
+                Some(name) => Some(
+                    world
+                        .castes(script_id)
+                        .iter()
+                        .position(|caste| caste.name == *name)
+                        .ok_or_else(|| {
+                            invalid(format!(
+                                "species '{}' has no caste '{name}'",
+                                microbe.species
+                            ))
+                        })?,
+                ),
+                None => None,
+            };
+            let id = microbe.id.unwrap_or_else(|| world.next_id());
+            world.microbes.insert(Microbe {
+                id,
+                lineage: microbe.lineage.unwrap_or(id),
+                transform: Transform::new(microbe.x, microbe.y, microbe.rotation),
+                script_id,
+                energy: microbe.energy.unwrap_or(self.tuning.health),
+                stamina: microbe.stamina.unwrap_or(self.tuning.stamina),
+                spiky: microbe.spiky,
+                jaws: microbe.jaws.unwrap_or_default(),
+                eyes: microbe.eyes.unwrap_or_default(),
+                cloaked: microbe.cloaked,
+                fat: microbe.fat.unwrap_or(0.),
+                cooldown: microbe.cooldown.unwrap_or(0),
+                caste,
+                color,
+                born: microbe.born.unwrap_or(self.tick),
+            });
+        }
+        if let Some(count) = self.ids {
+            world.ids = world.ids.max(count);
+        }
+        Ok((world, ids))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    /// Writes the scene as indented JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut json = serde_json::to_string_pretty(self)?;
+        json.push('\n');
+        fs::write(path, json)
+    }
+}
+
+fn hex(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+fn parse_hex(text: &str) -> Option<Color32> {
+    let digits = text.strip_prefix('#')?;
+    if digits.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok();
+    Some(Color32::from_rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disruption::Disruption;
+    use crate::Simulation;
+
+    const SCENE: &str = r##"{
+        "arena": 50.0,
+        "seed": 4,
+        "species": [
+            { "name": "hunter", "builtin": "hunter", "color": "#ff0000" },
+            { "name": "rock", "script": "new_controls()", "producer": true, "castes": [{ "name": "pebble", "spiky": true }] }
+        ],
+        "microbes": [
+            { "species": "hunter", "x": -5.0, "y": 0.0 },
+            { "species": "rock", "x": 5.0, "y": 0.0, "rotation": 1.5, "energy": 40.0, "color": "#0000ff", "caste": "pebble" }
+        ]
+    }"##;
+
+    #[test]
+    fn test_build() {
+        let scene = serde_json::from_str::<Scene>(SCENE).unwrap();
+        let (world, ids) = scene.build().unwrap();
+        assert_eq!(world.arena(), 50.);
+        assert_eq!(world.seed(), 4);
+        let snapshot = world.snapshot();
+        let hunter = snapshot
+            .microbes
+            .iter()
+            .find(|m| m.species == ids[0])
+            .unwrap();
+        assert_eq!((hunter.x, hunter.energy), (-5., world.tuning().health));
+        assert_eq!(hunter.color, Color32::RED);
+        assert_eq!(hunter.lineage, hunter.id);
+        let rock = snapshot
+            .microbes
+            .iter()
+            .find(|m| m.species == ids[1])
+            .unwrap();
+        assert_eq!((rock.rotation, rock.energy), (1.5, 40.));
+        assert_eq!(rock.color, Color32::BLUE);
+        assert_eq!(rock.caste, Some(0));
+        assert_eq!(world.castes(ids[1])[0].spiky, Some(true));
+        assert!(world.is_producer(ids[1]) && !world.is_producer(ids[0]));
+
+        let broken = [
+            SCENE.replace("\"builtin\": \"hunter\"", "\"builtin\": \"nope\""),
+            SCENE.replace("\"species\": \"rock\"", "\"species\": \"stone\""),
+            SCENE.replace("#ff0000", "red"),
+            SCENE.replace("\"seed\": 4", "\"seed\": 4, \"tick_duration\": 0"),
+            SCENE.replace("\"seed\": 4", "\"seed\": 4, \"tick_duration\": -0.1"),
+            SCENE.replace("\"caste\": \"pebble\"", "\"caste\": \"boulder\""),
+            SCENE.replace("\"rock\", \"script\"", "\"hunter\", \"script\""),
+        ];
+        for scene in broken {
+            let scene = serde_json::from_str::<Scene>(&scene).unwrap();
+            assert!(matches!(scene.build(), Err(SimError::Config(_))));
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let scene = serde_json::from_str::<Scene>(SCENE).unwrap();
+        let (world, _) = scene.build().unwrap();
+        let mut sim = Simulation::from(world);
+        for _ in 0..20 {
+            sim.step().unwrap();
+        }
+        let captured = Scene::capture(sim.world());
+        assert_eq!(captured.tick, 20);
+        assert!(captured.species.iter().any(|s| s.name == "rock"));
+
+        let json = serde_json::to_string_pretty(&captured).unwrap();
+        let (world, _) = serde_json::from_str::<Scene>(&json)
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut copy = Simulation::from(world);
+        assert_eq!(copy.snapshot().microbes, sim.snapshot().microbes);
+        assert_eq!(Scene::capture(copy.world()), captured);
+        sim.step().unwrap();
+        copy.step().unwrap();
+        assert_eq!(copy.snapshot().microbes, sim.snapshot().microbes);
+    }
+
+    #[test]
+    fn test_trickle() {
+        let scene = serde_json::from_str::<Scene>(SCENE).unwrap();
+        let (mut world, _) = scene.build().unwrap();
+        world.set_trickle(Some(Trickle::new(5, 10)));
+        let captured = Scene::capture(&world);
+        assert_eq!(captured.trickle, Some(Trickle::new(5, 10)));
+
+        // The wanderers come back as the trickle's species, not a new one.
+        let (mut copy, _) = captured.build().unwrap();
+        assert_eq!(copy.trickle(), world.trickle());
+        assert_eq!(copy.wanderers(), world.wanderers());
+        copy.set_trickle(Some(Trickle::new(1, 10)));
+        assert_eq!(copy.species().len(), 3);
+    }
+
+    #[test]
+    fn test_energy_cap() {
+        let scene = serde_json::from_str::<Scene>(SCENE).unwrap();
+        let (mut world, _) = scene.build().unwrap();
+        let cap = EnergyCap::new(150., crate::overflow::Overflow::Fat);
+        world.set_energy_cap(Some(cap));
+        let captured = Scene::capture(&world);
+        assert_eq!(captured.energy_cap, Some(cap));
+        let (copy, _) = captured.build().unwrap();
+        assert_eq!(copy.energy_cap(), Some(cap));
+    }
+
+    #[test]
+    fn test_population_cap() {
+        let scene = serde_json::from_str::<Scene>(SCENE).unwrap();
+        let (mut world, _) = scene.build().unwrap();
+        let cap = PopulationCap::new(1, crate::population::Cull::Weakest);
+        world.set_population_cap(Some(cap));
+        let captured = Scene::capture(&world);
+        assert_eq!(captured.population_cap, Some(cap));
+        let (copy, _) = captured.build().unwrap();
+        assert_eq!(copy.population_cap(), Some(cap));
+    }
+
+    #[test]
+    fn test_standoff() {
+        let scene = serde_json::from_str::<Scene>(SCENE).unwrap();
+        let (mut world, _) = scene.build().unwrap();
+        assert_eq!(world.standoff(), Standoff::Stronger);
+        world.set_standoff(Standoff::Trade);
+        let captured = Scene::capture(&world);
+        assert_eq!(captured.standoff, Standoff::Trade);
+        let (copy, _) = captured.build().unwrap();
+        assert_eq!(copy.standoff(), Standoff::Trade);
+    }
+
+    #[test]
+    fn test_disruptions() {
+        let scene = serde_json::from_str::<Scene>(SCENE).unwrap();
+        let (mut world, _) = scene.build().unwrap();
+        assert_eq!(Scene::capture(&world).famine_until, None);
+        let meteor = Disruption::Meteor {
+            x: 0.,
+            y: 0.,
+            radius: 10.,
+        };
+        world.schedule(world.tick(), Disruption::Famine { ticks: 50 });
+        world.schedule(100, meteor);
+        world.update(DELTA_TIME).unwrap();
+        assert!(world.in_famine());
+
+        let captured = Scene::capture(&world);
+        assert_eq!(captured.disruptions, [Scheduled::new(100, meteor)]);
+        assert_eq!(captured.famine_until, Some(world.famine_until));
+        let (copy, _) = captured.build().unwrap();
+        assert!(copy.in_famine());
+        assert_eq!(copy.disruptions(), world.disruptions());
+    }
+}
@@ -0,0 +1,359 @@
+//! Built-in species scripts.
+//!
+//! A script runs once per microbe per tick and returns a `Controls` object.
+//! All actions besides turning cost a small amount of energy.
+//!
+//! ```text
+//! // Create & modify a `Controls` object to return to the application
+//! let controls = new_controls();
+//! controls.forward = true;
+//! controls.left = true;
+//! controls.right = true;
+//! controls.back = true;
+//! controls.eat = true;
+//! // Costs energy every tick, but only microbes within attack range can
+//! // sense you next tick
+//! controls.cloak = true;
+//!
+//! // Returns the # of enemy microbes in range, in all 4 directions
+//! let front_far = sense_front();
+//! let left_far = sense_left();
+//! let right_far = sense_right();
+//! let back_far = sense_back();
+//!
+//! // Returns the # of enemy microbes within attack range, in all 4 directions
+//! // (You can only attack microbes in front of you)
+//! let front = sense_front_close();
+//! let left = sense_left_close();
+//! let right = sense_right_close();
+//! let back = sense_back_close();
+//!
+//! // Returns the distance to the arena's wall straight ahead
+//! let wall = sense_wall();
+//!
+//! // Returns how warm it is here, from -1 to 1. Warmth makes you faster
+//! // and hungrier
+//! let warmth = temperature();
+//!
+//! // Returns the angle to turn to face the middle of your own lineage in
+//! // range, negative to the left, and how spread out they are: their
+//! // average distance from that middle. Both are 0 with nobody around
+//! let bearing = allies_center_bearing();
+//! let spread = allies_cohesion();
+//!
+//! // Returns your current energy amount, you must eat to survive!
+//! let my_energy = energy();
+//!
+//! // Returns your stamina. Each tick of eating costs some and you can't
+//! // bite without enough; it comes back on ticks you don't.
+//! let my_stamina = stamina();
+//! ```
+//!
+//! A comment at the top of a script can name its species, which is shown
+//! instead of the species' id wherever no other name was given:
+//!
+//! ```text
+//! // name: Pack hunter
+//! let controls = new_controls();
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Aggressive hunter that directly chases the nearest microbe
+pub fn aggressive_hunter_script() -> String {
+    r#"
+        let controls = new_controls();
+
+        // Check all directions for closest target
+        let front_far = sense_front();
+        let left_far = sense_left();
+        let right_far = sense_right();
+        let back_far = sense_back();
+
+        // Periodically turn to search
+        if rand(0..=100) > 95 {
+            controls.forward = true;
+            if rand(0..=1) > 0.5 {
+                controls.right = true;
+            } else {
+                controls.left = true;
+            }
+        }
+        if front_far > 0 {
+            controls.forward = true;
+        }
+
+        if left_far > 0 {
+            controls.left = true;
+            controls.forward = true;
+        }
+        else
+        if right_far > 0 {
+            controls.right = true;
+            controls.forward = true;
+        }
+
+        if sense_front_close() > 0 {
+            controls.eat = true;
+        }
+
+        return controls;
+    "#
+    .to_string()
+}
+
+pub fn vampire_microbe_script() -> String {
+    r#"
+        let controls = new_controls();
+        let energy = energy();
+
+        // Sensing at different ranges
+        let front_far = sense_front();
+        let front_close = sense_front_close();
+        let left_far = sense_left();
+        let right_far = sense_right();
+        let left_close = sense_left_close();
+        let right_close = sense_right_close();
+
+        // Energy conservation mode when low
+        if energy < 30 {
+            // If prey is right in front, still take the opportunity
+            if front_close > 0 {
+                controls.eat = true;
+                return controls;
+            }
+
+            // Otherwise minimize movement and wait for energy regeneration
+            if front_far > 0 || left_far > 0 || right_far > 0 {
+                controls.back = true;
+                return controls;
+            }
+
+            // Occasional random movement to avoid getting stuck
+            if rand(0..=100) > 95 {
+                controls.forward = true;
+            }
+            return controls;
+        }
+
+        // Hunting mode when energy is sufficient
+        if front_close > 0 {
+            // Attack if prey is in range
+            controls.eat = true;
+        } else if front_far > 0 {
+            // Stalk prey that's further away
+            controls.forward = true;
+        } else if left_close > 0 || left_far > 0 {
+            // Turn towards nearby prey
+            controls.left = true;
+            if left_close == 0 {  // If not too close, move forward while turning
+                controls.forward = true;
+            }
+        } else if right_close > 0 || right_far > 0 {
+            // Turn towards nearby prey
+            controls.right = true;
+            if right_close == 0 {  // If not too close, move forward while turning
+                controls.forward = true;
+            }
+        } else {
+            // Search pattern when no prey is detected
+            controls.forward = true;
+            if rand(0..=100) > 92 {
+                if rand(0..=1) > 0.5 {
+                    controls.left = true;
+                } else {
+                    controls.right = true;
+                }
+            }
+        }
+
+        return controls;
+    "#
+    .to_string()
+}
+
+pub fn timid_herbivore_script() -> String {
+    r#"
+        let controls = new_controls();
+
+        // Detect threats
+        let front_far = sense_front();
+        let left_far = sense_left();
+        let right_far = sense_right();
+        let back_far = sense_back();
+
+        // Check for food in eating range
+        let front_close = sense_front_close();
+
+        // Run away if any threats are detected
+        if front_far > 0 || front_close > 0 {
+            controls.back = true;
+            // Pick random direction to flee
+            if rand(0..=1) > 0.5 {
+                controls.left = true;
+            } else {
+                controls.right = true;
+            }
+            return controls;
+        }
+
+        if left_far > 0 {
+            controls.right = true;
+            controls.forward = true;
+            return controls;
+        }
+
+        if right_far > 0 {
+            controls.left = true;
+            controls.forward = true;
+            return controls;
+        }
+
+        // If something is directly in front, try to eat it
+        // (game will only let us eat valid food)
+        if front_close > 0 {
+            controls.eat = true;
+            return controls;
+        }
+
+        // When no threats, occasionally move to find food
+        if rand(0..=100) > 80 {
+            controls.forward = true;
+            // Sometimes turn while moving
+            if rand(0..=100) > 70 {
+                if rand(0..=1) > 0.5 {
+                    controls.left = true;
+                } else {
+                    controls.right = true;
+                }
+            }
+        }
+
+        return controls;
+    "#
+    .to_string()
+}
+
+pub fn random_script() -> String {
+    r#"
+        let controls = new_controls();
+
+        if rand(0..=1) > 0.5 {
+            controls.right = true;
+        } else {
+            controls.left = true;
+        }
+        controls.forward = true;
+
+        return controls;
+    "#
+    .to_string()
+}
+
+/// Names accepted by [`builtin`].
+pub const BUILTINS: [&str; 4] = ["random", "hunter", "vampire", "herbivore"];
+
+/// Looks up one of the built-in scripts by name.
+pub fn builtin(name: &str) -> Option<String> {
+    match name {
+        "random" => Some(random_script()),
+        "hunter" => Some(aggressive_hunter_script()),
+        "vampire" => Some(vampire_microbe_script()),
+        "herbivore" => Some(timid_herbivore_script()),
+        _ => None,
+    }
+}
+
+/// The species name given by a `// name: ...` comment among the comments
+/// at the top of `source`.
+pub fn name(source: &str) -> Option<&str> {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map_while(|line| line.strip_prefix("//"))
+        .find_map(|comment| comment.trim().strip_prefix("name:"))
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+/// Parses `source` without running it, reporting the first syntax error.
+pub fn check(source: &str) -> Result<(), rhai::ParseError> {
+    rhai::Engine::new().compile(source).map(|_| ())
+}
+
+/// Reads every `.rhai` file in `dir`, returning `(name, source)` pairs
+/// sorted by file name. Each is named by its [`name`] comment, or else by
+/// its file stem.
+pub fn load_dir(dir: impl AsRef<Path>) -> io::Result<Vec<(String, String)>> {
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "rhai"));
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let source = fs::read_to_string(&path)?;
+            Ok((file_name(&path, &source), source))
+        })
+        .collect()
+}
+
+/// The script's [`name`] comment, or else the stem of its `path`.
+pub(crate) fn file_name(path: &Path, source: &str) -> String {
+    match name(source) {
+        Some(name) => name.to_owned(),
+        None => path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_scripts_parse() {
+        for name in BUILTINS {
+            check(&builtin(name).unwrap()).unwrap();
+        }
+        assert!(check("let controls = ;").is_err());
+    }
+
+    #[test]
+    fn test_load_dir() {
+        let dir = std::env::temp_dir().join(format!("microswarm-scripts-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("b.rhai"), "new_controls()").unwrap();
+        fs::write(dir.join("a.rhai"), "// name: Alpha\nnew_controls()").unwrap();
+        fs::write(dir.join("notes.txt"), "not a script").unwrap();
+
+        let scripts = load_dir(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let names = scripts
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Alpha", "b"]);
+    }
+
+    #[test]
+    fn test_name() {
+        assert_eq!(
+            name("// name: Pack hunter\nnew_controls()"),
+            Some("Pack hunter")
+        );
+        assert_eq!(
+            name("\n  // Chases things.\n  //name:hunter  \nnew_controls()"),
+            Some("hunter")
+        );
+        assert_eq!(name("new_controls()\n// name: too late"), None);
+        assert_eq!(name("// name:\nnew_controls()"), None);
+        assert_eq!(name(&aggressive_hunter_script()), None);
+    }
+}
@@ -0,0 +1,323 @@
+//! The outline of the playable area.
+//!
+//! The arena is always a rectangle of half-width [`World::arena`] and
+//! half-height [`World::arena_height`] centred on the origin, which bounds
+//! the quadtree and where microbes spawn. A [`Shape`] can cut the playable
+//! area down further, to the ellipse that fits inside the rectangle (a
+//! circle in a square arena) or to any convex polygon:
+//!
+//! ```toml
+//! shape = "circle"
+//! # or the corners of a polygon, in either winding order:
+//! shape = [[0.0, 200.0], [190.0, -150.0], [-190.0, -150.0]]
+//! ```
+//!
+//! Microbes that try to leave it are pushed back to the nearest point
+//! inside, and scripts can see how far the wall ahead is with
+//! `sense_wall()`. Parts of a polygon outside the rectangle are still
+//! walled off by the rectangle.
+//!
+//! The methods here take the arena's `size` as its half-width and
+//! half-height.
+//!
+//! [`World::arena`]: crate::World::arena
+//! [`World::arena_height`]: crate::World::arena_height
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::math::Math;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Shape {
+    /// The whole arena.
+    #[default]
+    #[serde(alias = "square")]
+    Rectangle,
+    /// The ellipse touching the middle of each side of the arena.
+    Circle,
+    /// A convex polygon, by its corners.
+    Polygon(Vec<[f32; 2]>),
+}
+
+impl Shape {
+    /// Whether a polygon's corners make a convex shape with some area. The
+    /// other shapes always do.
+    pub fn is_valid(&self) -> bool {
+        let Shape::Polygon(corners) = self else {
+            return true;
+        };
+        let winding = winding(corners);
+        corners.len() >= 3
+            && winding != 0.
+            && edges(corners).all(|(a, b)| {
+                corners
+                    .iter()
+                    .all(|p| cross(a, b, *p) * winding >= -f32::EPSILON)
+            })
+    }
+
+    /// Whether (`x`, `y`) is inside the shape, walls included.
+    pub fn contains(&self, size: [f32; 2], x: f32, y: f32) -> bool {
+        let [width, height] = size;
+        if x.abs() > width || y.abs() > height {
+            return false;
+        }
+        match self {
+            Shape::Rectangle => true,
+            Shape::Circle => (x / width).powi(2) + (y / height).powi(2) <= 1.,
+            Shape::Polygon(corners) => {
+                let winding = winding(corners);
+                edges(corners).all(|(a, b)| cross(a, b, [x, y]) * winding >= 0.)
+            }
+        }
+    }
+
+    /// The nearest point inside the shape to (`x`, `y`). An ellipse pulls
+    /// points straight towards its centre instead, which is the nearest
+    /// point on a circle.
+    pub fn clamp(&self, size: [f32; 2], x: f32, y: f32) -> (f32, f32) {
+        let [width, height] = size;
+        let (x, y) = (x.clamp(-width, width), y.clamp(-height, height));
+        if self.contains(size, x, y) {
+            return (x, y);
+        }
+        match self {
+            Shape::Rectangle => (x, y),
+            Shape::Circle => {
+                let scale = ((x / width).powi(2) + (y / height).powi(2)).sqrt().recip();
+                (x * scale, y * scale)
+            }
+            Shape::Polygon(corners) => {
+                let nearest = edges(corners)
+                    .map(|(a, b)| closest_on_segment(a, b, [x, y]))
+                    .min_by(|p, q| distance2(*p, [x, y]).total_cmp(&distance2(*q, [x, y])))
+                    .unwrap_or([x, y]);
+                (
+                    nearest[0].clamp(-width, width),
+                    nearest[1].clamp(-height, height),
+                )
+            }
+        }
+    }
+
+    /// A random point inside the shape, for spawning. Draws points in the
+    /// arena until one lands inside, so a rectangle takes a single draw.
+    pub fn sample(&self, size: [f32; 2], rng: &mut impl Rng) -> (f32, f32) {
+        let [width, height] = size;
+        let mut point = || (rng.gen_range(-width..width), rng.gen_range(-height..height));
+        let (mut x, mut y) = point();
+        for _ in 0..100 {
+            if self.contains(size, x, y) {
+                break;
+            }
+            (x, y) = point();
+        }
+        // A sliver of a polygon might never be hit.
+        self.clamp(size, x, y)
+    }
+
+    /// How far a microbe inside the shape at (`x`, `y`) facing `angle` is
+    /// from the wall ahead of it.
+    pub fn wall_distance(&self, size: [f32; 2], x: f32, y: f32, angle: f32, math: Math) -> f32 {
+        let [width, height] = size;
+        let (sin, cos) = math.sin_cos(angle);
+        let rectangle = [
+            [-width, -height],
+            [width, -height],
+            [width, height],
+            [-width, height],
+        ];
+        let to_rectangle = ray_to_polygon(&rectangle, [x, y], [cos, sin]);
+        let distance = match self {
+            Shape::Rectangle => to_rectangle,
+            Shape::Circle => {
+                // Squashes the ellipse into a unit circle, where the ray is
+                // p + t d, and solves |p + t d| = 1 for the t ahead.
+                let (px, py) = (x / width, y / height);
+                let (dx, dy) = (cos / width, sin / height);
+                let a = dx * dx + dy * dy;
+                let b = px * dx + py * dy;
+                let c = px * px + py * py - 1.;
+                let discriminant = b * b - a * c;
+                if discriminant < 0. {
+                    0.
+                } else {
+                    ((-b + discriminant.sqrt()) / a).min(to_rectangle)
+                }
+            }
+            Shape::Polygon(corners) => {
+                ray_to_polygon(corners, [x, y], [cos, sin]).min(to_rectangle)
+            }
+        };
+        distance.max(0.)
+    }
+
+    /// The points to draw the outline through, in order. An ellipse is
+    /// approximated by `segments` of them.
+    pub fn outline(&self, size: [f32; 2], segments: usize) -> Vec<[f32; 2]> {
+        let [width, height] = size;
+        match self {
+            Shape::Rectangle => vec![
+                [-width, -height],
+                [width, -height],
+                [width, height],
+                [-width, height],
+            ],
+            Shape::Circle => (0..segments.max(3))
+                .map(|i| {
+                    let angle = i as f32 / segments.max(3) as f32 * std::f32::consts::TAU;
+                    [angle.cos() * width, angle.sin() * height]
+                })
+                .collect(),
+            Shape::Polygon(corners) => corners.clone(),
+        }
+    }
+}
+
+/// Each corner paired with the next, wrapping around.
+fn edges(corners: &[[f32; 2]]) -> impl Iterator<Item = ([f32; 2], [f32; 2])> + '_ {
+    corners
+        .iter()
+        .zip(corners.iter().cycle().skip(1))
+        .map(|(a, b)| (*a, *b))
+}
+
+/// 1 for counter-clockwise corners, -1 for clockwise and 0 for corners on
+/// a line.
+fn winding(corners: &[[f32; 2]]) -> f32 {
+    let area = edges(corners)
+        .map(|(a, b)| a[0] * b[1] - b[0] * a[1])
+        .sum::<f32>();
+    if area == 0. {
+        0.
+    } else {
+        area.signum()
+    }
+}
+
+/// Positive when `p` is left of the line from `a` to `b`.
+fn cross(a: [f32; 2], b: [f32; 2], p: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+fn distance2(a: [f32; 2], b: [f32; 2]) -> f32 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)
+}
+
+fn closest_on_segment(a: [f32; 2], b: [f32; 2], p: [f32; 2]) -> [f32; 2] {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let length2 = dx * dx + dy * dy;
+    if length2 == 0. {
+        return a;
+    }
+    let t = (((p[0] - a[0]) * dx + (p[1] - a[1]) * dy) / length2).clamp(0., 1.);
+    [a[0] + t * dx, a[1] + t * dy]
+}
+
+/// How far along `direction` from `origin` the nearest edge is, or 0 if
+/// none is ahead.
+fn ray_to_polygon(corners: &[[f32; 2]], origin: [f32; 2], direction: [f32; 2]) -> f32 {
+    edges(corners)
+        .filter_map(|(a, b)| {
+            let edge = [b[0] - a[0], b[1] - a[1]];
+            let denominator = direction[0] * edge[1] - direction[1] * edge[0];
+            if denominator == 0. {
+                return None;
+            }
+            let offset = [a[0] - origin[0], a[1] - origin[1]];
+            let t = (offset[0] * edge[1] - offset[1] * edge[0]) / denominator;
+            let u = (offset[0] * direction[1] - offset[1] * direction[0]) / denominator;
+            (t >= 0. && (0. ..=1.).contains(&u)).then_some(t)
+        })
+        .min_by(f32::total_cmp)
+        .unwrap_or(0.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    const SQUARE: [f32; 2] = [50., 50.];
+
+    fn triangle() -> Shape {
+        Shape::Polygon(vec![[0., 40.], [40., -40.], [-40., -40.]])
+    }
+
+    #[test]
+    fn test_contains_and_clamp() {
+        assert!(Shape::Rectangle.contains(SQUARE, 49., -49.));
+        assert!(!Shape::Circle.contains(SQUARE, 49., -49.));
+        assert_eq!(Shape::Circle.clamp(SQUARE, 60., 0.), (50., 0.));
+        assert_eq!(Shape::Rectangle.clamp(SQUARE, 60., -70.), (50., -50.));
+
+        // Either winding order works.
+        let mut reversed = vec![[0., 40.], [40., -40.], [-40., -40.]];
+        reversed.reverse();
+        for shape in [triangle(), Shape::Polygon(reversed)] {
+            assert!(shape.contains(SQUARE, 0., 0.));
+            assert!(!shape.contains(SQUARE, 30., 30.));
+            assert_eq!(shape.clamp(SQUARE, 0., -45.), (0., -40.));
+            let (x, y) = shape.clamp(SQUARE, 30., 30.);
+            assert!(shape.contains(SQUARE, x + (0. - x) * 1e-3, y + (0. - y) * 1e-3));
+            assert!((x - 10.).abs() < 1e-3 && (y - 20.).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_wall_distance() {
+        let math = Math::Float;
+        let near = |a: f32, b: f32| (a - b).abs() < 1e-3;
+        assert!(near(
+            Shape::Rectangle.wall_distance(SQUARE, 0., 0., 0., math),
+            50.
+        ));
+        assert!(near(
+            Shape::Rectangle.wall_distance(SQUARE, 10., 0., PI, math),
+            60.
+        ));
+        assert!(near(
+            Shape::Circle.wall_distance(SQUARE, 0., 30., 0., math),
+            40.
+        ));
+        assert!(near(
+            triangle().wall_distance(SQUARE, 0., 0., -PI / 2., math),
+            40.
+        ));
+        assert!(near(
+            triangle().wall_distance(SQUARE, 0., 0., 0., math),
+            20.
+        ));
+        assert_eq!(Shape::Circle.wall_distance(SQUARE, 60., 60., 0., math), 0.);
+
+        let corridor = [100., 20.];
+        let ellipse = |x, y, angle| Shape::Circle.wall_distance(corridor, x, y, angle, math);
+        assert!(near(ellipse(0., 0., 0.), 100.));
+        assert!(near(ellipse(0., 0., PI / 2.), 20.));
+        assert!(near(ellipse(-50., 0., PI), 50.));
+        let rectangle = Shape::Rectangle.wall_distance(corridor, 0., 10., PI / 2., math);
+        assert!(near(rectangle, 10.));
+    }
+
+    #[test]
+    fn test_sample() {
+        use rand::SeedableRng;
+        let rng = &mut rand::rngs::StdRng::seed_from_u64(1);
+        for _ in 0..100 {
+            let (x, y) = triangle().sample(SQUARE, rng);
+            assert!(triangle().contains(SQUARE, x, y));
+        }
+    }
+
+    #[test]
+    fn test_is_valid() {
+        assert!(Shape::Circle.is_valid());
+        assert!(triangle().is_valid());
+        assert!(!Shape::Polygon(vec![[0., 0.], [1., 1.]]).is_valid());
+        assert!(!Shape::Polygon(vec![[0., 0.], [1., 1.], [2., 2.]]).is_valid());
+        let dart = vec![[0., 0.], [10., 10.], [0., 3.], [-10., 10.]];
+        assert!(!Shape::Polygon(dart).is_valid());
+    }
+}
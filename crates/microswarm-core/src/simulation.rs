@@ -0,0 +1,1248 @@
+use ecolor::Color32;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::brain::{self, Brain};
+use crate::caste::Caste;
+use crate::climate::Climate;
+use crate::controls::Controls;
+use crate::cooldown::Cooldown;
+use crate::disruption::{Disruption, Scheduled};
+use crate::error::SimError;
+use crate::events::{Event, StepReport};
+use crate::eyes::Eyes;
+use crate::jaws::Jaws;
+use crate::math::Math;
+use crate::mutation::Mutation;
+use crate::observer::Observer;
+use crate::overflow::EnergyCap;
+use crate::palette::Palette;
+use crate::plugin::WorldPlugin;
+use crate::population::PopulationCap;
+use crate::rules::{MatchResult, Rules};
+use crate::sandbox::Sandbox;
+use crate::shape::Shape;
+use crate::standoff::Standoff;
+use crate::systems::Senses;
+use crate::trickle::Trickle;
+use crate::tuning::Tuning;
+use crate::wells::Well;
+use crate::world::{UnknownSpecies, World};
+
+/// Seconds of simulated time per [`Simulation::step`], unless the world's
+/// [`Simulation::tick_duration`] is changed.
+pub const DELTA_TIME: f32 = 0.1;
+
+/// A running microbe world: register species scripts, spawn microbes into
+/// the arena, and step it forward.
+///
+/// ```
+/// use microswarm::{scripts, Color32, Simulation};
+///
+/// let mut sim = Simulation::new(400.).unwrap();
+/// let hunters = sim.add_species(scripts::aggressive_hunter_script());
+/// sim.spawn(hunters, 0., 0., 0., Color32::GREEN);
+///
+/// sim.step().unwrap();
+/// let snapshot = sim.snapshot();
+/// assert_eq!(snapshot.tick, 1);
+/// assert_eq!(snapshot.microbes.len(), 1);
+/// ```
+#[derive(Debug)]
+pub struct Simulation {
+    world: World,
+}
+
+/// A plain-data copy of the world at one tick. Besides being what viewers
+/// draw from, it holds everything needed to roll the world back to that tick
+/// with [`Simulation::restore`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+    pub tick: u64,
+    /// Every registered species, including ones with no living microbes.
+    pub species: Vec<Uuid>,
+    pub microbes: Vec<MicrobeState>,
+    pub tuning: Tuning,
+    /// Ids handed out so far, so a restored world hands out the same ids as
+    /// the original did from here.
+    pub ids: u64,
+    /// Running kill counts, keyed by victim species and then killer species.
+    pub predation: HashMap<Uuid, HashMap<Uuid, usize>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MicrobeState {
+    pub id: Uuid,
+    pub lineage: Uuid,
+    pub species: Uuid,
+    pub x: f32,
+    pub y: f32,
+    pub rotation: f32,
+    pub energy: f32,
+    pub stamina: f32,
+    /// See [`World::set_spiky`].
+    pub spiky: bool,
+    /// How far and wide it can bite. See [`crate::jaws`].
+    pub jaws: Jaws,
+    /// How far and wide it sees. See [`crate::eyes`].
+    pub eyes: Eyes,
+    /// Whether it cloaked last tick, hiding from far senses.
+    pub cloaked: bool,
+    /// Energy stored past the world's energy cap. See [`crate::overflow`].
+    pub fat: f32,
+    /// Ticks left before it can split again. See [`crate::cooldown`].
+    pub cooldown: u64,
+    /// Its index in [`World::castes`] of its species, if it has a caste.
+    pub caste: Option<usize>,
+    pub color: Color32,
+    /// Tick the microbe was spawned or born on.
+    pub born: u64,
+}
+
+impl Simulation {
+    /// Creates an empty square arena extending `arena` units from the origin
+    /// in each direction.
+    pub fn new(arena: f32) -> Result<Self, SimError> {
+        Ok(Self {
+            world: World::with_arena(arena)?,
+        })
+    }
+
+    /// Like [`Simulation::new`], but every random choice (ids, script `rand`
+    /// calls, palette colors) follows from `seed`.
+    pub fn with_seed(arena: f32, seed: u64) -> Result<Self, SimError> {
+        Ok(Self {
+            world: World::with_seed(arena, seed)?,
+        })
+    }
+
+    /// Like [`Simulation::with_seed`], for an arena extending `width` units
+    /// from the origin sideways and `height` units up and down.
+    pub fn with_size(width: f32, height: f32, seed: u64) -> Result<Self, SimError> {
+        Ok(Self {
+            world: World::with_size(width, height, seed)?,
+        })
+    }
+
+    /// Adds a plugin to the world. See [`crate::plugin`].
+    pub fn plugin(mut self, plugin: impl WorldPlugin + 'static) -> Self {
+        self.world.add_plugin(plugin);
+        self
+    }
+
+    /// Adds an observer that sees the world after every step. See
+    /// [`crate::observer`].
+    pub fn add_observer(&mut self, observer: Box<dyn Observer>) {
+        self.world.add_observer(observer);
+    }
+
+    /// Calls `subscriber` with the tick and each event after every step.
+    /// See [`crate::events`].
+    pub fn subscribe(&mut self, subscriber: impl FnMut(u64, &Event) + 'static) {
+        self.world.subscribe(subscriber);
+    }
+
+    /// Registers a species script and returns its id.
+    pub fn add_species(&mut self, script: impl Into<String>) -> Uuid {
+        let id = self.world.next_id();
+        self.world.scripts.insert(id, script.into());
+        id
+    }
+
+    /// Registers a species driven by `brain` and returns its id. See
+    /// [`crate::brain`].
+    pub fn add_brain(&mut self, brain: impl Brain + 'static) -> Uuid {
+        let id = self.add_species(brain::IDLE);
+        self.world.brains.insert(id, Box::new(brain));
+        id
+    }
+
+    /// Drives `species` with `brain` instead of its script from the next step.
+    pub fn set_brain(
+        &mut self,
+        species: Uuid,
+        brain: impl Brain + 'static,
+    ) -> Result<(), UnknownSpecies> {
+        if !self.world.scripts.contains_key(&species) {
+            return Err(UnknownSpecies(species));
+        }
+        self.world.brains.insert(species, Box::new(brain));
+        Ok(())
+    }
+
+    /// Species ids in a stable order.
+    pub fn species(&self) -> Vec<Uuid> {
+        self.world.species()
+    }
+
+    /// What to call `species`: the name it was given, else the one in its
+    /// script's `// name:` comment, else the start of its id.
+    pub fn species_name(&self, species: Uuid) -> String {
+        self.world.species_name(species)
+    }
+
+    /// Names `species`, replacing any name from its script.
+    pub fn set_species_name(&mut self, species: Uuid, name: impl Into<String>) {
+        self.world.set_species_name(species, name);
+    }
+
+    pub fn is_producer(&self, species: Uuid) -> bool {
+        self.world.is_producer(species)
+    }
+
+    pub fn mutation(&self, species: Uuid) -> Mutation {
+        self.world.mutation(species)
+    }
+
+    /// Changes how `species`' offspring differ from their parents. See
+    /// [`crate::mutation`].
+    pub fn set_mutation(&mut self, species: Uuid, mutation: Mutation) {
+        self.world.set_mutation(species, mutation);
+    }
+
+    pub fn cooldown(&self, species: Uuid) -> Cooldown {
+        self.world.cooldown(species)
+    }
+
+    /// Changes how long `species`' microbes rest after splitting. See
+    /// [`crate::cooldown`].
+    pub fn set_cooldown(&mut self, species: Uuid, cooldown: Cooldown) {
+        self.world.set_cooldown(species, cooldown);
+    }
+
+    pub fn castes(&self, species: Uuid) -> &[Caste] {
+        self.world.castes(species)
+    }
+
+    /// Changes the castes `species`' offspring can be born into. See
+    /// [`crate::caste`].
+    pub fn set_castes(&mut self, species: Uuid, castes: Vec<Caste>) {
+        self.world.set_castes(species, castes);
+    }
+
+    /// Makes a microbe spiky, or smooth. See [`World::set_spiky`].
+    pub fn set_spiky(&mut self, microbe: Uuid, spiky: bool) -> bool {
+        self.world.set_spiky(microbe, spiky)
+    }
+
+    /// Gives a microbe different jaws. See [`crate::jaws`].
+    pub fn set_jaws(&mut self, microbe: Uuid, jaws: Jaws) -> bool {
+        self.world.set_jaws(microbe, jaws)
+    }
+
+    /// Gives a microbe different eyes. See [`crate::eyes`].
+    pub fn set_eyes(&mut self, microbe: Uuid, eyes: Eyes) -> bool {
+        self.world.set_eyes(microbe, eyes)
+    }
+
+    /// Makes `species` feed on light. See [`World::set_producer`].
+    pub fn set_producer(&mut self, species: Uuid, producer: bool) {
+        self.world.set_producer(species, producer);
+    }
+
+    /// Spawns a microbe of `species` with full energy and its own lineage.
+    pub fn spawn(&mut self, species: Uuid, x: f32, y: f32, rotation: f32, color: Color32) -> Uuid {
+        self.world.add_microbe(x, y, rotation, species, color)
+    }
+
+    /// Advances the world by one tick. If a script fails, the world is left
+    /// as it was and the step can be retried, for example after
+    /// [`Simulation::set_brain`] replaces the broken species.
+    pub fn step(&mut self) -> Result<&StepReport, SimError> {
+        self.world.update(self.world.tick_duration())?;
+        Ok(self.world.report())
+    }
+
+    /// Advances the world by one tick with every microbe doing what
+    /// `controls` says, bypassing brains and scripts.
+    ///
+    /// ```
+    /// use microswarm::{Color32, Controls, Simulation};
+    ///
+    /// let mut sim = Simulation::new(100.).unwrap();
+    /// let species = sim.add_species("new_controls()");
+    /// sim.spawn(species, 0., 0., 0., Color32::RED);
+    ///
+    /// sim.step_with(|_, _| Controls {
+    ///     forward: true,
+    ///     ..Controls::default()
+    /// })
+    /// .unwrap();
+    /// assert!(sim.snapshot().microbes[0].x > 0.);
+    /// ```
+    pub fn step_with(
+        &mut self,
+        controls: impl FnMut(&MicrobeState, &Senses) -> Controls,
+    ) -> Result<&StepReport, SimError> {
+        self.world.update_with(self.world.tick_duration(), controls)
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        self.world.snapshot()
+    }
+
+    /// Every living microbe, in no particular order. Unlike
+    /// [`Simulation::snapshot`], nothing is collected or sorted.
+    pub fn microbes(&self) -> impl Iterator<Item = MicrobeState> + '_ {
+        self.world.microbes().map(|microbe| microbe.state())
+    }
+
+    /// Rolls the world back to `snapshot`. See [`World::restore`].
+    pub fn restore(&mut self, snapshot: Snapshot) -> Result<(), UnknownSpecies> {
+        self.world.restore(snapshot)
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.world.tick()
+    }
+
+    /// Half the arena's width.
+    pub fn arena(&self) -> f32 {
+        self.world.arena()
+    }
+
+    /// Half the arena's height, the same as [`Simulation::arena`] for a
+    /// square.
+    pub fn arena_height(&self) -> f32 {
+        self.world.arena_height()
+    }
+
+    /// Half the arena's width and height.
+    pub fn arena_size(&self) -> [f32; 2] {
+        self.world.arena_size()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.world.seed()
+    }
+
+    pub fn tuning(&self) -> &Tuning {
+        self.world.tuning()
+    }
+
+    /// Tuning changes take effect from the next step.
+    pub fn tuning_mut(&mut self) -> &mut Tuning {
+        self.world.tuning_mut()
+    }
+
+    pub fn sandbox(&self) -> &Sandbox {
+        self.world.sandbox()
+    }
+
+    /// Changes the limits on scripts from the next step. See
+    /// [`crate::sandbox`].
+    pub fn set_sandbox(&mut self, sandbox: Sandbox) {
+        self.world.set_sandbox(sandbox);
+    }
+
+    pub fn math(&self) -> Math {
+        self.world.math()
+    }
+
+    pub fn shape(&self) -> &Shape {
+        self.world.shape()
+    }
+
+    /// Changes the playable area. See [`crate::shape`].
+    pub fn set_shape(&mut self, shape: Shape) {
+        self.world.set_shape(shape);
+    }
+
+    pub fn climate(&self) -> Climate {
+        self.world.climate()
+    }
+
+    /// Changes how warm each part of the arena is. See [`crate::climate`].
+    pub fn set_climate(&mut self, climate: Climate) {
+        self.world.set_climate(climate);
+    }
+
+    pub fn energy_cap(&self) -> Option<EnergyCap> {
+        self.world.energy_cap()
+    }
+
+    /// Limits how much energy microbes hold. See [`crate::overflow`].
+    pub fn set_energy_cap(&mut self, cap: Option<EnergyCap>) {
+        self.world.set_energy_cap(cap);
+    }
+
+    pub fn standoff(&self) -> Standoff {
+        self.world.standoff()
+    }
+
+    /// Changes who wins when microbes bite each other. See
+    /// [`crate::standoff`].
+    pub fn set_standoff(&mut self, standoff: Standoff) {
+        self.world.set_standoff(standoff);
+    }
+
+    pub fn population_cap(&self) -> Option<PopulationCap> {
+        self.world.population_cap()
+    }
+
+    /// Culls microbes whenever a tick ends with too many. See
+    /// [`crate::population`].
+    pub fn set_population_cap(&mut self, cap: Option<PopulationCap>) {
+        self.world.set_population_cap(cap);
+    }
+
+    /// Disruptions yet to happen. See [`crate::disruption`].
+    pub fn disruptions(&self) -> &[Scheduled] {
+        self.world.disruptions()
+    }
+
+    /// Replaces the disruptions yet to happen. See [`crate::disruption`].
+    pub fn set_disruptions(&mut self, disruptions: Vec<Scheduled>) {
+        self.world.set_disruptions(disruptions);
+    }
+
+    /// Makes `disruption` happen at the end of `tick`. See
+    /// [`World::schedule`].
+    pub fn schedule(&mut self, tick: u64, disruption: Disruption) {
+        self.world.schedule(tick, disruption);
+    }
+
+    pub fn trickle(&self) -> Option<Trickle> {
+        self.world.trickle()
+    }
+
+    /// Starts or stops spawning wanderers at the arena's edge. See
+    /// [`crate::trickle`].
+    pub fn set_trickle(&mut self, trickle: Option<Trickle>) {
+        self.world.set_trickle(trickle);
+    }
+
+    /// The species of the wanderers a trickle spawns.
+    pub fn wanderers(&self) -> Option<Uuid> {
+        self.world.wanderers()
+    }
+
+    pub fn wells(&self) -> &[Well] {
+        self.world.wells()
+    }
+
+    /// The points pulling microbes in or pushing them away. See
+    /// [`crate::wells`].
+    pub fn wells_mut(&mut self) -> &mut Vec<Well> {
+        self.world.wells_mut()
+    }
+
+    /// Switches how moving and sensing do their trigonometry. See
+    /// [`crate::math`].
+    pub fn set_math(&mut self, math: Math) {
+        self.world.set_math(math);
+    }
+
+    /// Seconds of simulated time per step.
+    pub fn tick_duration(&self) -> f32 {
+        self.world.tick_duration()
+    }
+
+    /// Changes how much time each step simulates, from the next step.
+    /// Movement scales with it, so shorter ticks make for smoother, slower
+    /// steps that cover the same ground over the same simulated time. See
+    /// [`World::set_tick_duration`] for the values it ignores.
+    pub fn set_tick_duration(&mut self, seconds: f32) {
+        self.world.set_tick_duration(seconds);
+    }
+
+    pub fn rules(&self) -> Option<&Rules> {
+        self.world.rules()
+    }
+
+    /// Starts a match under `rules` from the next step, or stops refereeing
+    /// with `None`. See [`crate::rules`].
+    pub fn set_rules(&mut self, rules: Option<Rules>) {
+        self.world.set_rules(rules);
+    }
+
+    /// How the match ended, once it has.
+    pub fn result(&self) -> Option<&MatchResult> {
+        self.world.result()
+    }
+
+    /// The species that has killed the most members of `species` so far.
+    pub fn top_predator(&self, species: &Uuid) -> Option<Uuid> {
+        self.world.top_predator(species)
+    }
+
+    /// Gives every microbe the color assigned to its species by `palette`.
+    pub fn recolor(&mut self, palette: Palette) {
+        self.world.recolor(palette);
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub(crate) fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// Saves the world to `path`. See [`World::save`].
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.world.save(path)
+    }
+
+    /// Resumes a world saved with [`Simulation::save`].
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            world: World::load(path)?,
+        })
+    }
+}
+
+impl From<World> for Simulation {
+    /// Runs a world, such as one set up with [`crate::WorldBuilder`].
+    fn from(world: World) -> Self {
+        Self { world }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Cause;
+    use crate::mutation::Mutation;
+    use crate::overflow::Overflow;
+    use crate::population::Cull;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_snapshot_lists_empty_species() {
+        let mut sim = Simulation::new(100.).unwrap();
+        let a = sim.add_species("new_controls()");
+        let b = sim.add_species("new_controls()");
+        let id = sim.spawn(a, 1., 2., 0.5, Color32::RED);
+
+        let snapshot = sim.snapshot();
+        let mut species = vec![a, b];
+        species.sort();
+        assert_eq!(snapshot.species, species);
+        assert_eq!(snapshot.microbes.len(), 1);
+        let microbe = snapshot.microbes[0];
+        assert_eq!(
+            (microbe.id, microbe.species, microbe.x, microbe.y),
+            (id, a, 1., 2.)
+        );
+    }
+
+    #[test]
+    fn test_step_advances_tick() {
+        let mut sim = Simulation::new(100.).unwrap();
+        let idle = sim.add_species("new_controls()");
+        sim.spawn(idle, 0., 0., 0., Color32::RED);
+
+        let report = sim.step().unwrap();
+        assert_eq!(report.births(), 0);
+        assert_eq!(report.deaths().count(), 0);
+        assert_eq!(sim.tick(), 1);
+    }
+
+    #[test]
+    fn test_step_with_combat_and_reproduction() {
+        let mut sim = Simulation::new(100.).unwrap();
+        // Flat bites, so the prey runs out.
+        sim.tuning_mut().eat_transfer = 0.;
+        // Never run: the controls below stand in for it.
+        let species = sim.add_species("not a script");
+        let eater = sim.spawn(species, 0., 0., 0., Color32::RED);
+        let prey = sim.spawn(species, 5., 0., std::f32::consts::PI, Color32::RED);
+        let eat = |microbe: &MicrobeState, senses: &Senses| Controls {
+            eat: microbe.id == eater && senses.close[0] > 0,
+            ..Controls::default()
+        };
+
+        let report = sim.step_with(eat).unwrap();
+        assert_eq!(report.events.len(), 1);
+        let energy = |sim: &Simulation, id| {
+            sim.snapshot()
+                .microbes
+                .iter()
+                .find(|m| m.id == id)
+                .map(|m| m.energy)
+        };
+        assert!(energy(&sim, eater).unwrap() > 129.);
+        assert!(energy(&sim, prey).unwrap() < 71.);
+
+        for _ in 0..2 {
+            sim.step_with(eat).unwrap();
+        }
+        // The fourth bite kills the prey and takes the eater past the
+        // reproduction threshold.
+        let report = sim.step_with(eat).unwrap();
+        assert_eq!(report.deaths().count(), 1);
+        assert_eq!(report.births(), 4);
+        assert_eq!(energy(&sim, prey), None);
+        assert_eq!(sim.snapshot().microbes.len(), 5);
+    }
+
+    #[test]
+    fn test_step_with_stays_in_arena() {
+        let mut sim = Simulation::new(100.).unwrap();
+        let species = sim.add_species("not a script");
+        sim.spawn(
+            species,
+            99.,
+            -99.,
+            -std::f32::consts::FRAC_PI_4,
+            Color32::RED,
+        );
+        for _ in 0..5 {
+            sim.step_with(|_, _| Controls {
+                forward: true,
+                ..Controls::default()
+            })
+            .unwrap();
+        }
+        let microbe = sim.snapshot().microbes[0];
+        assert_eq!((microbe.x, microbe.y), (100., -100.));
+    }
+
+    #[test]
+    fn test_movement_follows_tick_duration() {
+        let travel = |tick_duration: f32, steps| {
+            let mut sim = Simulation::with_seed(100., 0).unwrap();
+            sim.set_tick_duration(tick_duration);
+            let species = sim.add_species("not a script");
+            sim.spawn(species, 0., 0., 0., Color32::RED);
+            for _ in 0..steps {
+                sim.step_with(|_, _| Controls {
+                    forward: true,
+                    right: true,
+                    ..Controls::default()
+                })
+                .unwrap();
+            }
+            let microbe = sim.snapshot().microbes[0];
+            (microbe.x, microbe.y, microbe.rotation, microbe.energy)
+        };
+        let mut sim = Simulation::with_seed(100., 0).unwrap();
+        for seconds in [0., -0.1, f32::NAN] {
+            sim.set_tick_duration(seconds);
+        }
+        assert_eq!(sim.tick_duration(), DELTA_TIME);
+
+        let (x, y, rotation, energy) = travel(DELTA_TIME, 1);
+        assert_eq!(x, Tuning::default().speed);
+        // Half-length ticks take twice as many to cover the same ground,
+        // and spend the same energy doing it.
+        let halves = travel(DELTA_TIME / 2., 2);
+        assert!((halves.0 - x).abs() < 0.5 && halves.1 > y);
+        assert!((halves.2 - rotation).abs() < 1e-5);
+        assert!((halves.3 - energy).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_climate() {
+        let mut sim = Simulation::with_seed(100., 0).unwrap();
+        sim.set_climate(Climate::HotCenter);
+        let species = sim.add_species("let c = new_controls(); c.forward = temperature() > 0.; c");
+        // Facing away from the centre at the middle and out near the edge.
+        sim.spawn(species, 0., 0., 0., Color32::RED);
+        sim.spawn(species, 0., 60., std::f32::consts::FRAC_PI_2, Color32::RED);
+        sim.step().unwrap();
+
+        let tuning = Tuning::default();
+        let microbes = sim.snapshot().microbes;
+        let warm = microbes.iter().find(|m| m.y == 0.).unwrap();
+        let cool = microbes.iter().find(|m| m.y != 0.).unwrap();
+        assert_eq!(warm.x, tuning.speed * (1. + tuning.temperature_effect));
+        let used = |microbe: &MicrobeState| tuning.health - microbe.energy;
+        assert!((used(warm) - 1.5 * tuning.action_energy_consumption).abs() < 1e-5);
+        // Too cold to want to move, and using less energy to stand still.
+        assert_eq!(cool.y, 60.);
+        let chill = 1. - tuning.temperature_effect * 0.2;
+        assert!((used(cool) - chill * tuning.action_energy_consumption).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_producers() {
+        let mut sim = Simulation::with_seed(100., 0).unwrap();
+        let still = sim.add_species("new_controls()");
+        let walker = sim.add_species("let c = new_controls(); c.forward = true; c");
+        let plain = sim.add_species("new_controls()");
+        sim.set_producer(still, true);
+        sim.set_producer(walker, true);
+        sim.spawn(still, -60., -60., 0., Color32::RED);
+        sim.spawn(walker, 0., 60., 0., Color32::RED);
+        sim.spawn(plain, 60., -60., 0., Color32::RED);
+        sim.step().unwrap();
+
+        let tuning = Tuning::default();
+        let microbes = sim.snapshot().microbes;
+        let of = |species| microbes.iter().find(|m| m.species == species).unwrap();
+        let rested = tuning.health - tuning.action_energy_consumption;
+        assert_eq!(of(plain).energy, rested);
+        assert_eq!(of(still).energy, rested + tuning.photosynthesis);
+        // Moving gets no light, and a producer moves at a handicap.
+        assert_eq!(of(walker).energy, rested);
+        assert_eq!(of(walker).x, tuning.speed * tuning.producer_handicap);
+
+        sim.set_producer(still, false);
+        assert!(!sim.is_producer(still));
+    }
+
+    #[test]
+    fn test_spikes() {
+        let mut sim = Simulation::with_seed(100., 0).unwrap();
+        let species = sim.add_species("new_controls()");
+        let smooth = sim.spawn(species, -60., 0., 0., Color32::RED);
+        let spiky = sim.spawn(species, 60., 0., 0., Color32::RED);
+        assert!(sim.set_spiky(spiky, true));
+        assert!(!sim.set_spiky(Uuid::from_u128(7), true));
+        sim.step().unwrap();
+
+        let tuning = Tuning::default();
+        let microbes = sim.snapshot().microbes;
+        let of = |id| microbes.iter().find(|m| m.id == id).unwrap();
+        assert!(of(spiky).spiky && !of(smooth).spiky);
+        let used = |microbe: &MicrobeState| tuning.health - microbe.energy;
+        let consumption = tuning.action_energy_consumption;
+        assert!((used(of(smooth)) - consumption).abs() < 1e-5);
+        assert!((used(of(spiky)) - tuning.spike_upkeep * consumption).abs() < 1e-5);
+
+        // Offspring keep their parent's spikes.
+        sim.tuning_mut().reproduction_threshold = 0.;
+        sim.step().unwrap();
+        let microbes = sim.snapshot().microbes;
+        let children = |lineage| microbes.iter().filter(move |m| m.lineage == lineage);
+        assert_eq!(children(spiky).count(), 4);
+        assert!(children(spiky).all(|m| m.spiky));
+        assert!(children(smooth).all(|m| !m.spiky));
+    }
+
+    #[test]
+    fn test_castes() {
+        let mut sim = Simulation::with_seed(100., 0).unwrap();
+        let species = sim.add_species(
+            r#"let c = new_controls(); if caste() == "" { c.spawn_as = "soldier"; } c"#,
+        );
+        let soldier = Caste {
+            script: Some("let c = new_controls(); c.forward = true; c".into()),
+            spiky: Some(true),
+            ..Caste::new("soldier")
+        };
+        sim.set_castes(species, vec![Caste::new("worker"), soldier]);
+        let queen = sim.spawn(species, 0., 0., 0., Color32::RED);
+        sim.tuning_mut().reproduction_threshold = 90.;
+
+        // The queen splits into soldiers, which get the caste's spikes.
+        sim.step().unwrap();
+        let microbes = sim.snapshot().microbes;
+        assert_eq!(microbes.len(), 4);
+        assert!(microbes.iter().all(|m| m.lineage == queen));
+        assert!(microbes.iter().all(|m| m.caste == Some(1) && m.spiky));
+
+        // ...and run the caste's script instead of the species'.
+        sim.step().unwrap();
+        assert!(sim.snapshot().microbes.iter().all(|m| m.x > 0.));
+    }
+
+    #[test]
+    fn test_allies() {
+        let mut sim = Simulation::with_seed(100., 0).unwrap();
+        let species = sim.add_species("new_controls()");
+        let me = sim.spawn(species, 0., 0., 0., Color32::RED);
+        let alone = sim.spawn(species, 60., 60., 0., Color32::RED);
+        let offspring =
+            [(0., 10.), (10., 10.)].map(|(x, y)| sim.spawn(species, x, y, 0., Color32::RED));
+        sim.spawn(species, -5., 0., 0., Color32::RED);
+        let mut snapshot = sim.snapshot();
+        for microbe in &mut snapshot.microbes {
+            if offspring.contains(&microbe.id) {
+                microbe.lineage = me;
+            }
+        }
+        sim.restore(snapshot).unwrap();
+
+        let sensed = RefCell::new(HashMap::new());
+        sim.step_with(|microbe, senses| {
+            let allies = (senses.allies_center_bearing, senses.allies_cohesion);
+            sensed.borrow_mut().insert(microbe.id, allies);
+            Controls::default()
+        })
+        .unwrap();
+        let sensed = sensed.into_inner();
+        // The middle of (0, 10) and (10, 10) is ahead and to the right.
+        let (bearing, spread) = sensed[&me];
+        assert!((bearing - 2f32.atan()).abs() < 1e-5);
+        assert!((spread - 5.).abs() < 1e-5);
+        assert_eq!(sensed[&alone], (0., 0.));
+    }
+
+    #[test]
+    fn test_metabolism() {
+        let mut sim = Simulation::with_seed(100., 0).unwrap();
+        let species = sim.add_species("new_controls()");
+        let usual = sim.spawn(species, -60., 0., 0., Color32::RED);
+        let gulper = sim.spawn(species, -20., 0., 0., Color32::RED);
+        let spotter = sim.spawn(species, 20., 0., 0., Color32::RED);
+        let myope = sim.spawn(species, 60., 0., 0., Color32::RED);
+        sim.set_jaws(gulper, Jaws::new(2., 1.));
+        sim.set_eyes(spotter, Eyes::new(3.));
+        sim.set_eyes(myope, Eyes::new(0.5));
+        sim.step().unwrap();
+
+        let tuning = Tuning::default();
+        let microbes = sim.snapshot().microbes;
+        let used = |id| tuning.health - microbes.iter().find(|m| m.id == id).unwrap().energy;
+        let consumption = tuning.action_energy_consumption;
+        let expect = [
+            (usual, 1.),
+            (gulper, 1. + tuning.jaw_upkeep),
+            (spotter, 1. + 2. * tuning.eye_upkeep),
+            (myope, 1. - 0.5 * tuning.eye_upkeep),
+        ];
+        for (id, metabolism) in expect {
+            assert!((used(id) - metabolism * consumption).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_jaws() {
+        let mut sim = Simulation::with_seed(100., 0).unwrap();
+        // Never run: the controls below stand in for it.
+        let species = sim.add_species("not a script");
+        let prey = sim.add_species("not a script");
+        let eater = sim.spawn(species, 0., 0., 0., Color32::RED);
+        let far = sim.spawn(prey, 15., 0., 0., Color32::GREEN);
+        let eat = |microbe: &MicrobeState, _: &Senses| Controls {
+            eat: microbe.id == eater,
+            ..Controls::default()
+        };
+        let bitten = |sim: &mut Simulation| {
+            let report = sim.step_with(eat).unwrap();
+            report
+                .events
+                .iter()
+                .any(|event| matches!(event, Event::MicrobeAte { victim, .. } if *victim == far))
+        };
+
+        // Out of reach of the usual jaws, but not of longer ones.
+        assert!(!bitten(&mut sim));
+        let jaws = Jaws::new(1., 2.);
+        assert!(sim.set_jaws(eater, jaws));
+        assert!(!sim.set_jaws(Uuid::from_u128(7), jaws));
+        assert!(bitten(&mut sim));
+
+        // Longer jaws cost more to eat with, upkeep aside.
+        let mut sim = Simulation::with_seed(100., 0).unwrap();
+        sim.tuning_mut().jaw_upkeep = 0.;
+        let species = sim.add_species("let c = new_controls(); c.eat = true; c");
+        let usual = sim.spawn(species, -60., 0., 0., Color32::RED);
+        let long = sim.spawn(species, 60., 0., 0., Color32::RED);
+        sim.set_jaws(long, jaws);
+        sim.step().unwrap();
+        let microbes = sim.snapshot().microbes;
+        let energy = |id| microbes.iter().find(|m| m.id == id).unwrap().energy;
+        let cost = jaws.cost(Tuning::default().jaw_cost);
+        assert!(cost > 0.);
+        assert!((energy(usual) - energy(long) - cost).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_eyes() {
+        let mut sim = Simulation::with_seed(100., 0).unwrap();
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let log = seen.clone();
+        let watcher = sim.add_brain(move |_: &MicrobeState, senses: &Senses| {
+            log.borrow_mut().push(senses.far);
+            Controls::default()
+        });
+        let idle = sim.add_brain(|_: &MicrobeState, _: &Senses| Controls::default());
+        let eyes = sim.spawn(watcher, 0., 0., 0., Color32::RED);
+        let (sin, cos) = std::f32::consts::FRAC_PI_4.sin_cos();
+        sim.spawn(idle, 60., 0., 0., Color32::RED);
+        sim.spawn(idle, cos * 20., sin * 20., 0., Color32::RED);
+        sim.step().unwrap();
+        assert!(sim.set_eyes(eyes, Eyes::new(2.)));
+        sim.step().unwrap();
+
+        // Usual eyes see the near one, off to the side, in front and to the
+        // right. Telescopic ones see only the distant one straight ahead.
+        assert_eq!(*seen.borrow(), [[1, 0, 1, 0], [1, 0, 0, 0]]);
+    }
+
+    #[test]
+    fn test_cloak() {
+        let mut sim = Simulation::with_seed(100., 0).unwrap();
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let log = seen.clone();
+        let watcher = sim.add_brain(move |_: &MicrobeState, senses: &Senses| {
+            log.borrow_mut().push((senses.far[0], senses.close[0]));
+            Controls::default()
+        });
+        let cloaker = sim.add_brain(|_: &MicrobeState, _: &Senses| Controls {
+            cloak: true,
+            ..Controls::default()
+        });
+        sim.spawn(watcher, 0., 0., 0., Color32::RED);
+        let far = sim.spawn(cloaker, 20., 0., 0., Color32::RED);
+        sim.spawn(cloaker, 5., 0., 0., Color32::RED);
+        sim.step().unwrap();
+        sim.step().unwrap();
+
+        // Seen by both senses until it cloaks, then only from close by.
+        assert_eq!(*seen.borrow(), [(2, 1), (1, 1)]);
+        let tuning = Tuning::default();
+        let microbes = sim.snapshot().microbes;
+        let far = microbes.iter().find(|m| m.id == far).unwrap();
+        assert!(far.cloaked);
+        let used = tuning.health - far.energy;
+        let cost = 2. * (tuning.action_energy_consumption + tuning.cloak_energy);
+        assert!((used - cost).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_wells() {
+        let mut sim = Simulation::with_seed(100., 0).unwrap();
+        let species = sim.add_species("new_controls()");
+        sim.spawn(species, 0., 20., 0., Color32::RED);
+        sim.wells_mut().push(Well::new(0., 0., 40.));
+        sim.step().unwrap();
+        let y = |sim: &Simulation| sim.snapshot().microbes[0].y;
+        assert_eq!(y(&sim), 18.);
+
+        // Switched off, it leaves microbes be.
+        sim.wells_mut()[0].active = false;
+        sim.step().unwrap();
+        assert_eq!(y(&sim), 18.);
+        sim.wells_mut()[0] = Well::new(0., 0., -36.);
+        sim.step().unwrap();
+        assert_eq!(y(&sim), 20.);
+    }
+
+    #[test]
+    fn test_mutation() {
+        let run = |mutation| {
+            let mut sim = Simulation::with_seed(100., 3).unwrap();
+            let species = sim.add_species("new_controls()");
+            sim.set_mutation(species, mutation);
+            sim.tuning_mut().reproduction_threshold = 0.;
+            sim.spawn(species, 0., 0., 0., Color32::from_rgb(100, 100, 100));
+            sim.step().unwrap();
+            sim.snapshot().microbes
+        };
+        let copies = run(Mutation::default());
+        assert_eq!(copies.len(), 4);
+        assert!(copies.iter().all(|m| !m.spiky));
+        assert!(copies
+            .iter()
+            .all(|m| m.color == Color32::from_rgb(100, 100, 100)));
+
+        let mutants = run(Mutation::new(1., 0.5));
+        assert!(mutants.iter().all(|m| m.spiky));
+        assert!(mutants
+            .iter()
+            .any(|m| m.color != Color32::from_rgb(100, 100, 100)));
+        // The same seed mutates the same way.
+        assert_eq!(run(Mutation::new(1., 0.5)), mutants);
+    }
+
+    #[test]
+    fn test_energy_cap() {
+        let mut sim = Simulation::with_seed(100., 0).unwrap();
+        let species = sim.add_species("new_controls()");
+        sim.spawn(species, 0., 0., 0., Color32::RED);
+        sim.set_energy_cap(Some(EnergyCap::new(60., Overflow::Fat)));
+        sim.step().unwrap();
+        let microbe = sim.snapshot().microbes[0];
+        let tuning = Tuning::default();
+        assert_eq!(microbe.energy, 60.);
+        let fat = tuning.health - tuning.action_energy_consumption - 60.;
+        assert!((microbe.fat - fat).abs() < 1e-4);
+
+        // Wasted, there's nothing to fall back on.
+        sim.set_energy_cap(Some(EnergyCap::new(30., Overflow::Wasted)));
+        sim.step().unwrap();
+        let microbe = sim.snapshot().microbes[0];
+        assert_eq!(microbe.energy, 30.);
+        assert!((microbe.fat - fat).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_cooldown() {
+        let run = |cooldown| {
+            let mut sim = Simulation::with_seed(100., 0).unwrap();
+            let species = sim.add_species("let c = new_controls(); c.forward = true; c");
+            sim.set_cooldown(species, cooldown);
+            let parent = sim.spawn(species, 0., 0., 0., Color32::RED);
+            sim.tuning_mut().health = 10.;
+            sim.tuning_mut().reproduction_threshold = 50.;
+            let mut splits = 0;
+            let mut x = Vec::new();
+            for _ in 0..5 {
+                let report = sim.step().unwrap();
+                splits += report
+                    .events
+                    .iter()
+                    .filter(|event| matches!(event, Event::MicrobeBorn { parent: p, .. } if *p == parent))
+                    .count()
+                    / 4;
+                let snapshot = sim.snapshot();
+                x.push(snapshot.microbes.iter().find(|m| m.id == parent).unwrap().x);
+            }
+            (splits, x)
+        };
+        // Splitting every tick, or resting three ticks between splits.
+        let (splits, eager) = run(Cooldown::default());
+        assert_eq!(splits, 5);
+        let (splits, _) = run(Cooldown::new(3, 1.));
+        assert_eq!(splits, 2);
+
+        // Slower while resting, the tick after splitting.
+        let (_, resting) = run(Cooldown::new(3, 0.5));
+        assert_eq!(resting[0], eager[0]);
+        let (eager, resting) = (eager[1] - eager[0], resting[1] - resting[0]);
+        assert!((resting - eager * 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_disruptions() {
+        let mut sim = Simulation::with_seed(100., 0).unwrap();
+        let idle = sim.add_species("new_controls()");
+        for x in [0., 5., 50.] {
+            sim.spawn(idle, x, 0., 0., Color32::RED);
+        }
+        let meteor = Disruption::Meteor {
+            x: 0.,
+            y: 0.,
+            radius: 10.,
+        };
+        sim.schedule(1, meteor);
+        sim.schedule(2, Disruption::Bloom { count: 5 });
+        sim.schedule(2, Disruption::Famine { ticks: 3 });
+        assert!(!sim.world().in_famine());
+
+        let report = sim.step().unwrap();
+        let struck = report
+            .events
+            .iter()
+            .filter(|event| {
+                matches!(
+                    event,
+                    Event::MicrobeDied {
+                        cause: Cause::Struck,
+                        ..
+                    }
+                )
+            })
+            .count();
+        assert_eq!(struck, 2);
+        assert!(report
+            .events
+            .contains(&Event::Disrupted { disruption: meteor }));
+        assert_eq!(sim.snapshot().microbes.len(), 1);
+        assert_eq!(sim.disruptions().len(), 2);
+
+        sim.step().unwrap();
+        let wanderers = sim.world().wanderers().unwrap();
+        let microbes = sim.snapshot().microbes;
+        assert_eq!(
+            microbes.iter().filter(|m| m.species == wanderers).count(),
+            5
+        );
+        assert!(sim.disruptions().is_empty());
+        // The famine covers the three ticks after the one it struck on.
+        let famine = (0..4)
+            .map(|_| {
+                sim.step().unwrap();
+                sim.world().in_famine()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(famine, [true, true, true, false]);
+    }
+
+    #[test]
+    fn test_famine() {
+        let run = |famine| {
+            let mut sim = Simulation::with_seed(100., 0).unwrap();
+            let plant = sim.add_species("new_controls()");
+            sim.set_producer(plant, true);
+            let id = sim.spawn(plant, 0., 0., 0., Color32::GREEN);
+            if famine {
+                sim.schedule(0, Disruption::Famine { ticks: 10 });
+            }
+            sim.step().unwrap();
+            sim.step().unwrap();
+            sim.snapshot()
+                .microbes
+                .iter()
+                .find(|m| m.id == id)
+                .unwrap()
+                .energy
+        };
+        let photosynthesis = Tuning::default().photosynthesis;
+        assert!((run(false) - run(true) - photosynthesis * 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_population_cap() {
+        let mut sim = Simulation::with_seed(100., 0).unwrap();
+        let idle = sim.add_species("new_controls()");
+        let first = sim.spawn(idle, -10., 0., 0., Color32::RED);
+        sim.step().unwrap();
+        for n in 0..5 {
+            sim.spawn(idle, n as f32 * 10., 0., 0., Color32::RED);
+        }
+        sim.set_population_cap(Some(PopulationCap::new(3, Cull::Oldest)));
+        let report = sim.step().unwrap();
+        let culled = report
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                Event::MicrobeDied {
+                    microbe,
+                    cause: Cause::Culled,
+                    ..
+                } => Some(microbe.id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(culled.len(), 3);
+        assert!(culled.contains(&first));
+        assert_eq!(sim.snapshot().microbes.len(), 3);
+    }
+
+    #[test]
+    fn test_trickle() {
+        let mut sim = Simulation::with_seed(100., 0).unwrap();
+        assert_eq!(sim.wanderers(), None);
+        sim.set_trickle(Some(Trickle::new(2, 3)));
+        let wanderers = sim.wanderers().unwrap();
+        for _ in 0..10 {
+            sim.step().unwrap();
+        }
+        // One every other tick, until there are three.
+        let microbes = sim.snapshot().microbes;
+        assert_eq!(microbes.len(), 3);
+        for microbe in &microbes {
+            assert_eq!(microbe.species, wanderers);
+            assert!(microbe.x.abs() > 90. || microbe.y.abs() > 90.);
+        }
+
+        // Stopping and starting again keeps the same species.
+        sim.set_trickle(None);
+        sim.set_trickle(Some(Trickle::new(1, 10)));
+        assert_eq!(sim.wanderers(), Some(wanderers));
+        assert_eq!(sim.species().len(), 1);
+    }
+
+    #[test]
+    fn test_failed_step_leaves_world_alone() {
+        let mut sim = Simulation::new(100.).unwrap();
+        let broken = sim.add_species("let c = new_controls(); c.forward = 1 / 0; c");
+        sim.spawn(broken, 0., 0., 0., Color32::RED);
+        let before = sim.snapshot();
+        match sim.step() {
+            Err(SimError::Script { species, .. }) => assert_eq!(species, broken),
+            other => panic!("expected a script error, got {other:?}"),
+        }
+        assert_eq!(sim.snapshot(), before);
+
+        // Replacing the broken script lets the world carry on.
+        sim.set_brain(broken, |_: &MicrobeState, _: &Senses| Controls::default())
+            .unwrap();
+        sim.step().unwrap();
+        assert_eq!(sim.tick(), 1);
+
+        let stranger = Uuid::from_u128(7);
+        sim.spawn(stranger, 0., 0., 0., Color32::RED);
+        assert!(matches!(
+            sim.step(),
+            Err(SimError::MissingScript(species)) if species == stranger
+        ));
+        assert_eq!(sim.tick(), 1);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut sim = Simulation::new(100.).unwrap();
+        let species = sim.add_species(crate::scripts::aggressive_hunter_script());
+        for i in 0..20 {
+            sim.spawn(species, i as f32 * 4. - 40., 0., 0., Color32::RED);
+        }
+        sim.tuning_mut().speed = 3.;
+        for _ in 0..5 {
+            sim.step().unwrap();
+        }
+
+        let path =
+            std::env::temp_dir().join(format!("microswarm-save-{}.json", std::process::id()));
+        sim.save(&path).unwrap();
+        let loaded = Simulation::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let mut loaded = loaded.unwrap();
+
+        let sorted = |sim: &Simulation| {
+            let mut snapshot = sim.snapshot();
+            snapshot.microbes.sort_by_key(|m| m.id);
+            snapshot
+        };
+        assert_eq!(sorted(&loaded), sorted(&sim));
+        assert_eq!(loaded.tuning(), sim.tuning());
+        assert_eq!(loaded.arena(), 100.);
+        loaded.step().unwrap();
+        assert_eq!(loaded.tick(), 6);
+    }
+
+    #[test]
+    fn test_same_seed_same_run() {
+        let run = |seed| {
+            let mut sim = Simulation::with_seed(60., seed).unwrap();
+            let hunters = sim.add_species(crate::scripts::aggressive_hunter_script());
+            let randoms = sim.add_species(crate::scripts::random_script());
+            for i in 0..40 {
+                let species = if i % 2 == 0 { hunters } else { randoms };
+                let offset = i as f32 * 3. - 60.;
+                sim.spawn(species, offset, -offset, i as f32, Color32::RED);
+            }
+            for _ in 0..30 {
+                sim.step().unwrap();
+            }
+            sim.snapshot()
+        };
+        assert_eq!(run(11), run(11));
+        assert_ne!(run(11), run(12));
+    }
+
+    #[test]
+    fn test_restore_rolls_back() {
+        let mut sim = Simulation::with_seed(60., 4).unwrap();
+        let hunters = sim.add_species(crate::scripts::aggressive_hunter_script());
+        for i in 0..30 {
+            sim.spawn(hunters, i as f32 * 4. - 60., 0., i as f32, Color32::RED);
+        }
+        for _ in 0..5 {
+            sim.step().unwrap();
+        }
+        let checkpoint = sim.snapshot();
+        let run = |sim: &mut Simulation| {
+            for _ in 0..10 {
+                sim.step().unwrap();
+            }
+            sim.snapshot()
+        };
+        let original = run(&mut sim);
+
+        sim.tuning_mut().speed = 5.;
+        sim.spawn(hunters, 0., 0., 0., Color32::BLUE);
+        sim.restore(checkpoint.clone()).unwrap();
+        assert_eq!(sim.snapshot(), checkpoint);
+        assert_eq!(run(&mut sim), original);
+
+        let stranger = Snapshot {
+            microbes: vec![MicrobeState {
+                species: Uuid::nil(),
+                ..checkpoint.microbes[0]
+            }],
+            ..checkpoint
+        };
+        assert!(sim.restore(stranger).is_err());
+        assert_eq!(sim.tick(), 15);
+    }
+}
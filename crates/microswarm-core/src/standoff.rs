@@ -0,0 +1,83 @@
+//! Who wins when two microbes bite each other.
+//!
+//! A microbe biting one that is also eating is a standoff. By default the
+//! one with more energy wins and the other's bite misses, which
+//! [`Standoff`] lets a config change:
+//!
+//! ```toml
+//! standoff = "stronger"     # or "both_hurt", "random", or "trade"
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::microbe::Microbe;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Standoff {
+    /// The bite lands only if the biter has more energy, so equals hold
+    /// each other off.
+    #[default]
+    Stronger,
+    /// Both bites land, but neither biter gains from them.
+    BothHurt,
+    /// A coin toss, fixed by the world's seed, picks whose bite lands.
+    Random,
+    /// Both bites land as usual, so each eats from the other.
+    Trade,
+}
+
+/// What a bite does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Bite {
+    Misses,
+    Hurts,
+    Feeds,
+}
+
+impl Standoff {
+    /// What `eater`'s bite does to `victim`, which is eating too. `won_toss`
+    /// says whether the eater won the pair's coin toss.
+    pub(crate) fn settle(self, eater: &Microbe, victim: &Microbe, won_toss: bool) -> Bite {
+        match self {
+            Standoff::Stronger if eater.energy > victim.energy => Bite::Feeds,
+            Standoff::Random if won_toss => Bite::Feeds,
+            Standoff::Stronger | Standoff::Random => Bite::Misses,
+            Standoff::BothHurt => Bite::Hurts,
+            Standoff::Trade => Bite::Feeds,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ecolor::Color32;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_settle() {
+        let [weak, strong] = [10., 20.].map(|energy| {
+            let mut microbe = Microbe::new(Uuid::new_v4(), 0., 0., 0., Uuid::nil(), Color32::RED);
+            microbe.energy = energy;
+            microbe
+        });
+        let settle = |standoff: Standoff, won_toss| {
+            (
+                standoff.settle(&strong, &weak, won_toss),
+                standoff.settle(&weak, &strong, !won_toss),
+            )
+        };
+        assert_eq!(
+            settle(Standoff::Stronger, false),
+            (Bite::Feeds, Bite::Misses)
+        );
+        assert_eq!(
+            settle(Standoff::BothHurt, false),
+            (Bite::Hurts, Bite::Hurts)
+        );
+        assert_eq!(settle(Standoff::Random, false), (Bite::Misses, Bite::Feeds));
+        assert_eq!(settle(Standoff::Trade, false), (Bite::Feeds, Bite::Feeds));
+        assert_eq!(Standoff::Stronger.settle(&weak, &weak, true), Bite::Misses);
+    }
+}
@@ -0,0 +1,192 @@
+//! Per-species statistics, kept by the world as it runs.
+//!
+//! After every update the world adds a [`TickStats`] to its [`Stats`], with
+//! each species' population, energy and age, and its births, deaths and
+//! kills during that tick. The last [`Stats::HISTORY`] ticks are kept, so
+//! charts and scorers read the same numbers instead of each counting them
+//! from snapshots and events.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use uuid::Uuid;
+
+use crate::events::{Cause, Event, StepReport};
+use crate::microbe::Microbe;
+
+/// One species' numbers at the end of a tick.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct SpeciesStats {
+    /// Living microbes.
+    pub population: usize,
+    /// Total energy of the living microbes.
+    pub biomass: f32,
+    /// 0 when there are no living microbes.
+    pub mean_energy: f32,
+    /// Mean ticks since the living microbes were born, 0 when there are
+    /// none.
+    pub mean_age: f32,
+    /// Microbes born during the tick.
+    pub births: usize,
+    /// Microbes that died during the tick.
+    pub deaths: usize,
+    /// Deaths of other microbes during the tick that this species took a
+    /// bite in.
+    pub kills: usize,
+}
+
+/// Every species' numbers at the end of a tick.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TickStats {
+    pub tick: u64,
+    /// Every species the world has a script for, even without microbes.
+    pub species: BTreeMap<Uuid, SpeciesStats>,
+}
+
+impl TickStats {
+    /// Tallies `microbes` and the events of `report`.
+    pub(crate) fn new<'a>(
+        species: impl IntoIterator<Item = Uuid>,
+        microbes: impl IntoIterator<Item = &'a Microbe>,
+        report: &StepReport,
+    ) -> Self {
+        let mut stats = species
+            .into_iter()
+            .map(|id| (id, SpeciesStats::default()))
+            .collect::<BTreeMap<_, _>>();
+        let mut ages = BTreeMap::<Uuid, u64>::new();
+        for microbe in microbes {
+            let entry = stats.entry(microbe.script_id).or_default();
+            entry.population += 1;
+            entry.biomass += microbe.energy;
+            *ages.entry(microbe.script_id).or_default() += report.tick.saturating_sub(microbe.born);
+        }
+        for event in &report.events {
+            match event {
+                Event::MicrobeBorn { microbe, .. } => {
+                    stats.entry(microbe.species).or_default().births += 1;
+                }
+                Event::MicrobeDied { microbe, cause, .. } => {
+                    stats.entry(microbe.species).or_default().deaths += 1;
+                    if let Cause::Eaten { by } = cause {
+                        let mut killers = by.clone();
+                        killers.sort();
+                        killers.dedup();
+                        for killer in killers {
+                            stats.entry(killer).or_default().kills += 1;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        for (id, entry) in &mut stats {
+            if entry.population > 0 {
+                let population = entry.population as f32;
+                entry.mean_energy = entry.biomass / population;
+                entry.mean_age = ages.get(id).copied().unwrap_or_default() as f32 / population;
+            }
+        }
+        Self {
+            tick: report.tick,
+            species: stats,
+        }
+    }
+}
+
+/// The recent history of a world's [`TickStats`], oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    history: VecDeque<TickStats>,
+}
+
+impl Stats {
+    /// Ticks of history kept.
+    pub const HISTORY: usize = 1000;
+
+    pub(crate) fn push(&mut self, stats: TickStats) {
+        if self.history.len() == Self::HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(stats);
+    }
+
+    /// Forgets the ticks after `tick`, for when the world goes back to it.
+    pub(crate) fn rewind(&mut self, tick: u64) {
+        while self.history.back().is_some_and(|s| s.tick > tick) {
+            self.history.pop_back();
+        }
+    }
+
+    /// The most recent tick, if the world has been updated.
+    pub fn latest(&self) -> Option<&TickStats> {
+        self.history.back()
+    }
+
+    pub fn history(&self) -> impl DoubleEndedIterator<Item = &TickStats> + ExactSizeIterator {
+        self.history.iter()
+    }
+
+    /// One species' numbers for every tick kept, with their ticks.
+    pub fn species(&self, species: Uuid) -> impl Iterator<Item = (u64, SpeciesStats)> + '_ {
+        self.history
+            .iter()
+            .map(move |s| (s.tick, s.species.get(&species).copied().unwrap_or_default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{scripts, Color32, Simulation};
+
+    #[test]
+    fn test_stats() {
+        let mut sim = Simulation::with_seed(40., 6).unwrap();
+        let hunters = sim.add_species(scripts::aggressive_hunter_script());
+        let idle = sim.add_species("new_controls()");
+        let empty = sim.add_species("new_controls()");
+        for i in 0..10 {
+            sim.spawn(hunters, i as f32 * 4. - 20., 0., 0., Color32::RED);
+            sim.spawn(idle, i as f32 * 4. - 20., 3., 0., Color32::BLUE);
+        }
+        assert!(sim.world().stats().latest().is_none());
+
+        let (mut births, mut deaths, mut kills) = (0, 0, 0);
+        for _ in 0..300 {
+            sim.step().unwrap();
+            let report = sim.world().report();
+            let latest = sim.world().stats().latest().unwrap();
+            assert_eq!(latest.tick, report.tick);
+            births += report.births();
+            deaths += report.deaths().count();
+            kills += report.kills();
+
+            let snapshot = sim.snapshot();
+            for (species, stats) in &latest.species {
+                let living = snapshot.microbes.iter().filter(|m| m.species == *species);
+                assert_eq!(stats.population, living.clone().count());
+                let biomass = living.map(|m| m.energy).sum::<f32>();
+                assert!((stats.biomass - biomass).abs() < 1e-2);
+            }
+        }
+        let stats = sim.world().stats();
+        assert_eq!(stats.history().len(), 300);
+        let total = |field: fn(&crate::stats::SpeciesStats) -> usize| {
+            stats
+                .history()
+                .flat_map(|s| s.species.values())
+                .map(field)
+                .sum::<usize>()
+        };
+        assert!(births > 0 && kills > 0);
+        assert_eq!(total(|s| s.births), births);
+        assert_eq!(total(|s| s.deaths), deaths);
+        assert_eq!(total(|s| s.kills), kills);
+
+        let latest = stats.latest().unwrap();
+        assert_eq!(latest.species[&empty], Default::default());
+        let idle_stats = stats.species(idle).last().unwrap().1;
+        if idle_stats.population > 0 {
+            assert!(idle_stats.mean_age > 0.);
+        }
+    }
+}
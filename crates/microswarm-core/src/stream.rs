@@ -0,0 +1,605 @@
+//! Live world state over WebSocket, so remote and browser viewers can watch
+//! a headless run.
+//!
+//! [`Broadcaster::serve`] accepts WebSocket connections on any path. Every
+//! [`Broadcaster::broadcast`] sends each client one message per tick,
+//! as JSON text unless the client connected with `?format=binary`. Nothing
+//! is sent for ticks before a client connects, and clients that fall behind
+//! are dropped. A [`Spectator`] is the other end, for watching from another
+//! process or machine.
+//!
+//! # JSON
+//!
+//! ```json
+//! {
+//!   "tick": 120,
+//!   "arena": 400,
+//!   "species": ["4b3a…", "9f10…"],
+//!   "microbes": [[12.5, -3.25, 1.57, 99.9, 0, "#ff8000"], …]
+//! }
+//! ```
+//!
+//! `arena` is half the width of the square arena, centred on the origin.
+//! Each microbe is `[x, y, rotation, energy, species, color]`, where
+//! `rotation` is in radians, `species` indexes `species`, and `color` is a
+//! CSS hex color.
+//!
+//! # Binary
+//!
+//! All numbers are little-endian.
+//!
+//! | bytes  | field                                                      |
+//! |--------|------------------------------------------------------------|
+//! | 1      | format version, currently 1                                |
+//! | 8      | tick, `u64`                                                |
+//! | 4      | arena, `f32`                                               |
+//! | 2      | number of species, `u16`, then each species id (16 bytes)  |
+//! | 4      | number of microbes, `u32`, then 22 bytes for each:         |
+//! |        | `x`, `y`, `rotation`, `energy` as `f32`, then `r`, `g`,    |
+//! |        | `b`, `a` as `u8`, then the species index as `u16`          |
+
+use ecolor::Color32;
+use rand::Rng;
+use sha1::{Digest, Sha1};
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::simulation::{MicrobeState, Snapshot};
+
+const BINARY_VERSION: u8 = 1;
+/// Defined by RFC 6455 for computing `Sec-WebSocket-Accept`.
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// A client that can't take a message within this long is dropped.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Binary,
+}
+
+struct Client {
+    stream: TcpStream,
+    format: Format,
+}
+
+/// Streams snapshots to every connected WebSocket client.
+pub struct Broadcaster {
+    clients: Arc<Mutex<Vec<Client>>>,
+    addr: SocketAddr,
+    arena: f32,
+}
+
+impl Broadcaster {
+    /// Listens on `addr` for viewers of a world with the given arena size.
+    pub fn serve(addr: impl ToSocketAddrs, arena: f32) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let shared = clients.clone();
+        thread::Builder::new()
+            .name("stream".to_owned())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let peer = stream.peer_addr().ok();
+                    match handshake(stream) {
+                        Ok(client) => {
+                            tracing::info!(?peer, format = ?client.format, "spectator joined");
+                            lock(&shared).push(client);
+                        }
+                        Err(error) => tracing::debug!(?peer, %error, "bad handshake"),
+                    }
+                }
+            })?;
+        Ok(Self {
+            clients,
+            addr,
+            arena,
+        })
+    }
+
+    /// The address actually listened on, useful when binding port 0.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn clients(&self) -> usize {
+        lock(&self.clients).len()
+    }
+
+    /// Sends `snapshot` to every client, dropping any that can't keep up.
+    pub fn broadcast(&self, snapshot: &Snapshot) {
+        let mut clients = lock(&self.clients);
+        if clients.is_empty() {
+            return;
+        }
+        let mut json = None;
+        let mut binary = None;
+        clients.retain_mut(|client| {
+            let frame = match client.format {
+                Format::Json => json.get_or_insert_with(|| {
+                    frame(0x1, encode_json(snapshot, self.arena).as_bytes())
+                }),
+                Format::Binary => {
+                    binary.get_or_insert_with(|| frame(0x2, &encode_binary(snapshot, self.arena)))
+                }
+            };
+            let sent = client.stream.write_all(frame);
+            if let Err(error) = &sent {
+                tracing::info!(peer = ?client.stream.peer_addr().ok(), %error, "spectator dropped");
+            }
+            sent.is_ok()
+        });
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// One tick received by a [`Spectator`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub snapshot: Snapshot,
+    /// Half the width of the arena.
+    pub arena: f32,
+}
+
+/// Watches a [`Broadcaster`], keeping only the newest tick so a slow viewer
+/// skips ticks rather than falling behind.
+pub struct Spectator {
+    latest: Arc<Mutex<Option<Frame>>>,
+    connected: Arc<AtomicBool>,
+    addr: SocketAddr,
+}
+
+impl Spectator {
+    /// Connects to a broadcaster at `addr`, such as `192.168.1.20:9001`, and
+    /// receives its ticks on a background thread.
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let mut stream = TcpStream::connect(addr)?;
+        let addr = stream.peer_addr()?;
+        let key = base64(&rand::thread_rng().gen::<[u8; 16]>());
+        write!(
+            stream,
+            "GET /?format=binary HTTP/1.1\r\nHost: {addr}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n\r\n"
+        )?;
+        let mut reader = BufReader::new(stream);
+        let mut status = String::new();
+        reader.read_line(&mut status)?;
+        if status.split_whitespace().nth(1) != Some("101") {
+            return Err(invalid(format!(
+                "not a microswarm stream: {}",
+                status.trim()
+            )));
+        }
+        let mut accept = None;
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 2 {
+            if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("sec-websocket-accept") {
+                    accept = Some(value.trim().to_owned());
+                }
+            }
+            line.clear();
+        }
+        if accept != Some(accept_key(&key)) {
+            return Err(invalid("bad WebSocket handshake".to_owned()));
+        }
+
+        let latest = Arc::new(Mutex::new(None));
+        let connected = Arc::new(AtomicBool::new(true));
+        let (shared, still_connected) = (latest.clone(), connected.clone());
+        thread::Builder::new()
+            .name("spectator".to_owned())
+            .spawn(move || {
+                if let Err(error) = receive(reader, &shared) {
+                    tracing::info!(%addr, %error, "stream ended");
+                }
+                still_connected.store(false, Ordering::Relaxed);
+            })?;
+        Ok(Self {
+            latest,
+            connected,
+            addr,
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Whether the broadcaster is still sending.
+    pub fn connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// The newest tick received since the last call, if there is one.
+    pub fn latest(&self) -> Option<Frame> {
+        lock(&self.latest).take()
+    }
+}
+
+/// Reads frames until the stream closes, keeping the newest.
+fn receive(mut reader: impl Read, latest: &Mutex<Option<Frame>>) -> io::Result<()> {
+    loop {
+        let mut header = [0; 2];
+        reader.read_exact(&mut header)?;
+        let opcode = header[0] & 0x0f;
+        let len = match header[1] & 0x7f {
+            126 => {
+                let mut len = [0; 2];
+                reader.read_exact(&mut len)?;
+                u16::from_be_bytes(len) as u64
+            }
+            127 => {
+                let mut len = [0; 8];
+                reader.read_exact(&mut len)?;
+                u64::from_be_bytes(len)
+            }
+            len => len as u64,
+        };
+        let mut mask = [0; 4];
+        if header[1] & 0x80 != 0 {
+            reader.read_exact(&mut mask)?;
+        }
+        let mut payload = Vec::new();
+        reader.by_ref().take(len).read_to_end(&mut payload)?;
+        if payload.len() as u64 != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+        match opcode {
+            0x2 => *lock(latest) = Some(decode_binary(&payload)?),
+            0x8 => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Answers a WebSocket opening handshake.
+fn handshake(mut stream: TcpStream) -> io::Result<Client> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    let mut key = None;
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("sec-websocket-key") {
+                key = Some(value.trim().to_owned());
+            }
+        }
+        line.clear();
+    }
+    let Some(key) = key else {
+        let body = "this is a WebSocket endpoint\n";
+        write!(
+            stream,
+            "HTTP/1.1 400 Bad Request\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )?;
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a WebSocket request",
+        ));
+    };
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(&key)
+    )?;
+    let path = request.split_whitespace().nth(1).unwrap_or("");
+    let format = if path.contains("format=binary") {
+        Format::Binary
+    } else {
+        Format::Json
+    };
+    Ok(Client { stream, format })
+}
+
+fn accept_key(key: &str) -> String {
+    let digest = Sha1::new()
+        .chain_update(key.as_bytes())
+        .chain_update(WEBSOCKET_GUID.as_bytes())
+        .finalize();
+    base64(&digest)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// An unmasked, unfragmented WebSocket frame.
+fn frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        len @ 0..=125 => frame.push(len as u8),
+        len @ 126..=0xffff => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Rounds to hundredths, which keeps the JSON short.
+fn round(value: f32) -> f32 {
+    (value * 100.).round() / 100.
+}
+
+pub fn encode_json(snapshot: &Snapshot, arena: f32) -> String {
+    let mut out = format!(
+        "{{\"tick\":{},\"arena\":{arena},\"species\":[",
+        snapshot.tick
+    );
+    for (i, species) in snapshot.species.iter().enumerate() {
+        let comma = if i == 0 { "" } else { "," };
+        let _ = write!(out, "{comma}\"{species}\"");
+    }
+    out.push_str("],\"microbes\":[");
+    for (i, microbe) in snapshot.microbes.iter().enumerate() {
+        let species = snapshot
+            .species
+            .iter()
+            .position(|s| *s == microbe.species)
+            .unwrap_or(0);
+        let [r, g, b, _] = microbe.color.to_array();
+        let comma = if i == 0 { "" } else { "," };
+        let _ = write!(
+            out,
+            "{comma}[{},{},{},{},{species},\"#{r:02x}{g:02x}{b:02x}\"]",
+            round(microbe.x),
+            round(microbe.y),
+            round(microbe.rotation),
+            round(microbe.energy),
+        );
+    }
+    out.push_str("]}");
+    out
+}
+
+pub fn encode_binary(snapshot: &Snapshot, arena: f32) -> Vec<u8> {
+    let mut out = vec![BINARY_VERSION];
+    out.extend_from_slice(&snapshot.tick.to_le_bytes());
+    out.extend_from_slice(&arena.to_le_bytes());
+    out.extend_from_slice(&(snapshot.species.len() as u16).to_le_bytes());
+    for species in &snapshot.species {
+        out.extend_from_slice(species.as_bytes());
+    }
+    out.extend_from_slice(&(snapshot.microbes.len() as u32).to_le_bytes());
+    for microbe in &snapshot.microbes {
+        for value in [microbe.x, microbe.y, microbe.rotation, microbe.energy] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out.extend_from_slice(&microbe.color.to_array());
+        let species = snapshot
+            .species
+            .iter()
+            .position(|s| *s == microbe.species)
+            .unwrap_or(0);
+        out.extend_from_slice(&(species as u16).to_le_bytes());
+    }
+    out
+}
+
+/// Reads a message written by [`encode_binary`]. The stream doesn't carry
+/// microbe ids, lineages or birth ticks, so those are left empty.
+pub fn decode_binary(data: &[u8]) -> io::Result<Frame> {
+    let mut data = data;
+    let mut take = |len: usize| match data.split_at_checked(len) {
+        Some((taken, rest)) => {
+            data = rest;
+            Ok(taken)
+        }
+        None => Err(invalid("message ends early".to_owned())),
+    };
+    let version = take(1)?[0];
+    if version != BINARY_VERSION {
+        return Err(invalid(format!("unsupported stream version {version}")));
+    }
+    let tick = u64::from_le_bytes(take(8)?.try_into().unwrap_or_default());
+    let f32 = |bytes: &[u8]| f32::from_le_bytes(bytes.try_into().unwrap_or_default());
+    let arena = f32(take(4)?);
+    let species_count = u16::from_le_bytes(take(2)?.try_into().unwrap_or_default());
+    let species = (0..species_count)
+        .map(|_| Ok(Uuid::from_slice(take(16)?).unwrap_or_default()))
+        .collect::<io::Result<Vec<_>>>()?;
+    let microbe_count = u32::from_le_bytes(take(4)?.try_into().unwrap_or_default());
+    let mut microbes = Vec::new();
+    for _ in 0..microbe_count {
+        let bytes = take(22)?;
+        let [r, g, b, a] = [bytes[16], bytes[17], bytes[18], bytes[19]];
+        let index = u16::from_le_bytes([bytes[20], bytes[21]]) as usize;
+        microbes.push(MicrobeState {
+            id: Uuid::nil(),
+            lineage: Uuid::nil(),
+            species: species.get(index).copied().unwrap_or_default(),
+            x: f32(&bytes[0..4]),
+            y: f32(&bytes[4..8]),
+            rotation: f32(&bytes[8..12]),
+            energy: f32(&bytes[12..16]),
+            stamina: 0.,
+            spiky: false,
+            jaws: Default::default(),
+            eyes: Default::default(),
+            cloaked: false,
+            fat: 0.,
+            cooldown: 0,
+            caste: None,
+            color: Color32::from_rgba_premultiplied(r, g, b, a),
+            born: 0,
+        });
+    }
+    Ok(Frame {
+        snapshot: Snapshot {
+            tick,
+            species,
+            microbes,
+            ..Snapshot::default()
+        },
+        arena,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color32, Simulation};
+    use std::io::Read;
+    use std::time::Instant;
+
+    fn sample() -> Snapshot {
+        let mut sim = Simulation::new(100.).unwrap();
+        let idle = sim.add_species("new_controls()");
+        sim.spawn(idle, 1.234, -5., 0.5, Color32::from_rgb(255, 128, 0));
+        sim.snapshot()
+    }
+
+    #[test]
+    fn test_accept_key() {
+        // The example from RFC 6455, section 1.3.
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(base64(b"a"), "YQ==");
+    }
+
+    #[test]
+    fn test_encodings() {
+        let snapshot = sample();
+        let json = encode_json(&snapshot, 100.);
+        let value = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(value["tick"], 0);
+        assert_eq!(value["species"][0], snapshot.species[0].to_string());
+        let microbe = value["microbes"][0].as_array().unwrap();
+        let numbers = microbe[..5].iter().map(|v| v.as_f64().unwrap());
+        assert_eq!(numbers.collect::<Vec<_>>(), vec![1.23, -5., 0.5, 100., 0.]);
+        assert_eq!(microbe[5], "#ff8000");
+
+        let binary = encode_binary(&snapshot, 100.);
+        assert_eq!(binary.len(), 1 + 8 + 4 + 2 + 16 + 4 + 22);
+        assert_eq!(binary[0], BINARY_VERSION);
+        assert_eq!(&binary[binary.len() - 6..], &[255, 128, 0, 255, 0, 0]);
+
+        let frame = decode_binary(&binary).unwrap();
+        assert_eq!(frame.arena, 100.);
+        assert_eq!(frame.snapshot.species, snapshot.species);
+        let (sent, received) = (snapshot.microbes[0], frame.snapshot.microbes[0]);
+        assert_eq!(
+            (received.x, received.y, received.color),
+            (sent.x, sent.y, sent.color)
+        );
+        assert_eq!(received.species, sent.species);
+        assert!(decode_binary(&binary[..binary.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_frame_lengths() {
+        assert_eq!(frame(0x1, b"hi"), vec![0x81, 2, b'h', b'i']);
+        assert_eq!(&frame(0x2, &[0; 300])[..4], &[0x82, 126, 1, 44]);
+        assert_eq!(frame(0x2, &[0; 70_000])[1], 127);
+    }
+
+    #[test]
+    fn test_spectator() {
+        let broadcaster = Broadcaster::serve("127.0.0.1:0", 100.).unwrap();
+        let spectator = Spectator::connect(broadcaster.addr()).unwrap();
+        let started = Instant::now();
+        while broadcaster.clients() == 0 && started.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let mut snapshot = sample();
+        broadcaster.broadcast(&snapshot);
+        snapshot.tick = 1;
+        broadcaster.broadcast(&snapshot);
+        let mut frame = None;
+        while frame.as_ref().is_none_or(|f: &Frame| f.snapshot.tick < 1)
+            && started.elapsed() < Duration::from_secs(5)
+        {
+            frame = spectator.latest().or(frame);
+            thread::sleep(Duration::from_millis(10));
+        }
+        let frame = frame.unwrap();
+        assert_eq!(frame.snapshot.tick, 1);
+        assert_eq!(frame.snapshot.microbes.len(), 1);
+        assert!(spectator.connected());
+        assert_eq!(spectator.latest(), None);
+
+        drop(broadcaster);
+        assert!(Spectator::connect("127.0.0.1:1").is_err());
+    }
+
+    #[test]
+    fn test_streams_to_clients() {
+        let broadcaster = Broadcaster::serve("127.0.0.1:0", 100.).unwrap();
+        let mut client = TcpStream::connect(broadcaster.addr()).unwrap();
+        write!(
+            client,
+            "GET /?format=json HTTP/1.1\r\nHost: test\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n"
+        )
+        .unwrap();
+        let started = Instant::now();
+        while broadcaster.clients() == 0 && started.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let snapshot = sample();
+        broadcaster.broadcast(&snapshot);
+        let mut reader = BufReader::new(client);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert!(line.starts_with("HTTP/1.1 101"));
+        while line != "\r\n" {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+        }
+        let mut header = [0; 2];
+        reader.read_exact(&mut header).unwrap();
+        assert_eq!(header[0], 0x81);
+        let len = header[1] as usize;
+        assert!(len < 126);
+        let mut payload = vec![0; len];
+        reader.read_exact(&mut payload).unwrap();
+        assert_eq!(
+            String::from_utf8(payload).unwrap(),
+            encode_json(&snapshot, 100.)
+        );
+    }
+}
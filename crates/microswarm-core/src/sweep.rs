@@ -0,0 +1,394 @@
+//! Parameter sweeps over the simulation constants.
+//!
+//! A [`Sweep`] varies one or more [`Tuning`] fields, runs seeded headless
+//! simulations of a config at every point, and returns a tidy dataset with
+//! one row per point, replicate and species. Points either cover a grid of
+//! evenly spaced values or are drawn at random from each axis' range:
+//!
+//! ```text
+//! eat_damage=10..50:5          10, 20, 30, 40 and 50
+//! speed=1..2                   anywhere between 1 and 2 when sampling
+//! ```
+//!
+//! Every point replays the same seeds, so differences between points come
+//! from the constants rather than luck. [`write`] saves the rows as CSV or
+//! JSON Lines, with a column per axis followed by:
+//!
+//! | field       | meaning                                             |
+//! |-------------|-----------------------------------------------------|
+//! | `point`     | index of the point, counting from 0                 |
+//! | `seed`      | seed of the replicate                               |
+//! | `species`   | species name                                        |
+//! | `survivors` | living microbes at the end                          |
+//! | `biomass`   | total energy of the living microbes at the end      |
+//! | `kills`     | deaths of other microbes the species took a bite in |
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::config::Config;
+use crate::metrics::{csv_field, Format};
+use crate::tournament;
+use crate::tuning::Tuning;
+
+/// A tuning field and the values it takes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Axis {
+    pub name: String,
+    pub min: f32,
+    pub max: f32,
+    /// Evenly spaced values in a grid, including both ends.
+    pub steps: usize,
+}
+
+impl Axis {
+    /// The grid values along the axis.
+    pub fn values(&self) -> Vec<f32> {
+        match self.steps {
+            0 => Vec::new(),
+            1 => vec![self.min],
+            steps => (0..steps)
+                .map(|i| self.min + (self.max - self.min) * i as f32 / (steps - 1) as f32)
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for Axis {
+    type Err = String;
+
+    /// Parses `NAME=MIN..MAX:STEPS`, `NAME=MIN..MAX` (one step per unit) or
+    /// `NAME=VALUE`.
+    fn from_str(text: &str) -> Result<Self, String> {
+        let error = || format!("expected NAME=MIN..MAX:STEPS, got '{text}'");
+        let (name, range) = text.split_once('=').ok_or_else(error)?;
+        let name = name.trim();
+        if !Tuning::FIELDS.contains(&name) {
+            return Err(format!(
+                "unknown tuning field '{name}', expected one of {}",
+                Tuning::FIELDS.join(", ")
+            ));
+        }
+        let (range, steps) = match range.split_once(':') {
+            Some((range, steps)) => (range, Some(steps.trim().parse().map_err(|_| error())?)),
+            None => (range, None),
+        };
+        let number = |text: &str| text.trim().parse::<f32>().map_err(|_| error());
+        let (min, max) = match range.split_once("..") {
+            Some((min, max)) => (number(min)?, number(max)?),
+            None => (number(range)?, number(range)?),
+        };
+        if min > max {
+            return Err(error());
+        }
+        Ok(Self {
+            name: name.to_owned(),
+            min,
+            max,
+            steps: steps.unwrap_or((max - min).floor() as usize + 1),
+        })
+    }
+}
+
+/// How a sweep picks its points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sampling {
+    /// Every combination of the axes' grid values.
+    Grid,
+    /// This many points, each value drawn uniformly from its axis' range.
+    Random(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    /// Arena, species, base tuning and seed for every run.
+    pub config: Config,
+    pub axes: Vec<Axis>,
+    pub sampling: Sampling,
+    /// Seeded runs per point.
+    pub replicates: usize,
+    pub ticks: u64,
+    /// Threads to run on, or 0 for one per core.
+    pub jobs: usize,
+}
+
+/// One species' result in one run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub point: usize,
+    /// The axes' values, in the same order as [`Sweep::axes`].
+    pub values: Vec<f32>,
+    pub seed: u64,
+    pub species: String,
+    pub survivors: usize,
+    pub biomass: f32,
+    pub kills: usize,
+}
+
+impl Sweep {
+    /// The values of every point, in the same order as the axes.
+    pub fn points(&self) -> Vec<Vec<f32>> {
+        match self.sampling {
+            Sampling::Grid => self.axes.iter().fold(vec![Vec::new()], |points, axis| {
+                points
+                    .into_iter()
+                    .flat_map(|point| {
+                        axis.values().into_iter().map(move |value| {
+                            let mut point = point.clone();
+                            point.push(value);
+                            point
+                        })
+                    })
+                    .collect()
+            }),
+            Sampling::Random(count) => {
+                let rng = &mut StdRng::seed_from_u64(self.seed());
+                (0..count)
+                    .map(|_| {
+                        self.axes
+                            .iter()
+                            .map(|axis| match axis.min < axis.max {
+                                true => rng.gen_range(axis.min..=axis.max),
+                                false => axis.min,
+                            })
+                            .collect()
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Runs every point and replicate and returns the rows, ordered by
+    /// point, then replicate, then species in config order.
+    pub fn run(&self) -> Result<Vec<Row>, String> {
+        let seed = self.seed();
+        let runs = self
+            .points()
+            .into_iter()
+            .enumerate()
+            .flat_map(|(point, values)| {
+                (0..self.replicates as u64)
+                    .map(move |r| (point, values.clone(), seed.wrapping_add(r)))
+            })
+            .collect::<Vec<_>>();
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.jobs)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let results = pool.install(|| {
+            runs.par_iter()
+                .map(|(point, values, seed)| self.play(*point, values, *seed))
+                .collect::<Result<Vec<_>, String>>()
+        })?;
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    fn seed(&self) -> u64 {
+        // A sweep without a seed still has to replay it at every point.
+        self.config.seed.unwrap_or_default()
+    }
+
+    fn play(&self, point: usize, values: &[f32], seed: u64) -> Result<Vec<Row>, String> {
+        let mut config = Config {
+            seed: Some(seed),
+            ..self.config.clone()
+        };
+        for (axis, value) in self.axes.iter().zip(values) {
+            if let Some(field) = config.tuning.field_mut(&axis.name) {
+                *field = *value;
+            }
+        }
+        let scores = tournament::play(&config, self.ticks)?;
+        Ok(config
+            .species
+            .iter()
+            .zip(scores)
+            .map(|(species, score)| Row {
+                point,
+                values: values.to_vec(),
+                seed,
+                species: species.name.clone(),
+                survivors: score.survivors,
+                biomass: score.biomass,
+                kills: score.kills,
+            })
+            .collect())
+    }
+}
+
+/// Writes `rows` as CSV with a header line, or as JSON Lines.
+pub fn write(out: &mut impl Write, axes: &[Axis], rows: &[Row], format: Format) -> io::Result<()> {
+    if format == Format::Csv {
+        let mut header = axes.iter().map(|a| a.name.as_str()).collect::<Vec<_>>();
+        header.extend(["point", "seed", "species", "survivors", "biomass", "kills"]);
+        writeln!(out, "{}", header.join(","))?;
+    }
+    for row in rows {
+        match format {
+            Format::Csv => {
+                for value in &row.values {
+                    write!(out, "{value},")?;
+                }
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{}",
+                    row.point,
+                    row.seed,
+                    csv_field(&row.species),
+                    row.survivors,
+                    row.biomass,
+                    row.kills
+                )?;
+            }
+            Format::Json => {
+                serde_json::to_writer(&mut *out, &JsonRow { axes, row })?;
+                writeln!(out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A row as a JSON object with its columns in CSV order.
+struct JsonRow<'a> {
+    axes: &'a [Axis],
+    row: &'a Row,
+}
+
+impl Serialize for JsonRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let row = self.row;
+        let mut map = serializer.serialize_map(Some(self.axes.len() + 6))?;
+        for (axis, value) in self.axes.iter().zip(&row.values) {
+            map.serialize_entry(&axis.name, value)?;
+        }
+        map.serialize_entry("point", &row.point)?;
+        map.serialize_entry("seed", &row.seed)?;
+        map.serialize_entry("species", &row.species)?;
+        map.serialize_entry("survivors", &row.survivors)?;
+        map.serialize_entry("biomass", &row.biomass)?;
+        map.serialize_entry("kills", &row.kills)?;
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Channel, SpeciesConfig};
+    use crate::scripts;
+
+    fn sweep(sampling: Sampling) -> Sweep {
+        let species = |name: &str, script: String| SpeciesConfig {
+            name: name.to_owned(),
+            script,
+            count: 10,
+            color: [Channel::Fixed(255); 3],
+            remote: None,
+            producer: false,
+            spiky: false,
+            jaws: Default::default(),
+            eyes: Default::default(),
+            mutation: Default::default(),
+            cooldown: Default::default(),
+            castes: Vec::new(),
+        };
+        Sweep {
+            config: Config {
+                arena: 30.,
+                arena_height: None,
+                shape: Default::default(),
+                climate: Default::default(),
+                wells: Vec::new(),
+                trickle: None,
+                energy_cap: None,
+                population_cap: None,
+                disruptions: Vec::new(),
+                standoff: Default::default(),
+                seed: Some(7),
+                tuning: Tuning {
+                    eat_transfer: 0.,
+                    ..Tuning::default()
+                },
+                sandbox: Default::default(),
+                math: Default::default(),
+                tick_duration: crate::DELTA_TIME,
+                rules: None,
+                islands: None,
+                species: vec![
+                    species("hunter", scripts::aggressive_hunter_script()),
+                    species("idle", "new_controls()".to_owned()),
+                ],
+            },
+            axes: vec!["eat_damage=0..60:2".parse().unwrap()],
+            sampling,
+            replicates: 2,
+            ticks: 20,
+            jobs: 1,
+        }
+    }
+
+    #[test]
+    fn test_parse_axis() {
+        let axis = "eat_damage=10..50:5".parse::<Axis>().unwrap();
+        assert_eq!(axis.values(), [10., 20., 30., 40., 50.]);
+        assert_eq!("speed=1..3".parse::<Axis>().unwrap().values(), [1., 2., 3.]);
+        assert_eq!("speed=2".parse::<Axis>().unwrap().values(), [2.]);
+        assert!("food_rate=1..2".parse::<Axis>().is_err());
+        assert!("speed=3..1".parse::<Axis>().is_err());
+        assert!("speed".parse::<Axis>().is_err());
+    }
+
+    #[test]
+    fn test_points() {
+        let mut grid = sweep(Sampling::Grid);
+        grid.axes.push("speed=1..2:3".parse().unwrap());
+        let points = grid.points();
+        assert_eq!(points.len(), 6);
+        assert_eq!(points[0], [0., 1.]);
+        assert_eq!(points[5], [60., 2.]);
+
+        let mut sweep = sweep(Sampling::Random(4));
+        let points = sweep.points();
+        assert_eq!(points.len(), 4);
+        assert!(points.iter().all(|p| (0. ..=60.).contains(&p[0])));
+        assert_eq!(points, sweep.points());
+        sweep.axes.clear();
+        assert_eq!(sweep.points(), vec![Vec::<f32>::new(); 4]);
+    }
+
+    #[test]
+    fn test_run() {
+        let sweep = sweep(Sampling::Grid);
+        let rows = sweep.run().unwrap();
+        // Two points, two replicates, two species.
+        assert_eq!(rows.len(), 8);
+        assert_eq!(rows[0].species, "hunter");
+        assert_eq!((rows[0].seed, rows[2].seed), (7, 8));
+        // Without damage nobody gets eaten.
+        assert!(rows[..4].iter().all(|r| r.kills == 0));
+        assert!(rows[4..].iter().any(|r| r.kills > 0));
+
+        let mut out = Vec::new();
+        write(&mut out, &sweep.axes, &rows, Format::Csv).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 9);
+        assert_eq!(
+            lines[0],
+            "eat_damage,point,seed,species,survivors,biomass,kills"
+        );
+        assert!(lines[1].starts_with("0,0,7,hunter,"));
+
+        let mut out = Vec::new();
+        write(&mut out, &sweep.axes, &rows, Format::Json).unwrap();
+        let row: serde_json::Value =
+            serde_json::from_str(String::from_utf8(out).unwrap().lines().last().unwrap()).unwrap();
+        assert_eq!(row["eat_damage"], 60.);
+        assert_eq!(row["species"], "idle");
+    }
+}
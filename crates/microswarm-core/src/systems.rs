@@ -0,0 +1,835 @@
+//! The stages of a world update.
+//!
+//! A microbe is a bundle of components: its [`Transform`], its energy, and
+//! the species script that acts as its brain. Each tick [`World::update`]
+//! runs these systems over them in order:
+//!
+//! 1. [`sense`] counts the other lineages around every microbe, against the
+//!    world as it was at the start of the tick.
+//! 2. [`World::think`] runs each microbe's brain or script on what it
+//!    sensed, unless [`World::update_with`] decides for them.
+//! 3. [`combat`] settles who bites whom.
+//! 4. [`World::act`] moves microbes and [`Bites::feed`] moves energy
+//!    between them, up to any [`crate::overflow`] cap.
+//! 5. [`World::reproduce`] splits microbes with enough energy,
+//!    [`World::bury`] removes the ones that ran out, and [`World::cull`]
+//!    any past the population cap.
+//! 6. [`World::trickle_in`] spawns a wanderer at the edge when one is due.
+//!
+//! [`Transform`]: crate::microbe::Transform
+
+use ecolor::Color32;
+use rand::Rng;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::f32::consts::{PI, TAU};
+use uuid::Uuid;
+
+use crate::climate::Climate;
+use crate::controls::Controls;
+use crate::disruption::{Disruption, Scheduled};
+use crate::error::SimError;
+use crate::events::{Cause, Event};
+use crate::jaws;
+use crate::math::Math;
+use crate::microbe::{Death, Microbe};
+use crate::population::PopulationCap;
+use crate::quadtree::{Locatable, QuadTree};
+use crate::random;
+use crate::sandbox;
+use crate::shape::Shape;
+use crate::simulation::DELTA_TIME;
+use crate::standoff::{Bite, Standoff};
+use crate::trickle::{self, Trickle};
+use crate::tuning::Tuning;
+use crate::world::{Decide, World};
+
+/// Wanderers from a [`crate::trickle`] are grey.
+const WANDERER_COLOR: Color32 = Color32::from_rgb(160, 160, 160);
+
+/// Front, left, right and back, relative to a microbe's heading.
+const DIRECTIONS: [f32; 4] = [0., -PI * 0.5, PI * 0.5, PI];
+
+/// What a microbe can see this tick.
+#[derive(Debug, Clone)]
+pub struct Senses {
+    /// Microbes of other lineages within close range, in front, left, right
+    /// and back order.
+    pub close: [i64; 4],
+    /// Microbes of other lineages within far range, in the same order.
+    pub far: [i64; 4],
+    /// Distance to the arena's wall straight ahead.
+    pub wall: f32,
+    /// How warm it is where the microbe is, from -1 to 1. See
+    /// [`crate::climate`].
+    pub temperature: f32,
+    /// Radians from its heading to the middle of its own lineage's other
+    /// microbes within far range, negative to the left. 0 with none.
+    pub allies_center_bearing: f32,
+    /// How spread out those microbes are: their average distance from their
+    /// middle. 0 with none.
+    pub allies_cohesion: f32,
+    /// Microbes within its [`crate::jaws`], which it can bite.
+    pub(crate) edible: Vec<Uuid>,
+}
+
+pub(crate) fn sense(
+    frozen: &QuadTree<Microbe>,
+    microbe: &Microbe,
+    tuning: &Tuning,
+    math: Math,
+    size: [f32; 2],
+    shape: &Shape,
+    climate: Climate,
+) -> Senses {
+    let position = microbe.transform.position;
+    let look_with = |direction: f32, half_angle: f32, range: f32| {
+        let angle = microbe.transform.rotation + direction;
+        World::get_nearby_microbes(frozen, microbe, angle, half_angle, range, math)
+    };
+    let look = |direction: f32, range: f32| look_with(direction, jaws::HALF_ANGLE, range);
+    let (jaws, eyes) = (microbe.jaws, microbe.eyes);
+    let (allies_center_bearing, allies_cohesion) =
+        allies(frozen, microbe, eyes.range(tuning.detect_range_far), math);
+    Senses {
+        close: DIRECTIONS.map(|d| look(d, tuning.detect_range_close).len() as i64),
+        // Cloaked microbes can only be sensed from close by.
+        far: DIRECTIONS.map(|d| {
+            look_with(d, eyes.half_angle(), eyes.range(tuning.detect_range_far))
+                .iter()
+                .filter(|m| {
+                    let close = tuning.detect_range_close * tuning.detect_range_close;
+                    !m.cloaked || m.location().distance_squared(microbe.location()) <= close
+                })
+                .count() as i64
+        }),
+        wall: shape.wall_distance(
+            size,
+            position.x,
+            position.y,
+            microbe.transform.rotation,
+            math,
+        ),
+        temperature: climate.temperature(size, position.x, position.y, math),
+        allies_center_bearing,
+        allies_cohesion,
+        edible: look_with(
+            DIRECTIONS[0],
+            jaws.half_angle(),
+            tuning.detect_range_close * jaws.reach,
+        )
+        .iter()
+        .map(|m| m.id)
+        .collect(),
+    }
+}
+
+/// The bearing to the middle of `microbe`'s lineage within `range`, and
+/// their average distance from it, from a single look all around.
+fn allies(frozen: &QuadTree<Microbe>, microbe: &Microbe, range: f32, math: Math) -> (f32, f32) {
+    let allies = frozen
+        .query_circle(microbe.location(), range)
+        .into_iter()
+        .filter(|m| m.id != microbe.id && m.lineage == microbe.lineage)
+        .map(|m| m.transform.position)
+        .collect::<Vec<_>>();
+    if allies.is_empty() {
+        return (0., 0.);
+    }
+    let count = allies.len() as f32;
+    let x = allies.iter().map(|p| p.x).sum::<f32>() / count;
+    let y = allies.iter().map(|p| p.y).sum::<f32>() / count;
+    let spread = allies
+        .iter()
+        .map(|p| ((p.x - x) * (p.x - x) + (p.y - y) * (p.y - y)).sqrt())
+        .sum::<f32>()
+        / count;
+    // Turn the offset into the microbe's frame, so straight ahead is 0.
+    let (dx, dy) = (
+        x - microbe.transform.position.x,
+        y - microbe.transform.position.y,
+    );
+    let (sin, cos) = math.sin_cos(microbe.transform.rotation);
+    let bearing = math.atan2(dy * cos - dx * sin, dx * cos + dy * sin);
+    (bearing, spread)
+}
+
+/// Bites settled by [`combat`].
+#[derive(Default)]
+pub(crate) struct Bites {
+    /// Victim id to the species of each microbe that bit it.
+    pub eaten: HashMap<Uuid, Vec<Uuid>>,
+    /// Eater id to the energy its bites gained it.
+    pub gained: HashMap<Uuid, f32>,
+    /// Victim id to the energy bitten out of it.
+    pub lost: HashMap<Uuid, f32>,
+    /// Eater id to the energy spiky victims cost it.
+    pub pricked: HashMap<Uuid, f32>,
+    pub events: Vec<Event>,
+}
+
+/// A microbe that wants to eat bites everything edible in front of it. If
+/// the other microbe is eating too, the world's [`Standoff`] settles whether
+/// the bite lands. Eating without the stamina to bite neither bites nor
+/// makes a standoff.
+///
+/// Each bite moves [`Tuning::eat_transfer`] of the victim's energy to the
+/// eater, shared out so a crowd can't take more than all of it. Producers
+/// take only [`Tuning::producer_handicap`] of that, and each spiky victim
+/// costs its eater [`Tuning::spike_reflect`] of the bite. With no transfer,
+/// each bite costs the victim a flat [`Tuning::eat_damage`] instead, and
+/// eating gains that much however many bites were taken.
+pub(crate) fn combat(
+    microbes: &BTreeMap<Uuid, Microbe>,
+    decisions: &BTreeMap<Uuid, (Controls, Senses)>,
+    tuning: &Tuning,
+    producers: &HashSet<Uuid>,
+    standoff: Standoff,
+    rng: &mut impl Rng,
+) -> Bites {
+    let mut bites = Bites::default();
+    let mut bitten = BTreeMap::<Uuid, Vec<(&Microbe, bool)>>::new();
+    // Whether the lower id of each pair in a standoff won its toss.
+    let mut tosses = HashMap::new();
+    for (id, (controls, senses)) in decisions {
+        let Some(eater) = microbes.get(id) else {
+            continue;
+        };
+        if !eater.can_bite(controls, tuning) {
+            continue;
+        }
+        for edible in &senses.edible {
+            let (Some((edible_controls, _)), Some(victim)) =
+                (decisions.get(edible), microbes.get(edible))
+            else {
+                continue;
+            };
+            let bite = if victim.can_bite(edible_controls, tuning) {
+                let lower = eater.id < victim.id;
+                let won_toss = standoff == Standoff::Random && {
+                    let pair = if lower {
+                        (eater.id, victim.id)
+                    } else {
+                        (victim.id, eater.id)
+                    };
+                    *tosses.entry(pair).or_insert_with(|| rng.gen_bool(0.5)) == lower
+                };
+                standoff.settle(eater, victim, won_toss)
+            } else {
+                Bite::Feeds
+            };
+            if bite == Bite::Misses {
+                continue;
+            }
+            bites
+                .eaten
+                .entry(*edible)
+                .or_default()
+                .push(eater.script_id);
+            bitten
+                .entry(*edible)
+                .or_default()
+                .push((eater, bite == Bite::Feeds));
+            bites.events.push(Event::MicrobeAte {
+                eater: eater.id,
+                eater_species: eater.script_id,
+                victim: victim.id,
+                victim_species: victim.script_id,
+            });
+        }
+    }
+    let handicap = |eater: &Microbe| {
+        if producers.contains(&eater.script_id) {
+            tuning.producer_handicap
+        } else {
+            1.
+        }
+    };
+    for (victim, eaters) in bitten {
+        let victim = &microbes[&victim];
+        let shares = eaters
+            .iter()
+            .map(|(eater, _)| tuning.eat_transfer * handicap(eater))
+            .collect::<Vec<_>>();
+        let crowd = shares.iter().sum::<f32>().max(1.);
+        for ((eater, feeds), share) in eaters.into_iter().zip(shares) {
+            let bite = if tuning.eat_transfer > 0. {
+                victim.energy.max(0.) * share / crowd
+            } else {
+                tuning.eat_damage * handicap(eater)
+            };
+            if feeds {
+                let gained = bites.gained.entry(eater.id).or_insert(0.);
+                if tuning.eat_transfer > 0. {
+                    *gained += bite;
+                } else {
+                    *gained = bite;
+                }
+            }
+            *bites.lost.entry(victim.id).or_insert(0.) += bite;
+            if victim.spiky {
+                *bites.pricked.entry(eater.id).or_insert(0.) += bite * tuning.spike_reflect;
+            }
+        }
+    }
+    bites
+}
+
+impl Bites {
+    /// Settles what a microbe's bites gained it, what it lost to being
+    /// bitten and what spikes cost it.
+    pub(crate) fn feed(&self, microbe: &mut Microbe) {
+        let energy = |amounts: &HashMap<Uuid, f32>| amounts.get(&microbe.id).copied().unwrap_or(0.);
+        microbe.energy += energy(&self.gained) - energy(&self.lost) - energy(&self.pricked);
+    }
+}
+
+impl World {
+    /// Moves a microbe as its controls say, as fast and at the energy cost
+    /// the climate where it starts makes it, keeping it inside the arena's
+    /// shape and on the grid of [`Math::Fixed`]. Producers move only
+    /// [`Tuning::producer_handicap`] as fast. [`crate::wells`] pull it
+    /// along whatever it does.
+    pub(crate) fn act(&self, microbe: &mut Microbe, controls: Option<&Controls>, delta_time: f32) {
+        let (size, math) = (self.arena_size(), self.math);
+        let position = microbe.transform.position;
+        if let Some(controls) = controls {
+            let warmth = self.climate.factor(
+                size,
+                position.x,
+                position.y,
+                math,
+                self.tuning.temperature_effect,
+            );
+            let mut pace = if self.is_producer(microbe.script_id) {
+                self.tuning.producer_handicap
+            } else {
+                1.
+            };
+            if microbe.cooldown > 0 {
+                pace *= self.cooldown(microbe.script_id).speed;
+            }
+            microbe.update(controls, &self.tuning, math, delta_time, warmth, pace);
+        }
+        let scale = delta_time / DELTA_TIME;
+        for well in &self.wells {
+            let position = &mut microbe.transform.position;
+            let (dx, dy) = well.pull(position.x, position.y, scale, math);
+            position.x += dx;
+            position.y += dy;
+        }
+        let position = &mut microbe.transform.position;
+        let (x, y) = self.shape.clamp(size, position.x, position.y);
+        position.x = math.position(x);
+        position.y = math.position(y);
+    }
+
+    /// Gives a producer standing still without eating its share of the
+    /// light, [`Tuning::photosynthesis`] split with the other microbes close
+    /// around it, scaled to the tick's length.
+    pub(crate) fn photosynthesize(
+        &self,
+        microbe: &mut Microbe,
+        decision: Option<&(Controls, Senses)>,
+        delta_time: f32,
+    ) {
+        let Some((controls, senses)) = decision else {
+            return;
+        };
+        if !self.is_producer(microbe.script_id) || controls.forward || controls.back || controls.eat
+        {
+            return;
+        }
+        let crowd = senses.close.iter().sum::<i64>() as f32;
+        let famine = if self.in_famine() { 0.5 } else { 1. };
+        let scale = delta_time / DELTA_TIME;
+        microbe.energy += self.tuning.photosynthesis * scale * famine / (1. + crowd);
+    }
+
+    /// Decides what every microbe does: brains answer for all their microbes
+    /// at once, scripts run one microbe at a time, and then plugins can
+    /// override the result. `decide`, if given, answers for every microbe
+    /// instead of brains and scripts.
+    pub(crate) fn think(
+        &mut self,
+        microbes: &BTreeMap<Uuid, Microbe>,
+        senses: BTreeMap<Uuid, Senses>,
+        mut decide: Option<&mut Decide>,
+    ) -> Result<BTreeMap<Uuid, (Controls, Senses)>, SimError> {
+        let mut decided = HashMap::new();
+        let mut species = self.brains.keys().copied().collect::<Vec<_>>();
+        species.sort();
+        if decide.is_some() {
+            species.clear();
+        }
+        for species in species {
+            let batch = microbes
+                .values()
+                .filter(|microbe| microbe.script_id == species)
+                .filter_map(|microbe| Some((microbe.state(), senses.get(&microbe.id)?)))
+                .collect::<Vec<_>>();
+            let Some(brain) = self.brains.get_mut(&species) else {
+                continue;
+            };
+            if batch.is_empty() {
+                continue;
+            }
+            let controls = brain.think_all(&batch);
+            for ((microbe, _), controls) in batch.iter().zip(controls) {
+                decided.insert(microbe.id, controls);
+            }
+        }
+
+        let mut decisions = BTreeMap::new();
+        for (id, senses) in senses {
+            let Some(microbe) = microbes.get(&id) else {
+                continue;
+            };
+            let mut controls = match (decided.remove(&id), &mut decide) {
+                (_, Some(decide)) => decide(&microbe.state(), &senses),
+                (Some(controls), None) => controls,
+                // Microbes a brain didn't answer for sit still.
+                (None, None) if self.brains.contains_key(&microbe.script_id) => Controls::default(),
+                (None, None) => self.run_script(microbe, &senses)?,
+            };
+            if !self.plugins.is_empty() {
+                let state = microbe.state();
+                for plugin in &mut self.plugins {
+                    plugin.after_sense(&state, &mut controls);
+                }
+            }
+            decisions.insert(id, (controls, senses));
+        }
+        Ok(decisions)
+    }
+
+    fn run_script(&mut self, microbe: &Microbe, senses: &Senses) -> Result<Controls, SimError> {
+        let names = [
+            [
+                "sense_front_close",
+                "sense_left_close",
+                "sense_right_close",
+                "sense_back_close",
+            ],
+            ["sense_front", "sense_left", "sense_right", "sense_back"],
+        ];
+        for (names, counts) in names.iter().zip([senses.close, senses.far]) {
+            for (name, count) in names.iter().zip(counts) {
+                self.engine.register_fn(*name, move || count);
+            }
+        }
+        let energy = microbe.energy;
+        self.engine.register_fn("energy", move || energy);
+        let stamina = microbe.stamina;
+        self.engine.register_fn("stamina", move || stamina);
+        let wall = f64::from(senses.wall);
+        self.engine.register_fn("sense_wall", move || wall);
+        let temperature = f64::from(senses.temperature);
+        self.engine.register_fn("temperature", move || temperature);
+        let bearing = f64::from(senses.allies_center_bearing);
+        self.engine
+            .register_fn("allies_center_bearing", move || bearing);
+        let cohesion = f64::from(senses.allies_cohesion);
+        self.engine.register_fn("allies_cohesion", move || cohesion);
+
+        let caste = microbe
+            .caste
+            .and_then(|caste| self.castes.get(&microbe.script_id)?.get(caste));
+        let name = caste.map(|caste| caste.name.clone()).unwrap_or_default();
+        self.engine.register_fn("caste", move || name.clone());
+
+        random::reseed_scripts(self.seed, self.tick, microbe.id);
+        let species = microbe.script_id;
+        let script = match caste.and_then(|caste| caste.script.as_ref()) {
+            Some(script) => script,
+            None => self
+                .scripts
+                .get(&species)
+                .ok_or(SimError::MissingScript(species))?,
+        };
+        match self.engine.eval::<Controls>(script) {
+            Ok(controls) => Ok(controls),
+            // The sandbox stopped it, so it sits still.
+            Err(error) if sandbox::over_limit(&error) => {
+                tracing::warn!(microbe = %microbe.id, %species, %error, "script stopped");
+                Ok(Controls::default())
+            }
+            Err(error) => Err(SimError::Script { species, error }),
+        }
+    }
+
+    /// Splits off four children if the microbe has enough energy, born into
+    /// the caste its `controls` name.
+    pub(crate) fn reproduce(
+        &mut self,
+        microbe: &mut Microbe,
+        controls: Option<&Controls>,
+        result: &mut Vec<Microbe>,
+    ) {
+        if microbe.cooldown > 0 {
+            microbe.cooldown -= 1;
+            return;
+        }
+        if microbe.energy < self.tuning.reproduction_threshold {
+            return;
+        }
+        microbe.energy -= self.tuning.health;
+        let castes = self.castes(microbe.script_id);
+        let caste = match controls.map(|c| c.spawn_as.as_str()) {
+            None | Some("") => None,
+            Some(name) => match castes.iter().position(|caste| caste.name == name) {
+                Some(index) => Some(index),
+                None => {
+                    tracing::warn!(microbe = %microbe.id, caste = name, "no such caste");
+                    None
+                }
+            },
+        };
+        let caste = caste.map(|index| (index, castes[index].clone()));
+        for _ in 0..4 {
+            let mut child = microbe.clone();
+            child.id = self.next_id();
+            child.energy = self.tuning.health * 0.25;
+            child.stamina = self.tuning.stamina;
+            child.born = self.tick;
+            if let Some((index, caste)) = &caste {
+                child.caste = Some(*index);
+                caste.apply(&mut child);
+            }
+            if let Some(mutation) = self.mutations.get(&child.script_id) {
+                let mut rng = random::for_microbe(self.seed, child.id);
+                mutation.apply(&mut child, &mut rng);
+            }
+            let state = child.state();
+            tracing::trace!(microbe = %child.id, parent = %microbe.id, species = %child.script_id, "born");
+            for plugin in &mut self.plugins {
+                plugin.on_spawn(&state);
+            }
+            self.report.events.push(Event::MicrobeBorn {
+                microbe: state,
+                parent: microbe.id,
+            });
+            result.push(child);
+        }
+        microbe.cooldown = self.cooldown(microbe.script_id).ticks;
+    }
+
+    /// Spawns a wanderer at the arena's edge if the [`crate::trickle`] has
+    /// one due this tick.
+    pub(crate) fn trickle_in(&mut self) {
+        let (Some(trickle), Some(species)) = (self.trickle, self.wanderers) else {
+            return;
+        };
+        let alive = self.microbes().filter(|m| m.script_id == species).count();
+        let every = if self.in_famine() {
+            trickle.every * 2
+        } else {
+            trickle.every
+        };
+        if !Trickle::new(every, trickle.max).is_due(self.tick, alive) {
+            return;
+        }
+        let mut rng = random::for_tick(self.seed, self.tick);
+        let (x, y, rotation) = trickle::edge(&self.shape, self.arena_size(), self.math, &mut rng);
+        self.add_microbe(x, y, rotation, species, WANDERER_COLOR);
+    }
+
+    /// Records a microbe's death, crediting whoever ate it.
+    pub(crate) fn bury(&mut self, microbe: Microbe, bites: &mut Bites) {
+        let cause = match bites.eaten.remove(&microbe.id) {
+            Some(eaters) => {
+                let killers = self.predation.entry(microbe.script_id).or_default();
+                for eater in &eaters {
+                    *killers.entry(*eater).or_insert(0) += 1;
+                }
+                Cause::Eaten { by: eaters }
+            }
+            None => Cause::Starved,
+        };
+        self.mourn(microbe, cause);
+    }
+
+    /// Culls the microbes past the world's [`crate::population`] cap.
+    pub(crate) fn cull(&mut self, cap: PopulationCap, microbes: &mut Vec<Microbe>) {
+        let victims = cap.victims(microbes, &mut random::for_tick(self.seed, self.tick));
+        if victims.is_empty() {
+            return;
+        }
+        let (culled, kept): (Vec<_>, Vec<_>) = std::mem::take(microbes)
+            .into_iter()
+            .partition(|m| victims.contains(&m.id));
+        *microbes = kept;
+        tracing::debug!(culled = culled.len(), "population capped");
+        for microbe in culled {
+            self.mourn(microbe, Cause::Culled);
+        }
+    }
+
+    /// Sets off the disruptions due by this tick on `microbes`, the world's
+    /// microbes for the next tick.
+    pub(crate) fn disrupt(&mut self, microbes: &mut Vec<Microbe>) {
+        if self.disruptions.iter().all(|s| s.tick > self.tick) {
+            return;
+        }
+        let (due, later) = std::mem::take(&mut self.disruptions)
+            .into_iter()
+            .partition::<Vec<_>, _>(|s| s.tick <= self.tick);
+        self.disruptions = later;
+        for Scheduled { disruption, .. } in due {
+            tracing::info!(%disruption, "disrupted");
+            match disruption {
+                Disruption::Meteor { x, y, radius } => {
+                    let (struck, kept): (Vec<_>, Vec<_>) =
+                        std::mem::take(microbes).into_iter().partition(|m| {
+                            let (dx, dy) = (m.transform.position.x - x, m.transform.position.y - y);
+                            dx * dx + dy * dy <= radius * radius
+                        });
+                    *microbes = kept;
+                    for microbe in struck {
+                        self.mourn(microbe, Cause::Struck);
+                    }
+                }
+                Disruption::Famine { ticks } => {
+                    self.famine_until = self.famine_until.max(self.tick + ticks + 1);
+                }
+                Disruption::Bloom { count } => {
+                    let species = self.wanderer_species();
+                    let mut rng = random::for_tick(self.seed, self.tick);
+                    for _ in 0..count {
+                        let (x, y) = self.shape.sample(self.arena_size(), &mut rng);
+                        let rotation = rng.gen_range(0. ..TAU);
+                        microbes.push(self.new_microbe(x, y, rotation, species, WANDERER_COLOR));
+                    }
+                }
+            }
+            self.report.events.push(Event::Disrupted { disruption });
+        }
+    }
+
+    /// Tells plugins and the report about a death.
+    fn mourn(&mut self, microbe: Microbe, cause: Cause) {
+        let death = Death {
+            species: microbe.script_id,
+            lifespan: self.tick - microbe.born,
+            eaten: matches!(cause, Cause::Eaten { .. }),
+        };
+        let state = microbe.state();
+        for plugin in &mut self.plugins {
+            plugin.on_death(&state, &death);
+        }
+        tracing::trace!(
+            microbe = %microbe.id,
+            species = %microbe.script_id,
+            lifespan = death.lifespan,
+            eaten = death.eaten,
+            "died"
+        );
+        self.report.events.push(Event::MicrobeDied {
+            microbe: state,
+            lifespan: death.lifespan,
+            cause,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ecolor::Color32;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn decision(eat: bool, edible: Vec<Uuid>) -> (Controls, Senses) {
+        let controls = Controls {
+            eat,
+            ..Controls::new()
+        };
+        let senses = Senses {
+            close: [0; 4],
+            far: [0; 4],
+            wall: 0.,
+            temperature: 0.,
+            allies_center_bearing: 0.,
+            allies_cohesion: 0.,
+            edible,
+        };
+        (controls, senses)
+    }
+
+    #[test]
+    fn test_combat() {
+        let species = Uuid::new_v4();
+        let [a, b, c] = [1, 2, 3].map(|n| {
+            let mut microbe = Microbe::new(Uuid::from_u128(n), 0., 0., 0., species, Color32::RED);
+            microbe.energy = n as f32 * 10.;
+            microbe
+        });
+        let microbes = BTreeMap::from([(a.id, a.clone()), (b.id, b.clone()), (c.id, c.clone())]);
+
+        // A fasting microbe is bitten by anyone, but an eating one only by
+        // microbes with more energy.
+        let decisions = BTreeMap::from([
+            (a.id, decision(true, vec![b.id])),
+            (b.id, decision(true, vec![a.id])),
+            (c.id, decision(false, vec![a.id])),
+        ]);
+        let tuning = Tuning::default();
+        let none = HashSet::new();
+        let rng = &mut StdRng::seed_from_u64(0);
+        let bites = combat(
+            &microbes,
+            &decisions,
+            &tuning,
+            &none,
+            Standoff::Stronger,
+            rng,
+        );
+        assert_eq!(bites.eaten.keys().collect::<Vec<_>>(), vec![&a.id]);
+        assert_eq!(bites.gained.keys().collect::<Vec<_>>(), vec![&b.id]);
+        assert_eq!(bites.events.len(), 1);
+
+        // The bite moves a share of a's energy over to b.
+        let bite = a.energy * tuning.eat_transfer;
+        let (mut fed, mut bitten) = (b.clone(), a.clone());
+        bites.feed(&mut fed);
+        bites.feed(&mut bitten);
+        assert_eq!(
+            (fed.energy, bitten.energy),
+            (b.energy + bite, a.energy - bite)
+        );
+        // Producers bite weakly, taking less.
+        let producers = HashSet::from([species]);
+        let bites = combat(
+            &microbes,
+            &decisions,
+            &tuning,
+            &producers,
+            Standoff::Stronger,
+            rng,
+        );
+        let mut fed = b.clone();
+        bites.feed(&mut fed);
+        assert_eq!(fed.energy, b.energy + bite * tuning.producer_handicap);
+
+        // Biting a spiky microbe hurts back.
+        let mut spiky = microbes.clone();
+        spiky.get_mut(&a.id).unwrap().spiky = true;
+        let bites = combat(&spiky, &decisions, &tuning, &none, Standoff::Stronger, rng);
+        let prick = bite * tuning.spike_reflect;
+        assert_eq!(bites.pricked, HashMap::from([(b.id, prick)]));
+        let mut fed = b.clone();
+        bites.feed(&mut fed);
+        assert_eq!(fed.energy, b.energy + bite - prick);
+
+        // Without a transfer, bites are flat.
+        let flat = Tuning {
+            eat_transfer: 0.,
+            ..Tuning::default()
+        };
+        let bites = combat(&microbes, &decisions, &flat, &none, Standoff::Stronger, rng);
+        let (mut fed, mut bitten) = (b.clone(), a.clone());
+        bites.feed(&mut fed);
+        bites.feed(&mut bitten);
+        assert_eq!(
+            (fed.energy, bitten.energy),
+            (b.energy + flat.eat_damage, a.energy - flat.eat_damage)
+        );
+
+        // Too tired to bite, b and c neither bite a nor hold it off.
+        let mut tired = microbes.clone();
+        for id in [b.id, c.id] {
+            tired.get_mut(&id).unwrap().stamina = tuning.bite_stamina / 2.;
+        }
+        let decisions = BTreeMap::from([
+            (a.id, decision(true, vec![c.id])),
+            (b.id, decision(true, vec![a.id])),
+            (c.id, decision(true, vec![a.id])),
+        ]);
+        let bites = combat(&tired, &decisions, &tuning, &none, Standoff::Stronger, rng);
+        assert_eq!(bites.eaten.keys().collect::<Vec<_>>(), vec![&c.id]);
+        assert_eq!(bites.gained.keys().collect::<Vec<_>>(), vec![&a.id]);
+    }
+
+    #[test]
+    fn test_ganging_up() {
+        let species = Uuid::new_v4();
+        let [a, b, c, giant] = [1, 2, 3, 4].map(|n| {
+            let mut microbe = Microbe::new(Uuid::from_u128(n), 0., 0., 0., species, Color32::RED);
+            microbe.energy = if n == 4 { 900. } else { 10. };
+            microbe
+        });
+        let decisions = BTreeMap::from([
+            (a.id, decision(true, vec![giant.id])),
+            (b.id, decision(true, vec![giant.id])),
+            (c.id, decision(true, vec![giant.id])),
+            (giant.id, decision(false, vec![])),
+        ]);
+        let microbes = BTreeMap::from([a, b, c, giant.clone()].map(|m| (m.id, m)));
+        let tuning = Tuning {
+            eat_transfer: 0.5,
+            ..Tuning::default()
+        };
+        let rng = &mut StdRng::seed_from_u64(0);
+        // Three bites of half its energy each share out all of it, and no
+        // energy appears or disappears.
+        let bites = combat(
+            &microbes,
+            &decisions,
+            &tuning,
+            &HashSet::new(),
+            Standoff::Stronger,
+            rng,
+        );
+        assert_eq!(bites.lost[&giant.id], 900.);
+        assert!(bites.gained.values().all(|gained| *gained == 300.));
+        let mut microbes = microbes;
+        let before = microbes.values().map(|m| m.energy).sum::<f32>();
+        for microbe in microbes.values_mut() {
+            bites.feed(microbe);
+        }
+        assert_eq!(microbes.values().map(|m| m.energy).sum::<f32>(), before);
+    }
+
+    #[test]
+    fn test_standoff() {
+        let species = Uuid::new_v4();
+        let [a, b] = [1, 2].map(|n| {
+            let mut microbe = Microbe::new(Uuid::from_u128(n), 0., 0., 0., species, Color32::RED);
+            microbe.energy = n as f32 * 10.;
+            microbe
+        });
+        let decisions = BTreeMap::from([
+            (a.id, decision(true, vec![b.id])),
+            (b.id, decision(true, vec![a.id])),
+        ]);
+        let microbes = BTreeMap::from([(a.id, a.clone()), (b.id, b.clone())]);
+        let tuning = Tuning::default();
+        let rng = &mut StdRng::seed_from_u64(0);
+        let mut bite = |standoff| {
+            let bites = combat(
+                &microbes,
+                &decisions,
+                &tuning,
+                &HashSet::new(),
+                standoff,
+                rng,
+            );
+            let ids = |map: &HashMap<Uuid, f32>| {
+                let mut ids = map.keys().copied().collect::<Vec<_>>();
+                ids.sort();
+                ids
+            };
+            (ids(&bites.lost), ids(&bites.gained))
+        };
+
+        assert_eq!(bite(Standoff::Stronger), (vec![a.id], vec![b.id]));
+        assert_eq!(bite(Standoff::BothHurt), (vec![a.id, b.id], vec![]));
+        assert_eq!(bite(Standoff::Trade), (vec![a.id, b.id], vec![a.id, b.id]));
+        // One of the two wins each toss, the weaker one sometimes.
+        let tosses = (0..20).map(|_| bite(Standoff::Random)).collect::<Vec<_>>();
+        assert!(tosses
+            .iter()
+            .all(|(lost, gained)| lost.len() == 1 && gained.len() == 1));
+        assert!(tosses.iter().any(|(_, gained)| gained == &[a.id]));
+        assert!(tosses.iter().any(|(_, gained)| gained == &[b.id]));
+    }
+}
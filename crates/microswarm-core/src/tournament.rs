@@ -0,0 +1,320 @@
+//! Seeded tournaments between species scripts.
+//!
+//! A [`Tournament`] pits its entrants against each other in headless
+//! matches, either every pair in its own arena or everyone at once, and
+//! repeats each pairing over several seeds. Every match scores each species
+//! on what it has at the end:
+//!
+//! - **survivors**: living microbes
+//! - **biomass**: total energy of the living microbes
+//! - **kills**: deaths of other microbes the species took a bite in
+//! - **survival**: mean ticks its microbes lived, counting survivors up to
+//!   the end
+//!
+//! Within a match, a species gets a point for every opponent that ended with
+//! less biomass than it, and half a point for a tie. [`Tournament::run`]
+//! ranks species by points, then by mean biomass.
+//!
+//! Pairings replay the same seeds, and swap which species spawns first on
+//! every other match, so no entrant is luckier with its spawn positions.
+
+use rayon::prelude::*;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::config::{Config, SpeciesConfig};
+use crate::world::World;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Every pair of entrants, alone in the arena.
+    Pairs,
+    /// All entrants in one arena.
+    FreeForAll,
+}
+
+#[derive(Debug, Clone)]
+pub struct Tournament {
+    /// Arena, tuning and seed for every match. Its species are ignored.
+    pub config: Config,
+    pub entrants: Vec<SpeciesConfig>,
+    pub format: Format,
+    /// Matches per pairing, each with its own seed.
+    pub matches: usize,
+    pub ticks: u64,
+    /// Microbes each entrant starts a match with.
+    pub population: usize,
+}
+
+/// How one entrant did across the tournament.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standing {
+    pub name: String,
+    pub matches: usize,
+    /// Matches where it ended with more biomass than every opponent.
+    pub wins: usize,
+    pub points: f32,
+    /// Means per match.
+    pub survivors: f32,
+    pub biomass: f32,
+    pub kills: f32,
+    pub survival: f32,
+}
+
+/// One species' result in one match.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Score {
+    pub survivors: usize,
+    pub biomass: f32,
+    pub kills: usize,
+    /// Mean ticks its microbes lived, 0 if it never had any.
+    pub survival: f32,
+}
+
+/// Tallies each species' [`Score`] while a world is stepped, for matches
+/// played outside a [`Tournament`].
+#[derive(Debug, Clone, Default)]
+pub struct Scorer {
+    kills: HashMap<Uuid, usize>,
+    /// Ticks lived by each species' dead microbes, and how many died.
+    lifespans: HashMap<Uuid, (u64, usize)>,
+}
+
+impl Scorer {
+    /// Counts the tick `world` has just been stepped through.
+    pub fn record(&mut self, world: &World) {
+        if let Some(stats) = world.stats().latest() {
+            for (species, stats) in &stats.species {
+                *self.kills.entry(*species).or_default() += stats.kills;
+            }
+        }
+        for death in world.report().deaths() {
+            let (ticks, deaths) = self.lifespans.entry(death.species).or_default();
+            *ticks += death.lifespan;
+            *deaths += 1;
+        }
+    }
+
+    /// Scores `species` on `world` as it is now, in the same order.
+    pub fn scores(&self, world: &World, species: &[Uuid]) -> Vec<Score> {
+        let latest = world.stats().latest().cloned().unwrap_or_default();
+        species
+            .iter()
+            .map(|id| {
+                let stats = latest.species.get(id).copied().unwrap_or_default();
+                let (ticks, deaths) = self.lifespans.get(id).copied().unwrap_or_default();
+                let lived = ticks as f32 + stats.mean_age * stats.population as f32;
+                let microbes = deaths + stats.population;
+                Score {
+                    survivors: stats.population,
+                    biomass: stats.biomass,
+                    kills: self.kills.get(id).copied().unwrap_or_default(),
+                    survival: if microbes == 0 {
+                        0.
+                    } else {
+                        lived / microbes as f32
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+impl Tournament {
+    /// Plays every match, spread across threads, and returns the standings
+    /// from first to last.
+    pub fn run(&self) -> Result<Vec<Standing>, String> {
+        let seed = self.config.seed.unwrap_or_else(rand::random);
+        let matches = self
+            .pairings()
+            .into_iter()
+            .flat_map(|entrants| (0..self.matches).map(move |round| (entrants.clone(), round)))
+            .collect::<Vec<_>>();
+        let results = matches
+            .par_iter()
+            .map(|(entrants, round)| {
+                let mut entrants = entrants.clone();
+                if round % 2 == 1 {
+                    entrants.reverse();
+                }
+                let scores = self.play(&entrants, seed.wrapping_add(*round as u64))?;
+                Ok(entrants.into_iter().zip(scores).collect::<Vec<_>>())
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let mut standings = self
+            .entrants
+            .iter()
+            .map(|entrant| Standing {
+                name: entrant.name.clone(),
+                matches: 0,
+                wins: 0,
+                points: 0.,
+                survivors: 0.,
+                biomass: 0.,
+                kills: 0.,
+                survival: 0.,
+            })
+            .collect::<Vec<_>>();
+        for result in &results {
+            for (entrant, score) in result {
+                let standing = &mut standings[*entrant];
+                let opponents = result.iter().filter(|(other, _)| other != entrant);
+                let points = opponents
+                    .map(|(_, other)| match score.biomass.total_cmp(&other.biomass) {
+                        std::cmp::Ordering::Greater => 1.,
+                        std::cmp::Ordering::Equal => 0.5,
+                        std::cmp::Ordering::Less => 0.,
+                    })
+                    .sum::<f32>();
+                standing.matches += 1;
+                standing.wins += usize::from(points == (result.len() - 1) as f32);
+                standing.points += points;
+                standing.survivors += score.survivors as f32;
+                standing.biomass += score.biomass;
+                standing.kills += score.kills as f32;
+                standing.survival += score.survival;
+            }
+        }
+        for standing in &mut standings {
+            let matches = standing.matches.max(1) as f32;
+            standing.survivors /= matches;
+            standing.biomass /= matches;
+            standing.kills /= matches;
+            standing.survival /= matches;
+        }
+        standings.sort_by(|a, b| {
+            b.points
+                .total_cmp(&a.points)
+                .then(b.biomass.total_cmp(&a.biomass))
+        });
+        Ok(standings)
+    }
+
+    /// The entrants, by index, in each match of a round.
+    fn pairings(&self) -> Vec<Vec<usize>> {
+        let count = self.entrants.len();
+        match self.format {
+            Format::FreeForAll => vec![(0..count).collect()],
+            Format::Pairs => (0..count)
+                .flat_map(|a| (a + 1..count).map(move |b| vec![a, b]))
+                .collect(),
+        }
+    }
+
+    /// Plays one match between `entrants` and scores them in the same order.
+    fn play(&self, entrants: &[usize], seed: u64) -> Result<Vec<Score>, String> {
+        let config = Config {
+            seed: Some(seed),
+            species: entrants
+                .iter()
+                .map(|&i| SpeciesConfig {
+                    count: self.population,
+                    ..self.entrants[i].clone()
+                })
+                .collect(),
+            ..self.config.clone()
+        };
+        play(&config, self.ticks)
+    }
+}
+
+/// Runs `config` for `ticks` and scores its species, in config order.
+pub(crate) fn play(config: &Config, ticks: u64) -> Result<Vec<Score>, String> {
+    let (mut sim, ids) = config.build().map_err(|e| e.to_string())?;
+    let mut scorer = Scorer::default();
+    for _ in 0..ticks {
+        sim.step().map_err(|e| e.to_string())?;
+        scorer.record(sim.world());
+    }
+    Ok(scorer.scores(sim.world(), &ids))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Channel;
+    use crate::scripts;
+
+    fn entrant(name: &str, script: String) -> SpeciesConfig {
+        SpeciesConfig {
+            name: name.to_owned(),
+            script,
+            count: 0,
+            color: [Channel::Fixed(255); 3],
+            remote: None,
+            producer: false,
+            spiky: false,
+            jaws: Default::default(),
+            eyes: Default::default(),
+            mutation: Default::default(),
+            cooldown: Default::default(),
+            castes: Vec::new(),
+        }
+    }
+
+    fn tournament(format: Format) -> Tournament {
+        Tournament {
+            config: Config {
+                arena: 40.,
+                arena_height: None,
+                shape: Default::default(),
+                climate: Default::default(),
+                wells: Vec::new(),
+                trickle: None,
+                energy_cap: None,
+                population_cap: None,
+                disruptions: Vec::new(),
+                standoff: Default::default(),
+                seed: Some(1),
+                // Idle microbes starve quickly, while hunters feed.
+                tuning: crate::Tuning {
+                    action_energy_consumption: 2.,
+                    ..crate::Tuning::default()
+                },
+                sandbox: Default::default(),
+                math: Default::default(),
+                tick_duration: crate::DELTA_TIME,
+                rules: None,
+                islands: None,
+                species: Vec::new(),
+            },
+            entrants: vec![
+                entrant("idle", "new_controls()".to_owned()),
+                entrant("hunter", scripts::aggressive_hunter_script()),
+                entrant("also idle", "new_controls()".to_owned()),
+            ],
+            format,
+            matches: 2,
+            ticks: 30,
+            population: 15,
+        }
+    }
+
+    #[test]
+    fn test_pairs() {
+        let standings = tournament(Format::Pairs).run().unwrap();
+        assert_eq!(standings.len(), 3);
+        // Each entrant meets the other two, twice.
+        assert!(standings.iter().all(|s| s.matches == 4));
+        assert_eq!(standings[0].name, "hunter");
+        assert_eq!(standings[0].wins, 4);
+        assert_eq!(standings[0].points, 4.);
+        // The idle species tie each other.
+        assert_eq!(standings[1].points, 1.);
+        assert_eq!(standings[2].points, 1.);
+        assert!(standings[0].kills > 0.);
+        // Nothing can live longer than the match.
+        assert!(standings
+            .iter()
+            .all(|s| s.survival > 0. && s.survival <= 30.));
+    }
+
+    #[test]
+    fn test_free_for_all_is_reproducible() {
+        let standings = tournament(Format::FreeForAll).run().unwrap();
+        assert!(standings.iter().all(|s| s.matches == 2));
+        assert_eq!(standings[0].name, "hunter");
+        assert_eq!(standings, tournament(Format::FreeForAll).run().unwrap());
+    }
+}
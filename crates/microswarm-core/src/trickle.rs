@@ -0,0 +1,89 @@
+//! A steady supply of prey.
+//!
+//! A closed world runs out of easy prey and stalls. A [`Trickle`] keeps it
+//! going by spawning a microbe running the built-in `random` script at the
+//! arena's edge every few ticks, facing inwards, while there are fewer than
+//! `max` of them. The wanderers are a species of their own, registered the
+//! first time a trickle is set; see [`crate::World::wanderers`].
+//!
+//! ```toml
+//! [trickle]
+//! every = 20                # ticks between wanderers
+//! max = 100                 # wanderers alive at once
+//! ```
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{PI, TAU};
+
+use crate::math::Math;
+use crate::shape::Shape;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trickle {
+    /// Ticks between wanderers.
+    pub every: u64,
+    /// No more wanderers spawn while this many are alive.
+    pub max: usize,
+}
+
+impl Trickle {
+    pub fn new(every: u64, max: usize) -> Self {
+        Self { every, max }
+    }
+
+    /// Whether a wanderer is due on `tick`, with `alive` of them around.
+    pub(crate) fn is_due(&self, tick: u64, alive: usize) -> bool {
+        self.every > 0 && tick.is_multiple_of(self.every) && alive < self.max
+    }
+}
+
+/// A random point on the edge of `shape` and the heading from it towards
+/// the arena's centre.
+pub(crate) fn edge(
+    shape: &Shape,
+    size: [f32; 2],
+    math: Math,
+    rng: &mut impl Rng,
+) -> (f32, f32, f32) {
+    let [width, height] = size;
+    let angle = rng.gen_range(0. ..TAU);
+    let (sin, cos) = math.sin_cos(angle);
+    // Out along the angle to the arena's rectangle, then in to the shape.
+    let reach = (width / cos.abs()).min(height / sin.abs());
+    let (x, y) = shape.clamp(size, cos * reach, sin * reach);
+    (math.position(x), math.position(y), (angle + PI) % TAU)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_is_due() {
+        let trickle = Trickle::new(10, 2);
+        assert!(trickle.is_due(20, 0));
+        assert!(!trickle.is_due(25, 0));
+        assert!(!trickle.is_due(20, 2));
+        assert!(!Trickle::new(0, 2).is_due(20, 0));
+    }
+
+    #[test]
+    fn test_edge() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let size = [100., 50.];
+        for _ in 0..50 {
+            let (x, y, _) = edge(&Shape::Rectangle, size, Math::Float, &mut rng);
+            let on_side = (x.abs() - 100.).abs() < 1e-3 || (y.abs() - 50.).abs() < 1e-3;
+            assert!(on_side && x.abs() <= 100. && y.abs() <= 50.);
+
+            let (x, y, heading) = edge(&Shape::Circle, size, Math::Float, &mut rng);
+            assert!((((x / 100.).powi(2) + (y / 50.).powi(2)).sqrt() - 1.).abs() < 1e-3);
+            // Facing roughly back towards the centre.
+            let (sin, cos) = heading.sin_cos();
+            assert!(cos * x + sin * y < 0.);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+
+/// Half the width of the default arena.
+pub const BOX_SIZE: f32 = 400.;
+
+pub const HEALTH: f32 = 100.;
+pub const SPEED: f32 = 1.5;
+pub const ROTATION_SPEED: f32 = 1.;
+pub const DETECT_RANGE_FAR: f32 = 40.;
+pub const DETECT_RANGE_CLOSE: f32 = 10.;
+pub const EAT_DAMAGE: f32 = 30.;
+pub const EAT_TRANSFER: f32 = 0.3;
+pub const ACTION_ENERGY_CONSUMPTION: f32 = 0.001;
+pub const STAMINA: f32 = 100.;
+pub const BITE_STAMINA: f32 = 10.;
+pub const STAMINA_REGEN: f32 = 5.;
+pub const TEMPERATURE_EFFECT: f32 = 0.5;
+pub const PHOTOSYNTHESIS: f32 = 0.25;
+pub const PRODUCER_HANDICAP: f32 = 0.5;
+pub const SPIKE_REFLECT: f32 = 0.5;
+pub const SPIKE_UPKEEP: f32 = 2.;
+pub const CLOAK_ENERGY: f32 = 0.05;
+pub const STARVING_SPEED: f32 = 0.5;
+pub const BULKY_SPEED: f32 = 0.75;
+pub const JAW_COST: f32 = 0.5;
+pub const JAW_UPKEEP: f32 = 0.25;
+pub const EYE_UPKEEP: f32 = 0.5;
+
+/// Simulation constants that can be changed while the world is running.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tuning {
+    /// Energy a spawned microbe starts with. Splitting costs this much and
+    /// each of the four offspring gets a quarter of it.
+    pub health: f32,
+    /// Distance moved per tick of [`DELTA_TIME`] seconds. Longer or shorter
+    /// ticks move proportionally further or less.
+    ///
+    /// [`DELTA_TIME`]: crate::DELTA_TIME
+    pub speed: f32,
+    /// Radians turned per tick of [`DELTA_TIME`] seconds, scaled the same
+    /// way as `speed`.
+    ///
+    /// [`DELTA_TIME`]: crate::DELTA_TIME
+    pub rotation_speed: f32,
+    pub detect_range_far: f32,
+    pub detect_range_close: f32,
+    /// Energy each bite takes when `eat_transfer` is 0.
+    pub eat_damage: f32,
+    /// The share of its victim's energy each bite moves to the eater. 0
+    /// bites for a flat `eat_damage` instead.
+    pub eat_transfer: f32,
+    /// Energy a microbe spends each tick, times its
+    /// [`Microbe::metabolism`]. Like every other per-tick rate, it's scaled
+    /// the same way as `speed`.
+    ///
+    /// [`Microbe::metabolism`]: crate::Microbe::metabolism
+    pub action_energy_consumption: f32,
+    /// Energy at which a microbe splits into offspring.
+    pub reproduction_threshold: f32,
+    /// Stamina a microbe starts with and can rest back up to.
+    pub stamina: f32,
+    /// Stamina each tick of eating costs. A microbe with less can't bite,
+    /// nor hold off a bite by eating back.
+    pub bite_stamina: f32,
+    /// Stamina regained each tick a microbe doesn't bite.
+    pub stamina_regen: f32,
+    /// How much the [`Climate`] speeds up and starves warm microbes and
+    /// slows down cold ones. See [`crate::climate`].
+    ///
+    /// [`Climate`]: crate::climate::Climate
+    pub temperature_effect: f32,
+    /// Energy a producer species gains each tick it stands still without
+    /// eating, split with the other microbes close around it.
+    pub photosynthesis: f32,
+    /// The share of the usual speed producers move at and of the usual
+    /// damage their bites do.
+    pub producer_handicap: f32,
+    /// The share of each bite of a spiky microbe it costs the biter.
+    pub spike_reflect: f32,
+    /// How many times the usual energy spiky microbes spend each tick.
+    pub spike_upkeep: f32,
+    /// Energy each tick of cloaking costs.
+    pub cloak_energy: f32,
+    /// The share of `speed` a microbe with no energy left moves at. See
+    /// [`Tuning::speed_factor`].
+    pub starving_speed: f32,
+    /// The share of `speed` a microbe about to split moves at.
+    pub bulky_speed: f32,
+    /// Energy each tick of eating costs for every time over the usual size
+    /// a microbe's jaws are. See [`crate::jaws`].
+    pub jaw_cost: f32,
+    /// How much more energy a microbe spends each tick for every time over
+    /// the usual size its jaws are, and how much less for smaller ones.
+    pub jaw_upkeep: f32,
+    /// How much more energy a microbe spends each tick for every time
+    /// further than usual its eyes see, and how much less for closer ones.
+    pub eye_upkeep: f32,
+}
+
+impl Tuning {
+    /// The names of every field, as written in configs.
+    pub const FIELDS: [&'static str; 23] = [
+        "health",
+        "speed",
+        "rotation_speed",
+        "detect_range_far",
+        "detect_range_close",
+        "eat_damage",
+        "eat_transfer",
+        "action_energy_consumption",
+        "reproduction_threshold",
+        "stamina",
+        "bite_stamina",
+        "stamina_regen",
+        "temperature_effect",
+        "photosynthesis",
+        "producer_handicap",
+        "spike_reflect",
+        "spike_upkeep",
+        "cloak_energy",
+        "starving_speed",
+        "bulky_speed",
+        "jaw_cost",
+        "jaw_upkeep",
+        "eye_upkeep",
+    ];
+
+    /// The field called `name`, if there is one.
+    pub fn field_mut(&mut self, name: &str) -> Option<&mut f32> {
+        Some(match name {
+            "health" => &mut self.health,
+            "speed" => &mut self.speed,
+            "rotation_speed" => &mut self.rotation_speed,
+            "detect_range_far" => &mut self.detect_range_far,
+            "detect_range_close" => &mut self.detect_range_close,
+            "eat_damage" => &mut self.eat_damage,
+            "eat_transfer" => &mut self.eat_transfer,
+            "action_energy_consumption" => &mut self.action_energy_consumption,
+            "reproduction_threshold" => &mut self.reproduction_threshold,
+            "stamina" => &mut self.stamina,
+            "bite_stamina" => &mut self.bite_stamina,
+            "stamina_regen" => &mut self.stamina_regen,
+            "temperature_effect" => &mut self.temperature_effect,
+            "photosynthesis" => &mut self.photosynthesis,
+            "producer_handicap" => &mut self.producer_handicap,
+            "spike_reflect" => &mut self.spike_reflect,
+            "spike_upkeep" => &mut self.spike_upkeep,
+            "cloak_energy" => &mut self.cloak_energy,
+            "starving_speed" => &mut self.starving_speed,
+            "bulky_speed" => &mut self.bulky_speed,
+            "jaw_cost" => &mut self.jaw_cost,
+            "jaw_upkeep" => &mut self.jaw_upkeep,
+            "eye_upkeep" => &mut self.eye_upkeep,
+            _ => return None,
+        })
+    }
+
+    /// What `speed` is scaled by for a microbe with `energy`: full speed at
+    /// `health`, falling in a straight line to `starving_speed` at no
+    /// energy and to `bulky_speed` at `reproduction_threshold`.
+    pub fn speed_factor(&self, energy: f32) -> f32 {
+        let lerp = |from: f32, to: f32, t: f32| from + (to - from) * t.clamp(0., 1.);
+        if energy < self.health {
+            lerp(self.starving_speed, 1., energy / self.health)
+        } else {
+            let span = self.reproduction_threshold - self.health;
+            if span <= 0. {
+                return 1.;
+            }
+            lerp(1., self.bulky_speed, (energy - self.health) / span)
+        }
+    }
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            health: HEALTH,
+            speed: SPEED,
+            rotation_speed: ROTATION_SPEED,
+            detect_range_far: DETECT_RANGE_FAR,
+            detect_range_close: DETECT_RANGE_CLOSE,
+            eat_damage: EAT_DAMAGE,
+            eat_transfer: EAT_TRANSFER,
+            action_energy_consumption: ACTION_ENERGY_CONSUMPTION,
+            reproduction_threshold: HEALTH + HEALTH,
+            stamina: STAMINA,
+            bite_stamina: BITE_STAMINA,
+            stamina_regen: STAMINA_REGEN,
+            temperature_effect: TEMPERATURE_EFFECT,
+            photosynthesis: PHOTOSYNTHESIS,
+            producer_handicap: PRODUCER_HANDICAP,
+            spike_reflect: SPIKE_REFLECT,
+            spike_upkeep: SPIKE_UPKEEP,
+            cloak_energy: CLOAK_ENERGY,
+            starving_speed: STARVING_SPEED,
+            bulky_speed: BULKY_SPEED,
+            jaw_cost: JAW_COST,
+            jaw_upkeep: JAW_UPKEEP,
+            eye_upkeep: EYE_UPKEEP,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speed_factor() {
+        let tuning = Tuning::default();
+        assert_eq!(tuning.speed_factor(HEALTH), 1.);
+        assert_eq!(tuning.speed_factor(0.), STARVING_SPEED);
+        assert_eq!(tuning.speed_factor(-10.), STARVING_SPEED);
+        assert_eq!(tuning.speed_factor(HEALTH / 2.), 0.75);
+        assert_eq!(tuning.speed_factor(HEALTH * 2.), BULKY_SPEED);
+        assert_eq!(tuning.speed_factor(HEALTH * 1.5), 0.875);
+        assert_eq!(tuning.speed_factor(HEALTH * 10.), BULKY_SPEED);
+
+        let flat = Tuning {
+            starving_speed: 1.,
+            bulky_speed: 1.,
+            ..tuning
+        };
+        assert_eq!(flat.speed_factor(5.), 1.);
+        assert_eq!(flat.speed_factor(150.), 1.);
+    }
+}
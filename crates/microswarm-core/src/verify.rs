@@ -0,0 +1,224 @@
+//! Checks that a recorded run plays out the same when simulated again.
+//!
+//! [`verify`] restarts the world kept at the start of a recording, steps it
+//! tick by tick, and every few ticks compares a [`fingerprint`] of it with
+//! the recorded frame. The first mismatch is reported with the last tick
+//! that still matched, narrowing down where a change to the simulation, a
+//! different platform or a non-deterministic script took the run elsewhere.
+//!
+//! Recordings keep positions and energy only roughly, so fingerprints use
+//! the same precision and a drift smaller than that goes unnoticed until it
+//! grows. Species driven by brains, such as remote agents, aren't part of
+//! the kept world and run their scripts instead, so their runs diverge. So
+//! do runs changed while recording, for example through a control socket.
+
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::error::SimError;
+use crate::replay::{self, Replay};
+use crate::simulation::{MicrobeState, Simulation, Snapshot};
+use crate::world::World;
+
+/// The result of [`verify`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verification {
+    /// Frames whose fingerprints were compared.
+    pub checked: usize,
+    /// The last tick simulated.
+    pub tick: u64,
+    pub divergence: Option<Divergence>,
+}
+
+/// Where a run first stopped matching its recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// The first tick found not to match.
+    pub tick: u64,
+    /// The last checked tick that still matched.
+    pub last_match: Option<u64>,
+    pub expected: u64,
+    pub actual: u64,
+    /// What differs, such as a microbe's position.
+    pub detail: String,
+}
+
+/// Simulates `world` again alongside `replay`, comparing fingerprints on the
+/// first and last frames and every `every` ticks in between.
+pub fn verify(world: World, replay: &mut Replay, every: u64) -> Result<Verification, SimError> {
+    let every = every.max(1);
+    let mut sim = Simulation::from(world);
+    let mut verification = Verification {
+        checked: 0,
+        tick: sim.world().tick(),
+        divergence: None,
+    };
+    let mut last_match = None;
+    let start = replay.ticks().first().copied().unwrap_or_default();
+    for index in 0..replay.len() {
+        let tick = replay.ticks()[index];
+        while sim.world().tick() < tick {
+            sim.step()?;
+        }
+        verification.tick = sim.world().tick();
+        let last = index + 1 == replay.len();
+        if !(tick - start).is_multiple_of(every) && !last {
+            continue;
+        }
+        let Some(frame) = replay.frame(index) else {
+            break;
+        };
+        let actual = sim.snapshot();
+        verification.checked += 1;
+        let (expected_hash, actual_hash) = (fingerprint(&frame), fingerprint(&actual));
+        if expected_hash != actual_hash || actual.tick != frame.tick {
+            verification.divergence = Some(Divergence {
+                tick,
+                last_match,
+                expected: expected_hash,
+                actual: actual_hash,
+                detail: difference(&frame, &actual),
+            });
+            break;
+        }
+        last_match = Some(tick);
+    }
+    Ok(verification)
+}
+
+/// A hash of the snapshot's tick and of every microbe's id, species, and
+/// position, rotation and energy at the precision recordings keep. The same
+/// on every platform and build.
+pub fn fingerprint(snapshot: &Snapshot) -> u64 {
+    let mut hash = Fnv::default();
+    hash.write(&snapshot.tick.to_le_bytes());
+    for microbe in by_id(snapshot).into_values() {
+        hash.write(microbe.id.as_bytes());
+        hash.write(microbe.species.as_bytes());
+        for value in replay::quantize(microbe) {
+            hash.write(&value.to_le_bytes());
+        }
+    }
+    hash.0
+}
+
+fn by_id(snapshot: &Snapshot) -> BTreeMap<Uuid, &MicrobeState> {
+    snapshot.microbes.iter().map(|m| (m.id, m)).collect()
+}
+
+/// Describes the first difference between two snapshots.
+fn difference(expected: &Snapshot, actual: &Snapshot) -> String {
+    if expected.tick != actual.tick {
+        return format!("recorded tick {}, simulated {}", expected.tick, actual.tick);
+    }
+    let (expected, actual) = (by_id(expected), by_id(actual));
+    for (id, microbe) in &expected {
+        let short = &id.to_string()[..8];
+        let Some(other) = actual.get(id) else {
+            return format!("microbe {short} is missing");
+        };
+        if microbe.species != other.species || replay::quantize(microbe) != replay::quantize(other)
+        {
+            return format!(
+                "microbe {short} is at ({:.2}, {:.2}) with {:.1} energy, recorded at ({:.2}, {:.2}) with {:.1}",
+                other.x, other.y, other.energy, microbe.x, microbe.y, microbe.energy
+            );
+        }
+    }
+    match actual.keys().find(|id| !expected.contains_key(id)) {
+        Some(id) => format!("microbe {} wasn't recorded", &id.to_string()[..8]),
+        None => "no difference found".to_owned(),
+    }
+}
+
+/// 64-bit FNV-1a, which unlike the standard library's hasher is fixed
+/// across Rust versions.
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::Recorder;
+    use crate::{scripts, Color32};
+
+    fn record(sim: &mut Simulation, ticks: usize) -> Replay {
+        let mut recorder = Recorder::for_world(Vec::new(), sim.world()).unwrap();
+        recorder.record(&sim.snapshot()).unwrap();
+        for _ in 0..ticks {
+            sim.step().unwrap();
+            recorder.record(&sim.snapshot()).unwrap();
+        }
+        Replay::read(recorder.finish().unwrap().as_slice()).unwrap()
+    }
+
+    fn world() -> Simulation {
+        let mut sim = Simulation::with_seed(60., 11).unwrap();
+        let hunters = sim.add_species(scripts::aggressive_hunter_script());
+        let herbivores = sim.add_species(scripts::timid_herbivore_script());
+        for i in 0..20 {
+            let offset = i as f32 * 5. - 50.;
+            sim.spawn(hunters, offset, 5., i as f32, Color32::RED);
+            sim.spawn(herbivores, offset, -5., -(i as f32), Color32::GREEN);
+        }
+        sim
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut sim = world();
+        let mut replay = record(&mut sim, 120);
+        let world = replay.world().unwrap().unwrap();
+        let verification = verify(world, &mut replay, 25).unwrap();
+        assert_eq!(verification.divergence, None);
+        assert_eq!(verification.tick, 120);
+        // Ticks 0, 25, 50, 75, 100 and the last one.
+        assert_eq!(verification.checked, 6);
+    }
+
+    #[test]
+    fn test_verify_finds_divergence() {
+        let mut sim = world();
+        let mut recorder = Recorder::for_world(Vec::new(), sim.world()).unwrap();
+        recorder.record(&sim.snapshot()).unwrap();
+        for tick in 1..=60 {
+            sim.step().unwrap();
+            if tick == 33 {
+                sim.tuning_mut().speed *= 2.;
+            }
+            recorder.record(&sim.snapshot()).unwrap();
+        }
+        let mut replay = Replay::read(recorder.finish().unwrap().as_slice()).unwrap();
+        let world = replay.world().unwrap().unwrap();
+        let divergence = verify(world, &mut replay, 10).unwrap().divergence.unwrap();
+        assert_eq!(divergence.tick, 40);
+        assert_eq!(divergence.last_match, Some(30));
+        assert_ne!(divergence.expected, divergence.actual);
+        assert!(divergence.detail.starts_with("microbe "), "{divergence:?}");
+    }
+
+    #[test]
+    fn test_recordings_without_a_world() {
+        let mut sim = world();
+        let mut recorder = Recorder::new(Vec::new(), sim.arena()).unwrap();
+        sim.step().unwrap();
+        recorder.record(&sim.snapshot()).unwrap();
+        let replay = Replay::read(recorder.finish().unwrap().as_slice()).unwrap();
+        assert_eq!(replay.len(), 1);
+        assert!(replay.world().unwrap().is_none());
+    }
+}
@@ -0,0 +1,217 @@
+//! New species dropped into a running world.
+//!
+//! A [`ScriptWatcher`] keeps an eye on a directory of `.rhai` scripts, such
+//! as `scripts/`. Every script that appears there while the world runs
+//! joins it as a new species, named as [`scripts::load_dir`] would name it,
+//! with a starter population spread at random over the arena. Spectators
+//! can drop a challenger into a match just by saving a file.
+//!
+//! ```no_run
+//! use microswarm::watcher::ScriptWatcher;
+//! use microswarm::Simulation;
+//!
+//! let mut sim = Simulation::new(400.).unwrap();
+//! let mut watcher = ScriptWatcher::new("scripts").unwrap().with_population(30);
+//! loop {
+//!     sim.step().unwrap();
+//!     for joined in watcher.poll(&mut sim).unwrap() {
+//!         println!("{} joined from {}", joined.name, joined.path.display());
+//!     }
+//! }
+//! ```
+//!
+//! Scripts already in the directory when watching starts are left alone,
+//! since they're usually loaded at startup. A new script joins once it has
+//! stayed the same between two looks, so a half-saved file isn't read, and
+//! one that can't be read or doesn't parse is skipped until it changes. The
+//! directory is checked by polling, at most once per
+//! [`ScriptWatcher::interval`], which works on any file system without
+//! platform notification APIs.
+
+use ecolor::Color32;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::PI;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use uuid::Uuid;
+
+use crate::scripts;
+use crate::simulation::Simulation;
+
+/// Microbes each new species starts with by default.
+pub const STARTER_POPULATION: usize = 20;
+
+/// A species that joined from a new script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Joined {
+    pub species: Uuid,
+    pub name: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug)]
+pub struct ScriptWatcher {
+    dir: PathBuf,
+    /// Every script looked at so far, with when it was last modified.
+    seen: HashMap<PathBuf, Option<SystemTime>>,
+    /// Scripts that already have a species, including the ones that were
+    /// there from the start.
+    joined: HashSet<PathBuf>,
+    /// Scripts that couldn't be read or didn't parse as they are now.
+    failed: HashSet<PathBuf>,
+    population: usize,
+    interval: Duration,
+    checked: Option<Instant>,
+}
+
+impl ScriptWatcher {
+    /// Watches `dir`, ignoring the scripts already in it.
+    pub fn new(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref().to_owned();
+        let seen = scan(&dir)?.into_iter().collect::<HashMap<_, _>>();
+        Ok(Self {
+            dir,
+            joined: seen.keys().cloned().collect(),
+            seen,
+            failed: HashSet::new(),
+            population: STARTER_POPULATION,
+            interval: Duration::from_secs(1),
+            checked: None,
+        })
+    }
+
+    /// Microbes each new species starts with.
+    pub fn with_population(mut self, population: usize) -> Self {
+        self.population = population;
+        self
+    }
+
+    /// How often [`ScriptWatcher::poll`] looks at the directory.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Adds any new scripts to `sim` as species, if the interval has passed
+    /// since the last look.
+    pub fn poll(&mut self, sim: &mut Simulation) -> io::Result<Vec<Joined>> {
+        if self.checked.is_some_and(|at| at.elapsed() < self.interval) {
+            return Ok(Vec::new());
+        }
+        self.checked = Some(Instant::now());
+
+        let mut joined = Vec::new();
+        for (path, modified) in scan(&self.dir)? {
+            if self.seen.insert(path.clone(), modified) != Some(modified) {
+                // It may still be being written, so look again next time.
+                self.failed.remove(&path);
+                continue;
+            }
+            // A species keeps the script it joined with, however it's edited.
+            if self.joined.contains(&path) || self.failed.contains(&path) {
+                continue;
+            }
+            let source = match fs::read_to_string(&path) {
+                Ok(source) => source,
+                Err(error) => {
+                    tracing::warn!(path = %path.display(), %error, "skipping script that can't be read");
+                    self.failed.insert(path);
+                    continue;
+                }
+            };
+            if let Err(error) = scripts::check(&source) {
+                tracing::warn!(path = %path.display(), %error, "skipping script that doesn't parse");
+                self.failed.insert(path);
+                continue;
+            }
+            self.joined.insert(path.clone());
+            let name = scripts::file_name(&path, &source);
+            let species = join(sim, source, self.population);
+            sim.set_species_name(species, &name);
+            tracing::info!(%species, name, path = %path.display(), "species joined");
+            joined.push(Joined {
+                species,
+                name,
+                path,
+            });
+        }
+        Ok(joined)
+    }
+}
+
+/// Registers `script` as a new species in a running world and spawns
+/// `population` microbes of it at random over the arena.
+pub fn join(sim: &mut Simulation, script: impl Into<String>, population: usize) -> Uuid {
+    let species = sim.add_species(script);
+    let world = sim.world();
+    let (size, shape) = (world.arena_size(), world.shape().clone());
+    let (high, low) = species.as_u64_pair();
+    let rng = &mut StdRng::seed_from_u64(world.seed() ^ world.tick() ^ high ^ low);
+    let color = Color32::from_rgb(rng.gen(), rng.gen(), rng.gen());
+    for _ in 0..population {
+        let (x, y) = shape.sample(size, rng);
+        sim.spawn(species, x, y, rng.gen_range(0.0..=(2. * PI)), color);
+    }
+    species
+}
+
+/// Every `.rhai` file in `dir`, with when it was last modified.
+fn scan(dir: &Path) -> io::Result<Vec<(PathBuf, Option<SystemTime>)>> {
+    let mut scripts = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "rhai") {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            scripts.push((path, modified));
+        }
+    }
+    scripts.sort();
+    Ok(scripts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_scripts_join() {
+        let dir = std::env::temp_dir().join(format!("microswarm-watch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("old.rhai"), "new_controls()").unwrap();
+
+        let mut sim = Simulation::with_seed(50., 1).unwrap();
+        let mut watcher = ScriptWatcher::new(&dir)
+            .unwrap()
+            .with_population(5)
+            .interval(Duration::ZERO);
+        let mut poll = |sim: &mut Simulation| watcher.poll(sim).unwrap();
+        assert!(poll(&mut sim).is_empty());
+
+        fs::write(dir.join("new.rhai"), "// name: Rock\nnew_controls()").unwrap();
+        fs::write(dir.join("broken.rhai"), "let controls = ;").unwrap();
+        fs::write(dir.join("garbled.rhai"), [0xff, 0xfe, 0xfd]).unwrap();
+        fs::write(dir.join("notes.txt"), "not a script").unwrap();
+        // New files wait a look in case they're still being written.
+        assert!(poll(&mut sim).is_empty());
+        let joined = poll(&mut sim);
+        assert_eq!(joined.len(), 1);
+        assert_eq!(joined[0].name, "Rock");
+        assert_eq!(joined[0].path, dir.join("new.rhai"));
+        assert_eq!(sim.species(), [joined[0].species]);
+        assert_eq!(sim.species_name(joined[0].species), "Rock");
+        assert_eq!(sim.microbes().count(), 5);
+        assert!(sim.microbes().all(|m| m.x.abs() <= 50. && m.y.abs() <= 50.));
+        sim.step().unwrap();
+        assert!(poll(&mut sim).is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,107 @@
+//! Points in the arena that pull microbes in or push them away.
+//!
+//! Each tick, every active [`Well`] moves every microbe towards it by its
+//! `strength` divided by how far away the microbe is, so the pull is
+//! strongest up close. A negative strength pushes microbes away instead.
+//! An attractor never pulls a microbe past its centre, so microbes settle
+//! on it rather than orbit.
+//!
+//! ```toml
+//! [[wells]]
+//! x = 0.0
+//! y = 0.0
+//! strength = 20.0           # negative to repel
+//! active = true             # the default
+//! ```
+//!
+//! Wells can be switched on and off while the world runs, with
+//! [`crate::Simulation::wells_mut`].
+
+use serde::{Deserialize, Serialize};
+
+use crate::math::Math;
+
+/// Microbes closer than this are pulled as though they were this far away.
+const MIN_DISTANCE: f32 = 1.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Well {
+    pub x: f32,
+    pub y: f32,
+    /// Distance a microbe one unit away moves each tick of
+    /// [`DELTA_TIME`] seconds. Negative to push microbes away.
+    ///
+    /// [`DELTA_TIME`]: crate::DELTA_TIME
+    pub strength: f32,
+    #[serde(default = "active")]
+    pub active: bool,
+}
+
+impl Well {
+    pub fn new(x: f32, y: f32, strength: f32) -> Self {
+        Self {
+            x,
+            y,
+            strength,
+            active: true,
+        }
+    }
+
+    /// How far the well moves a microbe at (`x`, `y`) along each axis in
+    /// a tick `scale` times the default length.
+    pub fn pull(&self, x: f32, y: f32, scale: f32, math: Math) -> (f32, f32) {
+        let (dx, dy) = (self.x - x, self.y - y);
+        let distance = (dx * dx + dy * dy).sqrt();
+        if !self.active || distance == 0. {
+            return (0., 0.);
+        }
+        let mut step = self.strength * scale / distance.max(MIN_DISTANCE);
+        if step > distance {
+            step = distance;
+        }
+        let (sin, cos) = math.sin_cos(math.atan2(dy, dx));
+        (cos * step, sin * step)
+    }
+}
+
+fn active() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: (f32, f32), b: (f32, f32)) -> bool {
+        (a.0 - b.0).abs() < 1e-5 && (a.1 - b.1).abs() < 1e-5
+    }
+
+    #[test]
+    fn test_pull() {
+        let well = Well::new(10., 0., 20.);
+        assert!(close(well.pull(-10., 0., 1., Math::Float), (1., 0.)));
+        assert!(close(well.pull(10., -40., 1., Math::Float), (0., 0.5)));
+        // Twice as long a tick pulls twice as far.
+        assert!(close(well.pull(-10., 0., 2., Math::Float), (2., 0.)));
+        // Never past the centre.
+        assert!(close(well.pull(8., 0., 1., Math::Float), (2., 0.)));
+        assert_eq!(well.pull(10., 0., 1., Math::Float), (0., 0.));
+
+        let repulsor = Well::new(0., 0., -20.);
+        assert!(close(repulsor.pull(0., 10., 1., Math::Float), (0., 2.)));
+
+        let off = Well {
+            active: false,
+            ..well
+        };
+        assert_eq!(off.pull(-10., 0., 1., Math::Float), (0., 0.));
+    }
+
+    #[test]
+    fn test_serde() {
+        let well = serde_json::from_str::<Well>(r#"{"x": 1, "y": 2, "strength": -3}"#).unwrap();
+        assert_eq!(well, Well::new(1., 2., -3.));
+        let json = serde_json::to_string(&well).unwrap();
+        assert_eq!(serde_json::from_str::<Well>(&json).unwrap(), well);
+    }
+}
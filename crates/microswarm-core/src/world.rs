@@ -0,0 +1,1180 @@
+use ecolor::Color32;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rhai::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::brain::Brain;
+use crate::caste::Caste;
+use crate::climate::Climate;
+use crate::controls::Controls;
+use crate::cooldown::Cooldown;
+use crate::disruption::{Disruption, Scheduled};
+use crate::error::SimError;
+use crate::events::{Event, EventBus, StepReport};
+use crate::eyes::Eyes;
+use crate::jaws::Jaws;
+use crate::math::Math;
+use crate::microbe::{Microbe, Transform};
+use crate::mutation::Mutation;
+use crate::observer::Observer;
+use crate::overflow::EnergyCap;
+use crate::palette::Palette;
+use crate::plugin::WorldPlugin;
+use crate::population::PopulationCap;
+use crate::quadtree::{Locatable, QuadTree, Rect};
+use crate::random;
+use crate::rules::{MatchResult, Referee, Rules};
+use crate::sandbox::Sandbox;
+use crate::scripts;
+use crate::shape::Shape;
+use crate::simulation::{MicrobeState, Snapshot, DELTA_TIME};
+use crate::standoff::Standoff;
+use crate::stats::{Stats, TickStats};
+use crate::systems::{self, Senses};
+use crate::trickle::Trickle;
+use crate::tuning::{Tuning, BOX_SIZE};
+use crate::wells::Well;
+
+/// Decides what a microbe does, in place of its brain or script.
+pub(crate) type Decide<'a> = dyn FnMut(&MicrobeState, &Senses) -> Controls + 'a;
+
+/// Returned by [`World::restore`] for a snapshot with microbes of a species
+/// the world has no script for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownSpecies(pub Uuid);
+
+impl std::fmt::Display for UnknownSpecies {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown species {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownSpecies {}
+
+/// The arena, its microbes, and the script engine that drives them.
+///
+/// Everything except the engine is serializable, so a world can be saved
+/// with [`World::save`] and resumed with [`World::load`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct World {
+    pub(crate) microbes: QuadTree<Microbe>,
+    pub(crate) scripts: HashMap<Uuid, String>,
+    /// Names given to species, shown instead of their ids.
+    #[serde(default)]
+    pub(crate) names: HashMap<Uuid, String>,
+    /// Species that feed on light. See [`World::set_producer`].
+    #[serde(default)]
+    pub(crate) producers: HashSet<Uuid>,
+    /// How each species' offspring differ. See [`crate::mutation`].
+    #[serde(default)]
+    pub(crate) mutations: HashMap<Uuid, Mutation>,
+    /// How long each species rests after splitting. See [`crate::cooldown`].
+    #[serde(default)]
+    pub(crate) cooldowns: HashMap<Uuid, Cooldown>,
+    /// The roles each species' offspring can be born into. See
+    /// [`crate::caste`].
+    #[serde(default)]
+    pub(crate) castes: HashMap<Uuid, Vec<Caste>>,
+    #[serde(skip, default = "World::engine")]
+    pub(crate) engine: Engine,
+    /// Limits on every script run, applied to the engine.
+    #[serde(default)]
+    pub(crate) sandbox: Sandbox,
+    /// Half the width of the arena, centred on the origin.
+    pub(crate) arena: f32,
+    /// Half the height of the arena, if it isn't square.
+    #[serde(default)]
+    pub(crate) height: Option<f32>,
+    /// The playable area within the square.
+    #[serde(default)]
+    pub(crate) shape: Shape,
+    #[serde(default)]
+    pub(crate) climate: Climate,
+    #[serde(default)]
+    pub(crate) wells: Vec<Well>,
+    #[serde(default)]
+    pub(crate) trickle: Option<Trickle>,
+    #[serde(default)]
+    pub(crate) energy_cap: Option<EnergyCap>,
+    #[serde(default)]
+    pub(crate) population_cap: Option<PopulationCap>,
+    /// Disruptions yet to happen. See [`crate::disruption`].
+    #[serde(default)]
+    pub(crate) disruptions: Vec<Scheduled>,
+    /// The first tick after the current famine, if there's one.
+    #[serde(default)]
+    pub(crate) famine_until: u64,
+    /// Who wins when microbes bite each other. See [`crate::standoff`].
+    #[serde(default)]
+    pub(crate) standoff: Standoff,
+    /// The species a trickle spawns, once there's been one.
+    #[serde(default)]
+    pub(crate) wanderers: Option<Uuid>,
+    pub(crate) tuning: Tuning,
+    /// How moving and sensing do their trigonometry.
+    #[serde(default)]
+    pub(crate) math: Math,
+    /// Decides the match, if the world is playing one.
+    #[serde(default)]
+    pub(crate) referee: Option<Referee>,
+    /// Seconds of simulated time per [`Simulation::step`].
+    ///
+    /// [`Simulation::step`]: crate::Simulation::step
+    #[serde(default = "World::default_tick_duration")]
+    pub(crate) tick_duration: f32,
+    pub(crate) time: f32,
+    pub(crate) tick: u64,
+    /// Every random choice in the world is derived from this.
+    pub(crate) seed: u64,
+    /// Number of ids handed out so far.
+    pub(crate) ids: u64,
+    #[serde(skip)]
+    pub(crate) report: StepReport,
+    #[serde(skip)]
+    pub(crate) stats: Stats,
+    /// Running kill counts, keyed by victim species and then killer species.
+    pub(crate) predation: HashMap<Uuid, HashMap<Uuid, usize>>,
+    #[serde(skip)]
+    pub(crate) plugins: Vec<Box<dyn WorldPlugin>>,
+    #[serde(skip)]
+    pub(crate) observers: Vec<Box<dyn Observer>>,
+    /// Species driven by Rust code rather than their script.
+    #[serde(skip)]
+    pub(crate) brains: HashMap<Uuid, Box<dyn Brain>>,
+    #[serde(skip)]
+    pub(crate) events: EventBus,
+}
+
+impl World {
+    pub fn new() -> Result<Self, SimError> {
+        Self::with_arena(BOX_SIZE)
+    }
+
+    /// Creates a world with a random seed.
+    pub fn with_arena(arena: f32) -> Result<Self, SimError> {
+        Self::with_seed(arena, rand::random())
+    }
+
+    /// Creates a world whose runs are fully determined by `seed` and the
+    /// scripts and spawns added to it.
+    pub fn with_seed(arena: f32, seed: u64) -> Result<Self, SimError> {
+        Self::with_size(arena, arena, seed)
+    }
+
+    /// Like [`World::with_seed`], for an arena `width` across and `height`
+    /// tall, both given as halves.
+    pub fn with_size(width: f32, height: f32, seed: u64) -> Result<Self, SimError> {
+        Ok(Self {
+            microbes: QuadTree::new(Rect::new(-width, -height, width * 2., height * 2.), 10),
+            scripts: HashMap::new(),
+            names: HashMap::new(),
+            producers: HashSet::new(),
+            mutations: HashMap::new(),
+            cooldowns: HashMap::new(),
+            castes: HashMap::new(),
+            engine: Self::engine(),
+            sandbox: Sandbox::default(),
+            arena: width,
+            height: (height != width).then_some(height),
+            shape: Shape::Rectangle,
+            climate: Climate::Uniform,
+            wells: Vec::new(),
+            trickle: None,
+            wanderers: None,
+            energy_cap: None,
+            population_cap: None,
+            disruptions: Vec::new(),
+            famine_until: 0,
+            standoff: Standoff::Stronger,
+            tuning: Tuning::default(),
+            math: Math::default(),
+            referee: None,
+            tick_duration: DELTA_TIME,
+            time: 0.0,
+            tick: 0,
+            seed,
+            ids: 0,
+            report: StepReport::default(),
+            stats: Stats::default(),
+            predation: HashMap::new(),
+            plugins: Vec::new(),
+            observers: Vec::new(),
+            brains: HashMap::new(),
+            events: EventBus::default(),
+        })
+    }
+
+    fn engine() -> Engine {
+        Self::sandboxed_engine(&Sandbox::default())
+    }
+
+    fn default_tick_duration() -> f32 {
+        DELTA_TIME
+    }
+
+    fn sandboxed_engine(sandbox: &Sandbox) -> Engine {
+        let mut engine = Engine::new();
+        sandbox.apply(&mut engine);
+        engine.build_type::<Controls>();
+        random::register(&mut engine);
+        engine
+    }
+
+    pub fn sandbox(&self) -> &Sandbox {
+        &self.sandbox
+    }
+
+    /// Changes the limits on scripts from the next update. See
+    /// [`crate::sandbox`].
+    pub fn set_sandbox(&mut self, sandbox: Sandbox) {
+        self.engine = Self::sandboxed_engine(&sandbox);
+        self.sandbox = sandbox;
+    }
+
+    pub fn rules(&self) -> Option<&Rules> {
+        self.referee.as_ref().map(|referee| &referee.rules)
+    }
+
+    /// Starts a match under `rules` from the next update, or stops
+    /// refereeing with `None`. See [`crate::rules`].
+    pub fn set_rules(&mut self, rules: Option<Rules>) {
+        self.referee = rules.map(Referee::new);
+    }
+
+    /// How the match ended, once it has.
+    pub fn result(&self) -> Option<&MatchResult> {
+        self.referee.as_ref()?.result.as_ref()
+    }
+
+    /// Adds a plugin whose hooks run on every update from now on. Plugins run
+    /// in the order they were added.
+    pub fn add_plugin(&mut self, plugin: impl WorldPlugin + 'static) {
+        self.plugins.push(Box::new(plugin));
+    }
+
+    /// Adds an observer that sees the world after every update from now on.
+    /// Observers run in the order they were added. See [`crate::observer`].
+    pub fn add_observer(&mut self, observer: Box<dyn Observer>) {
+        self.observers.push(observer);
+    }
+
+    /// Calls `subscriber` with the tick and each event after every update.
+    /// See [`crate::events`].
+    pub fn subscribe(&mut self, subscriber: impl FnMut(u64, &Event) + 'static) {
+        self.events.subscribe(subscriber);
+    }
+
+    /// Writes the whole world, including its scripts, to `path` as JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        serde_json::to_writer(&mut file, self)?;
+        file.flush()
+    }
+
+    /// Reads a world written by [`World::save`]. It resumes exactly where it
+    /// was saved.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read(io::BufReader::new(fs::File::open(path)?))
+    }
+
+    /// Reads a world in the format of [`World::save`].
+    pub(crate) fn read(reader: impl io::Read) -> io::Result<Self> {
+        let mut world: Self = serde_json::from_reader(reader)?;
+        world.set_sandbox(world.sandbox.clone());
+        Ok(world)
+    }
+
+    pub fn add_microbe(
+        &mut self,
+        x: f32,
+        y: f32,
+        rotation: f32,
+        script_id: Uuid,
+        color: Color32,
+    ) -> Uuid {
+        let microbe = self.new_microbe(x, y, rotation, script_id, color);
+        let id = microbe.id;
+        self.microbes.insert(microbe);
+        id
+    }
+
+    /// A microbe with full health and stamina, as told to plugins, but not
+    /// yet in the world.
+    pub(crate) fn new_microbe(
+        &mut self,
+        x: f32,
+        y: f32,
+        rotation: f32,
+        script_id: Uuid,
+        color: Color32,
+    ) -> Microbe {
+        let id = self.next_id();
+        let mut microbe = Microbe::new(id, x, y, rotation, script_id, color);
+        microbe.energy = self.tuning.health;
+        microbe.stamina = self.tuning.stamina;
+        microbe.born = self.tick;
+        let state = microbe.state();
+        for plugin in &mut self.plugins {
+            plugin.on_spawn(&state);
+        }
+        microbe
+    }
+
+    /// A new id, derived from the seed so that reruns hand out the same ids.
+    pub(crate) fn next_id(&mut self) -> Uuid {
+        self.ids += 1;
+        random::uuid(self.seed, self.ids)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Half the arena's width.
+    pub fn arena(&self) -> f32 {
+        self.arena
+    }
+
+    /// Half the arena's height, the same as [`World::arena`] for a square.
+    pub fn arena_height(&self) -> f32 {
+        self.height.unwrap_or(self.arena)
+    }
+
+    /// Half the arena's width and height.
+    pub fn arena_size(&self) -> [f32; 2] {
+        [self.arena, self.arena_height()]
+    }
+
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+
+    /// Changes the playable area. Microbes outside it are pushed inside on
+    /// their next move. See [`crate::shape`].
+    pub fn set_shape(&mut self, shape: Shape) {
+        self.shape = shape;
+    }
+
+    pub fn climate(&self) -> Climate {
+        self.climate
+    }
+
+    /// Changes how warm each part of the arena is, from the next tick. See
+    /// [`crate::climate`].
+    pub fn set_climate(&mut self, climate: Climate) {
+        self.climate = climate;
+    }
+
+    pub fn energy_cap(&self) -> Option<EnergyCap> {
+        self.energy_cap
+    }
+
+    /// Limits how much energy microbes hold after eating, and what happens
+    /// to the rest, from the next tick. See [`crate::overflow`].
+    pub fn set_energy_cap(&mut self, cap: Option<EnergyCap>) {
+        self.energy_cap = cap;
+    }
+
+    pub fn standoff(&self) -> Standoff {
+        self.standoff
+    }
+
+    /// Changes who wins when microbes bite each other, from the next tick.
+    /// See [`crate::standoff`].
+    pub fn set_standoff(&mut self, standoff: Standoff) {
+        self.standoff = standoff;
+    }
+
+    pub fn population_cap(&self) -> Option<PopulationCap> {
+        self.population_cap
+    }
+
+    /// Culls microbes whenever a tick ends with too many, from the next
+    /// tick. See [`crate::population`].
+    pub fn set_population_cap(&mut self, cap: Option<PopulationCap>) {
+        self.population_cap = cap;
+    }
+
+    /// Disruptions yet to happen, in the order they were scheduled.
+    pub fn disruptions(&self) -> &[Scheduled] {
+        &self.disruptions
+    }
+
+    /// Replaces the disruptions yet to happen. See [`crate::disruption`].
+    pub fn set_disruptions(&mut self, disruptions: Vec<Scheduled>) {
+        self.disruptions = disruptions;
+    }
+
+    /// Makes `disruption` happen at the end of `tick`, or of the next tick
+    /// if that has passed. See [`crate::disruption`].
+    pub fn schedule(&mut self, tick: u64, disruption: Disruption) {
+        self.disruptions.push(Scheduled::new(tick, disruption));
+    }
+
+    /// Whether a famine is halving the food this tick.
+    pub fn in_famine(&self) -> bool {
+        self.tick < self.famine_until
+    }
+
+    pub fn trickle(&self) -> Option<Trickle> {
+        self.trickle
+    }
+
+    /// Starts or stops spawning wanderers at the arena's edge, registering
+    /// their species the first time. See [`crate::trickle`].
+    pub fn set_trickle(&mut self, trickle: Option<Trickle>) {
+        if trickle.is_some() {
+            self.wanderer_species();
+        }
+        self.trickle = trickle;
+    }
+
+    /// The wanderers' species, registering it if there isn't one yet.
+    pub(crate) fn wanderer_species(&mut self) -> Uuid {
+        if let Some(id) = self.wanderers {
+            return id;
+        }
+        let id = self.next_id();
+        self.scripts
+            .insert(id, scripts::builtin("random").unwrap_or_default());
+        self.set_species_name(id, "wanderers");
+        self.wanderers = Some(id);
+        id
+    }
+
+    /// The species spawned by [`World::set_trickle`] and blooms, if either
+    /// has been used.
+    pub fn wanderers(&self) -> Option<Uuid> {
+        self.wanderers
+    }
+
+    pub fn wells(&self) -> &[Well] {
+        &self.wells
+    }
+
+    /// The points pulling microbes in or pushing them away, to add, move or
+    /// switch off from the next tick. See [`crate::wells`].
+    pub fn wells_mut(&mut self) -> &mut Vec<Well> {
+        &mut self.wells
+    }
+
+    pub fn report(&self) -> &StepReport {
+        &self.report
+    }
+
+    /// Per-species numbers for the most recent ticks. See [`crate::stats`].
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn tuning(&self) -> &Tuning {
+        &self.tuning
+    }
+
+    /// Tuning changes take effect from the next sense.
+    pub fn tuning_mut(&mut self) -> &mut Tuning {
+        &mut self.tuning
+    }
+
+    pub fn math(&self) -> Math {
+        self.math
+    }
+
+    /// Switches how moving and sensing do their trigonometry. See
+    /// [`crate::math`].
+    pub fn set_math(&mut self, math: Math) {
+        self.math = math;
+    }
+
+    pub fn tick_duration(&self) -> f32 {
+        self.tick_duration
+    }
+
+    /// Changes the seconds of simulated time per tick from the next update.
+    /// Anything but a positive, finite number of seconds is ignored, since
+    /// it would freeze the world or run it backwards.
+    pub fn set_tick_duration(&mut self, seconds: f32) {
+        if seconds.is_finite() && seconds > 0. {
+            self.tick_duration = seconds;
+        }
+    }
+
+    /// Every living microbe, in no particular order.
+    pub fn microbes(&self) -> impl Iterator<Item = &Microbe> {
+        self.microbes.iter()
+    }
+
+    /// Species ids in a stable order.
+    pub fn species(&self) -> Vec<Uuid> {
+        let mut species = self.scripts.keys().copied().collect::<Vec<_>>();
+        species.sort();
+        species
+    }
+
+    /// What to call `species`: the name it was given, else the one in its
+    /// script's `// name:` comment, else the start of its id.
+    pub fn species_name(&self, species: Uuid) -> String {
+        let script = self.scripts.get(&species).map(String::as_str);
+        match self
+            .names
+            .get(&species)
+            .map(String::as_str)
+            .or_else(|| scripts::name(script?))
+        {
+            Some(name) => name.to_owned(),
+            None => species.to_string()[..8].to_owned(),
+        }
+    }
+
+    /// Names `species`, replacing any name from its script.
+    pub fn set_species_name(&mut self, species: Uuid, name: impl Into<String>) {
+        self.names.insert(species, name.into());
+    }
+
+    pub fn is_producer(&self, species: Uuid) -> bool {
+        self.producers.contains(&species)
+    }
+
+    /// Makes `species` a producer, or stops it being one. Producers gain
+    /// [`Tuning::photosynthesis`] energy while they stand still without
+    /// eating, less when crowded, but move and bite more weakly, by
+    /// [`Tuning::producer_handicap`].
+    pub fn set_producer(&mut self, species: Uuid, producer: bool) {
+        if producer {
+            self.producers.insert(species);
+        } else {
+            self.producers.remove(&species);
+        }
+    }
+
+    pub fn mutation(&self, species: Uuid) -> Mutation {
+        self.mutations.get(&species).copied().unwrap_or_default()
+    }
+
+    /// Changes how `species`' offspring differ from their parents, from
+    /// the next birth. See [`crate::mutation`].
+    pub fn set_mutation(&mut self, species: Uuid, mutation: Mutation) {
+        if mutation.is_none() {
+            self.mutations.remove(&species);
+        } else {
+            self.mutations.insert(species, mutation);
+        }
+    }
+
+    pub fn cooldown(&self, species: Uuid) -> Cooldown {
+        self.cooldowns.get(&species).copied().unwrap_or_default()
+    }
+
+    /// Changes how long `species`' microbes rest after splitting, from the
+    /// next split. See [`crate::cooldown`].
+    pub fn set_cooldown(&mut self, species: Uuid, cooldown: Cooldown) {
+        if cooldown.is_none() {
+            self.cooldowns.remove(&species);
+        } else {
+            self.cooldowns.insert(species, cooldown);
+        }
+    }
+
+    pub fn castes(&self, species: Uuid) -> &[Caste] {
+        self.castes.get(&species).map_or(&[], Vec::as_slice)
+    }
+
+    /// Changes the castes `species`' offspring can be born into, from the
+    /// next birth. Microbes already in a caste keep its index, so removing
+    /// or reordering castes moves them into whichever is there now. See
+    /// [`crate::caste`].
+    pub fn set_castes(&mut self, species: Uuid, castes: Vec<Caste>) {
+        if castes.is_empty() {
+            self.castes.remove(&species);
+        } else {
+            self.castes.insert(species, castes);
+        }
+    }
+
+    /// Makes a microbe spiky, or smooth. Biting a spiky microbe costs the
+    /// biter [`Tuning::spike_reflect`] of the bite, while the spikes cost
+    /// their owner [`Tuning::spike_upkeep`] times the usual energy, and its
+    /// offspring inherit them. False if there's no such microbe.
+    pub fn set_spiky(&mut self, microbe: Uuid, spiky: bool) -> bool {
+        let mut found = false;
+        self.for_each_microbe_mut(|m| {
+            if m.id == microbe {
+                m.spiky = spiky;
+                found = true;
+            }
+        });
+        found
+    }
+
+    /// Gives a microbe different jaws, which its offspring inherit. See
+    /// [`crate::jaws`]. False if there's no such microbe.
+    pub fn set_jaws(&mut self, microbe: Uuid, jaws: Jaws) -> bool {
+        let mut found = false;
+        self.for_each_microbe_mut(|m| {
+            if m.id == microbe {
+                m.jaws = jaws;
+                found = true;
+            }
+        });
+        found
+    }
+
+    /// Gives a microbe different eyes, which its offspring inherit. See
+    /// [`crate::eyes`]. False if there's no such microbe.
+    pub fn set_eyes(&mut self, microbe: Uuid, eyes: Eyes) -> bool {
+        let mut found = false;
+        self.for_each_microbe_mut(|m| {
+            if m.id == microbe {
+                m.eyes = eyes;
+                found = true;
+            }
+        });
+        found
+    }
+
+    pub(crate) fn for_each_microbe_mut(&mut self, f: impl FnMut(&mut Microbe)) {
+        let bounds = self.microbes.root.bounds;
+        self.microbes.for_each_in_rect_mut(&bounds, f);
+    }
+
+    /// A copy of the world at this tick, with microbes in id order.
+    pub fn snapshot(&self) -> Snapshot {
+        let mut microbes = self.microbes().collect::<Vec<_>>();
+        microbes.sort_by_key(|m| m.id);
+        Snapshot {
+            tick: self.tick,
+            species: self.species(),
+            microbes: microbes.into_iter().map(Microbe::state).collect(),
+            tuning: self.tuning.clone(),
+            ids: self.ids,
+            predation: self.predation.clone(),
+        }
+    }
+
+    /// Rolls the world back (or forward) to `snapshot`, which must come from
+    /// a world with the same species. Species registered since are kept.
+    /// Stepping on from a restored snapshot repeats the original run.
+    pub fn restore(&mut self, snapshot: Snapshot) -> Result<(), UnknownSpecies> {
+        if let Some(microbe) = snapshot
+            .microbes
+            .iter()
+            .find(|m| !self.scripts.contains_key(&m.species))
+        {
+            return Err(UnknownSpecies(microbe.species));
+        }
+        let microbes = snapshot.microbes.into_iter().map(|state| Microbe {
+            id: state.id,
+            lineage: state.lineage,
+            transform: Transform::new(state.x, state.y, state.rotation),
+            script_id: state.species,
+            energy: state.energy,
+            stamina: state.stamina,
+            spiky: state.spiky,
+            jaws: state.jaws,
+            eyes: state.eyes,
+            cloaked: state.cloaked,
+            fat: state.fat,
+            cooldown: state.cooldown,
+            caste: state.caste,
+            color: state.color,
+            born: state.born,
+        });
+        let (bounds, capacity) = (self.microbes.root.bounds, self.microbes.root.capacity);
+        self.microbes = QuadTree::from_iter(bounds, capacity, microbes);
+        self.tick = snapshot.tick;
+        self.stats.rewind(snapshot.tick);
+        if let Some(referee) = &mut self.referee {
+            referee.result.take_if(|result| result.tick > snapshot.tick);
+        }
+        self.tuning = snapshot.tuning;
+        self.ids = snapshot.ids;
+        self.predation = snapshot.predation;
+        self.report = StepReport::default();
+        Ok(())
+    }
+
+    /// The species that has killed the most members of `species` so far.
+    pub fn top_predator(&self, species: &Uuid) -> Option<Uuid> {
+        self.predation
+            .get(species)?
+            .iter()
+            .max_by_key(|(_, kills)| **kills)
+            .map(|(killer, _)| *killer)
+    }
+
+    /// Gives every microbe the color assigned to its species by `palette`.
+    pub fn recolor(&mut self, palette: Palette) {
+        let mut species = self.scripts.keys().copied().collect::<Vec<_>>();
+        species.sort();
+        let colors = palette.colors(species.len(), &mut StdRng::seed_from_u64(self.seed));
+        let colors = species.into_iter().zip(colors).collect::<HashMap<_, _>>();
+        let mut microbes = self.microbes.take_items();
+        for microbe in &mut microbes {
+            if let Some(color) = colors.get(&microbe.script_id) {
+                microbe.color = *color;
+            }
+        }
+        let (bounds, capacity) = (self.microbes.root.bounds, self.microbes.root.capacity);
+        self.microbes = QuadTree::from_iter(bounds, capacity, microbes);
+    }
+
+    /// Advances the world by one tick. If a script fails, the world is left
+    /// as it was before the update.
+    pub fn update(&mut self, delta_time: f32) -> Result<(), SimError> {
+        self.advance(delta_time, None)
+    }
+
+    /// Like [`World::update`], but every microbe does what `controls` says
+    /// instead of running its brain or script. Plugins still apply. Useful
+    /// for testing the world's rules without writing scripts.
+    pub fn update_with(
+        &mut self,
+        delta_time: f32,
+        mut controls: impl FnMut(&MicrobeState, &Senses) -> Controls,
+    ) -> Result<&StepReport, SimError> {
+        self.advance(delta_time, Some(&mut controls))?;
+        Ok(&self.report)
+    }
+
+    fn advance(&mut self, delta_time: f32, controls: Option<&mut Decide>) -> Result<(), SimError> {
+        if controls.is_none() {
+            if let Some(microbe) = self
+                .microbes()
+                .find(|m| !self.scripts.contains_key(&m.script_id))
+            {
+                return Err(SimError::MissingScript(microbe.script_id));
+            }
+        }
+        self.time += delta_time;
+        self.tick += 1;
+        let _tick = tracing::debug_span!("tick", tick = self.tick).entered();
+        self.report = StepReport {
+            tick: self.tick,
+            events: Vec::new(),
+        };
+
+        let mut plugins = std::mem::take(&mut self.plugins);
+        for plugin in &mut plugins {
+            plugin.before_tick(self);
+        }
+        self.plugins = plugins;
+
+        let frozen = self.microbes.clone();
+        let items = self.microbes.take_items();
+        // Ordered maps keep the update independent of hash seeds.
+        let microbes = items.into_iter().fold(BTreeMap::new(), |mut acc, i| {
+            acc.insert(i.id, i);
+            acc
+        });
+
+        let mut populations = BTreeMap::<Uuid, usize>::new();
+        for microbe in microbes.values() {
+            *populations.entry(microbe.script_id).or_default() += 1;
+        }
+
+        let senses = tracing::trace_span!("sense").in_scope(|| {
+            microbes
+                .values()
+                .map(|microbe| {
+                    (
+                        microbe.id,
+                        systems::sense(
+                            &frozen,
+                            microbe,
+                            &self.tuning,
+                            self.math,
+                            self.arena_size(),
+                            &self.shape,
+                            self.climate,
+                        ),
+                    )
+                })
+                .collect()
+        });
+        let decisions = match tracing::trace_span!("think")
+            .in_scope(|| self.think(&microbes, senses, controls))
+        {
+            Ok(decisions) => decisions,
+            Err(error) => {
+                tracing::error!(%error, "tick abandoned");
+                self.microbes = frozen;
+                self.time -= delta_time;
+                self.tick -= 1;
+                self.report = StepReport::default();
+                return Err(error);
+            }
+        };
+
+        let mut bites = tracing::trace_span!("combat").in_scope(|| {
+            systems::combat(
+                &microbes,
+                &decisions,
+                &self.tuning,
+                &self.producers,
+                self.standoff,
+                &mut random::for_tick(self.seed, self.tick),
+            )
+        });
+        self.report.events.append(&mut bites.events);
+
+        let act = tracing::trace_span!("act").entered();
+        let mut result = Vec::with_capacity(microbes.len());
+        for mut microbe in microbes.into_values() {
+            let decision = decisions.get(&microbe.id);
+            self.act(
+                &mut microbe,
+                decision.map(|(controls, _)| controls),
+                delta_time,
+            );
+            bites.feed(&mut microbe);
+            self.photosynthesize(&mut microbe, decision, delta_time);
+            if !self.plugins.is_empty() {
+                let state = microbe.state();
+                for plugin in &mut self.plugins {
+                    plugin.after_combat(&state, &mut microbe.energy);
+                }
+            }
+            if let Some(cap) = &self.energy_cap {
+                cap.apply(&mut microbe, delta_time / DELTA_TIME);
+            }
+            self.reproduce(
+                &mut microbe,
+                decision.map(|(controls, _)| controls),
+                &mut result,
+            );
+            if microbe.energy > 0. {
+                result.push(microbe);
+            } else {
+                self.bury(microbe, &mut bites);
+            }
+        }
+        if let Some(cap) = self.population_cap {
+            self.cull(cap, &mut result);
+        }
+        self.disrupt(&mut result);
+        let (bounds, capacity) = (self.microbes.root.bounds, self.microbes.root.capacity);
+        self.microbes = QuadTree::from_iter(bounds, capacity, result);
+        drop(act);
+
+        for microbe in self.microbes() {
+            populations.remove(&microbe.script_id);
+        }
+        for species in populations.into_keys() {
+            tracing::info!(%species, "species went extinct");
+            self.report.events.push(Event::SpeciesExtinct {
+                species,
+                top_predator: self.top_predator(&species),
+            });
+        }
+        self.trickle_in();
+        self.stats.push(TickStats::new(
+            self.scripts.keys().copied(),
+            self.microbes.iter(),
+            &self.report,
+        ));
+        if let Some(mut referee) = self.referee.take() {
+            referee.judge(self);
+            self.referee = Some(referee);
+        }
+        tracing::debug!(
+            microbes = self.microbes.len(),
+            events = self.report.events.len(),
+            "tick finished"
+        );
+        self.events.publish(&self.report);
+        let mut observers = std::mem::take(&mut self.observers);
+        for observer in &mut observers {
+            observer.observe(self);
+        }
+        self.observers = observers;
+        Ok(())
+    }
+
+    /// Microbes of other lineages within `range` of `viewer` and
+    /// `half_angle` either side of `angle`.
+    pub(crate) fn get_nearby_microbes<'a>(
+        microbes: &'a QuadTree<Microbe>,
+        viewer: &Microbe,
+        angle: f32,
+        half_angle: f32,
+        range: f32,
+        math: Math,
+    ) -> Vec<&'a Microbe> {
+        microbes
+            .query_sector_with(viewer.location(), angle, half_angle, range, |y, x| {
+                math.atan2(y, x)
+            })
+            .into_iter()
+            .filter(|m| viewer.id != m.id && viewer.lineage != m.lineage)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jaws::HALF_ANGLE;
+    use crate::microbe::{Transform, Vector2};
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_get_nearby_microbes() {
+        let mut microbes = QuadTree::new(Rect::new(-3., -3., 6., 6.), 10);
+
+        fn assert_detected(angle: f32, m: Microbe, ms: &mut QuadTree<Microbe>) {
+            let viewer = Microbe::new(Uuid::new_v4(), 0., 0., 0., Uuid::nil(), Color32::RED);
+            let range = 10.0;
+            ms.insert(m.clone());
+            assert!(
+                World::get_nearby_microbes(ms, &viewer, angle, HALF_ANGLE, range, Math::Float)
+                    .contains(&&m)
+            );
+        }
+
+        // FORWARD
+        assert_detected(
+            0.,
+            Microbe {
+                id: Uuid::new_v4(),
+                lineage: Uuid::new_v4(),
+                transform: Transform {
+                    position: Vector2 { x: 1.0, y: 0.0 },
+                    rotation: 0.0,
+                },
+                script_id: Uuid::new_v4(),
+                energy: 100.,
+                stamina: 100.,
+                spiky: false,
+                jaws: Default::default(),
+                eyes: Default::default(),
+                cloaked: false,
+                fat: 0.,
+                cooldown: 0,
+                caste: None,
+                color: Color32::WHITE,
+                born: 0,
+            },
+            &mut microbes,
+        );
+
+        // BACK
+        assert_detected(
+            PI,
+            Microbe {
+                id: Uuid::new_v4(),
+                lineage: Uuid::new_v4(),
+                transform: Transform {
+                    position: Vector2 { x: -1.0, y: 0.0 },
+                    rotation: 0.0,
+                },
+                script_id: Uuid::new_v4(),
+                energy: 100.,
+                stamina: 100.,
+                spiky: false,
+                jaws: Default::default(),
+                eyes: Default::default(),
+                cloaked: false,
+                fat: 0.,
+                cooldown: 0,
+                caste: None,
+                color: Color32::WHITE,
+                born: 0,
+            },
+            &mut microbes,
+        );
+
+        // RIGHT
+        assert_detected(
+            PI * 0.5,
+            Microbe {
+                id: Uuid::new_v4(),
+                lineage: Uuid::new_v4(),
+                transform: Transform {
+                    position: Vector2 { x: 0.0, y: 1.0 },
+                    rotation: 0.0,
+                },
+                script_id: Uuid::new_v4(),
+                energy: 100.,
+                stamina: 100.,
+                spiky: false,
+                jaws: Default::default(),
+                eyes: Default::default(),
+                cloaked: false,
+                fat: 0.,
+                cooldown: 0,
+                caste: None,
+                color: Color32::WHITE,
+                born: 0,
+            },
+            &mut microbes,
+        );
+
+        // LEFT
+        assert_detected(
+            -PI * 0.5,
+            Microbe {
+                id: Uuid::new_v4(),
+                lineage: Uuid::new_v4(),
+                transform: Transform {
+                    position: Vector2 { x: 0.0, y: -1.0 },
+                    rotation: 0.0,
+                },
+                script_id: Uuid::new_v4(),
+                energy: 100.,
+                stamina: 100.,
+                spiky: false,
+                jaws: Default::default(),
+                eyes: Default::default(),
+                cloaked: false,
+                fat: 0.,
+                cooldown: 0,
+                caste: None,
+                color: Color32::WHITE,
+                born: 0,
+            },
+            &mut microbes,
+        );
+    }
+
+    #[test]
+    fn test_recolor_by_species() {
+        let mut world = World::new().unwrap();
+        let a = Uuid::new_v4();
+        let b = Uuid::new_v4();
+        world.scripts.insert(a, String::new());
+        world.scripts.insert(b, String::new());
+        world.add_microbe(0., 0., 0., a, Color32::BLACK);
+        world.add_microbe(10., 0., 0., a, Color32::BLACK);
+        world.add_microbe(20., 0., 0., b, Color32::BLACK);
+
+        world.recolor(Palette::OkabeIto);
+
+        let color_of = |script_id| {
+            world
+                .microbes()
+                .filter(|m| m.script_id == script_id)
+                .map(|m| m.color)
+                .collect::<Vec<_>>()
+        };
+        let a_colors = color_of(a);
+        let b_colors = color_of(b);
+        assert_eq!(a_colors.len(), 2);
+        assert_eq!(a_colors[0], a_colors[1]);
+        assert_ne!(a_colors[0], b_colors[0]);
+        assert_ne!(a_colors[0], Color32::BLACK);
+    }
+
+    #[test]
+    fn test_stamina() {
+        let mut world = World::new().unwrap();
+        let species = Uuid::new_v4();
+        world.scripts.insert(species, String::new());
+        world.add_microbe(0., 0., 0., species, Color32::BLACK);
+        let tuning = world.tuning().clone();
+        let stamina = |world: &World| world.microbes().next().unwrap().stamina;
+        assert_eq!(stamina(&world), tuning.stamina);
+
+        let eat = |_: &MicrobeState, _: &Senses| Controls {
+            eat: true,
+            ..Controls::new()
+        };
+        let bites = (tuning.stamina / tuning.bite_stamina) as usize;
+        for _ in 0..bites {
+            world.update_with(DELTA_TIME, eat).unwrap();
+        }
+        assert_eq!(stamina(&world), 0.);
+        // Eating while too tired to bite rests it all the same.
+        world.update_with(DELTA_TIME, eat).unwrap();
+        assert_eq!(stamina(&world), tuning.stamina_regen);
+        for _ in 0..100 {
+            world
+                .update_with(DELTA_TIME, |_, _| Controls::new())
+                .unwrap();
+        }
+        assert_eq!(stamina(&world), tuning.stamina);
+    }
+
+    #[test]
+    fn test_top_predator() {
+        let mut world = World::new().unwrap();
+        let prey = Uuid::new_v4();
+        let wolf = Uuid::new_v4();
+        let fox = Uuid::new_v4();
+        assert_eq!(world.top_predator(&prey), None);
+
+        world
+            .predation
+            .insert(prey, HashMap::from([(wolf, 3), (fox, 1)]));
+        assert_eq!(world.top_predator(&prey), Some(wolf));
+    }
+
+    #[test]
+    fn test_shape() {
+        // Stoppers walk until the wall ahead is close, unless it already is.
+        let script = "let c = new_controls(); c.forward = sense_wall() > 10.0; c";
+        let (mut world, ids) = crate::WorldBuilder::new()
+            .arena(30.)
+            .seed(2)
+            .shape(Shape::Circle)
+            .species(script, 20, Color32::RED)
+            .species(scripts::random_script(), 20, Color32::BLUE)
+            .build_with_ids()
+            .unwrap();
+        let wall = |world: &World, microbe: &Microbe| {
+            let Vector2 { x, y } = microbe.transform.position;
+            let rotation = microbe.transform.rotation;
+            world
+                .shape()
+                .wall_distance(world.arena_size(), x, y, rotation, world.math())
+        };
+        let start = world
+            .microbes()
+            .map(|m| (m.id, wall(&world, m)))
+            .collect::<HashMap<_, _>>();
+        for _ in 0..300 {
+            world.update(crate::DELTA_TIME).unwrap();
+        }
+        let mut moved = 0;
+        for microbe in world.microbes() {
+            let Vector2 { x, y } = microbe.transform.position;
+            assert!(x * x + y * y <= 30. * 30. + 1e-3);
+            if microbe.script_id == ids[0] && start[&microbe.id] > 10. {
+                let wall = wall(&world, microbe);
+                assert!(wall > 8. && wall < 10.);
+                moved += 1;
+            }
+        }
+        assert!(moved > 0);
+    }
+
+    #[test]
+    fn test_species_names() {
+        let mut world = World::new().unwrap();
+        let (named, commented, anonymous) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        world
+            .scripts
+            .insert(named, "// name: Old\nnew_controls()".into());
+        world
+            .scripts
+            .insert(commented, "// name: Drifter\nnew_controls()".into());
+        world.scripts.insert(anonymous, "new_controls()".into());
+        world.set_species_name(named, "Rock");
+        assert_eq!(world.species_name(named), "Rock");
+        assert_eq!(world.species_name(commented), "Drifter");
+        assert_eq!(world.species_name(anonymous), anonymous.to_string()[..8]);
+
+        let json = serde_json::to_string(&world).unwrap();
+        let loaded = World::read(json.as_bytes()).unwrap();
+        assert_eq!(loaded.species_name(named), "Rock");
+    }
+}
@@ -0,0 +1,258 @@
+"""Drive microswarm simulations from Python.
+
+Build the library first with ``cargo build --release -p microswarm-python``.
+This module looks for it in the workspace's ``target`` directory, or at the
+path in the ``MICROSWARM_LIB`` environment variable.
+
+    import microswarm
+
+    sim = microswarm.Simulation(arena=200, seed=1)
+    hunters = sim.add_species(microswarm.builtin("hunter"))
+
+    def cruise(microbe, senses):
+        return microswarm.Controls(forward=True, eat=senses.close[0] > 0)
+
+    cruisers = sim.add_brain(cruise)
+    for i in range(20):
+        sim.spawn(hunters, i * 5 - 50, 20)
+        sim.spawn(cruisers, i * 5 - 50, -20)
+    sim.step(100)
+    print(sim.tick, len(sim.microbes()))
+
+``Simulation.array()`` returns the living microbes as a numpy structured
+array, for parameter sweeps that collect a lot of data.
+"""
+
+import ctypes
+import json
+import os
+import sys
+import uuid
+from collections import namedtuple
+from pathlib import Path
+
+__all__ = [
+    "Simulation",
+    "Microbe",
+    "Senses",
+    "Controls",
+    "MicroswarmError",
+    "builtin",
+]
+
+Microbe = namedtuple(
+    "Microbe", ["id", "species", "lineage", "x", "y", "rotation", "energy", "born"]
+)
+Senses = namedtuple("Senses", ["close", "far"])
+Controls = namedtuple(
+    "Controls",
+    ["right", "left", "forward", "back", "eat"],
+    defaults=[False] * 5,
+)
+
+
+class _Microbe(ctypes.Structure):
+    _fields_ = [
+        ("id", ctypes.c_uint8 * 16),
+        ("species", ctypes.c_uint8 * 16),
+        ("lineage", ctypes.c_uint8 * 16),
+        ("x", ctypes.c_float),
+        ("y", ctypes.c_float),
+        ("rotation", ctypes.c_float),
+        ("energy", ctypes.c_float),
+        ("born", ctypes.c_uint64),
+    ]
+
+    def to_tuple(self):
+        return Microbe(
+            uuid.UUID(bytes=bytes(self.id)),
+            uuid.UUID(bytes=bytes(self.species)),
+            uuid.UUID(bytes=bytes(self.lineage)),
+            self.x,
+            self.y,
+            self.rotation,
+            self.energy,
+            self.born,
+        )
+
+
+class _Senses(ctypes.Structure):
+    _fields_ = [("close", ctypes.c_int64 * 4), ("far", ctypes.c_int64 * 4)]
+
+
+class _Controls(ctypes.Structure):
+    _fields_ = [(name, ctypes.c_bool) for name in Controls._fields]
+
+
+_Id = ctypes.c_uint8 * 16
+_Brain = ctypes.CFUNCTYPE(
+    None,
+    ctypes.c_void_p,
+    ctypes.POINTER(_Microbe),
+    ctypes.POINTER(_Senses),
+    ctypes.POINTER(_Controls),
+)
+
+
+def _library_path():
+    if "MICROSWARM_LIB" in os.environ:
+        return Path(os.environ["MICROSWARM_LIB"])
+    name = {
+        "win32": "microswarm_python.dll",
+        "darwin": "libmicroswarm_python.dylib",
+    }.get(sys.platform, "libmicroswarm_python.so")
+    target = Path(__file__).resolve().parents[3] / "target"
+    for profile in ["release", "debug"]:
+        if (target / profile / name).exists():
+            return target / profile / name
+    raise ImportError(
+        f"couldn't find {name}; run `cargo build --release -p microswarm-python` "
+        "or set MICROSWARM_LIB"
+    )
+
+
+def _load():
+    lib = ctypes.CDLL(str(_library_path()))
+    sim = ctypes.c_void_p
+    signatures = {
+        "ms_last_error": ([], ctypes.c_char_p),
+        "ms_new": ([ctypes.c_float, ctypes.c_bool, ctypes.c_uint64], sim),
+        "ms_free": ([sim], None),
+        "ms_add_species": ([sim, ctypes.c_char_p, _Id], ctypes.c_bool),
+        "ms_add_brain": ([sim, _Brain, ctypes.c_void_p, _Id], ctypes.c_bool),
+        "ms_spawn": (
+            [sim, _Id, ctypes.c_float, ctypes.c_float, ctypes.c_float,
+             ctypes.c_uint32, _Id],
+            ctypes.c_bool,
+        ),
+        "ms_step": ([sim], ctypes.c_bool),
+        "ms_tick": ([sim], ctypes.c_uint64),
+        "ms_arena": ([sim], ctypes.c_float),
+        "ms_microbes": ([sim, ctypes.POINTER(_Microbe), ctypes.c_size_t], ctypes.c_size_t),
+        # A void pointer rather than c_char_p, so it can be freed.
+        "ms_tuning": ([sim], ctypes.c_void_p),
+        "ms_set_tuning": ([sim, ctypes.c_char_p], ctypes.c_bool),
+        "ms_string_free": ([ctypes.c_void_p], None),
+        "ms_builtin": ([ctypes.c_char_p], ctypes.c_void_p),
+    }
+    for name, (argtypes, restype) in signatures.items():
+        function = getattr(lib, name)
+        function.argtypes = argtypes
+        function.restype = restype
+    return lib
+
+
+_lib = _load()
+
+
+class MicroswarmError(Exception):
+    pass
+
+
+def _check(ok):
+    if not ok:
+        raise MicroswarmError(_lib.ms_last_error().decode())
+
+
+def _take_string(pointer):
+    try:
+        return ctypes.string_at(pointer).decode()
+    finally:
+        _lib.ms_string_free(pointer)
+
+
+def builtin(name):
+    """The script of a built-in species: random, hunter, vampire or
+    herbivore."""
+    pointer = _lib.ms_builtin(name.encode())
+    _check(pointer)
+    return _take_string(pointer)
+
+
+class Simulation:
+    """A microbe world. See the Rust ``microswarm::Simulation`` docs."""
+
+    def __init__(self, arena=400.0, seed=None):
+        self._sim = _lib.ms_new(arena, seed is not None, seed or 0)
+        _check(self._sim)
+        # Callbacks must outlive the simulation that calls them.
+        self._brains = []
+
+    def __del__(self):
+        if getattr(self, "_sim", None):
+            _lib.ms_free(self._sim)
+            self._sim = None
+
+    def add_species(self, script):
+        """Registers a Rhai species script and returns its id."""
+        id = _Id()
+        _check(_lib.ms_add_species(self._sim, script.encode(), id))
+        return uuid.UUID(bytes=bytes(id))
+
+    def add_brain(self, brain):
+        """Registers a species driven by ``brain(microbe, senses)``, which
+        returns ``Controls`` or a dict of them, and returns its id. If the
+        brain raises, the exception is printed and the microbe idles."""
+
+        def think(_user, microbe, senses, controls):
+            senses = senses.contents
+            decision = brain(
+                microbe.contents.to_tuple(),
+                Senses(tuple(senses.close), tuple(senses.far)),
+            )
+            if isinstance(decision, dict):
+                decision = Controls(**decision)
+            for name, value in zip(Controls._fields, decision):
+                setattr(controls.contents, name, bool(value))
+
+        callback = _Brain(think)
+        self._brains.append(callback)
+        id = _Id()
+        _check(_lib.ms_add_brain(self._sim, callback, None, id))
+        return uuid.UUID(bytes=bytes(id))
+
+    def spawn(self, species, x, y, rotation=0.0, color=0xFFFFFF):
+        """Spawns a microbe of ``species``, colored ``0xRRGGBB``, and returns
+        its id."""
+        id = _Id()
+        species = _Id(*species.bytes)
+        _check(_lib.ms_spawn(self._sim, species, x, y, rotation, color, id))
+        return uuid.UUID(bytes=bytes(id))
+
+    def step(self, ticks=1):
+        for _ in range(ticks):
+            _check(_lib.ms_step(self._sim))
+
+    @property
+    def tick(self):
+        return _lib.ms_tick(self._sim)
+
+    @property
+    def arena(self):
+        return _lib.ms_arena(self._sim)
+
+    @property
+    def tuning(self):
+        """The simulation constants, as a dict."""
+        return json.loads(_take_string(_lib.ms_tuning(self._sim)))
+
+    def set_tuning(self, **changes):
+        """Changes some simulation constants, such as ``speed=2.0``."""
+        _check(_lib.ms_set_tuning(self._sim, json.dumps(changes).encode()))
+
+    def _buffer(self):
+        count = _lib.ms_microbes(self._sim, None, 0)
+        buffer = (_Microbe * count)()
+        _lib.ms_microbes(self._sim, buffer, count)
+        return buffer
+
+    def microbes(self):
+        """The living microbes, in id order."""
+        return [microbe.to_tuple() for microbe in self._buffer()]
+
+    def array(self):
+        """The living microbes as a numpy structured array, with ids as
+        16-byte fields."""
+        import numpy
+
+        return numpy.frombuffer(self._buffer(), dtype=numpy.dtype(_Microbe)).copy()
@@ -0,0 +1,451 @@
+//! A C interface to [`microswarm`], for the `microswarm` Python module in
+//! `python/`, or any other language with a foreign function interface.
+//!
+//! Build the library with `cargo build --release -p microswarm-python`,
+//! then point Python at it:
+//!
+//! ```text
+//! PYTHONPATH=crates/microswarm-python/python python3
+//! >>> import microswarm
+//! >>> sim = microswarm.Simulation(arena=200, seed=1)
+//! ```
+//!
+//! Every function takes the handle returned by [`ms_new`]. Ids are passed
+//! as 16 raw bytes. Functions returning `bool` return `false` on failure and
+//! leave a message for [`ms_last_error`].
+
+use microswarm::{Color32, Controls, MicrobeState, Senses, Simulation, Tuning};
+use std::cell::RefCell;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::ptr;
+use std::slice;
+use uuid::Uuid;
+
+/// A microbe's state, laid out for C.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MsMicrobe {
+    pub id: [u8; 16],
+    pub species: [u8; 16],
+    pub lineage: [u8; 16],
+    pub x: f32,
+    pub y: f32,
+    pub rotation: f32,
+    pub energy: f32,
+    /// Tick the microbe was spawned or born on.
+    pub born: u64,
+}
+
+impl From<&MicrobeState> for MsMicrobe {
+    fn from(microbe: &MicrobeState) -> Self {
+        Self {
+            id: microbe.id.into_bytes(),
+            species: microbe.species.into_bytes(),
+            lineage: microbe.lineage.into_bytes(),
+            x: microbe.x,
+            y: microbe.y,
+            rotation: microbe.rotation,
+            energy: microbe.energy,
+            born: microbe.born,
+        }
+    }
+}
+
+/// Other lineages in sight, in front, left, right and back order.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MsSenses {
+    pub close: [i64; 4],
+    pub far: [i64; 4],
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MsControls {
+    pub right: bool,
+    pub left: bool,
+    pub forward: bool,
+    pub back: bool,
+    pub eat: bool,
+}
+
+/// A brain implemented in C: fills `controls`, which start out all false,
+/// for the microbe. `user` is passed through from [`ms_add_brain`].
+pub type MsBrain = extern "C" fn(
+    user: *mut c_void,
+    microbe: *const MsMicrobe,
+    senses: *const MsSenses,
+    controls: *mut MsControls,
+);
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+fn fail(message: impl ToString) -> bool {
+    let message = message.to_string().replace('\0', " ");
+    LAST_ERROR.with(|e| *e.borrow_mut() = CString::new(message).unwrap_or_default());
+    false
+}
+
+/// The message from the last call on this thread that failed. The pointer
+/// is valid until the next failure.
+#[no_mangle]
+pub extern "C" fn ms_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ptr())
+}
+
+/// Creates a simulation, seeded with `seed` unless `seeded` is false.
+/// Returns null if the scripting engine fails to start.
+#[no_mangle]
+pub extern "C" fn ms_new(arena: f32, seeded: bool, seed: u64) -> *mut Simulation {
+    let sim = match seeded {
+        true => Simulation::with_seed(arena, seed),
+        false => Simulation::new(arena),
+    };
+    match sim {
+        Ok(sim) => Box::into_raw(Box::new(sim)),
+        Err(err) => {
+            fail(err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+///
+/// `sim` must come from [`ms_new`] and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn ms_free(sim: *mut Simulation) {
+    if !sim.is_null() {
+        drop(Box::from_raw(sim));
+    }
+}
+
+/// Registers a species script, writing its id to `id`.
+///
+/// # Safety
+///
+/// `sim` must come from [`ms_new`], `script` must be a NUL-terminated
+/// string and `id` must have room for 16 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ms_add_species(
+    sim: *mut Simulation,
+    script: *const c_char,
+    id: *mut u8,
+) -> bool {
+    let Ok(script) = CStr::from_ptr(script).to_str() else {
+        return fail("the script isn't valid UTF-8");
+    };
+    if let Err(err) = microswarm::scripts::check(script) {
+        return fail(err);
+    }
+    write_id((*sim).add_species(script), id);
+    true
+}
+
+/// Registers a species driven by `brain`, writing its id to `id`.
+///
+/// # Safety
+///
+/// `sim` must come from [`ms_new`] and `id` must have room for 16 bytes.
+/// `brain` is called with `user` during [`ms_step`], so `user` must stay
+/// valid for as long as `sim` does.
+#[no_mangle]
+pub unsafe extern "C" fn ms_add_brain(
+    sim: *mut Simulation,
+    brain: MsBrain,
+    user: *mut c_void,
+    id: *mut u8,
+) -> bool {
+    let species = (*sim).add_brain(move |microbe: &MicrobeState, senses: &Senses| {
+        let microbe = MsMicrobe::from(microbe);
+        let senses = MsSenses {
+            close: senses.close,
+            far: senses.far,
+        };
+        let mut controls = MsControls::default();
+        brain(user, &microbe, &senses, &mut controls);
+        Controls {
+            right: controls.right,
+            left: controls.left,
+            forward: controls.forward,
+            back: controls.back,
+            eat: controls.eat,
+            ..Controls::default()
+        }
+    });
+    write_id(species, id);
+    true
+}
+
+/// Spawns a microbe of `species` colored `0xRRGGBB`, writing its id to `id`.
+///
+/// # Safety
+///
+/// `sim` must come from [`ms_new`], and `species` and `id` must point to 16
+/// bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ms_spawn(
+    sim: *mut Simulation,
+    species: *const u8,
+    x: f32,
+    y: f32,
+    rotation: f32,
+    color: u32,
+    id: *mut u8,
+) -> bool {
+    let sim = &mut *sim;
+    let species = read_id(species);
+    if !sim.species().contains(&species) {
+        return fail(format!("unknown species {species}"));
+    }
+    let [_, r, g, b] = color.to_be_bytes();
+    write_id(
+        sim.spawn(species, x, y, rotation, Color32::from_rgb(r, g, b)),
+        id,
+    );
+    true
+}
+
+/// Advances the simulation by one tick.
+///
+/// # Safety
+///
+/// `sim` must come from [`ms_new`].
+#[no_mangle]
+pub unsafe extern "C" fn ms_step(sim: *mut Simulation) -> bool {
+    match (*sim).step() {
+        Ok(_) => true,
+        Err(err) => fail(err),
+    }
+}
+
+/// # Safety
+///
+/// `sim` must come from [`ms_new`].
+#[no_mangle]
+pub unsafe extern "C" fn ms_tick(sim: *const Simulation) -> u64 {
+    (*sim).tick()
+}
+
+/// # Safety
+///
+/// `sim` must come from [`ms_new`].
+#[no_mangle]
+pub unsafe extern "C" fn ms_arena(sim: *const Simulation) -> f32 {
+    (*sim).arena()
+}
+
+/// Copies up to `len` living microbes, in id order, into `out` and returns
+/// how many are alive. Pass a null `out` to only count them.
+///
+/// # Safety
+///
+/// `sim` must come from [`ms_new`], and `out` must be null or have room for
+/// `len` microbes.
+#[no_mangle]
+pub unsafe extern "C" fn ms_microbes(
+    sim: *const Simulation,
+    out: *mut MsMicrobe,
+    len: usize,
+) -> usize {
+    let microbes = (*sim).snapshot().microbes;
+    if !out.is_null() {
+        let out = slice::from_raw_parts_mut(out, len);
+        for (slot, microbe) in out.iter_mut().zip(&microbes) {
+            *slot = microbe.into();
+        }
+    }
+    microbes.len()
+}
+
+/// The simulation's [`Tuning`] as a JSON object. Free the string with
+/// [`ms_string_free`].
+///
+/// # Safety
+///
+/// `sim` must come from [`ms_new`].
+#[no_mangle]
+pub unsafe extern "C" fn ms_tuning(sim: *const Simulation) -> *mut c_char {
+    let json = serde_json::to_string((*sim).tuning()).unwrap_or_default();
+    CString::new(json).unwrap_or_default().into_raw()
+}
+
+/// Changes the fields present in the JSON object `json`, leaving the rest
+/// of the tuning as it is.
+///
+/// # Safety
+///
+/// `sim` must come from [`ms_new`] and `json` must be a NUL-terminated
+/// string.
+#[no_mangle]
+pub unsafe extern "C" fn ms_set_tuning(sim: *mut Simulation, json: *const c_char) -> bool {
+    let sim = &mut *sim;
+    let Ok(serde_json::Value::Object(changes)) =
+        serde_json::from_slice::<serde_json::Value>(CStr::from_ptr(json).to_bytes())
+    else {
+        return fail("tuning must be a JSON object");
+    };
+    let mut tuning = match serde_json::to_value(sim.tuning()) {
+        Ok(serde_json::Value::Object(tuning)) => tuning,
+        _ => unreachable!("tuning serializes to an object"),
+    };
+    for (key, value) in changes {
+        if !tuning.contains_key(&key) {
+            return fail(format!("unknown tuning field {key}"));
+        }
+        tuning.insert(key, value);
+    }
+    match serde_json::from_value::<Tuning>(tuning.into()) {
+        Ok(tuning) => {
+            *sim.tuning_mut() = tuning;
+            true
+        }
+        Err(err) => fail(err),
+    }
+}
+
+/// The script of the built-in species `name`, or null if there is none.
+/// Free the string with [`ms_string_free`].
+///
+/// # Safety
+///
+/// `name` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ms_builtin(name: *const c_char) -> *mut c_char {
+    let name = CStr::from_ptr(name).to_string_lossy();
+    match microswarm::scripts::builtin(&name) {
+        Some(script) => CString::new(script).unwrap_or_default().into_raw(),
+        None => {
+            fail(format!(
+                "unknown builtin {name}, expected one of {}",
+                microswarm::scripts::BUILTINS.join(", ")
+            ));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+///
+/// `string` must come from this library and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn ms_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+unsafe fn read_id(id: *const u8) -> Uuid {
+    Uuid::from_bytes(*(id as *const [u8; 16]))
+}
+
+unsafe fn write_id(value: Uuid, id: *mut u8) {
+    ptr::copy_nonoverlapping(value.as_bytes().as_ptr(), id, 16);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(ms_last_error()) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    extern "C" fn forward(
+        user: *mut c_void,
+        _microbe: *const MsMicrobe,
+        senses: *const MsSenses,
+        controls: *mut MsControls,
+    ) {
+        unsafe {
+            *(user as *mut usize) += 1;
+            assert_eq!((*senses).close, [0; 4]);
+            (*controls).forward = true;
+        }
+    }
+
+    #[test]
+    fn test_drive_a_simulation() {
+        unsafe {
+            let sim = ms_new(100., true, 7);
+            let mut calls = 0usize;
+            let mut species = [0; 16];
+            assert!(ms_add_brain(
+                sim,
+                forward,
+                &mut calls as *mut usize as *mut c_void,
+                species.as_mut_ptr()
+            ));
+            let mut id = [0; 16];
+            assert!(ms_spawn(
+                sim,
+                species.as_ptr(),
+                0.,
+                0.,
+                0.,
+                0xff0000,
+                id.as_mut_ptr()
+            ));
+            assert!(ms_step(sim));
+            assert!(ms_step(sim));
+
+            assert_eq!(calls, 2);
+            assert_eq!(ms_tick(sim), 2);
+            assert_eq!(ms_microbes(sim, ptr::null_mut(), 0), 1);
+            let mut microbes = [MsMicrobe::default(); 1];
+            assert_eq!(ms_microbes(sim, microbes.as_mut_ptr(), 1), 1);
+            assert_eq!(microbes[0].id, id);
+            assert_eq!(microbes[0].species, species);
+            assert!(microbes[0].x > 0.);
+            ms_free(sim);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        unsafe {
+            let sim = ms_new(100., false, 0);
+            let mut id = [0; 16];
+            assert!(!ms_add_species(sim, c"let".as_ptr(), id.as_mut_ptr()));
+            assert!(!last_error().is_empty());
+            assert!(!ms_spawn(
+                sim,
+                [1; 16].as_ptr(),
+                0.,
+                0.,
+                0.,
+                0,
+                id.as_mut_ptr()
+            ));
+            assert!(last_error().starts_with("unknown species"));
+            assert!(ms_builtin(c"plankton".as_ptr()).is_null());
+            assert!(last_error().starts_with("unknown builtin plankton"));
+            let hunter = ms_builtin(c"hunter".as_ptr());
+            assert!(ms_add_species(sim, hunter, id.as_mut_ptr()));
+            ms_string_free(hunter);
+            ms_free(sim);
+        }
+    }
+
+    #[test]
+    fn test_tuning() {
+        unsafe {
+            let sim = ms_new(100., false, 0);
+            assert!(ms_set_tuning(sim, cr#"{"speed": 3.0}"#.as_ptr()));
+            assert_eq!((*sim).tuning().speed, 3.);
+            assert!(!ms_set_tuning(sim, cr#"{"sped": 3.0}"#.as_ptr()));
+            assert_eq!(last_error(), "unknown tuning field sped");
+
+            let json = ms_tuning(sim);
+            let tuning: serde_json::Value =
+                serde_json::from_slice(CStr::from_ptr(json).to_bytes()).unwrap();
+            assert_eq!(tuning["speed"], 3.0);
+            ms_string_free(json);
+            ms_free(sim);
+        }
+    }
+}
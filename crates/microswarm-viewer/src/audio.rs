@@ -0,0 +1,167 @@
+use std::f32::consts::PI;
+#[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+use std::io::Write;
+#[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use web_time::Instant;
+
+const SAMPLE_RATE: u32 = 22_050;
+/// Cues of the same kind closer together than this are dropped, so a bloom
+/// doesn't turn into a continuous drone.
+const MIN_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cue {
+    Kill,
+    Birth,
+    Extinction,
+}
+
+impl Cue {
+    pub const ALL: [Cue; 3] = [Cue::Kill, Cue::Birth, Cue::Extinction];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Cue::Kill => "Kills",
+            Cue::Birth => "Births",
+            Cue::Extinction => "Extinctions",
+        }
+    }
+
+    /// Frequency in Hz and duration in seconds of the cue's tone.
+    fn tone(self) -> (f32, f32) {
+        match self {
+            Cue::Kill => (220., 0.08),
+            Cue::Birth => (880., 0.05),
+            Cue::Extinction => (110., 0.6),
+        }
+    }
+}
+
+/// Optional sound effects for simulation events, with a volume per cue.
+#[derive(Debug)]
+pub struct AudioCues {
+    pub enabled: bool,
+    pub volumes: [f32; Cue::ALL.len()],
+    last_played: [Option<Instant>; Cue::ALL.len()],
+}
+
+impl Default for AudioCues {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            volumes: [0.5; Cue::ALL.len()],
+            last_played: [None; Cue::ALL.len()],
+        }
+    }
+}
+
+impl AudioCues {
+    pub fn play(&mut self, cue: Cue) {
+        let index = cue as usize;
+        let volume = self.volumes[index];
+        if !self.enabled || volume <= 0. {
+            return;
+        }
+        let now = Instant::now();
+        if self.last_played[index].is_some_and(|last| now - last < MIN_INTERVAL) {
+            return;
+        }
+        self.last_played[index] = Some(now);
+
+        let (frequency, duration) = cue.tone();
+        output(tone_wav(frequency, duration, volume));
+    }
+}
+
+/// Plays a WAV file in the background.
+#[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+fn output(wav: Vec<u8>) {
+    // Playback is handed to the platform's command line player so a slow
+    // or missing audio device never stalls the simulation.
+    std::thread::spawn(move || {
+        if let Ok(mut child) = player().stdin(Stdio::piped()).spawn() {
+            if let Some(mut stdin) = child.stdin.take() {
+                _ = stdin.write_all(&wav);
+            }
+            _ = child.wait();
+        }
+    });
+}
+
+/// Browsers have no command line player, so the web build is silent, as is
+/// any build without the `audio` feature.
+#[cfg(any(not(feature = "audio"), target_arch = "wasm32"))]
+fn output(_wav: Vec<u8>) {}
+
+#[cfg(all(feature = "audio", target_os = "macos", not(target_arch = "wasm32")))]
+fn player() -> Command {
+    // afplay can't read from stdin, so go through a temporary file.
+    let mut command = Command::new("sh");
+    command.args([
+        "-c",
+        "d=$(mktemp -d); cat > \"$d/cue.wav\"; afplay \"$d/cue.wav\"; rm -r \"$d\"",
+    ]);
+    command
+}
+
+#[cfg(all(
+    feature = "audio",
+    not(any(target_os = "macos", target_arch = "wasm32"))
+))]
+fn player() -> Command {
+    let mut command = Command::new("aplay");
+    command.args(["-q", "-"]).stderr(Stdio::null());
+    command
+}
+
+/// Renders a mono 16-bit PCM sine tone with a linear fade out.
+fn tone_wav(frequency: f32, duration: f32, volume: f32) -> Vec<u8> {
+    let samples = (SAMPLE_RATE as f32 * duration) as u32;
+    let data_len = samples * 2;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for i in 0..samples {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let fade = 1. - i as f32 / samples as f32;
+        let sample = (2. * PI * frequency * t).sin() * fade * volume.clamp(0., 1.);
+        wav.extend_from_slice(&((sample * i16::MAX as f32) as i16).to_le_bytes());
+    }
+    wav
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tone_wav_layout() {
+        let wav = tone_wav(440., 0.1, 1.);
+        let samples = (SAMPLE_RATE as f32 * 0.1) as usize;
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(&wav[8..12], b"WAVE");
+        assert_eq!(wav.len(), 44 + samples * 2);
+        assert_eq!(
+            u32::from_le_bytes(wav[40..44].try_into().unwrap()) as usize,
+            samples * 2
+        );
+    }
+
+    #[test]
+    fn test_muted_tone_is_silent() {
+        let wav = tone_wav(440., 0.1, 0.);
+        assert!(wav[44..].iter().all(|b| *b == 0));
+    }
+}
@@ -0,0 +1,864 @@
+use microswarm::evolve::Fitness;
+use microswarm::sweep::Axis;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+usage: microswarm [gui] [--config FILE] [--seed N] [--arena SIZE]
+                      [--load FILE | --scene FILE | --resume] [--record FILE]
+                      [--window-size WxH] [--fullscreen] [--watch-scripts DIR]
+                      [--script-url URL]... [--watch-config]
+       microswarm run [--config FILE] [--scripts DIR] [--ticks N] [--seed N] [--population N]
+                      [--arena SIZE] [--load FILE | --scene FILE] [--save FILE]
+                      [--save-scene FILE] [--record FILE] [--metrics FILE] [--metrics-every N] [--serve-metrics ADDR]
+                      [--events FILE] [--leaderboard FILE] [--watch-scripts DIR]
+                      [--stream ADDR] [--control ADDR] [--genealogy FILE]
+                      [--checkpoints DIR] [--checkpoint-every N] [--keep-checkpoints N]
+                      [--script-url URL]... [--watch-config]
+       microswarm tournament [--config FILE] [--scripts DIR] [--matches N] [--ticks N]
+                      [--seed N] [--population N] [--arena SIZE] [--free-for-all]
+                      [--leaderboard FILE] [--script-url URL]...
+       microswarm party [--config FILE] [--listen ADDR] [--stream ADDR] [--lobby SECS]
+                      [--ticks N] [--tick-rate N] [--seed N] [--population N] [--arena SIZE]
+                      [--leaderboard FILE]
+       microswarm evolve SCRIPT [--config FILE] [--generations N] [--size N] [--matches N]
+                      [--ticks N] [--seed N] [--population N] [--arena SIZE]
+                      [--fitness biomass|survivors|kills] [--mutation P] [--save FILE]
+       microswarm sweep --vary NAME=MIN..MAX[:STEPS]... [--config FILE] [--scripts DIR]
+                      [--samples N] [--replicates N] [--ticks N] [--seed N]
+                      [--population N] [--arena SIZE] [--jobs N] [--out FILE]
+       microswarm check [--config FILE] [--scripts DIR]
+       microswarm replay FILE
+       microswarm verify FILE [--every N]
+       microswarm watch ADDR
+       microswarm leaderboard [FILE]
+
+commands:
+  gui     open the viewer (the default)
+  run     simulate headlessly and print per-species results, stopping early once a
+          config's [rules] match has a winner; a config with [islands] runs every
+          island and prints each one's species
+  tournament
+          play every pair of species against each other and rank them
+  party   take scripts from players over HTTP for a while, then play them all in one
+          match streamed to spectators
+  evolve  search for the best values of a script's `// evolve MIN..MAX` constants,
+          playing it against the config's species
+  sweep   run the config across a range of tuning values and write a row per run
+          and species
+  check   parse every script and report syntax errors
+  replay  play back a recording made with --record
+  verify  simulate a recording made with `run --record` again and report the first
+          tick where it plays out differently
+  watch   spectate a run on another machine started with --stream ADDR
+  leaderboard
+          rank every script by its results across tournaments and scored runs
+
+options:
+  --config FILE      experiment config (default: world.toml if present, else built-ins)
+  --scripts DIR      load every .rhai file in DIR as a species instead of the config's
+  --ticks N          ticks to simulate (default 10000, 0 to run until interrupted;
+                     2000 per tournament match)
+  --seed N           seed for the whole run; the same seed and scripts give the same run
+  --population N     microbes to spawn, split evenly across species (in a tournament,
+                     per species, default 50)
+  --arena SIZE       half the width of a square arena, or WxH for half the width and
+                     height of a rectangular one, overriding the config
+  --load FILE        resume a saved world instead of starting from the config, or the
+                     newest checkpoint if FILE is a --checkpoints directory
+  --scene FILE       start from a JSON scene listing species and microbes by hand
+  --resume           resume the world the viewer saved when it last closed
+  --save FILE        save the world when the run finishes, or the best evolved script
+  --save-scene FILE  save the world as a JSON scene when the run finishes
+  --record FILE      record every tick for playback with `microswarm replay`
+  --metrics FILE     write per-species metrics as CSV, or JSON lines if FILE ends in .json
+  --metrics-every N  ticks between metrics rows (default 1)
+  --events FILE      write every birth, bite, death and extinction as JSON lines
+  --serve-metrics ADDR
+                     serve Prometheus metrics at http://ADDR/metrics, e.g. 0.0.0.0:9100
+  --stream ADDR      stream every tick to WebSocket clients at ws://ADDR, such as
+                     `microswarm watch ADDR` on another machine
+  --control ADDR     take commands such as pause, step 100 or stats over TCP at ADDR;
+                     send help for the full list
+  --genealogy FILE   write the family tree of every microbe as a Graphviz DOT file
+  --checkpoints DIR  save the world to DIR every --checkpoint-every ticks, to resume
+                     with --load DIR after a crash
+  --checkpoint-every N
+                     ticks between checkpoints (default 1000)
+  --keep-checkpoints N
+                     checkpoints to keep, deleting older ones (default 3)
+  --matches N        tournament matches per pairing, each with its own seed (default 5)
+  --free-for-all     put every species in each tournament match instead of pairs
+  --listen ADDR      where a party takes scripts, POSTed to http://ADDR/scripts
+                     (default 0.0.0.0:9002)
+  --lobby SECS       how long a party takes scripts before the match starts (default 120)
+  --tick-rate N      ticks per second a party plays at, so spectators can follow
+                     (default 60, 0 for as fast as possible)
+  --watch-scripts DIR
+                     add every .rhai file saved in DIR while the world runs as a new
+                     species, with a starter population
+  --watch-config     apply edits to the config's [tuning] and [sandbox] to the running
+                     world whenever the file is saved, and say which other edits need
+                     a restart
+  --script-url URL   download the script at an https:// URL, such as a gist's raw link,
+                     check it parses and runs within the sandbox, and add it as a
+                     species with a starter population (or as a tournament entrant);
+                     repeat for more
+  --leaderboard FILE add the results to the leaderboard in FILE; tournaments always
+                     do, by default to leaderboard.json in the settings directory
+  --generations N    generations to evolve (default 20)
+  --size N           genomes per generation (default 20)
+  --fitness NAME     what evolution maximizes: biomass (default), survivors or kills
+  --mutation P       chance of each value mutating in a child (default 0.2)
+  --vary NAME=MIN..MAX[:STEPS]
+                     sweep a tuning field, such as eat_damage=10..50:5; repeat for a grid
+  --samples N        sweep N random points instead of the whole grid
+  --replicates N     seeded runs per sweep point (default 3)
+  --jobs N           threads to sweep on (default: one per core)
+  --out FILE         write sweep results as CSV, or JSON lines if FILE ends in .json
+                     (default: CSV to stdout)
+  --every N          ticks between the states verify compares (default 100)
+  --window-size WxH  initial window size, e.g. 1280x720
+  --fullscreen       start fullscreen
+
+environment:
+  MICROSWARM_LOG       log level: off, error, warn (default), info, debug or trace
+  MICROSWARM_LOG_FILE  write logs to FILE as JSON lines instead of to stderr";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Gui(GuiArgs),
+    Run(Box<RunArgs>),
+    Tournament(TournamentArgs),
+    Party(PartyArgs),
+    Evolve(EvolveArgs),
+    Sweep(SweepArgs),
+    Check {
+        config: Option<PathBuf>,
+        scripts: Option<PathBuf>,
+    },
+    Replay {
+        file: PathBuf,
+    },
+    Watch {
+        addr: String,
+    },
+    Verify {
+        file: PathBuf,
+        every: u64,
+    },
+    Leaderboard {
+        /// The default leaderboard if `None`.
+        file: Option<PathBuf>,
+    },
+    Help,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GuiArgs {
+    pub config: Option<PathBuf>,
+    pub seed: Option<u64>,
+    pub arena: Option<[f32; 2]>,
+    pub load: Option<PathBuf>,
+    pub scene: Option<PathBuf>,
+    pub resume: bool,
+    pub record: Option<PathBuf>,
+    pub window_size: Option<[f32; 2]>,
+    pub fullscreen: bool,
+    pub watch_scripts: Option<PathBuf>,
+    pub script_urls: Vec<String>,
+    pub watch_config: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunArgs {
+    pub config: Option<PathBuf>,
+    pub scripts: Option<PathBuf>,
+    pub ticks: u64,
+    pub seed: Option<u64>,
+    pub population: Option<usize>,
+    pub arena: Option<[f32; 2]>,
+    pub load: Option<PathBuf>,
+    pub scene: Option<PathBuf>,
+    pub save: Option<PathBuf>,
+    pub save_scene: Option<PathBuf>,
+    pub record: Option<PathBuf>,
+    pub metrics: Option<PathBuf>,
+    pub metrics_every: u64,
+    pub serve_metrics: Option<String>,
+    pub events: Option<PathBuf>,
+    pub stream: Option<String>,
+    pub control: Option<String>,
+    pub genealogy: Option<PathBuf>,
+    pub checkpoints: Option<PathBuf>,
+    pub checkpoint_every: u64,
+    pub keep_checkpoints: usize,
+    pub leaderboard: Option<PathBuf>,
+    pub watch_scripts: Option<PathBuf>,
+    pub script_urls: Vec<String>,
+    pub watch_config: bool,
+}
+
+impl Default for RunArgs {
+    fn default() -> Self {
+        Self {
+            config: None,
+            scripts: None,
+            ticks: 10_000,
+            seed: None,
+            population: None,
+            arena: None,
+            load: None,
+            scene: None,
+            save: None,
+            save_scene: None,
+            record: None,
+            metrics: None,
+            metrics_every: 1,
+            serve_metrics: None,
+            events: None,
+            stream: None,
+            control: None,
+            genealogy: None,
+            checkpoints: None,
+            checkpoint_every: 1000,
+            keep_checkpoints: 3,
+            leaderboard: None,
+            watch_scripts: None,
+            script_urls: Vec::new(),
+            watch_config: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TournamentArgs {
+    pub config: Option<PathBuf>,
+    pub scripts: Option<PathBuf>,
+    pub matches: usize,
+    pub ticks: u64,
+    pub seed: Option<u64>,
+    /// Microbes per species.
+    pub population: usize,
+    pub arena: Option<[f32; 2]>,
+    pub free_for_all: bool,
+    /// The default leaderboard if `None`.
+    pub leaderboard: Option<PathBuf>,
+    pub script_urls: Vec<String>,
+}
+
+impl Default for TournamentArgs {
+    fn default() -> Self {
+        Self {
+            config: None,
+            scripts: None,
+            matches: 5,
+            ticks: 2_000,
+            seed: None,
+            population: 50,
+            arena: None,
+            free_for_all: false,
+            leaderboard: None,
+            script_urls: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartyArgs {
+    pub config: Option<PathBuf>,
+    pub listen: String,
+    pub stream: String,
+    /// Seconds to take scripts for.
+    pub lobby: u64,
+    pub ticks: u64,
+    /// Ticks per second, or 0 for as fast as possible.
+    pub tick_rate: u32,
+    pub seed: Option<u64>,
+    /// Microbes per species.
+    pub population: usize,
+    pub arena: Option<[f32; 2]>,
+    pub leaderboard: Option<PathBuf>,
+}
+
+impl Default for PartyArgs {
+    fn default() -> Self {
+        Self {
+            config: None,
+            listen: "0.0.0.0:9002".to_owned(),
+            stream: "0.0.0.0:9001".to_owned(),
+            lobby: 120,
+            ticks: 2_000,
+            tick_rate: 60,
+            seed: None,
+            population: 50,
+            arena: None,
+            leaderboard: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvolveArgs {
+    pub script: PathBuf,
+    pub config: Option<PathBuf>,
+    pub generations: usize,
+    pub size: usize,
+    pub matches: usize,
+    pub ticks: u64,
+    pub seed: Option<u64>,
+    /// Microbes per species.
+    pub population: usize,
+    pub arena: Option<[f32; 2]>,
+    pub fitness: Fitness,
+    pub mutation: f64,
+    pub save: Option<PathBuf>,
+}
+
+impl Default for EvolveArgs {
+    fn default() -> Self {
+        Self {
+            script: PathBuf::new(),
+            config: None,
+            generations: 20,
+            size: 20,
+            matches: 3,
+            ticks: 2_000,
+            seed: None,
+            population: 50,
+            arena: None,
+            fitness: Fitness::Biomass,
+            mutation: 0.2,
+            save: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepArgs {
+    pub config: Option<PathBuf>,
+    pub scripts: Option<PathBuf>,
+    pub vary: Vec<Axis>,
+    /// Random points to run instead of the grid.
+    pub samples: Option<usize>,
+    pub replicates: usize,
+    pub ticks: u64,
+    pub seed: Option<u64>,
+    pub population: Option<usize>,
+    pub arena: Option<[f32; 2]>,
+    pub jobs: usize,
+    pub out: Option<PathBuf>,
+}
+
+impl Default for SweepArgs {
+    fn default() -> Self {
+        Self {
+            config: None,
+            scripts: None,
+            vary: Vec::new(),
+            samples: None,
+            replicates: 3,
+            ticks: 2_000,
+            seed: None,
+            population: None,
+            arena: None,
+            jobs: 0,
+            out: None,
+        }
+    }
+}
+
+/// Parses the arguments after the program name.
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    let (command, mut rest) = match args.first().map(String::as_str) {
+        Some("gui") => ("gui", &args[1..]),
+        Some("run") => ("run", &args[1..]),
+        Some("tournament") => ("tournament", &args[1..]),
+        Some("party") => ("party", &args[1..]),
+        Some("evolve") => ("evolve", &args[1..]),
+        Some("sweep") => ("sweep", &args[1..]),
+        Some("check") => ("check", &args[1..]),
+        Some("replay") => {
+            return match &args[1..] {
+                [file] if !file.starts_with('-') => Ok(Command::Replay {
+                    file: PathBuf::from(file),
+                }),
+                [flag] if flag == "--help" || flag == "-h" => Ok(Command::Help),
+                _ => Err("replay expects a recording file".to_owned()),
+            };
+        }
+        Some("verify") => {
+            let every = |value: &str| number("--every", value);
+            return match &args[1..] {
+                [flag] if flag == "--help" || flag == "-h" => Ok(Command::Help),
+                [file] if !file.starts_with('-') => Ok(Command::Verify {
+                    file: PathBuf::from(file),
+                    every: 100,
+                }),
+                [file, flag, value] if !file.starts_with('-') && flag == "--every" => {
+                    Ok(Command::Verify {
+                        file: PathBuf::from(file),
+                        every: every(value)?,
+                    })
+                }
+                _ => Err("verify expects a recording file and optionally --every N".to_owned()),
+            };
+        }
+        Some("watch") => {
+            return match &args[1..] {
+                [flag] if flag == "--help" || flag == "-h" => Ok(Command::Help),
+                [addr] if !addr.starts_with('-') => Ok(Command::Watch {
+                    addr: addr.trim_start_matches("ws://").to_owned(),
+                }),
+                _ => Err("watch expects an address such as 192.168.1.20:9001".to_owned()),
+            };
+        }
+        Some("leaderboard") => {
+            return match &args[1..] {
+                [flag] if flag == "--help" || flag == "-h" => Ok(Command::Help),
+                [] => Ok(Command::Leaderboard { file: None }),
+                [file] if !file.starts_with('-') => Ok(Command::Leaderboard {
+                    file: Some(PathBuf::from(file)),
+                }),
+                _ => Err("leaderboard expects at most a leaderboard file".to_owned()),
+            };
+        }
+        Some("help" | "--help" | "-h") => return Ok(Command::Help),
+        _ => ("gui", args),
+    };
+
+    let mut gui = GuiArgs::default();
+    let mut run = RunArgs::default();
+    let mut tournament = TournamentArgs::default();
+    let mut party = PartyArgs::default();
+    let mut evolve = EvolveArgs::default();
+    let mut sweep = SweepArgs::default();
+    if command == "evolve" {
+        match rest.split_first() {
+            Some((script, tail)) if !script.starts_with('-') => {
+                evolve.script = PathBuf::from(script);
+                rest = tail;
+            }
+            Some((flag, _)) if flag == "--help" || flag == "-h" => return Ok(Command::Help),
+            _ => return Err("evolve expects a script".to_owned()),
+        }
+    }
+    while let Some((flag, tail)) = rest.split_first() {
+        rest = tail;
+        let mut value = || -> Result<&str, String> {
+            let (value, tail) = rest
+                .split_first()
+                .ok_or_else(|| format!("{flag} expects a value"))?;
+            rest = tail;
+            Ok(value.as_str())
+        };
+        match (command, flag.as_str()) {
+            (_, "--help" | "-h") => return Ok(Command::Help),
+            (_, "--config") => {
+                let config = Some(PathBuf::from(value()?));
+                gui.config.clone_from(&config);
+                run.config.clone_from(&config);
+                tournament.config.clone_from(&config);
+                party.config.clone_from(&config);
+                evolve.config.clone_from(&config);
+                sweep.config = config;
+            }
+            ("gui" | "run" | "tournament" | "party" | "evolve" | "sweep", "--arena") => {
+                let value = value()?;
+                let arena = match parse_size(value) {
+                    Some(size) => size,
+                    None => [number(flag, value)?; 2],
+                };
+                let arena = Some(arena);
+                gui.arena = arena;
+                run.arena = arena;
+                tournament.arena = arena;
+                party.arena = arena;
+                evolve.arena = arena;
+                sweep.arena = arena;
+            }
+            ("gui", "--window-size") => {
+                gui.window_size = Some(
+                    parse_size(value()?).ok_or_else(|| format!("{flag} expects WIDTHxHEIGHT"))?,
+                );
+            }
+            ("gui", "--fullscreen") => gui.fullscreen = true,
+            ("gui", "--resume") => gui.resume = true,
+            ("gui" | "run", "--load") => {
+                let load = Some(PathBuf::from(value()?));
+                gui.load.clone_from(&load);
+                run.load = load;
+            }
+            ("gui" | "run", "--scene") => {
+                let scene = Some(PathBuf::from(value()?));
+                gui.scene.clone_from(&scene);
+                run.scene = scene;
+            }
+            ("run", "--save-scene") => run.save_scene = Some(PathBuf::from(value()?)),
+            ("run" | "evolve", "--save") => {
+                let save = Some(PathBuf::from(value()?));
+                run.save.clone_from(&save);
+                evolve.save = save;
+            }
+            ("gui" | "run", "--record") => {
+                let record = Some(PathBuf::from(value()?));
+                gui.record.clone_from(&record);
+                run.record = record;
+            }
+            ("run" | "check" | "tournament" | "sweep", "--scripts") => {
+                let scripts = Some(PathBuf::from(value()?));
+                run.scripts.clone_from(&scripts);
+                tournament.scripts.clone_from(&scripts);
+                sweep.scripts = scripts;
+            }
+            ("run", "--ticks") => run.ticks = number(flag, value()?)?,
+            ("gui" | "run" | "tournament" | "party" | "evolve" | "sweep", "--seed") => {
+                let seed = Some(number(flag, value()?)?);
+                gui.seed = seed;
+                run.seed = seed;
+                tournament.seed = seed;
+                party.seed = seed;
+                evolve.seed = seed;
+                sweep.seed = seed;
+            }
+            ("run", "--metrics") => run.metrics = Some(PathBuf::from(value()?)),
+            ("run", "--metrics-every") => run.metrics_every = number(flag, value()?)?,
+            ("run", "--serve-metrics") => run.serve_metrics = Some(value()?.to_owned()),
+            ("run", "--events") => run.events = Some(PathBuf::from(value()?)),
+            ("run", "--stream") => run.stream = Some(value()?.to_owned()),
+            ("party", "--stream") => party.stream = value()?.to_owned(),
+            ("party", "--listen") => party.listen = value()?.to_owned(),
+            ("party", "--lobby") => party.lobby = number(flag, value()?)?,
+            ("party", "--ticks") => party.ticks = number(flag, value()?)?,
+            ("party", "--tick-rate") => party.tick_rate = number(flag, value()?)?,
+            ("party", "--population") => party.population = number(flag, value()?)?,
+            ("run", "--control") => run.control = Some(value()?.to_owned()),
+            ("run", "--genealogy") => run.genealogy = Some(PathBuf::from(value()?)),
+            ("run", "--checkpoints") => run.checkpoints = Some(PathBuf::from(value()?)),
+            ("run", "--checkpoint-every") => run.checkpoint_every = number(flag, value()?)?,
+            ("run", "--keep-checkpoints") => run.keep_checkpoints = number(flag, value()?)?,
+            ("run", "--population") => run.population = Some(number(flag, value()?)?),
+            ("tournament", "--population") => tournament.population = number(flag, value()?)?,
+            ("tournament", "--ticks") => tournament.ticks = number(flag, value()?)?,
+            ("tournament", "--matches") => tournament.matches = number(flag, value()?)?,
+            ("tournament", "--free-for-all") => tournament.free_for_all = true,
+            ("gui" | "run", "--watch-scripts") => {
+                let dir = Some(PathBuf::from(value()?));
+                gui.watch_scripts.clone_from(&dir);
+                run.watch_scripts = dir;
+            }
+            ("gui" | "run", "--watch-config") => {
+                gui.watch_config = true;
+                run.watch_config = true;
+            }
+            ("gui" | "run" | "tournament", "--script-url") => {
+                let url = value()?.to_owned();
+                gui.script_urls.push(url.clone());
+                run.script_urls.push(url.clone());
+                tournament.script_urls.push(url);
+            }
+            ("run" | "tournament" | "party", "--leaderboard") => {
+                let leaderboard = Some(PathBuf::from(value()?));
+                run.leaderboard.clone_from(&leaderboard);
+                party.leaderboard.clone_from(&leaderboard);
+                tournament.leaderboard = leaderboard;
+            }
+            ("evolve", "--population") => evolve.population = number(flag, value()?)?,
+            ("evolve", "--ticks") => evolve.ticks = number(flag, value()?)?,
+            ("evolve", "--matches") => evolve.matches = number(flag, value()?)?,
+            ("evolve", "--generations") => evolve.generations = number(flag, value()?)?,
+            ("evolve", "--size") => evolve.size = number(flag, value()?)?,
+            ("evolve", "--fitness") => evolve.fitness = value()?.parse()?,
+            ("evolve", "--mutation") => evolve.mutation = number(flag, value()?)?,
+            ("sweep", "--vary") => sweep.vary.push(value()?.parse()?),
+            ("sweep", "--samples") => sweep.samples = Some(number(flag, value()?)?),
+            ("sweep", "--replicates") => sweep.replicates = number(flag, value()?)?,
+            ("sweep", "--ticks") => sweep.ticks = number(flag, value()?)?,
+            ("sweep", "--population") => sweep.population = Some(number(flag, value()?)?),
+            ("sweep", "--jobs") => sweep.jobs = number(flag, value()?)?,
+            ("sweep", "--out") => sweep.out = Some(PathBuf::from(value()?)),
+            _ => return Err(format!("unexpected argument '{flag}' for {command}")),
+        }
+    }
+
+    Ok(match command {
+        "run" => Command::Run(Box::new(run)),
+        "tournament" => Command::Tournament(tournament),
+        "party" => Command::Party(party),
+        "evolve" => Command::Evolve(evolve),
+        "sweep" if sweep.vary.is_empty() => {
+            return Err("sweep expects at least one --vary".to_owned())
+        }
+        "sweep" => Command::Sweep(sweep),
+        "check" => Command::Check {
+            config: run.config,
+            scripts: run.scripts,
+        },
+        _ => Command::Gui(gui),
+    })
+}
+
+fn number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{flag} expects a number, got '{value}'"))
+}
+
+/// Parses a size such as `1280x720`.
+fn parse_size(size: &str) -> Option<[f32; 2]> {
+    let (width, height) = size.split_once('x')?;
+    let size = [width.trim().parse().ok()?, height.trim().parse().ok()?];
+    size.iter().all(|s: &f32| *s > 0.).then_some(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1280x720"), Some([1280., 720.]));
+        assert_eq!(parse_size("800 x 600"), Some([800., 600.]));
+        assert_eq!(parse_size("800"), None);
+        assert_eq!(parse_size("0x600"), None);
+        assert_eq!(parse_size("widexhigh"), None);
+    }
+
+    #[test]
+    fn test_gui_is_the_default() {
+        assert_eq!(parse(&[]), Ok(Command::Gui(GuiArgs::default())));
+        assert_eq!(
+            parse(&args("--arena 200 --fullscreen --scene start.json")),
+            Ok(Command::Gui(GuiArgs {
+                arena: Some([200.; 2]),
+                fullscreen: true,
+                scene: Some(PathBuf::from("start.json")),
+                ..GuiArgs::default()
+            }))
+        );
+        assert_eq!(
+            parse(&args("--arena 300x20")),
+            Ok(Command::Gui(GuiArgs {
+                arena: Some([300., 20.]),
+                ..GuiArgs::default()
+            }))
+        );
+        assert!(parse(&args("--arena wide")).is_err());
+    }
+
+    #[test]
+    fn test_parse_run() {
+        assert_eq!(
+            parse(&args(
+                "run --scripts bots/ --ticks 100000 --seed 42 --save out.json --metrics m.csv --metrics-every 10"
+            )),
+            Ok(Command::Run(Box::new(RunArgs {
+                scripts: Some(PathBuf::from("bots/")),
+                ticks: 100_000,
+                seed: Some(42),
+                save: Some(PathBuf::from("out.json")),
+                metrics: Some(PathBuf::from("m.csv")),
+                metrics_every: 10,
+                ..RunArgs::default()
+            })))
+        );
+        assert_eq!(
+            parse(&args(
+                "run --ticks 0 --stream 0.0.0.0:9001 --genealogy tree.dot --events ev.jsonl"
+            )),
+            Ok(Command::Run(Box::new(RunArgs {
+                ticks: 0,
+                events: Some(PathBuf::from("ev.jsonl")),
+                stream: Some("0.0.0.0:9001".to_owned()),
+                genealogy: Some(PathBuf::from("tree.dot")),
+                ..RunArgs::default()
+            })))
+        );
+        assert_eq!(
+            parse(&args(
+                "run --checkpoints night/ --checkpoint-every 5000 --keep-checkpoints 2 --watch-scripts scripts --watch-config"
+            )),
+            Ok(Command::Run(Box::new(RunArgs {
+                checkpoints: Some(PathBuf::from("night/")),
+                checkpoint_every: 5000,
+                keep_checkpoints: 2,
+                watch_scripts: Some(PathBuf::from("scripts")),
+                watch_config: true,
+                ..RunArgs::default()
+            })))
+        );
+    }
+
+    #[test]
+    fn test_parse_tournament() {
+        assert_eq!(
+            parse(&args(
+                "tournament --scripts bots/ --matches 3 --ticks 500 --seed 9 --free-for-all --leaderboard lb.json"
+            )),
+            Ok(Command::Tournament(TournamentArgs {
+                scripts: Some(PathBuf::from("bots/")),
+                matches: 3,
+                ticks: 500,
+                seed: Some(9),
+                free_for_all: true,
+                leaderboard: Some(PathBuf::from("lb.json")),
+                ..TournamentArgs::default()
+            }))
+        );
+        assert!(parse(&args("tournament --save out.json")).is_err());
+        assert_eq!(
+            parse(&args(
+                "tournament --script-url https://a.example/x.rhai --script-url https://b.example/y.rhai"
+            )),
+            Ok(Command::Tournament(TournamentArgs {
+                script_urls: vec![
+                    "https://a.example/x.rhai".to_owned(),
+                    "https://b.example/y.rhai".to_owned()
+                ],
+                ..TournamentArgs::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn test_parse_party() {
+        assert_eq!(
+            parse(&args(
+                "party --listen 0.0.0.0:8000 --lobby 30 --tick-rate 0 --population 20 --seed 4"
+            )),
+            Ok(Command::Party(PartyArgs {
+                listen: "0.0.0.0:8000".to_owned(),
+                lobby: 30,
+                tick_rate: 0,
+                population: 20,
+                seed: Some(4),
+                ..PartyArgs::default()
+            }))
+        );
+        assert!(parse(&args("party --scripts bots/")).is_err());
+    }
+
+    #[test]
+    fn test_parse_evolve() {
+        assert_eq!(
+            parse(&args(
+                "evolve bot.rhai --generations 5 --fitness kills --mutation 0.5 --save best.rhai"
+            )),
+            Ok(Command::Evolve(EvolveArgs {
+                script: PathBuf::from("bot.rhai"),
+                generations: 5,
+                fitness: Fitness::Kills,
+                mutation: 0.5,
+                save: Some(PathBuf::from("best.rhai")),
+                ..EvolveArgs::default()
+            }))
+        );
+        assert!(parse(&args("evolve --ticks 5")).is_err());
+        assert!(parse(&args("evolve bot.rhai --fitness speed")).is_err());
+    }
+
+    #[test]
+    fn test_parse_sweep() {
+        assert_eq!(
+            parse(&args(
+                "sweep --vary eat_damage=10..50:5 --vary speed=1..2 --samples 8 --jobs 2 --out sweep.csv"
+            )),
+            Ok(Command::Sweep(SweepArgs {
+                vary: vec![
+                    "eat_damage=10..50:5".parse().unwrap(),
+                    "speed=1..2".parse().unwrap()
+                ],
+                samples: Some(8),
+                jobs: 2,
+                out: Some(PathBuf::from("sweep.csv")),
+                ..SweepArgs::default()
+            }))
+        );
+        assert!(parse(&args("sweep --ticks 5")).is_err());
+        assert!(parse(&args("sweep --vary food_rate=1..2")).is_err());
+    }
+
+    #[test]
+    fn test_parse_check() {
+        assert_eq!(
+            parse(&args("check --config exp/world.toml")),
+            Ok(Command::Check {
+                config: Some(PathBuf::from("exp/world.toml")),
+                scripts: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_replay() {
+        assert_eq!(
+            parse(&args("replay runs/a.mswr")),
+            Ok(Command::Replay {
+                file: PathBuf::from("runs/a.mswr"),
+            })
+        );
+        assert!(parse(&args("replay")).is_err());
+        assert!(parse(&args("replay a b")).is_err());
+    }
+
+    #[test]
+    fn test_parse_verify() {
+        assert_eq!(
+            parse(&args("verify a.mswr")),
+            Ok(Command::Verify {
+                file: PathBuf::from("a.mswr"),
+                every: 100,
+            })
+        );
+        assert_eq!(
+            parse(&args("verify a.mswr --every 5")),
+            Ok(Command::Verify {
+                file: PathBuf::from("a.mswr"),
+                every: 5,
+            })
+        );
+        assert!(parse(&args("verify")).is_err());
+        assert!(parse(&args("verify a.mswr --every")).is_err());
+        assert!(parse(&args("verify a.mswr --every often")).is_err());
+    }
+
+    #[test]
+    fn test_parse_leaderboard() {
+        assert_eq!(
+            parse(&args("leaderboard")),
+            Ok(Command::Leaderboard { file: None })
+        );
+        assert_eq!(
+            parse(&args("leaderboard club.json")),
+            Ok(Command::Leaderboard {
+                file: Some(PathBuf::from("club.json")),
+            })
+        );
+        assert!(parse(&args("leaderboard a.json b.json")).is_err());
+        assert!(parse(&args("evolve bot.rhai --leaderboard lb.json")).is_err());
+    }
+
+    #[test]
+    fn test_parse_watch() {
+        assert_eq!(
+            parse(&args("watch ws://10.0.0.2:9001")),
+            Ok(Command::Watch {
+                addr: "10.0.0.2:9001".to_owned()
+            })
+        );
+        assert!(parse(&args("watch")).is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(&args("run --ticks")).is_err());
+        assert!(parse(&args("run --ticks many")).is_err());
+        assert!(parse(&args("gui --serve-metrics :9100")).is_err());
+        assert!(parse(&args("check --fullscreen")).is_err());
+        assert!(parse(&args("gui --ticks 4")).is_err());
+        assert_eq!(parse(&args("run --help")), Ok(Command::Help));
+    }
+}
@@ -0,0 +1,23 @@
+//! Where the viewer and command line keep files between runs.
+
+use std::path::PathBuf;
+
+/// `microswarm` in the user's config directory, if they have one.
+pub fn config_dir() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(dir.join("microswarm"))
+}
+
+/// Where the viewer saves the world on exit, for `--resume`.
+#[cfg(feature = "gui")]
+pub fn world_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("world.json"))
+}
+
+/// Where tournaments add their results by default.
+pub fn leaderboard_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("leaderboard.json"))
+}
@@ -0,0 +1,320 @@
+//! Writes the simulation's `tracing` spans and events to the console or a
+//! JSON Lines file.
+//!
+//! Set `MICROSWARM_LOG` to `error`, `warn` (the default), `info`, `debug`
+//! or `trace`, or `off`. At `debug` every tick is logged, and at `trace`
+//! every phase of it and every birth and death. With `MICROSWARM_LOG_FILE`
+//! set, lines go to that file as JSON instead of to stderr, one object per
+//! line:
+//!
+//! ```json
+//! {"elapsed":1.204,"error":"…","level":"WARN","message":"script failed","microbe":"…",
+//!  "spans":[{"name":"tick","tick":812},{"name":"think"}],"species":"…","target":"microswarm::systems"}
+//! ```
+
+use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{self, Write as _};
+use std::fs::File;
+use std::io::{self, LineWriter, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Subscriber;
+use tracing::{Event, Metadata};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Console,
+    Json,
+}
+
+/// A `tracing` subscriber that writes one line per event.
+pub struct Logger {
+    level: LevelFilter,
+    format: Format,
+    out: Mutex<Box<dyn Write + Send>>,
+    spans: Mutex<HashMap<u64, Span>>,
+    next_id: AtomicU64,
+    started: Instant,
+}
+
+struct Span {
+    name: &'static str,
+    fields: Map<String, Value>,
+    /// Handles to the span still alive.
+    refs: usize,
+}
+
+thread_local! {
+    /// Spans entered on this thread, innermost last.
+    static ENTERED: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Installs a [`Logger`] configured by the environment, as described in
+/// the [module docs](self).
+pub fn init() -> Result<(), String> {
+    let level = match std::env::var("MICROSWARM_LOG") {
+        Ok(level) => level
+            .parse()
+            .map_err(|_| format!("MICROSWARM_LOG should be a level such as warn, got '{level}'"))?,
+        Err(_) => LevelFilter::WARN,
+    };
+    let logger = match std::env::var_os("MICROSWARM_LOG_FILE") {
+        Some(path) => {
+            let file = File::create(&path)
+                .map_err(|e| format!("{}: {e}", std::path::Path::new(&path).display()))?;
+            Logger::new(LineWriter::new(file), Format::Json, level)
+        }
+        None => Logger::new(io::stderr(), Format::Console, level),
+    };
+    tracing::subscriber::set_global_default(logger).map_err(|e| e.to_string())
+}
+
+impl Logger {
+    pub fn new(out: impl Write + Send + 'static, format: Format, level: LevelFilter) -> Self {
+        Self {
+            level,
+            format,
+            out: Mutex::new(Box::new(out)),
+            spans: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            started: Instant::now(),
+        }
+    }
+
+    fn spans(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Span>> {
+        self.spans.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn line(&self, event: &Event, fields: Map<String, Value>) -> String {
+        let metadata = event.metadata();
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let entered = ENTERED.with(|entered| entered.borrow().clone());
+        let spans = self.spans();
+        let spans = entered.iter().filter_map(|id| spans.get(id));
+        match self.format {
+            Format::Console => {
+                let mut line = format!("{elapsed:9.3}s {:>5} ", metadata.level());
+                for span in spans {
+                    line += span.name;
+                    if !span.fields.is_empty() {
+                        let _ = write!(line, "{{{}}}", Pairs(&span.fields));
+                    }
+                    line += ":";
+                }
+                let mut fields = fields;
+                if let Some(message) = fields.remove("message") {
+                    let _ = write!(line, " {}", message.as_str().unwrap_or_default());
+                }
+                if !fields.is_empty() {
+                    let _ = write!(line, " {}", Pairs(&fields));
+                }
+                line
+            }
+            Format::Json => {
+                let mut object = Map::new();
+                object.insert("elapsed".into(), elapsed.into());
+                object.insert("level".into(), metadata.level().as_str().into());
+                object.insert("target".into(), metadata.target().into());
+                let spans = spans
+                    .map(|span| {
+                        let mut object = Map::new();
+                        object.insert("name".into(), span.name.into());
+                        object.extend(span.fields.clone());
+                        Value::Object(object)
+                    })
+                    .collect();
+                object.insert("spans".into(), Value::Array(spans));
+                object.extend(fields);
+                Value::Object(object).to_string()
+            }
+        }
+    }
+}
+
+impl Subscriber for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.level >= *metadata.level()
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(self.level)
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut fields = Fields::default();
+        attributes.record(&mut fields);
+        self.spans().insert(
+            id,
+            Span {
+                name: attributes.metadata().name(),
+                fields: fields.0,
+                refs: 1,
+            },
+        );
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        if let Some(span) = self.spans().get_mut(&span.into_u64()) {
+            let mut fields = Fields(std::mem::take(&mut span.fields));
+            values.record(&mut fields);
+            span.fields = fields.0;
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let line = self.line(event, fields.0);
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        // Logging has nowhere to report its own failures.
+        let _ = writeln!(out, "{line}");
+    }
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            if let Some(index) = entered.iter().rposition(|id| *id == span.into_u64()) {
+                entered.remove(index);
+            }
+        });
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some(span) = self.spans().get_mut(&span.into_u64()) {
+            span.refs += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut spans = self.spans();
+        let Some(data) = spans.get_mut(&span.into_u64()) else {
+            return false;
+        };
+        data.refs -= 1;
+        if data.refs == 0 {
+            spans.remove(&span.into_u64());
+            return true;
+        }
+        false
+    }
+}
+
+/// Collects an event's or span's fields as JSON values.
+#[derive(Default)]
+struct Fields(Map<String, Value>);
+
+impl Visit for Fields {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().into(), format!("{value:?}").into());
+    }
+}
+
+/// Fields written as `name=value` pairs.
+struct Pairs<'a>(&'a Map<String, Value>);
+
+impl fmt::Display for Pairs<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            match value {
+                Value::String(value) => write!(f, "{name}={value}")?,
+                value => write!(f, "{name}={value}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn log(format: Format, level: LevelFilter) -> Vec<String> {
+        let buffer = Buffer::default();
+        let logger = Logger::new(buffer.clone(), format, level);
+        tracing::subscriber::with_default(logger, || {
+            let _tick = tracing::debug_span!("tick", tick = 7).entered();
+            tracing::trace_span!("think").in_scope(|| {
+                tracing::warn!(species = "hunter", error = %"boom", "script failed");
+            });
+            tracing::info!(count = 3, "done");
+        });
+        let out = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        out.lines().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn test_console() {
+        let lines = log(Format::Console, LevelFilter::TRACE);
+        assert_eq!(lines.len(), 2);
+        assert!(
+            lines[0].ends_with(" WARN tick{tick=7}:think: script failed error=boom species=hunter")
+        );
+        assert!(lines[1].ends_with(" INFO tick{tick=7}: done count=3"));
+    }
+
+    #[test]
+    fn test_json_and_level() {
+        let lines = log(Format::Json, LevelFilter::WARN);
+        assert_eq!(lines.len(), 1);
+        let line: Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["message"], "script failed");
+        assert_eq!(line["species"], "hunter");
+        // The spans were below the level, so they weren't recorded.
+        assert_eq!(line["spans"], Value::Array(Vec::new()));
+        assert_eq!(log(Format::Json, LevelFilter::OFF).len(), 0);
+    }
+}
@@ -0,0 +1,36 @@
+#[cfg(all(target_arch = "wasm32", not(feature = "gui")))]
+compile_error!("the browser build is the viewer, so it needs the `gui` feature");
+
+#[cfg(feature = "gui")]
+mod audio;
+#[cfg(not(target_arch = "wasm32"))]
+mod cli;
+mod dirs;
+#[cfg(not(target_arch = "wasm32"))]
+mod logging;
+#[cfg(not(target_arch = "wasm32"))]
+mod native;
+#[cfg(all(feature = "gui", not(target_arch = "wasm32")))]
+mod player;
+#[cfg(not(target_arch = "wasm32"))]
+mod serve;
+#[cfg(feature = "gui")]
+mod settings;
+#[cfg(all(feature = "gui", feature = "net", not(target_arch = "wasm32")))]
+mod spectate;
+#[cfg(feature = "gui")]
+mod stats;
+#[cfg(feature = "gui")]
+mod ui;
+#[cfg(all(feature = "gui", any(target_arch = "wasm32", test)))]
+mod web;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> std::process::ExitCode {
+    native::main()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    web::start();
+}
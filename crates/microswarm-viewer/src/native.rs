@@ -0,0 +1,969 @@
+//! The desktop viewer and the command line.
+
+use microswarm::checkpoint::Checkpoints;
+use microswarm::config::{Channel, Config, SpeciesConfig};
+use microswarm::events::EventLog;
+use microswarm::evolve::{Evolution, Template};
+#[cfg(feature = "net")]
+use microswarm::fetch;
+use microswarm::genealogy::Genealogy;
+use microswarm::islands::Archipelago;
+use microswarm::leaderboard::Leaderboard;
+#[cfg(feature = "net")]
+use microswarm::lobby::{self, Lobby};
+use microswarm::metrics::{self, Exporter};
+use microswarm::reload::ConfigWatcher;
+use microswarm::replay::{Recorder, Replay};
+use microswarm::rules::Reason;
+use microswarm::sandbox::Sandbox;
+use microswarm::scene::Scene;
+#[cfg(feature = "net")]
+use microswarm::stream::Broadcaster;
+#[cfg(all(feature = "gui", feature = "net"))]
+use microswarm::stream::Spectator;
+use microswarm::sweep::{self, Sampling, Sweep};
+use microswarm::tournament::{Format, Scorer, Tournament};
+use microswarm::verify;
+use microswarm::watcher::{self, ScriptWatcher, STARTER_POPULATION};
+#[cfg(feature = "gui")]
+use microswarm::BOX_SIZE;
+use microswarm::{palette::Palette, scripts, Simulation, StepReport};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+#[cfg(feature = "net")]
+use std::thread;
+#[cfg(feature = "net")]
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::serve::Services;
+#[cfg(all(feature = "gui", feature = "net"))]
+use crate::spectate;
+use crate::{cli, dirs, logging};
+#[cfg(feature = "gui")]
+use crate::{player, settings, ui};
+
+const DEFAULT_CONFIG: &str = "world.toml";
+#[cfg(not(feature = "gui"))]
+const NO_GUI: &str = "this build of microswarm has no windows; rebuild it with the gui feature";
+/// Microbes spawned by `run --scripts` or `sweep --scripts` when no
+/// population is given.
+const DEFAULT_POPULATION: usize = 500;
+
+pub fn main() -> ExitCode {
+    if let Err(err) = logging::init() {
+        eprintln!("error: {err}");
+        return ExitCode::FAILURE;
+    }
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let result = match cli::parse(&args) {
+        Ok(cli::Command::Gui(args)) => gui(args),
+        Ok(cli::Command::Run(args)) => run(*args),
+        Ok(cli::Command::Tournament(args)) => tournament(args),
+        Ok(cli::Command::Party(args)) => party(args),
+        Ok(cli::Command::Evolve(args)) => evolve(args),
+        Ok(cli::Command::Sweep(args)) => sweep(args),
+        Ok(cli::Command::Check { config, scripts }) => check(config.as_deref(), scripts.as_deref()),
+        Ok(cli::Command::Replay { file }) => replay(&file),
+        Ok(cli::Command::Watch { addr }) => watch(&addr),
+        Ok(cli::Command::Verify { file, every }) => verify(&file, every),
+        Ok(cli::Command::Leaderboard { file }) => leaderboard(file.as_deref()),
+        Ok(cli::Command::Help) => {
+            println!("{}", cli::USAGE);
+            Ok(())
+        }
+        Err(err) => Err(format!("{err}\n\n{}", cli::USAGE)),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+fn gui(args: cli::GuiArgs) -> Result<(), String> {
+    let load = match args.resume {
+        true => Some(dirs::world_path().ok_or("no config directory to resume from")?),
+        false => args.load,
+    };
+    let mut sim = match (load, &args.scene) {
+        (Some(path), _) => load_world(&path)?,
+        (None, Some(path)) => load_scene(path)?.0,
+        (None, None) => {
+            let mut config = load_config(args.config.as_deref())?;
+            // The arena is independent of the window, which scales it to fit.
+            resize(&mut config, args.arena);
+            config.seed = args.seed.or(config.seed);
+            config.build().map_err(|e| e.to_string())?.0
+        }
+    };
+    for (name, source) in fetch_scripts(&args.script_urls, sim.sandbox())? {
+        let species = watcher::join(&mut sim, source, STARTER_POPULATION);
+        sim.set_species_name(species, &name);
+    }
+
+    // Command line options apply to this launch only and aren't saved.
+    let settings = settings::Settings::load();
+    let window_size = args
+        .window_size
+        .or(settings.window_size)
+        .unwrap_or([BOX_SIZE * 2., BOX_SIZE * 2.]);
+    let fullscreen = settings.fullscreen || args.fullscreen;
+
+    let native_options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size(window_size)
+            .with_min_inner_size([200., 200.])
+            .with_fullscreen(fullscreen),
+        ..Default::default()
+    };
+    let recorder = match &args.record {
+        Some(path) => Some(create_recorder(path, &sim)?),
+        None => None,
+    };
+    let watcher = match &args.watch_scripts {
+        Some(dir) => Some(ScriptWatcher::new(dir).map_err(|e| format!("{}: {e}", dir.display()))?),
+        None => None,
+    };
+    let reloader = match args.watch_config {
+        true => Some(watch_config(args.config.as_deref())?),
+        false => None,
+    };
+    eframe::run_native(
+        "Game Visualization",
+        native_options,
+        Box::new(|_cc| {
+            let mut app = ui::App::new(sim, settings);
+            if let Some(recorder) = recorder {
+                app = app.record(recorder);
+            }
+            if let Some(watcher) = watcher {
+                app = app.watch_scripts(watcher);
+            }
+            if let Some(reloader) = reloader {
+                app = app.watch_config(reloader);
+            }
+            Ok(Box::new(app))
+        }),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Plays back a recording in a window.
+#[cfg(feature = "gui")]
+fn replay(path: &Path) -> Result<(), String> {
+    let replay = Replay::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    if replay.is_empty() {
+        return Err(format!("{} has no frames", path.display()));
+    }
+    let settings = settings::Settings::load();
+    let native_options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size(
+                settings
+                    .window_size
+                    .unwrap_or([BOX_SIZE * 2., BOX_SIZE * 2.]),
+            )
+            .with_min_inner_size([200., 200.]),
+        ..Default::default()
+    };
+    eframe::run_native(
+        "Replay",
+        native_options,
+        Box::new(move |_cc| Ok(Box::new(player::Player::new(replay, &settings)))),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Simulates a recording again and reports where it stops matching.
+fn verify(path: &Path, every: u64) -> Result<(), String> {
+    let error = |e: io::Error| format!("{}: {e}", path.display());
+    let mut replay = Replay::open(path).map_err(error)?;
+    let world = replay.world().map_err(error)?.ok_or_else(|| {
+        format!(
+            "{} doesn't keep the world it started from; record it again with `run --record`",
+            path.display()
+        )
+    })?;
+    let verification = verify::verify(world, &mut replay, every).map_err(|e| e.to_string())?;
+    match verification.divergence {
+        None => {
+            println!(
+                "ok: {} ticks match the recording ({} checked)",
+                verification.tick, verification.checked
+            );
+            Ok(())
+        }
+        Some(divergence) => {
+            println!("diverged by tick {}", divergence.tick);
+            match divergence.last_match {
+                Some(tick) => println!("last match: tick {tick}"),
+                None => println!("last match: none"),
+            }
+            println!("expected:   {:016x}", divergence.expected);
+            println!("simulated:  {:016x}", divergence.actual);
+            println!("difference: {}", divergence.detail);
+            Err(format!("{} doesn't replay the same", path.display()))
+        }
+    }
+}
+
+/// Opens a window onto a run streamed by `run --stream`.
+#[cfg(all(feature = "gui", feature = "net"))]
+fn watch(addr: &str) -> Result<(), String> {
+    let spectator = Spectator::connect(addr).map_err(|e| format!("{addr}: {e}"))?;
+    let settings = settings::Settings::load();
+    let native_options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size(
+                settings
+                    .window_size
+                    .unwrap_or([BOX_SIZE * 2., BOX_SIZE * 2.]),
+            )
+            .with_min_inner_size([200., 200.]),
+        ..Default::default()
+    };
+    eframe::run_native(
+        &format!("Microswarm · {addr}"),
+        native_options,
+        Box::new(move |_cc| Ok(Box::new(spectate::Spectate::new(spectator, &settings)))),
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "gui"))]
+fn gui(_args: cli::GuiArgs) -> Result<(), String> {
+    Err(NO_GUI.to_owned())
+}
+
+#[cfg(not(feature = "gui"))]
+fn replay(_path: &Path) -> Result<(), String> {
+    Err(NO_GUI.to_owned())
+}
+
+#[cfg(not(all(feature = "gui", feature = "net")))]
+fn watch(_addr: &str) -> Result<(), String> {
+    Err("watching a stream needs a build with the gui and net features".to_owned())
+}
+
+/// Runs the simulation without a window and prints how each species fared.
+fn run(args: cli::RunArgs) -> Result<(), String> {
+    let mut config = load_config(args.config.as_deref())?;
+    resize(&mut config, args.arena);
+    config.seed = args.seed.or(config.seed);
+    if let Some(dir) = &args.scripts {
+        config.species = species_from(read_scripts(dir)?);
+        config = config.with_population(args.population.unwrap_or(DEFAULT_POPULATION));
+    } else if let Some(population) = args.population {
+        config = config.with_population(population);
+    }
+    if config.islands.is_some() && args.load.is_none() && args.scene.is_none() {
+        return run_islands(&config, &args);
+    }
+    let (mut sim, mut species) = match (&args.load, &args.scene) {
+        (Some(path), _) => {
+            let sim = load_world(path)?;
+            let species = sim.species().into_iter();
+            let species = species.map(|id| (sim.species_name(id), id)).collect();
+            (sim, species)
+        }
+        (None, Some(path)) => load_scene(path)?,
+        (None, None) => {
+            let (sim, ids) = config.build().map_err(|e| e.to_string())?;
+            let names = config.species.into_iter().map(|s| s.name);
+            (sim, names.zip(ids).collect::<Vec<_>>())
+        }
+    };
+    for (name, source) in fetch_scripts(&args.script_urls, sim.sandbox())? {
+        let id = watcher::join(&mut sim, source, STARTER_POPULATION);
+        sim.set_species_name(id, &name);
+        species.push((name, id));
+    }
+
+    let mut recorder = match &args.record {
+        Some(path) => Some(create_recorder(path, &sim)?),
+        None => None,
+    };
+    let mut exporter = match &args.metrics {
+        Some(path) => {
+            let mut exporter = Exporter::create(path, args.metrics_every)
+                .map_err(|e| format!("{}: {e}", path.display()))?;
+            for (name, id) in &species {
+                exporter.name(*id, name);
+            }
+            Some(exporter)
+        }
+        None => None,
+    };
+    let mut events = match &args.events {
+        Some(path) => {
+            let mut events =
+                EventLog::create(path).map_err(|e| format!("{}: {e}", path.display()))?;
+            for (name, id) in &species {
+                events.name(*id, name);
+            }
+            Some(events)
+        }
+        None => None,
+    };
+    let mut services = Services::start(&args, &sim, &species)?;
+    let mut genealogy = args.genealogy.as_ref().map(|_| {
+        let mut genealogy = Genealogy::new();
+        for (name, id) in &species {
+            genealogy.name(*id, name);
+        }
+        genealogy.record(&sim.snapshot(), &StepReport::default());
+        genealogy
+    });
+    let mut checkpoints = match &args.checkpoints {
+        Some(dir) => Some(
+            Checkpoints::new(dir, args.checkpoint_every, args.keep_checkpoints)
+                .map_err(|e| format!("{}: {e}", dir.display()))?,
+        ),
+        None => None,
+    };
+    let mut watcher = match &args.watch_scripts {
+        Some(dir) => Some(ScriptWatcher::new(dir).map_err(|e| format!("{}: {e}", dir.display()))?),
+        None => None,
+    };
+    let mut reloader = match args.watch_config {
+        true => Some(watch_config(args.config.as_deref())?),
+        false => None,
+    };
+    let mut scorer = args.leaderboard.as_ref().map(|_| Scorer::default());
+    let (mut births, mut deaths, mut kills) = (0, 0, 0);
+    let mut ticks = 0;
+    while args.ticks == 0 || ticks < args.ticks {
+        if sim.result().is_some() {
+            break;
+        }
+        if !services.poll(&mut sim, &species) {
+            continue;
+        }
+        if let Some(watcher) = &mut watcher {
+            // A directory that can't be read now may be back later.
+            let joined = watcher.poll(&mut sim).unwrap_or_else(|error| {
+                tracing::warn!(%error, "couldn't check for new scripts");
+                Vec::new()
+            });
+            for joined in joined {
+                eprintln!("{} joined from {}", joined.name, joined.path.display());
+                if let Some(exporter) = &mut exporter {
+                    exporter.name(joined.species, &joined.name);
+                }
+                if let Some(events) = &mut events {
+                    events.name(joined.species, &joined.name);
+                }
+                services.name(joined.species, &joined.name);
+                if let Some(genealogy) = &mut genealogy {
+                    genealogy.name(joined.species, &joined.name);
+                }
+                species.push((joined.name, joined.species));
+            }
+        }
+        if let Some(reloader) = &mut reloader {
+            match reloader.poll(&mut sim) {
+                Ok(Some(changes)) => eprintln!("{}: {changes}", reloader.path().display()),
+                Ok(None) => {}
+                // The run carries on with what it has until the file is fixed.
+                Err(error) => eprintln!("warning: not reloading: {error}"),
+            }
+        }
+        ticks += 1;
+        sim.step().map_err(|e| e.to_string())?;
+        let report = sim.world().report();
+        births += report.births();
+        deaths += report.deaths().count();
+        kills += report.kills();
+        if let Some(events) = &mut events {
+            events.record(report).map_err(|e| e.to_string())?;
+        }
+        if let (Some(exporter), Some(stats)) = (&mut exporter, sim.world().stats().latest()) {
+            exporter.record(stats).map_err(|e| e.to_string())?;
+        }
+        if let Some(scorer) = &mut scorer {
+            scorer.record(sim.world());
+        }
+        if let Some(checkpoints) = &mut checkpoints {
+            // A failed checkpoint shouldn't end the run it's protecting.
+            if let Err(error) = checkpoints.record(&sim) {
+                tracing::warn!(%error, "couldn't save a checkpoint");
+            }
+        }
+        if recorder.is_none() && genealogy.is_none() && !services.want_snapshots() {
+            continue;
+        }
+        let snapshot = sim.snapshot();
+        services.record(&snapshot, report);
+        if let Some(recorder) = &mut recorder {
+            recorder.record(&snapshot).map_err(|e| e.to_string())?;
+        }
+        if let Some(genealogy) = &mut genealogy {
+            genealogy.record(&snapshot, report);
+        }
+    }
+    if let Some(recorder) = recorder {
+        recorder.finish().map_err(|e| e.to_string())?;
+    }
+    if let Some(exporter) = exporter {
+        exporter.finish().map_err(|e| e.to_string())?;
+    }
+    if let Some(events) = events {
+        events.finish().map_err(|e| e.to_string())?;
+    }
+    if let (Some(genealogy), Some(path)) = (genealogy, &args.genealogy) {
+        genealogy
+            .save(path)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+    }
+
+    print_species(&sim, &species);
+    println!();
+    println!("seed:   {}", sim.seed());
+    println!("ticks:  {}", sim.world().tick());
+    println!("alive:  {}", sim.microbes().count());
+    println!("births: {births}");
+    println!("deaths: {deaths} ({kills} eaten)");
+    print_winner(&sim);
+    if let (Some(scorer), Some(path)) = (scorer, &args.leaderboard) {
+        add_match(path, &scorer, &sim, &species)?;
+    }
+    if let Some(path) = &args.save {
+        sim.save(path)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+    }
+    if let Some(path) = &args.save_scene {
+        Scene::capture(sim.world())
+            .save(path)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Runs a config with `[islands]` and prints each island's species.
+fn run_islands(config: &Config, args: &cli::RunArgs) -> Result<(), String> {
+    let outputs = [
+        ("--record", args.record.is_some()),
+        ("--metrics", args.metrics.is_some()),
+        ("--events", args.events.is_some()),
+        ("--serve-metrics", args.serve_metrics.is_some()),
+        ("--stream", args.stream.is_some()),
+        ("--control", args.control.is_some()),
+        ("--genealogy", args.genealogy.is_some()),
+        ("--checkpoints", args.checkpoints.is_some()),
+        ("--save", args.save.is_some()),
+        ("--save-scene", args.save_scene.is_some()),
+        ("--leaderboard", args.leaderboard.is_some()),
+        ("--watch-scripts", args.watch_scripts.is_some()),
+        ("--script-url", !args.script_urls.is_empty()),
+        ("--watch-config", args.watch_config),
+    ];
+    if let Some((flag, _)) = outputs.iter().find(|(_, given)| *given) {
+        return Err(format!(
+            "{flag} can't be used with a config that has [islands]"
+        ));
+    }
+    let mut archipelago = Archipelago::new(config).map_err(|e| e.to_string())?;
+    let mut migrations = 0;
+    while args.ticks == 0 || archipelago.tick() < args.ticks {
+        archipelago.step().map_err(|e| e.to_string())?;
+        migrations += archipelago.migrations().len();
+    }
+
+    println!(
+        "{:>6} {:<16} {:>10} {:>12}",
+        "island", "species", "population", "mean energy"
+    );
+    for (island, sim) in archipelago.islands().iter().enumerate() {
+        let stats = sim.world().stats().latest().cloned().unwrap_or_default();
+        for id in archipelago.species(island) {
+            let stats = stats.species.get(id).copied().unwrap_or_default();
+            println!(
+                "{island:>6} {:<16} {:>10} {:>12.1}",
+                sim.species_name(*id),
+                stats.population,
+                stats.mean_energy
+            );
+        }
+    }
+    println!();
+    println!("seed:   {}", archipelago.islands()[0].seed());
+    println!("ticks:  {}", archipelago.tick());
+    println!("migrations: {migrations}");
+    Ok(())
+}
+
+#[cfg(not(feature = "net"))]
+fn party(_args: cli::PartyArgs) -> Result<(), String> {
+    Err("party needs a build with the net feature".to_owned())
+}
+
+/// Takes scripts from players over HTTP until the lobby closes, then plays
+/// them all in one match streamed to spectators.
+#[cfg(feature = "net")]
+fn party(args: cli::PartyArgs) -> Result<(), String> {
+    let mut config = load_config(args.config.as_deref())?;
+    resize(&mut config, args.arena);
+    config.seed = args.seed.or(config.seed);
+    let lobby = Lobby::open(args.listen.as_str(), config.sandbox.clone())
+        .map_err(|e| format!("{}: {e}", args.listen))?;
+    // Spectators can tune in while the lobby is open.
+    let broadcaster = Broadcaster::serve(args.stream.as_str(), config.arena)
+        .map_err(|e| format!("{}: {e}", args.stream))?;
+    eprintln!(
+        "taking scripts at http://{}/scripts for {}s",
+        lobby.addr(),
+        args.lobby
+    );
+    eprintln!("streaming to ws://{}", broadcaster.addr());
+
+    let closes = Instant::now() + Duration::from_secs(args.lobby);
+    let mut announced = 0;
+    let mut announce = |entries: &[lobby::Entry]| {
+        for entry in &entries[announced..] {
+            eprintln!("{} entered from {}", entry.name, entry.from);
+        }
+        announced = entries.len();
+    };
+    while Instant::now() < closes {
+        thread::sleep(Duration::from_millis(200));
+        announce(&lobby.entries());
+    }
+    let entries = lobby.close();
+    announce(&entries);
+    if entries.len() < 2 {
+        return Err(format!(
+            "a match needs at least two scripts, but {} came in",
+            entries.len()
+        ));
+    }
+
+    let scripts = entries.into_iter().map(|e| (e.name, e.source)).collect();
+    config.species = species_from(scripts);
+    let population = args.population * config.species.len();
+    config = config.with_population(population);
+    let (mut sim, ids) = config.build().map_err(|e| e.to_string())?;
+    let species = config
+        .species
+        .into_iter()
+        .map(|s| s.name)
+        .zip(ids)
+        .collect::<Vec<_>>();
+    eprintln!("the match is on with {} species", species.len());
+    let tick = match args.tick_rate {
+        0 => Duration::ZERO,
+        rate => Duration::from_secs(1) / rate,
+    };
+    let mut scorer = Scorer::default();
+    while sim.world().tick() < args.ticks && sim.result().is_none() {
+        let started = Instant::now();
+        sim.step().map_err(|e| e.to_string())?;
+        scorer.record(sim.world());
+        broadcaster.broadcast(&sim.snapshot());
+        thread::sleep(tick.saturating_sub(started.elapsed()));
+    }
+
+    print_species(&sim, &species);
+    println!();
+    println!("seed:   {}", sim.seed());
+    println!("ticks:  {}", sim.world().tick());
+    print_winner(&sim);
+    if let Some(path) = &args.leaderboard {
+        add_match(path, &scorer, &sim, &species)?;
+    }
+    Ok(())
+}
+
+/// Prints how many of each species are alive, and how well fed they are.
+fn print_species(sim: &Simulation, species: &[(String, Uuid)]) {
+    println!(
+        "{:<16} {:>10} {:>12}",
+        "species", "population", "mean energy"
+    );
+    for (name, species) in species {
+        let (count, energy) = sim
+            .microbes()
+            .filter(|m| m.species == *species)
+            .fold((0, 0.), |(count, energy), m| (count + 1, energy + m.energy));
+        let mean = if count == 0 {
+            0.
+        } else {
+            energy / count as f32
+        };
+        println!("{name:<16} {count:>10} {mean:>12.1}");
+    }
+}
+
+/// Prints who won a config's `[rules]` match, if it's over.
+fn print_winner(sim: &Simulation) {
+    let Some(result) = sim.result() else {
+        return;
+    };
+    let reason = match result.reason {
+        Reason::LastStanding => "last standing",
+        Reason::Extinction => "everyone died out",
+        Reason::Biomass => "most biomass",
+        Reason::Hill => "held the hill",
+        Reason::TimeLimit => "time limit",
+    };
+    match result.winner {
+        Some(winner) => println!("winner: {} ({reason})", sim.species_name(winner)),
+        None => println!("winner: none, a draw ({reason})"),
+    }
+}
+
+/// Adds the run's scores to the leaderboard at `path`.
+fn add_match(
+    path: &Path,
+    scorer: &Scorer,
+    sim: &Simulation,
+    species: &[(String, Uuid)],
+) -> Result<(), String> {
+    let ids = species.iter().map(|(_, id)| *id).collect::<Vec<_>>();
+    let scores = scorer.scores(sim.world(), &ids);
+    let names = species.iter().map(|(name, _)| name.clone());
+    let mut board = Leaderboard::load(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    board.add_match(&names.zip(scores).collect::<Vec<_>>());
+    board
+        .save(path)
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    println!("leaderboard: {}", path.display());
+    Ok(())
+}
+
+/// Plays the species against each other and prints the standings.
+fn tournament(args: cli::TournamentArgs) -> Result<(), String> {
+    let mut config = load_config(args.config.as_deref())?;
+    resize(&mut config, args.arena);
+    config.seed = Some(args.seed.or(config.seed).unwrap_or_else(rand::random));
+    let mut entrants = match &args.scripts {
+        Some(dir) => species_from(read_scripts(dir)?),
+        None => std::mem::take(&mut config.species),
+    };
+    entrants.extend(species_from(fetch_scripts(
+        &args.script_urls,
+        &config.sandbox,
+    )?));
+    if entrants.len() < 2 {
+        return Err("a tournament needs at least two species".to_owned());
+    }
+    let seed = config.seed;
+    let tournament = Tournament {
+        config,
+        entrants,
+        format: match args.free_for_all {
+            true => Format::FreeForAll,
+            false => Format::Pairs,
+        },
+        matches: args.matches,
+        ticks: args.ticks,
+        population: args.population,
+    };
+    let standings = tournament.run()?;
+
+    println!(
+        "{:>4} {:<16} {:>7} {:>5} {:>7} {:>9} {:>9} {:>7}",
+        "rank", "species", "matches", "wins", "points", "survivors", "biomass", "kills"
+    );
+    for (rank, standing) in standings.iter().enumerate() {
+        println!(
+            "{:>4} {:<16} {:>7} {:>5} {:>7.1} {:>9.1} {:>9.0} {:>7.1}",
+            rank + 1,
+            standing.name,
+            standing.matches,
+            standing.wins,
+            standing.points,
+            standing.survivors,
+            standing.biomass,
+            standing.kills
+        );
+    }
+    println!();
+    println!("seed:   {}", seed.unwrap_or_default());
+    println!("ticks:  {} per match", args.ticks);
+
+    // The tournament itself has finished, so a leaderboard that can't be
+    // written is only worth a warning.
+    let path = args.leaderboard.or_else(dirs::leaderboard_path);
+    if let Some(path) = path {
+        let result = Leaderboard::load(&path).and_then(|mut board| {
+            board.add_standings(&standings);
+            board.save(&path)
+        });
+        match result {
+            Ok(()) => println!("leaderboard: {}", path.display()),
+            Err(error) => {
+                tracing::warn!(%error, path = %path.display(), "couldn't update the leaderboard")
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints every script's results across tournaments and scored runs.
+fn leaderboard(path: Option<&Path>) -> Result<(), String> {
+    let path = match path {
+        Some(path) => path.to_owned(),
+        None => dirs::leaderboard_path().ok_or("no config directory to find the leaderboard in")?,
+    };
+    let board = Leaderboard::load(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+    if board.scripts.is_empty() {
+        println!("no results in {} yet", path.display());
+        return Ok(());
+    }
+    println!(
+        "{:>4} {:<16} {:>7} {:>5} {:>6} {:>9} {:>9}",
+        "rank", "script", "matches", "wins", "win %", "survival", "biomass"
+    );
+    for (rank, (name, record)) in board.ranked().into_iter().enumerate() {
+        println!(
+            "{:>4} {:<16} {:>7} {:>5} {:>6.1} {:>9.1} {:>9.0}",
+            rank + 1,
+            name,
+            record.matches,
+            record.wins,
+            record.win_rate() * 100.,
+            record.mean_survival(),
+            record.mean_biomass()
+        );
+    }
+    Ok(())
+}
+
+/// Evolves a script's marked constants and prints the best values found.
+fn evolve(args: cli::EvolveArgs) -> Result<(), String> {
+    let path = &args.script;
+    let source = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let template = Template::parse(&source).map_err(|e| format!("{}: {e}", path.display()))?;
+    template
+        .check()
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut config = load_config(args.config.as_deref())?;
+    resize(&mut config, args.arena);
+    config.seed = Some(args.seed.or(config.seed).unwrap_or_else(rand::random));
+    let seed = config.seed;
+    let evolution = Evolution {
+        template,
+        config,
+        fitness: args.fitness,
+        size: args.size,
+        generations: args.generations,
+        matches: args.matches,
+        ticks: args.ticks,
+        population: args.population,
+        mutation: args.mutation,
+    };
+
+    println!("{:>10} {:>12} {:>12}", "generation", "best", "mean");
+    let best = evolution.run(|generation| {
+        println!(
+            "{:>10} {:>12.1} {:>12.1}",
+            generation.number, generation.best.fitness, generation.mean
+        );
+    })?;
+    println!();
+    for (param, value) in evolution.template.params().iter().zip(&best.genome) {
+        println!("{} = {}", param.name, param.format(*value));
+    }
+    println!();
+    println!("{}:  {:.1}", args.fitness, best.fitness);
+    println!("seed: {}", seed.unwrap_or_default());
+    if let Some(path) = &args.save {
+        std::fs::write(path, evolution.template.render(&best.genome))
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Runs the config across a range of tuning values and writes the results.
+fn sweep(args: cli::SweepArgs) -> Result<(), String> {
+    let mut config = load_config(args.config.as_deref())?;
+    resize(&mut config, args.arena);
+    config.seed = Some(args.seed.or(config.seed).unwrap_or_else(rand::random));
+    if let Some(dir) = &args.scripts {
+        config.species = species_from(read_scripts(dir)?);
+        config = config.with_population(args.population.unwrap_or(DEFAULT_POPULATION));
+    } else if let Some(population) = args.population {
+        config = config.with_population(population);
+    }
+    let sweep = Sweep {
+        config,
+        axes: args.vary,
+        sampling: args.samples.map_or(Sampling::Grid, Sampling::Random),
+        replicates: args.replicates,
+        ticks: args.ticks,
+        jobs: args.jobs,
+    };
+    let rows = sweep.run()?;
+    match &args.out {
+        Some(path) => {
+            let mut out =
+                BufWriter::new(File::create(path).map_err(|e| format!("{}: {e}", path.display()))?);
+            sweep::write(
+                &mut out,
+                &sweep.axes,
+                &rows,
+                metrics::Format::from_path(path),
+            )
+            .and_then(|()| out.flush())
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+            eprintln!("wrote {} rows to {}", rows.len(), path.display());
+        }
+        None => sweep::write(
+            &mut std::io::stdout().lock(),
+            &sweep.axes,
+            &rows,
+            metrics::Format::Csv,
+        )
+        .map_err(|e| e.to_string())?,
+    }
+    Ok(())
+}
+
+/// Parses every script and reports syntax errors.
+fn check(config: Option<&Path>, dir: Option<&Path>) -> Result<(), String> {
+    let scripts = match dir {
+        Some(dir) => read_scripts(dir)?,
+        None => load_config(config)?
+            .species
+            .into_iter()
+            .map(|species| (species.name, species.script))
+            .collect(),
+    };
+    let mut failed = 0;
+    for (name, source) in &scripts {
+        match scripts::check(source) {
+            Ok(()) => println!("ok     {name}"),
+            Err(err) => {
+                failed += 1;
+                println!("error  {name}: {err}");
+            }
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!(
+            "{failed} of {} scripts failed to parse",
+            scripts.len()
+        )),
+    }
+}
+
+/// Loads `path`, or `world.toml` in the working directory if it exists, or
+/// falls back to the built-in defaults.
+fn load_config(path: Option<&Path>) -> Result<Config, String> {
+    match config_path(path) {
+        Some(path) => Config::load(path).map_err(|e| e.to_string()),
+        None => Ok(Config::default()),
+    }
+}
+
+/// `path`, or `world.toml` in the working directory if it exists.
+fn config_path(path: Option<&Path>) -> Option<PathBuf> {
+    let default = Some(Path::new(DEFAULT_CONFIG)).filter(|p| p.exists());
+    path.or(default).map(Path::to_owned)
+}
+
+/// Watches the config that [`load_config`] loads, for `--watch-config`.
+fn watch_config(path: Option<&Path>) -> Result<ConfigWatcher, String> {
+    let path = config_path(path)
+        .ok_or_else(|| format!("--watch-config needs --config or a {DEFAULT_CONFIG}"))?;
+    ConfigWatcher::new(path).map_err(|e| e.to_string())
+}
+
+/// Applies `--arena`, if given, to `config`.
+fn resize(config: &mut Config, arena: Option<[f32; 2]>) {
+    if let Some([width, height]) = arena {
+        config.arena = width;
+        config.arena_height = Some(height);
+    }
+}
+
+/// Builds the world in a scene file, with its species' names.
+fn load_scene(path: &Path) -> Result<(Simulation, Vec<(String, Uuid)>), String> {
+    let error = |e: &dyn std::fmt::Display| format!("{}: {e}", path.display());
+    let scene = Scene::load(path).map_err(|e| error(&e))?;
+    let (world, ids) = scene.build().map_err(|e| error(&e))?;
+    let names = scene.species.into_iter().map(|s| s.name);
+    Ok((Simulation::from(world), names.zip(ids).collect()))
+}
+
+/// Starts a recording with the world's current state as its first frame.
+fn create_recorder(path: &Path, sim: &Simulation) -> Result<Recorder<BufWriter<File>>, String> {
+    let error = |e: std::io::Error| format!("{}: {e}", path.display());
+    let mut recorder = Recorder::create_for_world(path, sim.world()).map_err(error)?;
+    recorder.record(&sim.snapshot()).map_err(error)?;
+    Ok(recorder)
+}
+
+/// Loads a saved world, or the newest checkpoint when `path` is a directory.
+fn load_world(path: &Path) -> Result<Simulation, String> {
+    let error = |e: io::Error| format!("{}: {e}", path.display());
+    let path = match path.is_dir() {
+        true => {
+            let latest = Checkpoints::latest(path)
+                .map_err(error)?
+                .ok_or_else(|| format!("no checkpoints in {}", path.display()))?;
+            eprintln!("resuming {}", latest.display());
+            latest
+        }
+        false => path.to_owned(),
+    };
+    Simulation::load(&path).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+fn read_scripts(dir: &Path) -> Result<Vec<(String, String)>, String> {
+    let scripts = scripts::load_dir(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+    if scripts.is_empty() {
+        return Err(format!("no .rhai scripts in {}", dir.display()));
+    }
+    Ok(scripts)
+}
+
+/// Downloads and vets the script at each `--script-url`, returning
+/// `(name, source)` pairs.
+#[cfg(feature = "net")]
+fn fetch_scripts(urls: &[String], sandbox: &Sandbox) -> Result<Vec<(String, String)>, String> {
+    urls.iter()
+        .map(|url| {
+            let fetched = fetch::fetch(url, sandbox).map_err(|e| format!("{url}: {e}"))?;
+            Ok((fetched.name, fetched.source))
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "net"))]
+fn fetch_scripts(urls: &[String], _sandbox: &Sandbox) -> Result<Vec<(String, String)>, String> {
+    match urls.is_empty() {
+        true => Ok(Vec::new()),
+        false => Err("--script-url needs a build with the net feature".to_owned()),
+    }
+}
+
+/// One species per script, each with its own Okabe-Ito color.
+fn species_from(scripts: Vec<(String, String)>) -> Vec<SpeciesConfig> {
+    let colors = Palette::OkabeIto.colors(scripts.len(), &mut rand::thread_rng());
+    scripts
+        .into_iter()
+        .zip(colors)
+        .map(|((name, script), color)| SpeciesConfig {
+            name,
+            script,
+            count: 0,
+            color: [color.r(), color.g(), color.b()].map(Channel::Fixed),
+            remote: None,
+            producer: false,
+            spiky: false,
+            jaws: Default::default(),
+            eyes: Default::default(),
+            mutation: Default::default(),
+            cooldown: Default::default(),
+            castes: Vec::new(),
+        })
+        .collect()
+}
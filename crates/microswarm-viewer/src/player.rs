@@ -0,0 +1,171 @@
+use egui::{Color32, Sense, Stroke};
+use web_time::Instant;
+
+use crate::settings::{Settings, Theme};
+use crate::ui::{draw_microbes, ArenaView, ColorMode, ScaleMode};
+use microswarm::replay::Replay;
+use microswarm::Snapshot;
+
+/// Playback pace at 1×, about what the live view manages at 60 fps.
+const TICKS_PER_SECOND: f32 = 60.;
+const SPEEDS: [f32; 7] = [0.25, 0.5, 1., 2., 4., 8., 16.];
+
+/// Plays back a recording with scrubbing and speed control.
+pub struct Player {
+    replay: Replay,
+    frame: usize,
+    snapshot: Snapshot,
+    playing: bool,
+    /// Multiple of `TICKS_PER_SECOND`.
+    speed: f32,
+    /// Fraction of a frame carried over between repaints.
+    progress: f32,
+    last_update: Instant,
+    color_mode: ColorMode,
+    scale_mode: ScaleMode,
+    theme: Theme,
+}
+
+impl Player {
+    pub fn new(mut replay: Replay, settings: &Settings) -> Self {
+        let snapshot = replay.frame(0).unwrap_or_default();
+        Self {
+            replay,
+            frame: 0,
+            snapshot,
+            playing: true,
+            speed: 1.,
+            progress: 0.,
+            last_update: Instant::now(),
+            color_mode: settings.color_mode,
+            scale_mode: settings.scale_mode,
+            theme: settings.theme,
+        }
+    }
+
+    fn seek(&mut self, frame: usize) {
+        let frame = frame.min(self.replay.len().saturating_sub(1));
+        if let Some(snapshot) = self.replay.frame(frame) {
+            self.frame = frame;
+            self.snapshot = snapshot;
+        }
+    }
+
+    fn advance(&mut self) {
+        let elapsed = self.last_update.elapsed().as_secs_f32();
+        self.last_update = Instant::now();
+        if !self.playing {
+            return;
+        }
+        self.progress += elapsed * TICKS_PER_SECOND * self.speed;
+        let frames = self.progress.floor();
+        self.progress -= frames;
+        if frames >= 1. {
+            let last = self.replay.len().saturating_sub(1);
+            self.seek(self.frame + frames as usize);
+            if self.frame == last {
+                self.playing = false;
+            }
+        }
+    }
+
+    fn controls(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::bottom("playback").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let label = if self.playing { "Pause" } else { "Play" };
+                if ui.button(label).clicked() {
+                    if !self.playing && self.frame + 1 >= self.replay.len() {
+                        self.seek(0);
+                    }
+                    self.playing = !self.playing;
+                }
+                if ui.button("◀").on_hover_text("Previous frame").clicked() {
+                    self.playing = false;
+                    self.seek(self.frame.saturating_sub(1));
+                }
+                if ui.button("▶").on_hover_text("Next frame").clicked() {
+                    self.playing = false;
+                    self.seek(self.frame + 1);
+                }
+                egui::ComboBox::from_id_salt("speed")
+                    .selected_text(format!("{}×", self.speed))
+                    .show_ui(ui, |ui| {
+                        for speed in SPEEDS {
+                            ui.selectable_value(&mut self.speed, speed, format!("{speed}×"));
+                        }
+                    });
+                ui.label(format!(
+                    "tick {} · {} microbes",
+                    self.snapshot.tick,
+                    self.snapshot.microbes.len()
+                ));
+            });
+            let mut frame = self.frame;
+            let last = self.replay.len().saturating_sub(1);
+            ui.spacing_mut().slider_width = ui.available_width() - 60.;
+            if ui
+                .add(egui::Slider::new(&mut frame, 0..=last).show_value(false))
+                .changed()
+            {
+                self.seek(frame);
+            }
+        });
+    }
+
+    fn menu_bar(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("menu").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.scale_mode, ScaleMode::Fit, "Fit");
+                ui.selectable_value(&mut self.scale_mode, ScaleMode::Actual, "1:1");
+                ui.separator();
+                egui::ComboBox::from_label("Color by")
+                    .selected_text(self.color_mode.label())
+                    .show_ui(ui, |ui| {
+                        for mode in ColorMode::ALL {
+                            ui.selectable_value(&mut self.color_mode, mode, mode.label());
+                        }
+                    });
+            });
+        });
+    }
+
+    fn arena(&self, ctx: &egui::Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let rect = ui.max_rect();
+            ui.allocate_rect(rect, Sense::hover());
+            let arena = self.replay.arena();
+            let view = ArenaView::new(rect, [arena; 2], self.scale_mode);
+            let painter = ui.painter_at(rect);
+            painter.rect_filled(rect, 0., Color32::from_gray(12));
+            painter.rect(
+                view.arena_rect([arena; 2]),
+                0.,
+                ui.visuals().extreme_bg_color,
+                Stroke::new(1.0, Color32::DARK_GRAY),
+            );
+            draw_microbes(
+                &painter,
+                view,
+                &self.snapshot.microbes,
+                self.color_mode,
+                None,
+            );
+        });
+    }
+}
+
+impl eframe::App for Player {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if ctx.style().visuals.dark_mode != (self.theme == Theme::Dark) {
+            ctx.set_visuals(self.theme.visuals());
+        }
+        if ctx.input(|i| i.key_pressed(egui::Key::Space)) {
+            self.playing = !self.playing;
+        }
+        self.advance();
+        self.menu_bar(ctx);
+        self.controls(ctx);
+        self.arena(ctx);
+        ctx.request_repaint();
+    }
+}
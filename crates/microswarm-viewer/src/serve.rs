@@ -0,0 +1,125 @@
+//! The network services `run` can start: `--serve-metrics`, `--stream` and
+//! `--control`. Builds without the `net` feature turn the flags away.
+
+use microswarm::{Simulation, Snapshot, StepReport};
+use uuid::Uuid;
+
+use crate::cli::RunArgs;
+
+#[cfg(feature = "net")]
+pub use self::net::Services;
+#[cfg(not(feature = "net"))]
+pub use self::offline::Services;
+
+#[cfg(feature = "net")]
+mod net {
+    use microswarm::control::Control;
+    use microswarm::monitor::Monitor;
+    use microswarm::stream::Broadcaster;
+
+    use super::*;
+
+    #[derive(Default)]
+    pub struct Services {
+        monitor: Option<Monitor>,
+        broadcaster: Option<Broadcaster>,
+        control: Option<Control>,
+    }
+
+    impl Services {
+        /// Starts the services asked for in `args`.
+        pub fn start(
+            args: &RunArgs,
+            sim: &Simulation,
+            species: &[(String, Uuid)],
+        ) -> Result<Self, String> {
+            let mut services = Self::default();
+            if let Some(addr) = &args.serve_metrics {
+                let monitor = Monitor::serve(addr.as_str()).map_err(|e| format!("{addr}: {e}"))?;
+                for (name, id) in species {
+                    monitor.name(*id, name);
+                }
+                eprintln!("serving metrics at http://{}/metrics", monitor.addr());
+                services.monitor = Some(monitor);
+            }
+            if let Some(addr) = &args.stream {
+                let broadcaster = Broadcaster::serve(addr.as_str(), sim.arena())
+                    .map_err(|e| format!("{addr}: {e}"))?;
+                eprintln!("streaming to ws://{}", broadcaster.addr());
+                services.broadcaster = Some(broadcaster);
+            }
+            if let Some(addr) = &args.control {
+                let control = Control::serve(addr.as_str()).map_err(|e| format!("{addr}: {e}"))?;
+                eprintln!("taking commands at {}", control.addr());
+                services.control = Some(control);
+            }
+            Ok(services)
+        }
+
+        /// Handles commands sent to `--control`, returning whether the run
+        /// should step.
+        pub fn poll(&mut self, sim: &mut Simulation, species: &[(String, Uuid)]) -> bool {
+            match &mut self.control {
+                Some(control) => control.poll(sim, species),
+                None => true,
+            }
+        }
+
+        pub fn name(&self, species: Uuid, name: &str) {
+            if let Some(monitor) = &self.monitor {
+                monitor.name(species, name);
+            }
+        }
+
+        /// Whether [`Services::record`] needs a snapshot.
+        pub fn want_snapshots(&self) -> bool {
+            self.monitor.is_some() || self.broadcaster.is_some()
+        }
+
+        pub fn record(&self, snapshot: &Snapshot, report: &StepReport) {
+            if let Some(broadcaster) = &self.broadcaster {
+                broadcaster.broadcast(snapshot);
+            }
+            if let Some(monitor) = &self.monitor {
+                monitor.record(snapshot, report);
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "net"))]
+mod offline {
+    use super::*;
+
+    pub struct Services;
+
+    impl Services {
+        pub fn start(
+            args: &RunArgs,
+            _sim: &Simulation,
+            _species: &[(String, Uuid)],
+        ) -> Result<Self, String> {
+            let flags = [
+                ("--serve-metrics", args.serve_metrics.is_some()),
+                ("--stream", args.stream.is_some()),
+                ("--control", args.control.is_some()),
+            ];
+            match flags.iter().find(|(_, given)| *given) {
+                Some((flag, _)) => Err(format!("{flag} needs a build with the net feature")),
+                None => Ok(Self),
+            }
+        }
+
+        pub fn poll(&mut self, _sim: &mut Simulation, _species: &[(String, Uuid)]) -> bool {
+            true
+        }
+
+        pub fn name(&self, _species: Uuid, _name: &str) {}
+
+        pub fn want_snapshots(&self) -> bool {
+            false
+        }
+
+        pub fn record(&self, _snapshot: &Snapshot, _report: &StepReport) {}
+    }
+}
@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::dirs;
+use crate::ui::{ColorMode, PanelState, ScaleMode};
+use microswarm::palette::Palette;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub fn visuals(self) -> egui::Visuals {
+        match self {
+            Theme::Dark => egui::Visuals::dark(),
+            Theme::Light => egui::Visuals::light(),
+        }
+    }
+}
+
+/// Things a key can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+    ToggleCharts,
+    ToggleEventLog,
+    ToggleInspector,
+    ToggleSettings,
+    CycleColorMode,
+    ToggleScaleMode,
+    ToggleTheme,
+}
+
+impl Action {
+    pub const ALL: [Action; 7] = [
+        Action::ToggleCharts,
+        Action::ToggleEventLog,
+        Action::ToggleInspector,
+        Action::ToggleSettings,
+        Action::CycleColorMode,
+        Action::ToggleScaleMode,
+        Action::ToggleTheme,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::ToggleCharts => "Toggle charts",
+            Action::ToggleEventLog => "Toggle event log",
+            Action::ToggleInspector => "Toggle inspector",
+            Action::ToggleSettings => "Toggle settings",
+            Action::CycleColorMode => "Cycle color mode",
+            Action::ToggleScaleMode => "Toggle fit / 1:1",
+            Action::ToggleTheme => "Toggle theme",
+        }
+    }
+
+    fn default_key(self) -> egui::Key {
+        match self {
+            Action::ToggleCharts => egui::Key::C,
+            Action::ToggleEventLog => egui::Key::L,
+            Action::ToggleInspector => egui::Key::I,
+            Action::ToggleSettings => egui::Key::S,
+            Action::CycleColorMode => egui::Key::M,
+            Action::ToggleScaleMode => egui::Key::F,
+            Action::ToggleTheme => egui::Key::T,
+        }
+    }
+}
+
+/// User preferences that survive restarts, stored as JSON in the user's
+/// config directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Keyed by panel title.
+    pub panels: BTreeMap<String, PanelState>,
+    pub color_mode: ColorMode,
+    pub scale_mode: ScaleMode,
+    pub palette: Palette,
+    pub theme: Theme,
+    pub sound: bool,
+    pub volumes: Vec<f32>,
+    /// Key names as understood by [`egui::Key::from_name`].
+    pub keybindings: BTreeMap<Action, String>,
+    /// Initial window size in points, defaulting to fit the standard arena.
+    pub window_size: Option<[f32; 2]>,
+    pub fullscreen: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            panels: BTreeMap::new(),
+            color_mode: ColorMode::default(),
+            scale_mode: ScaleMode::default(),
+            palette: Palette::default(),
+            theme: Theme::default(),
+            sound: false,
+            volumes: Vec::new(),
+            keybindings: Action::ALL
+                .iter()
+                .map(|a| (*a, a.default_key().name().to_owned()))
+                .collect(),
+            window_size: None,
+            fullscreen: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn path() -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("settings.json"))
+    }
+
+    /// Loads the saved settings, falling back to defaults if there are none
+    /// or they can't be read.
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn key(&self, action: Action) -> Option<egui::Key> {
+        self.keybindings
+            .get(&action)
+            .and_then(|name| egui::Key::from_name(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut settings = Settings {
+            theme: Theme::Light,
+            palette: Palette::OkabeIto,
+            ..Settings::default()
+        };
+        settings
+            .keybindings
+            .insert(Action::ToggleTheme, egui::Key::Q.name().to_owned());
+
+        let json = serde_json::to_string(&settings).unwrap();
+        let loaded = serde_json::from_str::<Settings>(&json).unwrap();
+        assert_eq!(loaded, settings);
+        assert_eq!(loaded.key(Action::ToggleTheme), Some(egui::Key::Q));
+        assert_eq!(loaded.key(Action::ToggleCharts), Some(egui::Key::C));
+    }
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let loaded = serde_json::from_str::<Settings>(r#"{"theme":"Light"}"#).unwrap();
+        assert_eq!(loaded.theme, Theme::Light);
+        assert_eq!(loaded.keybindings, Settings::default().keybindings);
+    }
+}
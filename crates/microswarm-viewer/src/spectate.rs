@@ -0,0 +1,108 @@
+use egui::{Color32, Sense, Stroke};
+use microswarm::stream::{Frame, Spectator};
+
+use crate::settings::{Settings, Theme};
+use crate::ui::{draw_microbes, ArenaView, ColorMode, ScaleMode};
+
+/// Draws a run streamed from another machine with `run --stream`.
+pub struct Spectate {
+    spectator: Spectator,
+    frame: Option<Frame>,
+    color_mode: ColorMode,
+    scale_mode: ScaleMode,
+    theme: Theme,
+}
+
+impl Spectate {
+    pub fn new(spectator: Spectator, settings: &Settings) -> Self {
+        Self {
+            spectator,
+            frame: None,
+            // The stream doesn't carry lineages.
+            color_mode: match settings.color_mode {
+                ColorMode::Lineage => ColorMode::Species,
+                mode => mode,
+            },
+            scale_mode: settings.scale_mode,
+            theme: settings.theme,
+        }
+    }
+
+    fn menu_bar(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("menu").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.scale_mode, ScaleMode::Fit, "Fit");
+                ui.selectable_value(&mut self.scale_mode, ScaleMode::Actual, "1:1");
+                ui.separator();
+                egui::ComboBox::from_label("Color by")
+                    .selected_text(self.color_mode.label())
+                    .show_ui(ui, |ui| {
+                        for mode in [ColorMode::Species, ColorMode::Energy] {
+                            ui.selectable_value(&mut self.color_mode, mode, mode.label());
+                        }
+                    });
+            });
+        });
+    }
+
+    fn status(&self, ctx: &egui::Context) {
+        egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let addr = self.spectator.addr();
+                match self.spectator.connected() {
+                    true => ui.label(format!("watching {addr}")),
+                    false => ui.colored_label(Color32::LIGHT_RED, format!("{addr} stopped")),
+                };
+                match &self.frame {
+                    Some(frame) => ui.label(format!(
+                        "tick {} · {} microbes",
+                        frame.snapshot.tick,
+                        frame.snapshot.microbes.len()
+                    )),
+                    None => ui.label("waiting for the first tick"),
+                };
+            });
+        });
+    }
+
+    fn arena(&self, ctx: &egui::Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let rect = ui.max_rect();
+            ui.allocate_rect(rect, Sense::hover());
+            let painter = ui.painter_at(rect);
+            painter.rect_filled(rect, 0., Color32::from_gray(12));
+            let Some(frame) = &self.frame else {
+                return;
+            };
+            let view = ArenaView::new(rect, [frame.arena; 2], self.scale_mode);
+            painter.rect(
+                view.arena_rect([frame.arena; 2]),
+                0.,
+                ui.visuals().extreme_bg_color,
+                Stroke::new(1.0, Color32::DARK_GRAY),
+            );
+            draw_microbes(
+                &painter,
+                view,
+                &frame.snapshot.microbes,
+                self.color_mode,
+                None,
+            );
+        });
+    }
+}
+
+impl eframe::App for Spectate {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if ctx.style().visuals.dark_mode != (self.theme == Theme::Dark) {
+            ctx.set_visuals(self.theme.visuals());
+        }
+        if let Some(frame) = self.spectator.latest() {
+            self.frame = Some(frame);
+        }
+        self.menu_bar(ctx);
+        self.status(ctx);
+        self.arena(ctx);
+        ctx.request_repaint();
+    }
+}
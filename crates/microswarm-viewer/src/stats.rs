@@ -0,0 +1,297 @@
+use egui::Color32;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use uuid::Uuid;
+
+use microswarm::{Death, Snapshot, StepReport};
+
+const HISTORY_LEN: usize = 2000;
+const DEATHS_LEN: usize = 1000;
+/// A species' population has peaked once it falls this far below its high.
+const PEAK_DROP: f32 = 0.8;
+/// Populations smaller than this never produce peak markers.
+const PEAK_MIN: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkerKind {
+    Extinction,
+    Peak,
+    /// Something the user did, like spawning microbes or changing parameters.
+    Intervention,
+}
+
+/// A notable moment shown on the population timeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Marker {
+    pub tick: u64,
+    pub kind: MarkerKind,
+    pub species: Option<Uuid>,
+    pub label: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub tick: u64,
+    pub populations: BTreeMap<Uuid, usize>,
+}
+
+/// Rolling population history, sampled once per world update.
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub history: VecDeque<Sample>,
+    pub colors: HashMap<Uuid, Color32>,
+    /// The most recent deaths of each species.
+    pub deaths: HashMap<Uuid, VecDeque<Death>>,
+    pub markers: VecDeque<Marker>,
+    /// Highest population of each species since its last peak marker, and
+    /// the tick it was reached.
+    highs: HashMap<Uuid, (usize, u64)>,
+}
+
+impl Stats {
+    pub fn record(&mut self, snapshot: &Snapshot, report: &StepReport) {
+        let mut populations = snapshot
+            .species
+            .iter()
+            .map(|id| (*id, 0))
+            .collect::<BTreeMap<_, _>>();
+        for microbe in &snapshot.microbes {
+            *populations.entry(microbe.species).or_insert(0) += 1;
+            self.colors.entry(microbe.species).or_insert(microbe.color);
+        }
+        for death in report.deaths() {
+            let deaths = self.deaths.entry(death.species).or_default();
+            if deaths.len() == DEATHS_LEN {
+                deaths.pop_front();
+            }
+            deaths.push_back(death);
+        }
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(Sample {
+            tick: snapshot.tick,
+            populations,
+        });
+        self.detect_peaks();
+        for species in report.extinctions() {
+            self.mark(
+                snapshot.tick,
+                MarkerKind::Extinction,
+                Some(species),
+                "extinct",
+            );
+        }
+        let oldest = self.history.front().map_or(0, |s| s.tick);
+        while self.markers.front().is_some_and(|m| m.tick < oldest) {
+            self.markers.pop_front();
+        }
+    }
+
+    pub fn mark(
+        &mut self,
+        tick: u64,
+        kind: MarkerKind,
+        species: Option<Uuid>,
+        label: impl Into<String>,
+    ) {
+        self.markers.push_back(Marker {
+            tick,
+            kind,
+            species,
+            label: label.into(),
+        });
+    }
+
+    fn detect_peaks(&mut self) {
+        let Some(latest) = self.history.back() else {
+            return;
+        };
+        for (species, count) in &latest.populations {
+            let high = self.highs.entry(*species).or_insert((*count, latest.tick));
+            if *count >= high.0 {
+                *high = (*count, latest.tick);
+            } else if high.0 >= PEAK_MIN && (*count as f32) < high.0 as f32 * PEAK_DROP {
+                let (peak, tick) = *high;
+                *high = (*count, latest.tick);
+                self.markers.push_back(Marker {
+                    tick,
+                    kind: MarkerKind::Peak,
+                    species: Some(*species),
+                    label: format!("peak of {peak}"),
+                });
+            }
+        }
+    }
+
+    /// Forgets samples and markers after `tick`, for when the world is rolled
+    /// back to a checkpoint.
+    pub fn rewind(&mut self, tick: u64) {
+        while self.history.back().is_some_and(|s| s.tick > tick) {
+            self.history.pop_back();
+        }
+        self.markers.retain(|m| m.tick <= tick);
+        self.highs.clear();
+    }
+
+    pub fn latest(&self) -> Option<&Sample> {
+        self.history.back()
+    }
+
+    pub fn peak(&self) -> usize {
+        self.history
+            .iter()
+            .flat_map(|s| s.populations.values())
+            .copied()
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn color(&self, script_id: &Uuid) -> Color32 {
+        self.colors.get(script_id).copied().unwrap_or(Color32::GRAY)
+    }
+}
+
+/// Counts `values` into `bins` equal-width bins spanning `0..=max`. Values
+/// outside the range land in the first or last bin.
+pub fn histogram(values: impl IntoIterator<Item = f32>, max: f32, bins: usize) -> Vec<usize> {
+    let mut counts = vec![0; bins];
+    if bins == 0 {
+        return counts;
+    }
+    for value in values {
+        let bin = if max > 0. {
+            ((value / max) * bins as f32).clamp(0., (bins - 1) as f32) as usize
+        } else {
+            0
+        };
+        counts[bin] += 1;
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use microswarm::events::Cause;
+    use microswarm::{Event, MicrobeState};
+
+    fn snapshot(tick: u64, species: Uuid, count: usize) -> Snapshot {
+        let microbe = MicrobeState {
+            id: Uuid::new_v4(),
+            lineage: Uuid::new_v4(),
+            species,
+            x: 0.,
+            y: 0.,
+            rotation: 0.,
+            energy: 100.,
+            stamina: 100.,
+            spiky: false,
+            jaws: Default::default(),
+            eyes: Default::default(),
+            cloaked: false,
+            fat: 0.,
+            cooldown: 0,
+            caste: None,
+            color: Color32::WHITE,
+            born: 0,
+        };
+        Snapshot {
+            tick,
+            species: vec![species],
+            microbes: vec![microbe; count],
+            ..Snapshot::default()
+        }
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let script_id = Uuid::new_v4();
+        let mut stats = Stats::default();
+        for tick in 0..HISTORY_LEN + 10 {
+            stats.record(&snapshot(tick as u64, script_id, 1), &StepReport::default());
+        }
+
+        assert_eq!(stats.history.len(), HISTORY_LEN);
+        assert_eq!(stats.latest().unwrap().populations[&script_id], 1);
+        assert_eq!(stats.peak(), 1);
+        assert_eq!(stats.color(&script_id), Color32::WHITE);
+    }
+
+    #[test]
+    fn test_peak_markers() {
+        let script_id = Uuid::new_v4();
+        let mut stats = Stats::default();
+        for (tick, count) in [5, 12, 20, 18, 15, 14, 30].into_iter().enumerate() {
+            stats.record(
+                &snapshot(tick as u64 + 1, script_id, count),
+                &StepReport::default(),
+            );
+        }
+
+        let peaks = stats
+            .markers
+            .iter()
+            .filter(|m| m.kind == MarkerKind::Peak)
+            .collect::<Vec<_>>();
+        assert_eq!(peaks.len(), 1);
+        assert_eq!(peaks[0].tick, 3);
+        assert_eq!(peaks[0].species, Some(script_id));
+    }
+
+    #[test]
+    fn test_histogram() {
+        assert_eq!(
+            histogram([0., 1., 4.9, 5., 9.9, 10., 42., -1.], 10., 2),
+            vec![4, 4]
+        );
+        assert_eq!(histogram([1., 2.], 0., 3), vec![2, 0, 0]);
+        assert!(histogram([1.], 10., 0).is_empty());
+    }
+
+    #[test]
+    fn test_records_deaths_and_extinctions() {
+        let script_id = Uuid::new_v4();
+        let mut stats = Stats::default();
+        stats.record(&snapshot(1, script_id, 1), &StepReport::default());
+
+        let last = snapshot(1, script_id, 1).microbes[0];
+        let report = StepReport {
+            tick: 2,
+            events: vec![
+                Event::MicrobeDied {
+                    microbe: last,
+                    lifespan: 2,
+                    cause: Cause::Starved,
+                },
+                Event::SpeciesExtinct {
+                    species: script_id,
+                    top_predator: None,
+                },
+            ],
+        };
+        stats.record(&snapshot(2, script_id, 0), &report);
+        assert_eq!(stats.deaths[&script_id].len(), 1);
+        assert_eq!(stats.deaths[&script_id][0].lifespan, 2);
+        let marker = stats.markers.back().unwrap();
+        assert_eq!(
+            (marker.tick, marker.kind, marker.species),
+            (2, MarkerKind::Extinction, Some(script_id))
+        );
+    }
+
+    #[test]
+    fn test_rewind() {
+        let script_id = Uuid::new_v4();
+        let mut stats = Stats::default();
+        for tick in 1..=10 {
+            stats.record(&snapshot(tick, script_id, 1), &StepReport::default());
+        }
+        stats.mark(4, MarkerKind::Intervention, None, "spawned");
+        stats.mark(8, MarkerKind::Intervention, None, "spawned");
+
+        stats.rewind(5);
+        assert_eq!(stats.latest().unwrap().tick, 5);
+        assert_eq!(stats.markers.len(), 1);
+        assert_eq!(stats.markers[0].tick, 4);
+    }
+}
@@ -0,0 +1,1181 @@
+use egui::{Color32, Pos2, Rect, Sense, Stroke, ViewportBuilder, ViewportClass, ViewportId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::BufWriter;
+#[cfg(feature = "net")]
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::Duration;
+use uuid::Uuid;
+use web_time::Instant;
+
+use crate::audio::{AudioCues, Cue};
+use crate::dirs;
+use crate::settings::{Action, Settings, Theme};
+use crate::stats::{histogram, Marker, MarkerKind, Stats};
+#[cfg(feature = "net")]
+use microswarm::fetch::{self, Fetched};
+use microswarm::palette::Palette;
+use microswarm::reload::ConfigWatcher;
+use microswarm::replay::Recorder;
+use microswarm::watcher::ScriptWatcher;
+#[cfg(feature = "net")]
+use microswarm::watcher::{self, STARTER_POPULATION};
+use microswarm::{Event, MicrobeState, Simulation, Snapshot, StepReport, Tuning, HEALTH};
+
+const EVENT_LOG_LEN: usize = 500;
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+const SELECT_RADIUS: f32 = 10.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Panel {
+    Charts,
+    EventLog,
+    Inspector,
+    Settings,
+}
+
+impl Panel {
+    const ALL: [Panel; 4] = [
+        Panel::Charts,
+        Panel::EventLog,
+        Panel::Inspector,
+        Panel::Settings,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            Panel::Charts => "Charts",
+            Panel::EventLog => "Event log",
+            Panel::Inspector => "Inspector",
+            Panel::Settings => "Settings",
+        }
+    }
+
+    fn viewport_id(self) -> ViewportId {
+        ViewportId::from_hash_of(self.title())
+    }
+}
+
+/// How microbes are colored in the arena.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColorMode {
+    /// The color each microbe was spawned with.
+    #[default]
+    Species,
+    /// Red when starving through green at the reproduction threshold.
+    Energy,
+    /// A stable, unique hue per lineage.
+    Lineage,
+}
+
+impl ColorMode {
+    pub(crate) const ALL: [ColorMode; 3] =
+        [ColorMode::Species, ColorMode::Energy, ColorMode::Lineage];
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            ColorMode::Species => "Species",
+            ColorMode::Energy => "Energy",
+            ColorMode::Lineage => "Lineage",
+        }
+    }
+
+    fn color(self, microbe: &MicrobeState) -> Color32 {
+        match self {
+            ColorMode::Species => microbe.color,
+            ColorMode::Energy => energy_color(microbe.energy),
+            ColorMode::Lineage => lineage_color(&microbe.lineage),
+        }
+    }
+}
+
+/// How the arena is fitted into the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ScaleMode {
+    /// Scale the arena to fill the window, letterboxing the spare axis.
+    #[default]
+    Fit,
+    /// One world unit per point, centred and cropped by the window.
+    Actual,
+}
+
+/// Maps world coordinates onto the screen.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ArenaView {
+    center: Pos2,
+    scale: f32,
+}
+
+impl ArenaView {
+    /// Centres an arena of half-width and half-height `size` in
+    /// `available`.
+    pub(crate) fn new(available: Rect, size: [f32; 2], mode: ScaleMode) -> Self {
+        let [width, height] = size;
+        let scale = match mode {
+            ScaleMode::Fit => {
+                (available.width() / (width * 2.)).min(available.height() / (height * 2.))
+            }
+            ScaleMode::Actual => 1.,
+        };
+        Self {
+            center: available.center(),
+            scale,
+        }
+    }
+
+    pub(crate) fn to_screen(self, x: f32, y: f32) -> Pos2 {
+        self.center + egui::vec2(x, y) * self.scale
+    }
+
+    fn to_world(self, position: Pos2) -> (f32, f32) {
+        let offset = (position - self.center) / self.scale;
+        (offset.x, offset.y)
+    }
+
+    pub(crate) fn arena_rect(self, size: [f32; 2]) -> Rect {
+        let [width, height] = size;
+        Rect::from_center_size(self.center, egui::vec2(width, height) * 2. * self.scale)
+    }
+
+    /// The playable area's outline on screen.
+    fn outline(self, size: [f32; 2], shape: &microswarm::shape::Shape) -> Vec<Pos2> {
+        shape
+            .outline(size, 64)
+            .into_iter()
+            .map(|[x, y]| self.to_screen(x, y))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct PanelState {
+    open: bool,
+    /// Shown in its own OS window rather than floating over the arena.
+    detached: bool,
+}
+
+pub struct App {
+    sim: Simulation,
+    /// The world as of the last step, which everything is drawn from.
+    snapshot: Snapshot,
+    report: StepReport,
+    stats: Stats,
+    events: VecDeque<(u64, String)>,
+    selected: Option<Uuid>,
+    panels: [PanelState; Panel::ALL.len()],
+    color_mode: ColorMode,
+    palette: Palette,
+    audio: AudioCues,
+    scale_mode: ScaleMode,
+    toasts: Vec<(String, Instant)>,
+    /// Tuning as of the last parameter-change marker.
+    marked_tuning: Tuning,
+    theme: Theme,
+    keybindings: BTreeMap<Action, String>,
+    window_size: Option<[f32; 2]>,
+    fullscreen: bool,
+    /// Action waiting for the next key press to become its binding.
+    rebinding: Option<Action>,
+    recorder: Option<Recorder<BufWriter<File>>>,
+    /// Adds species from scripts saved while the world runs.
+    watcher: Option<ScriptWatcher>,
+    /// Applies edits to the config file the world was built from.
+    reloader: Option<ConfigWatcher>,
+    /// Typed into the settings panel to add a species from.
+    #[cfg(feature = "net")]
+    script_url: String,
+    /// A script being downloaded in the background, with its URL.
+    #[cfg(feature = "net")]
+    fetching: Option<(String, Receiver<Result<Fetched, String>>)>,
+    /// World state to roll back to, taken with the Checkpoint button.
+    checkpoint: Option<Snapshot>,
+    /// Why the last step failed, shown once until a step succeeds again.
+    error: Option<String>,
+}
+
+impl App {
+    pub fn new(mut sim: Simulation, settings: Settings) -> Self {
+        let mut audio = AudioCues::default();
+        audio.enabled = settings.sound;
+        for (volume, saved) in audio.volumes.iter_mut().zip(&settings.volumes) {
+            *volume = *saved;
+        }
+        if settings.palette != Palette::default() {
+            sim.recolor(settings.palette);
+        }
+        Self {
+            marked_tuning: sim.tuning().clone(),
+            snapshot: sim.snapshot(),
+            report: StepReport::default(),
+            sim,
+            stats: Stats::default(),
+            events: VecDeque::new(),
+            selected: None,
+            panels: Panel::ALL.map(|panel| {
+                settings
+                    .panels
+                    .get(panel.title())
+                    .copied()
+                    .unwrap_or_default()
+            }),
+            color_mode: settings.color_mode,
+            palette: settings.palette,
+            audio,
+            scale_mode: settings.scale_mode,
+            toasts: Vec::new(),
+            theme: settings.theme,
+            keybindings: settings.keybindings,
+            window_size: settings.window_size,
+            fullscreen: settings.fullscreen,
+            rebinding: None,
+            recorder: None,
+            watcher: None,
+            reloader: None,
+            #[cfg(feature = "net")]
+            script_url: String::new(),
+            #[cfg(feature = "net")]
+            fetching: None,
+            checkpoint: None,
+            error: None,
+        }
+    }
+
+    /// Records every step from now on. See [`microswarm::replay`].
+    pub fn record(mut self, recorder: Recorder<BufWriter<File>>) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Adds every script saved in the watcher's directory from now on as a
+    /// new species. See [`microswarm::watcher`].
+    pub fn watch_scripts(mut self, watcher: ScriptWatcher) -> Self {
+        self.watcher = Some(watcher);
+        self
+    }
+
+    /// Applies edits to the config file from now on. See
+    /// [`microswarm::reload`].
+    pub fn watch_config(mut self, reloader: ConfigWatcher) -> Self {
+        self.reloader = Some(reloader);
+        self
+    }
+
+    /// Applies any saved edits to the config file.
+    fn reload_config(&mut self) {
+        let Some(reloader) = &mut self.reloader else {
+            return;
+        };
+        let message = match reloader.poll(&mut self.sim) {
+            Ok(Some(changes)) => format!("{}: {changes}", reloader.path().display()),
+            Ok(None) => return,
+            Err(error) => {
+                tracing::warn!(%error, "couldn't reload the config");
+                format!("Not reloading: {error}")
+            }
+        };
+        self.toasts.push((message.clone(), Instant::now()));
+        self.log(message);
+    }
+
+    /// Starts downloading the script at the URL typed in, on another
+    /// thread so the window stays responsive.
+    #[cfg(feature = "net")]
+    fn fetch_script(&mut self) {
+        let url = self.script_url.trim().to_owned();
+        let sandbox = self.sim.sandbox().clone();
+        let (sender, receiver) = mpsc::channel();
+        let fetching = url.clone();
+        std::thread::spawn(move || {
+            // Script errors can't cross threads, so only their messages do.
+            let _ = sender.send(fetch::fetch(&fetching, &sandbox).map_err(|e| e.to_string()));
+        });
+        self.fetching = Some((url, receiver));
+    }
+
+    /// Lets in the species from a finished download.
+    #[cfg(feature = "net")]
+    fn admit_download(&mut self) {
+        if let Some((url, receiver)) = &self.fetching {
+            let message = match receiver.try_recv() {
+                Err(TryRecvError::Empty) => None,
+                Ok(Ok(fetched)) => {
+                    let species = watcher::join(&mut self.sim, fetched.source, STARTER_POPULATION);
+                    self.sim.set_species_name(species, &fetched.name);
+                    self.script_url.clear();
+                    Some(format!("{} joined from {url}", fetched.name))
+                }
+                Ok(Err(error)) => {
+                    tracing::warn!(url, %error, "couldn't add species from URL");
+                    Some(format!("Couldn't add {url}: {error}"))
+                }
+                Err(TryRecvError::Disconnected) => Some(format!("Couldn't add {url}")),
+            };
+            if let Some(message) = message {
+                self.fetching = None;
+                self.toasts.push((message.clone(), Instant::now()));
+                self.log(message);
+            }
+        }
+    }
+
+    /// Lets in any species from newly saved or downloaded scripts.
+    fn admit_scripts(&mut self) {
+        #[cfg(feature = "net")]
+        self.admit_download();
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        let joined = match watcher.poll(&mut self.sim) {
+            Ok(joined) => joined,
+            Err(error) => {
+                tracing::warn!(%error, "couldn't check for new scripts");
+                return;
+            }
+        };
+        for joined in joined {
+            let message = format!("{} joined from {}", joined.name, joined.path.display());
+            self.toasts.push((message.clone(), Instant::now()));
+            self.log(message);
+        }
+    }
+
+    fn stop_recording(&mut self, err: std::io::Error) {
+        self.recorder = None;
+        let message = format!("Recording stopped: {err}");
+        self.toasts.push((message.clone(), Instant::now()));
+        self.log(message);
+    }
+
+    fn settings(&self) -> Settings {
+        Settings {
+            panels: Panel::ALL
+                .iter()
+                .zip(self.panels)
+                .map(|(panel, state)| (panel.title().to_owned(), state))
+                .collect(),
+            color_mode: self.color_mode,
+            scale_mode: self.scale_mode,
+            palette: self.palette,
+            theme: self.theme,
+            sound: self.audio.enabled,
+            volumes: self.audio.volumes.to_vec(),
+            keybindings: self.keybindings.clone(),
+            window_size: self.window_size,
+            fullscreen: self.fullscreen,
+        }
+    }
+
+    fn handle_keys(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        if let Some(action) = self.rebinding {
+            let pressed = ctx.input(|i| {
+                i.events.iter().find_map(|event| match event {
+                    egui::Event::Key {
+                        key, pressed: true, ..
+                    } => Some(*key),
+                    _ => None,
+                })
+            });
+            if let Some(key) = pressed {
+                if key != egui::Key::Escape {
+                    self.keybindings.insert(action, key.name().to_owned());
+                }
+                self.rebinding = None;
+            }
+            return;
+        }
+        let bindings = self.settings();
+        for action in Action::ALL {
+            let Some(key) = bindings.key(action) else {
+                continue;
+            };
+            if !ctx.input(|i| i.key_pressed(key)) {
+                continue;
+            }
+            match action {
+                Action::ToggleCharts => self.panels[0].open ^= true,
+                Action::ToggleEventLog => self.panels[1].open ^= true,
+                Action::ToggleInspector => self.panels[2].open ^= true,
+                Action::ToggleSettings => self.panels[3].open ^= true,
+                Action::CycleColorMode => {
+                    let index = ColorMode::ALL.iter().position(|m| *m == self.color_mode);
+                    self.color_mode =
+                        ColorMode::ALL[(index.unwrap_or(0) + 1) % ColorMode::ALL.len()];
+                }
+                Action::ToggleScaleMode => {
+                    self.scale_mode = match self.scale_mode {
+                        ScaleMode::Fit => ScaleMode::Actual,
+                        ScaleMode::Actual => ScaleMode::Fit,
+                    }
+                }
+                Action::ToggleTheme => {
+                    self.theme = match self.theme {
+                        Theme::Dark => Theme::Light,
+                        Theme::Light => Theme::Dark,
+                    }
+                }
+            }
+        }
+    }
+
+    fn step(&mut self) {
+        self.admit_scripts();
+        self.reload_config();
+        match self.sim.step() {
+            Ok(report) => {
+                self.report = report.clone();
+                self.error = None;
+            }
+            // The world stays paused on the failing tick.
+            Err(error) => {
+                let message = format!("The world stopped: {error}");
+                if self.error.as_ref() != Some(&message) {
+                    tracing::error!(%error, "step failed");
+                    self.toasts.push((message.clone(), Instant::now()));
+                    self.log(message.clone());
+                    self.error = Some(message);
+                }
+                return;
+            }
+        }
+        self.snapshot = self.sim.snapshot();
+        if let Some(Err(err)) = self.recorder.as_mut().map(|r| r.record(&self.snapshot)) {
+            self.stop_recording(err);
+        }
+        self.stats.record(&self.snapshot, &self.report);
+        let births = self.report.births();
+        let deaths = self.report.deaths().count();
+        if self.report.kills() > 0 {
+            self.audio.play(Cue::Kill);
+        }
+        if births > 0 {
+            self.audio.play(Cue::Birth);
+        }
+        if births > 0 || deaths > 0 {
+            self.log(format!("{births} born, {deaths} died"));
+        }
+        let disruptions = self.report.events.iter().filter_map(|event| match event {
+            Event::Disrupted { disruption } => Some(*disruption),
+            _ => None,
+        });
+        for disruption in disruptions.collect::<Vec<_>>() {
+            self.log(disruption.to_string());
+        }
+        let extinctions = self.report.events.iter().filter_map(|event| match event {
+            Event::SpeciesExtinct {
+                species,
+                top_predator,
+            } => Some((*species, *top_predator)),
+            _ => None,
+        });
+        for (species, top_predator) in extinctions.collect::<Vec<_>>() {
+            let message = match top_predator {
+                Some(killer) => format!(
+                    "{} went extinct, mostly eaten by {}",
+                    self.sim.species_name(species),
+                    self.sim.species_name(killer)
+                ),
+                None => format!("{} went extinct", self.sim.species_name(species)),
+            };
+            self.audio.play(Cue::Extinction);
+            self.toasts.push((message.clone(), Instant::now()));
+            self.log(message);
+        }
+    }
+
+    fn log(&mut self, message: String) {
+        if self.events.len() == EVENT_LOG_LEN {
+            self.events.pop_front();
+        }
+        self.events.push_back((self.snapshot.tick, message));
+    }
+
+    fn toasts(&mut self, ctx: &egui::Context) {
+        self.toasts
+            .retain(|(_, shown)| shown.elapsed() < TOAST_DURATION);
+        if self.toasts.is_empty() {
+            return;
+        }
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8., 32.))
+            .interactable(false)
+            .show(ctx, |ui| {
+                for (message, _) in &self.toasts {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.label(message);
+                    });
+                }
+            });
+    }
+
+    fn menu_bar(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("menu").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                for (panel, state) in Panel::ALL.iter().zip(self.panels.iter_mut()) {
+                    ui.toggle_value(&mut state.open, panel.title());
+                    if state.open {
+                        ui.checkbox(&mut state.detached, "detach");
+                    }
+                    ui.separator();
+                }
+                ui.selectable_value(&mut self.scale_mode, ScaleMode::Fit, "Fit");
+                ui.selectable_value(&mut self.scale_mode, ScaleMode::Actual, "1:1");
+                ui.separator();
+                egui::ComboBox::from_label("Color by")
+                    .selected_text(self.color_mode.label())
+                    .show_ui(ui, |ui| {
+                        for mode in ColorMode::ALL {
+                            ui.selectable_value(&mut self.color_mode, mode, mode.label());
+                        }
+                    });
+                ui.separator();
+                if ui.button("Save world").clicked() {
+                    let message = match self.save_world() {
+                        Ok(()) => "World saved".to_owned(),
+                        Err(err) => format!("Couldn't save the world: {err}"),
+                    };
+                    self.toasts.push((message, Instant::now()));
+                }
+                if ui.button("Load world").clicked() {
+                    match dirs::world_path().map(Simulation::load) {
+                        Some(Ok(sim)) => *self = App::new(sim, self.settings()),
+                        Some(Err(err)) => self
+                            .toasts
+                            .push((format!("Couldn't load the world: {err}"), Instant::now())),
+                        None => {}
+                    }
+                }
+                ui.separator();
+                if ui
+                    .button("Checkpoint")
+                    .on_hover_text("Remember this moment to roll back to later")
+                    .clicked()
+                {
+                    self.checkpoint = Some(self.snapshot.clone());
+                    self.toasts.push((
+                        format!("Checkpoint at tick {}", self.snapshot.tick),
+                        Instant::now(),
+                    ));
+                }
+                let restore =
+                    ui.add_enabled(self.checkpoint.is_some(), egui::Button::new("Restore"));
+                if restore
+                    .on_hover_text("Roll back to the checkpoint")
+                    .clicked()
+                {
+                    self.restore_checkpoint();
+                }
+            });
+        });
+    }
+
+    fn restore_checkpoint(&mut self) {
+        let Some(checkpoint) = self.checkpoint.clone() else {
+            return;
+        };
+        let tick = checkpoint.tick;
+        if let Err(err) = self.sim.restore(checkpoint) {
+            self.toasts
+                .push((format!("Couldn't restore: {err}"), Instant::now()));
+            return;
+        }
+        self.snapshot = self.sim.snapshot();
+        self.report = StepReport::default();
+        self.marked_tuning = self.sim.tuning().clone();
+        self.stats.rewind(tick);
+        self.stats
+            .mark(tick, MarkerKind::Intervention, None, "restored checkpoint");
+        self.log(format!("Rolled back to tick {tick}"));
+    }
+
+    fn save_world(&self) -> std::io::Result<()> {
+        let Some(path) = dirs::world_path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        self.sim.save(path)
+    }
+
+    fn arena(&mut self, ctx: &egui::Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let rect = ui.max_rect();
+            let response = ui
+                .interact(rect, ui.id().with("arena"), Sense::click())
+                .on_hover_text("Click to inspect, right click to spawn the inspected species");
+            let view = ArenaView::new(rect, self.sim.arena_size(), self.scale_mode);
+            let painter = ui.painter_at(rect);
+            painter.rect_filled(rect, 0., Color32::from_gray(12));
+            painter.add(egui::Shape::convex_polygon(
+                view.outline(self.sim.arena_size(), self.sim.shape()),
+                ui.visuals().extreme_bg_color,
+                Stroke::new(1.0, Color32::DARK_GRAY),
+            ));
+
+            if let Some(click) = response
+                .interact_pointer_pos()
+                .filter(|_| response.clicked())
+            {
+                self.selected = self
+                    .snapshot
+                    .microbes
+                    .iter()
+                    .map(|m| (m.id, view.to_screen(m.x, m.y).distance(click)))
+                    .filter(|(_, distance)| *distance < SELECT_RADIUS)
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(id, _)| id);
+            }
+            if let Some(click) = response
+                .interact_pointer_pos()
+                .filter(|_| response.secondary_clicked())
+            {
+                let (x, y) = view.to_world(click);
+                self.spawn_selected_at(x, y);
+            }
+
+            draw_microbes(
+                &painter,
+                view,
+                &self.snapshot.microbes,
+                self.color_mode,
+                self.selected,
+            );
+        });
+    }
+
+    /// Spawns a fresh microbe of the inspected microbe's species.
+    fn spawn_selected_at(&mut self, x: f32, y: f32) {
+        let Some((script_id, color)) = self.selected_microbe().map(|m| (m.species, m.color)) else {
+            return;
+        };
+        self.sim.spawn(script_id, x, y, 0., color);
+        self.stats.mark(
+            self.snapshot.tick,
+            MarkerKind::Intervention,
+            Some(script_id),
+            "spawned",
+        );
+    }
+
+    /// Marks parameter changes on the timeline once the user lets go of the
+    /// slider, so a drag produces one marker instead of one per frame.
+    fn mark_tuning_changes(&mut self, ctx: &egui::Context) {
+        if *self.sim.tuning() == self.marked_tuning || ctx.input(|i| i.pointer.any_down()) {
+            return;
+        }
+        self.marked_tuning = self.sim.tuning().clone();
+        self.stats.mark(
+            self.snapshot.tick,
+            MarkerKind::Intervention,
+            None,
+            "parameters changed",
+        );
+    }
+
+    fn show_panel(&mut self, ctx: &egui::Context, index: usize) {
+        let panel = Panel::ALL[index];
+        if !self.panels[index].open {
+            return;
+        }
+        if !self.panels[index].detached {
+            let mut open = true;
+            egui::Window::new(panel.title())
+                .open(&mut open)
+                .show(ctx, |ui| self.panel_ui(panel, ui));
+            self.panels[index].open = open;
+            return;
+        }
+
+        ctx.show_viewport_immediate(
+            panel.viewport_id(),
+            ViewportBuilder::default()
+                .with_title(panel.title())
+                .with_inner_size([420., 320.]),
+            |ctx, class| {
+                if class == ViewportClass::Embedded {
+                    // The backend can't open another OS window, fall back to floating.
+                    egui::Window::new(panel.title()).show(ctx, |ui| self.panel_ui(panel, ui));
+                } else {
+                    egui::CentralPanel::default().show(ctx, |ui| self.panel_ui(panel, ui));
+                }
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    // Closing the OS window docks the panel back into the main one.
+                    self.panels[index].detached = false;
+                }
+            },
+        );
+    }
+
+    fn panel_ui(&mut self, panel: Panel, ui: &mut egui::Ui) {
+        match panel {
+            Panel::Charts => self.charts_ui(ui),
+            Panel::EventLog => self.event_log_ui(ui),
+            Panel::Inspector => self.inspector_ui(ui),
+            Panel::Settings => self.settings_ui(ui),
+        }
+    }
+
+    fn charts_ui(&self, ui: &mut egui::Ui) {
+        let tick = self.stats.latest().map_or(0, |s| s.tick);
+        ui.label(format!("Population at tick {tick}"));
+        let (rect, response) =
+            ui.allocate_exact_size(egui::vec2(ui.available_width(), 160.), Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_stroke(rect, 0., Stroke::new(1.0, Color32::DARK_GRAY));
+
+        let first_tick = self.stats.history.front().map_or(0, |s| s.tick);
+        let span = self.stats.history.len();
+        let mut hovered = Vec::new();
+        for marker in &self.stats.markers {
+            let x = chart_point(rect, (marker.tick - first_tick) as usize, span, 0.).x;
+            let color = match marker.kind {
+                MarkerKind::Extinction => Color32::RED,
+                MarkerKind::Peak => Color32::LIGHT_BLUE,
+                MarkerKind::Intervention => Color32::YELLOW,
+            };
+            painter.vline(
+                x,
+                rect.y_range(),
+                Stroke::new(1.0, color.gamma_multiply(0.6)),
+            );
+            if response
+                .hover_pos()
+                .is_some_and(|pointer| (pointer.x - x).abs() < 3.)
+            {
+                hovered.push(marker);
+            }
+        }
+        if !hovered.is_empty() {
+            response.on_hover_ui_at_pointer(|ui| {
+                for marker in hovered {
+                    ui.label(marker_text(&self.sim, marker));
+                }
+            });
+        }
+
+        let peak = self.stats.peak().max(1) as f32;
+        let len = self.stats.history.len();
+        if let Some(latest) = self.stats.latest() {
+            for script_id in latest.populations.keys() {
+                let points = self
+                    .stats
+                    .history
+                    .iter()
+                    .enumerate()
+                    .map(|(i, sample)| {
+                        let count = sample.populations.get(script_id).copied().unwrap_or(0);
+                        chart_point(rect, i, len, count as f32 / peak)
+                    })
+                    .collect::<Vec<_>>();
+                painter.add(egui::Shape::line(
+                    points,
+                    Stroke::new(1.5, self.stats.color(script_id)),
+                ));
+            }
+            for (script_id, count) in &latest.populations {
+                ui.colored_label(
+                    self.stats.color(script_id),
+                    format!("{}: {}", self.sim.species_name(*script_id), count),
+                );
+            }
+
+            ui.collapsing("Landmarks", |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(120.)
+                    .show(ui, |ui| {
+                        for marker in self.stats.markers.iter().rev() {
+                            ui.label(marker_text(&self.sim, marker));
+                        }
+                    });
+            });
+            ui.collapsing("Distributions", |ui| {
+                for script_id in latest.populations.keys() {
+                    self.distributions_ui(ui, script_id);
+                }
+            });
+        }
+    }
+
+    fn distributions_ui(&self, ui: &mut egui::Ui, script_id: &Uuid) {
+        const BINS: usize = 20;
+        let color = self.stats.color(script_id);
+        let deaths = self.stats.deaths.get(script_id);
+        let deaths = deaths.iter().flat_map(|d| d.iter());
+        let max_lifespan = deaths.clone().map(|d| d.lifespan).max().unwrap_or(0) as f32;
+        let lifespans = |eaten: bool| {
+            deaths
+                .clone()
+                .filter(move |d| d.eaten == eaten)
+                .map(|d| d.lifespan as f32)
+        };
+        let energy_max = self.sim.tuning().reproduction_threshold;
+        let energies = self
+            .snapshot
+            .microbes
+            .iter()
+            .filter(|m| m.species == *script_id)
+            .map(|m| m.energy)
+            .collect::<Vec<_>>();
+
+        ui.colored_label(color, self.sim.species_name(*script_id));
+        ui.horizontal(|ui| {
+            draw_histogram(
+                ui,
+                &format!("lifespan (0..{max_lifespan:.0}), starved / eaten"),
+                &[
+                    (
+                        histogram(lifespans(false), max_lifespan, BINS),
+                        Color32::GRAY,
+                    ),
+                    (histogram(lifespans(true), max_lifespan, BINS), Color32::RED),
+                ],
+            );
+            draw_histogram(
+                ui,
+                &format!("energy (0..{energy_max:.0})"),
+                &[(histogram(energies, energy_max, BINS), color)],
+            );
+        });
+    }
+
+    fn event_log_ui(&self, ui: &mut egui::Ui) {
+        egui::ScrollArea::vertical()
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for (tick, message) in &self.events {
+                    ui.monospace(format!("[{tick:>6}] {message}"));
+                }
+            });
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        let mut palette = self.palette;
+        egui::ComboBox::from_label("Species palette")
+            .selected_text(palette.label())
+            .show_ui(ui, |ui| {
+                for option in Palette::ALL {
+                    ui.selectable_value(&mut palette, option, option.label());
+                }
+            });
+        if palette != self.palette {
+            self.palette = palette;
+            self.sim.recolor(palette);
+            // Chart and legend colors are sampled from the microbes.
+            self.stats.colors.clear();
+        }
+        ui.separator();
+
+        let tuning = self.sim.tuning_mut();
+        ui.add(egui::Slider::new(&mut tuning.health, 1.0..=HEALTH * 5.).text("starting energy"));
+        ui.add(egui::Slider::new(&mut tuning.speed, 0.0..=10.0).text("speed"));
+        ui.add(egui::Slider::new(&mut tuning.rotation_speed, 0.0..=PI).text("rotation speed"));
+        ui.add(
+            egui::Slider::new(&mut tuning.detect_range_far, 0.0..=200.0).text("far detect range"),
+        );
+        ui.add(
+            egui::Slider::new(&mut tuning.detect_range_close, 0.0..=100.0)
+                .text("close detect range"),
+        );
+        ui.add(egui::Slider::new(&mut tuning.eat_damage, 0.0..=HEALTH).text("eat damage"));
+        ui.add(egui::Slider::new(&mut tuning.eat_transfer, 0.0..=1.0).text("eat transfer"));
+        ui.add(
+            egui::Slider::new(&mut tuning.action_energy_consumption, 0.0..=1.0)
+                .logarithmic(true)
+                .text("action energy cost"),
+        );
+        ui.add(
+            egui::Slider::new(&mut tuning.reproduction_threshold, HEALTH..=HEALTH * 5.)
+                .text("reproduction threshold"),
+        );
+        ui.add(egui::Slider::new(&mut tuning.stamina, 0.0..=HEALTH * 5.).text("stamina"));
+        ui.add(egui::Slider::new(&mut tuning.bite_stamina, 0.0..=HEALTH).text("bite stamina cost"));
+        ui.add(egui::Slider::new(&mut tuning.stamina_regen, 0.0..=HEALTH).text("stamina regen"));
+        ui.add(
+            egui::Slider::new(&mut tuning.temperature_effect, 0.0..=1.0).text("temperature effect"),
+        );
+        ui.add(egui::Slider::new(&mut tuning.photosynthesis, 0.0..=2.0).text("photosynthesis"));
+        ui.add(
+            egui::Slider::new(&mut tuning.producer_handicap, 0.0..=1.0).text("producer handicap"),
+        );
+        ui.add(egui::Slider::new(&mut tuning.spike_reflect, 0.0..=1.0).text("spike reflect"));
+        ui.add(egui::Slider::new(&mut tuning.spike_upkeep, 1.0..=10.0).text("spike upkeep"));
+        ui.add(egui::Slider::new(&mut tuning.cloak_energy, 0.0..=1.0).text("cloak energy"));
+        ui.add(egui::Slider::new(&mut tuning.starving_speed, 0.0..=1.0).text("starving speed"));
+        ui.add(egui::Slider::new(&mut tuning.bulky_speed, 0.0..=1.0).text("bulky speed"));
+        ui.add(egui::Slider::new(&mut tuning.jaw_cost, 0.0..=10.0).text("jaw cost"));
+        ui.add(egui::Slider::new(&mut tuning.jaw_upkeep, 0.0..=1.0).text("jaw upkeep"));
+        ui.add(egui::Slider::new(&mut tuning.eye_upkeep, 0.0..=1.0).text("eye upkeep"));
+        if ui.button("Reset to defaults").clicked() {
+            *tuning = Tuning::default();
+        }
+        ui.collapsing("Mutation", |ui| {
+            for species in self.sim.species() {
+                let mut mutation = self.sim.mutation(species);
+                ui.colored_label(self.stats.color(&species), self.sim.species_name(species));
+                ui.add(egui::Slider::new(&mut mutation.rate, 0.0..=1.0).text("rate"));
+                ui.add(egui::Slider::new(&mut mutation.magnitude, 0.0..=1.0).text("magnitude"));
+                if mutation != self.sim.mutation(species) {
+                    self.sim.set_mutation(species, mutation);
+                }
+            }
+        });
+        ui.label(format!("Seed: {}", self.sim.seed()));
+        ui.separator();
+
+        #[cfg(feature = "net")]
+        self.script_url_ui(ui);
+
+        ui.horizontal(|ui| {
+            ui.label("Theme");
+            ui.selectable_value(&mut self.theme, Theme::Dark, "Dark");
+            ui.selectable_value(&mut self.theme, Theme::Light, "Light");
+        });
+        ui.collapsing("Keybindings", |ui| {
+            egui::Grid::new("keybindings").show(ui, |ui| {
+                for action in Action::ALL {
+                    ui.label(action.label());
+                    let text = if self.rebinding == Some(action) {
+                        "press a key…"
+                    } else {
+                        self.keybindings
+                            .get(&action)
+                            .map_or("unbound", |k| k.as_str())
+                    };
+                    if ui.button(text).clicked() {
+                        self.rebinding = Some(action);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+
+        if !cfg!(feature = "audio") {
+            return;
+        }
+        ui.separator();
+        ui.checkbox(&mut self.audio.enabled, "Sound effects");
+        ui.add_enabled_ui(self.audio.enabled, |ui| {
+            for cue in Cue::ALL {
+                ui.add(
+                    egui::Slider::new(&mut self.audio.volumes[cue as usize], 0.0..=1.0)
+                        .text(cue.label()),
+                );
+            }
+        });
+    }
+
+    /// Adds a species from a script shared at a URL.
+    #[cfg(feature = "net")]
+    fn script_url_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Script URL");
+            ui.text_edit_singleline(&mut self.script_url)
+                .on_hover_text("An https:// link to a .rhai script, such as a gist's raw link");
+        });
+        let ready = self.fetching.is_none() && !self.script_url.trim().is_empty();
+        let label = match self.fetching {
+            Some(_) => "Downloading…",
+            None => "Add species",
+        };
+        if ui.add_enabled(ready, egui::Button::new(label)).clicked() {
+            self.fetch_script();
+        }
+        ui.separator();
+    }
+
+    fn selected_microbe(&self) -> Option<&MicrobeState> {
+        let id = self.selected?;
+        self.snapshot.microbes.iter().find(|m| m.id == id)
+    }
+
+    fn inspector_ui(&self, ui: &mut egui::Ui) {
+        let Some(microbe) = self.selected_microbe() else {
+            ui.label("Click a microbe in the arena to inspect it.");
+            return;
+        };
+        egui::Grid::new("inspector").show(ui, |ui| {
+            ui.label("id");
+            ui.monospace(microbe.id.to_string());
+            ui.end_row();
+            ui.label("lineage");
+            ui.monospace(microbe.lineage.to_string());
+            ui.end_row();
+            ui.label("species");
+            ui.colored_label(microbe.color, self.sim.species_name(microbe.species));
+            ui.end_row();
+            ui.label("energy");
+            ui.label(format!("{:.1}", microbe.energy));
+            ui.end_row();
+            ui.label("position");
+            ui.label(format!("({:.1}, {:.1})", microbe.x, microbe.y));
+            ui.end_row();
+            ui.label("rotation");
+            ui.label(format!("{:.2}", microbe.rotation));
+            ui.end_row();
+        });
+    }
+}
+
+impl eframe::App for App {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if ctx.style().visuals.dark_mode != (self.theme == Theme::Dark) {
+            ctx.set_visuals(self.theme.visuals());
+        }
+        self.handle_keys(ctx);
+        self.step();
+        self.menu_bar(ctx);
+        for index in 0..Panel::ALL.len() {
+            self.show_panel(ctx, index);
+        }
+        self.arena(ctx);
+        self.toasts(ctx);
+        self.mark_tuning_changes(ctx);
+        ctx.request_repaint();
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if let Err(error) = self.settings().save() {
+            tracing::error!(%error, "failed to save settings");
+        }
+        if let Err(error) = self.save_world() {
+            tracing::error!(%error, "failed to save the world");
+        }
+        if let Some(Err(error)) = self.recorder.take().map(Recorder::finish) {
+            tracing::error!(%error, "failed to finish the recording");
+        }
+    }
+}
+
+pub(crate) fn draw_microbes(
+    painter: &egui::Painter,
+    view: ArenaView,
+    microbes: &[MicrobeState],
+    color_mode: ColorMode,
+    selected: Option<Uuid>,
+) {
+    for microbe in microbes {
+        let player_pos = view.to_screen(microbe.x, microbe.y);
+        let size = ((microbe.energy / (HEALTH)) + 1.) * view.scale.max(0.5);
+        painter.circle_filled(player_pos, size, color_mode.color(microbe));
+
+        let direction = egui::vec2(microbe.rotation.cos(), microbe.rotation.sin());
+        let line_end = player_pos + direction * size;
+        painter.line_segment([player_pos, line_end], Stroke::new(1.0, Color32::RED));
+
+        if Some(microbe.id) == selected {
+            painter.circle_stroke(player_pos, size + 3., Stroke::new(1.0, Color32::WHITE));
+        }
+    }
+}
+
+/// Draws one or more series of bin counts as stacked bars.
+fn draw_histogram(ui: &mut egui::Ui, label: &str, series: &[(Vec<usize>, Color32)]) {
+    ui.vertical(|ui| {
+        ui.small(label);
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(180., 60.), Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_stroke(rect, 0., Stroke::new(1.0, Color32::DARK_GRAY));
+        let bins = series.first().map_or(0, |(counts, _)| counts.len());
+        let totals = (0..bins)
+            .map(|bin| series.iter().map(|(counts, _)| counts[bin]).sum::<usize>())
+            .collect::<Vec<_>>();
+        let tallest = totals.iter().copied().max().unwrap_or(0).max(1) as f32;
+        let width = rect.width() / bins.max(1) as f32;
+        for bin in 0..bins {
+            let mut bottom = rect.bottom();
+            for (counts, color) in series {
+                let height = rect.height() * counts[bin] as f32 / tallest;
+                let left = rect.left() + width * bin as f32;
+                painter.rect_filled(
+                    Rect::from_min_max(
+                        egui::pos2(left, bottom - height),
+                        egui::pos2(left + width - 1., bottom),
+                    ),
+                    0.,
+                    *color,
+                );
+                bottom -= height;
+            }
+        }
+    });
+}
+
+fn marker_text(sim: &Simulation, marker: &Marker) -> String {
+    match marker.species {
+        Some(species) => format!(
+            "[{:>6}] {} {}",
+            marker.tick,
+            sim.species_name(species),
+            marker.label
+        ),
+        None => format!("[{:>6}] {}", marker.tick, marker.label),
+    }
+}
+
+fn chart_point(rect: Rect, index: usize, len: usize, value: f32) -> Pos2 {
+    let x = rect.left() + rect.width() * index as f32 / (len.max(2) - 1) as f32;
+    let y = rect.bottom() - rect.height() * value;
+    egui::pos2(x, y)
+}
+
+fn energy_color(energy: f32) -> Color32 {
+    let t = (energy / (HEALTH * 2.)).clamp(0., 1.);
+    Color32::from_rgb(((1. - t) * 255.) as u8, (t * 255.) as u8, 40)
+}
+
+fn lineage_color(lineage: &Uuid) -> Color32 {
+    // Spread hues with the golden ratio so neighbouring ids don't look alike.
+    let hue = (lineage.as_u128() as u64 as f64 * 0.618_033_988_75).fract() as f32;
+    egui::ecolor::Hsva::new(hue, 0.85, 0.95, 1.).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_energy_color_gradient() {
+        assert_eq!(energy_color(0.), Color32::from_rgb(255, 0, 40));
+        assert_eq!(energy_color(HEALTH * 2.), Color32::from_rgb(0, 255, 40));
+        assert_eq!(energy_color(-5.), energy_color(0.));
+        assert_eq!(energy_color(HEALTH * 10.), energy_color(HEALTH * 2.));
+    }
+
+    #[test]
+    fn test_arena_view_letterboxes() {
+        let available = Rect::from_min_size(Pos2::ZERO, egui::vec2(400., 200.));
+        let view = ArenaView::new(available, [100.; 2], ScaleMode::Fit);
+        assert_eq!(view.scale, 1.);
+        assert_eq!(
+            view.arena_rect([100.; 2]),
+            Rect::from_min_max(egui::pos2(100., 0.), egui::pos2(300., 200.))
+        );
+        assert_eq!(view.to_screen(-100., 100.), egui::pos2(100., 200.));
+        let square = view.outline([100.; 2], &microswarm::shape::Shape::Rectangle);
+        assert_eq!(square[0], egui::pos2(100., 0.));
+        assert_eq!(square[2], egui::pos2(300., 200.));
+
+        let view = ArenaView::new(available, [400.; 2], ScaleMode::Actual);
+        assert_eq!(view.scale, 1.);
+        assert_eq!(view.to_screen(0., 0.), available.center());
+
+        // A corridor fills the width and is letterboxed top and bottom.
+        let view = ArenaView::new(available, [400., 20.], ScaleMode::Fit);
+        assert_eq!(view.scale, 0.5);
+        assert_eq!(
+            view.arena_rect([400., 20.]),
+            Rect::from_min_max(egui::pos2(0., 90.), egui::pos2(400., 110.))
+        );
+    }
+
+    #[test]
+    fn test_lineage_color_is_stable() {
+        let lineage = Uuid::new_v4();
+        assert_eq!(lineage_color(&lineage), lineage_color(&lineage));
+    }
+}
@@ -0,0 +1,70 @@
+//! The viewer in a browser.
+//!
+//! Build it with [trunk](https://trunkrs.dev) from this crate's directory:
+//!
+//! ```text
+//! rustup target add wasm32-unknown-unknown
+//! trunk serve --release
+//! ```
+//!
+//! `index.html` holds the canvas the viewer draws into, so the page can be
+//! embedded anywhere, and builds with only the `gui` feature. The web build
+//! starts from the `world.toml` at the root of the repository, and has no
+//! command line, recording or sound.
+
+use microswarm::config::Config;
+use microswarm::Simulation;
+use std::path::Path;
+
+/// The experiment the page starts with.
+const WORLD: &str = include_str!("../../../world.toml");
+/// The id of the canvas in `index.html`.
+#[cfg(target_arch = "wasm32")]
+const CANVAS_ID: &str = "microswarm";
+
+#[cfg(target_arch = "wasm32")]
+pub fn start() {
+    use eframe::wasm_bindgen::JsCast;
+    use eframe::web_sys;
+
+    wasm_bindgen_futures::spawn_local(async {
+        let canvas = web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.get_element_by_id(CANVAS_ID))
+            .and_then(|element| element.dyn_into::<web_sys::HtmlCanvasElement>().ok())
+            .unwrap_or_else(|| panic!("the page has no <canvas id=\"{CANVAS_ID}\">"));
+        let result = eframe::WebRunner::new()
+            .start(
+                canvas,
+                eframe::WebOptions::default(),
+                Box::new(|_cc| {
+                    let sim = world()?;
+                    Ok(Box::new(crate::ui::App::new(
+                        sim,
+                        crate::settings::Settings::default(),
+                    )))
+                }),
+            )
+            .await;
+        if let Err(err) = result {
+            panic!("couldn't start the viewer: {err:?}");
+        }
+    });
+}
+
+fn world() -> Result<Simulation, String> {
+    // Built-in species only, since there are no script files to read.
+    let config = Config::parse(WORLD, Path::new("")).map_err(|e| e.to_string())?;
+    Ok(config.build().map_err(|e| e.to_string())?.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_world_builds() {
+        let sim = world().unwrap();
+        assert!(!sim.species().is_empty());
+    }
+}